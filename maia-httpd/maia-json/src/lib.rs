@@ -148,7 +148,8 @@ impl From<Ad9361> for PatchAd9361 {
 /// Spectrometer JSON schema.
 ///
 /// This JSON schema corresponds to GET requests on `/api/spectrometer`. It
-/// contains the settings of the spectrometer (waterfall).
+/// contains the settings of the spectrometer (waterfall). The FFT window and
+/// overlap are `None` if the device does not report them.
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Spectrometer {
     /// Input sampling frequency in samples per second (read-only).
@@ -159,6 +160,13 @@ pub struct Spectrometer {
     pub number_integrations: u32,
     /// FFT size (read-only).
     pub fft_size: u32,
+    /// Name of the FFT window (read-only).
+    #[serde(default)]
+    pub window: Option<String>,
+    /// Overlap between consecutive FFTs, as a fraction of the FFT size
+    /// (read-only).
+    #[serde(default)]
+    pub overlap: Option<f64>,
 }
 
 /// Spectrometer PATCH JSON schema.
@@ -299,3 +307,24 @@ impl From<Time> for PatchTime {
         get_fields!(PatchTime, val, time)
     }
 }
+
+/// Device health JSON schema.
+///
+/// This JSON schema corresponds to GET requests on `/api/health`. It contains
+/// health telemetry of the device. Each of the fields is `None` if the device
+/// does not report it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct Health {
+    /// FPGA (Zynq XADC) temperature in degrees Celsius.
+    #[serde(default)]
+    pub fpga_temperature: Option<f64>,
+    /// AD9361 temperature in degrees Celsius.
+    #[serde(default)]
+    pub ad9361_temperature: Option<f64>,
+    /// CPU load average over the last minute.
+    #[serde(default)]
+    pub cpu_load: Option<f64>,
+    /// Fraction of the memory that is in use, between 0 and 1.
+    #[serde(default)]
+    pub memory_usage: Option<f64>,
+}
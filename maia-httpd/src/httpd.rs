@@ -20,6 +20,7 @@ use tower_http::{services::ServeDir, trace::TraceLayer};
 
 mod ad9361;
 mod api;
+mod health;
 mod recording;
 mod spectrometer;
 mod time;
@@ -66,6 +67,7 @@ impl Server {
             ad9361: Arc::clone(&ad9361),
             spectrometer_samp_rate,
         };
+        let health = health::State::new(Arc::clone(&ad9361)).await;
         let api = api::Api::new(Arc::clone(&ad9361), spectrometer.clone(), recorder.clone());
 
         let app = Router::new()
@@ -77,6 +79,7 @@ impl Server {
                     .patch(ad9361::patch_ad9361)
                     .with_state(ad9361),
             )
+            .route("/api/health", get(health::get_health).with_state(health))
             .route(
                 "/api/spectrometer",
                 get(spectrometer::get_spectrometer)
@@ -0,0 +1,101 @@
+use super::json_error::JsonError;
+use crate::iio::{Ad9361, Xadc};
+use anyhow::Result;
+use axum::Json;
+use maia_json::Health;
+use std::sync::Arc;
+use tokio::fs;
+
+#[derive(Debug, Clone)]
+pub struct State {
+    ad9361: Arc<tokio::sync::Mutex<Ad9361>>,
+    // The XADC is not available in all devices.
+    xadc: Option<Arc<Xadc>>,
+}
+
+impl State {
+    pub async fn new(ad9361: Arc<tokio::sync::Mutex<Ad9361>>) -> State {
+        let xadc = match Xadc::new().await {
+            Ok(xadc) => Some(Arc::new(xadc)),
+            Err(err) => {
+                tracing::info!("FPGA temperature not available: {err:#}");
+                None
+            }
+        };
+        State { ad9361, xadc }
+    }
+}
+
+pub async fn health_json(state: &State) -> Health {
+    // Each of the fields is read independently, so that a field that cannot
+    // be read does not prevent reporting the others.
+    let fpga_temperature = match &state.xadc {
+        Some(xadc) => xadc.get_temperature().await.ok(),
+        None => None,
+    };
+    let ad9361_temperature = state.ad9361.lock().await.get_temperature().await.ok();
+    let cpu_load = fs::read_to_string("/proc/loadavg")
+        .await
+        .ok()
+        .and_then(|loadavg| parse_loadavg(&loadavg));
+    let memory_usage = fs::read_to_string("/proc/meminfo")
+        .await
+        .ok()
+        .and_then(|meminfo| parse_meminfo(&meminfo));
+    Health {
+        fpga_temperature,
+        ad9361_temperature,
+        cpu_load,
+        memory_usage,
+    }
+}
+
+// Returns the 1-minute load average.
+fn parse_loadavg(loadavg: &str) -> Option<f64> {
+    loadavg.split_whitespace().next()?.parse().ok()
+}
+
+// Returns the fraction of the memory that is in use.
+fn parse_meminfo(meminfo: &str) -> Option<f64> {
+    let field = |name: &str| -> Option<f64> {
+        meminfo
+            .lines()
+            .find_map(|line| line.strip_prefix(name)?.strip_prefix(':'))?
+            .split_whitespace()
+            .next()?
+            .parse()
+            .ok()
+    };
+    let total = field("MemTotal")?;
+    let available = field("MemAvailable")?;
+    if total <= 0.0 {
+        return None;
+    }
+    Some(1.0 - available / total)
+}
+
+pub async fn get_health(
+    axum::extract::State(state): axum::extract::State<State>,
+) -> Result<Json<Health>, JsonError> {
+    Ok(Json(health_json(&state).await))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn loadavg() {
+        assert_eq!(parse_loadavg("0.52 0.58 0.59 1/123 4567\n"), Some(0.52));
+        assert_eq!(parse_loadavg(""), None);
+    }
+
+    #[test]
+    fn meminfo() {
+        let meminfo = "MemTotal:         500000 kB\n\
+                       MemFree:          100000 kB\n\
+                       MemAvailable:     125000 kB\n";
+        assert_eq!(parse_meminfo(meminfo), Some(0.75));
+        assert_eq!(parse_meminfo("MemTotal:         500000 kB\n"), None);
+    }
+}
@@ -7,6 +7,9 @@ use std::sync::Arc;
 
 // TODO: do not hardcode FFT size
 const FFT_SIZE: u32 = 4096;
+// These correspond to the FFT in maia-hdl
+const FFT_WINDOW: &str = "Blackman-Harris";
+const FFT_OVERLAP: f64 = 0.0;
 
 #[derive(Debug, Clone)]
 pub struct State {
@@ -34,6 +37,8 @@ pub async fn spectrometer_json(state: &State) -> Result<Spectrometer> {
         output_sampling_frequency: samp_rate / (f64::from(FFT_SIZE) * f64::from(num_integrations)),
         number_integrations: num_integrations,
         fft_size: FFT_SIZE,
+        window: Some(FFT_WINDOW.to_string()),
+        overlap: Some(FFT_OVERLAP),
     })
 }
 
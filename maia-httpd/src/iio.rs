@@ -50,32 +50,12 @@ impl Ad9361 {
     /// This function opens the first IIO device with name ad9361-phy that is
     /// found in the system.
     pub async fn new() -> Result<Ad9361> {
-        let iio_device_path = Self::find_iio_device()
+        let iio_device_path = find_iio_device("ad9361-phy")
             .await?
             .ok_or_else(|| anyhow::anyhow!("ad9361-phy IIO device not found"))?;
         Ok(Ad9361 { iio_device_path })
     }
 
-    async fn find_iio_device() -> Result<Option<PathBuf>> {
-        let mut entries = fs::read_dir(Path::new("/sys/bus/iio/devices")).await?;
-        while let Some(entry) = entries.next_entry().await? {
-            if entry
-                .file_name()
-                .to_str()
-                .ok_or_else(|| anyhow::anyhow!("file name is not valid UTF8"))?
-                .starts_with("iio:device")
-            {
-                let mut path = entry.path();
-                path.push("name");
-                let this_name = fs::read_to_string(path).await?;
-                if this_name == "ad9361-phy\n" {
-                    return Ok(Some(entry.path()));
-                }
-            }
-        }
-        Ok(None)
-    }
-
     iio_getset!(
         sampling_frequency,
         "in_voltage_sampling_frequency",
@@ -94,6 +74,71 @@ impl Ad9361 {
         Ad9361GainMode,
         Ad9361GainMode
     );
+
+    /// Returns the temperature of the AD9361 in degrees Celsius.
+    pub async fn get_temperature(&self) -> Result<f64> {
+        let millidegrees: f64 = read_attribute(&self.iio_device_path, "in_temp0_input").await?;
+        Ok(millidegrees * 1e-3)
+    }
+}
+
+/// Zynq XADC IIO device.
+///
+/// This struct represents the XADC of the Zynq (xadc), which is used to
+/// monitor the temperature of the FPGA.
+#[derive(Debug)]
+pub struct Xadc {
+    iio_device_path: PathBuf,
+}
+
+impl Xadc {
+    /// Opens a Zynq XADC IIO device.
+    ///
+    /// This function opens the first IIO device with name xadc that is found in
+    /// the system.
+    pub async fn new() -> Result<Xadc> {
+        let iio_device_path = find_iio_device("xadc")
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("xadc IIO device not found"))?;
+        Ok(Xadc { iio_device_path })
+    }
+
+    /// Returns the temperature of the FPGA in degrees Celsius.
+    pub async fn get_temperature(&self) -> Result<f64> {
+        let raw: f64 = read_attribute(&self.iio_device_path, "in_temp0_raw").await?;
+        let offset: f64 = read_attribute(&self.iio_device_path, "in_temp0_offset").await?;
+        let scale: f64 = read_attribute(&self.iio_device_path, "in_temp0_scale").await?;
+        // The scale gives millidegrees per unit.
+        Ok((raw + offset) * scale * 1e-3)
+    }
+}
+
+async fn find_iio_device(name: &str) -> Result<Option<PathBuf>> {
+    let mut entries = fs::read_dir(Path::new("/sys/bus/iio/devices")).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        if entry
+            .file_name()
+            .to_str()
+            .ok_or_else(|| anyhow::anyhow!("file name is not valid UTF8"))?
+            .starts_with("iio:device")
+        {
+            let mut path = entry.path();
+            path.push("name");
+            let this_name = fs::read_to_string(path).await?;
+            if this_name.trim_end() == name {
+                return Ok(Some(entry.path()));
+            }
+        }
+    }
+    Ok(None)
+}
+
+async fn read_attribute<T: std::str::FromStr>(iio_device_path: &Path, filename: &str) -> Result<T> {
+    fs::read_to_string(iio_device_path.join(filename))
+        .await?
+        .trim_end()
+        .parse::<T>()
+        .map_err(|_| anyhow::anyhow!("failed to parse IIO attribute {filename}"))
 }
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
@@ -0,0 +1,96 @@
+//! Frame driver.
+//!
+//! This module contains the [`FrameDriver`], which renders the frames of the
+//! waterfall. By default, the frames are driven by an internal
+//! `requestAnimationFrame()` loop. Applications that embed the waterfall and
+//! have their own animation loop can instead render the frames on demand.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+use crate::render::RenderEngine;
+use crate::waterfall::Waterfall;
+
+/// Frame driver.
+///
+/// The frame driver renders frames of the [`Waterfall`] with a
+/// [`RenderEngine`]. It can either run its own `requestAnimationFrame()` loop,
+/// by calling [`FrameDriver::start_animation_loop`], or be given to the host
+/// application, which calls [`FrameDriver::render_frame`] whenever it wants to
+/// render a frame.
+#[wasm_bindgen]
+pub struct FrameDriver {
+    render_engine: Rc<RefCell<RenderEngine>>,
+    waterfall: Rc<RefCell<Waterfall>>,
+}
+
+impl FrameDriver {
+    /// Creates a new frame driver.
+    pub fn new(
+        render_engine: Rc<RefCell<RenderEngine>>,
+        waterfall: Rc<RefCell<Waterfall>>,
+    ) -> FrameDriver {
+        FrameDriver {
+            render_engine,
+            waterfall,
+        }
+    }
+
+    /// Starts rendering frames in a `requestAnimationFrame()` loop.
+    ///
+    /// A frame is rendered on each animation frame, subject to the maximum
+    /// frame rate set with [`RenderEngine::set_max_frame_rate`]. The loop runs
+    /// for the rest of the life of the application.
+    pub fn start_animation_loop(self) {
+        let f = Rc::new(RefCell::new(None));
+        let g = f.clone();
+        *g.borrow_mut() = Some(Closure::new(move |dt: f32| {
+            let due = self.render_engine.borrow_mut().frame_due(f64::from(dt));
+            if due {
+                if let Err(e) = self.render_frame(f64::from(dt)) {
+                    web_sys::console::error_1(&e);
+                    return;
+                }
+            }
+            // Schedule ourselves for another requestAnimationFrame callback.
+            request_animation_frame(f.borrow().as_ref().unwrap());
+        }));
+        // Initial requestAnimationFrame callback.
+        request_animation_frame(g.borrow().as_ref().unwrap());
+    }
+}
+
+#[wasm_bindgen]
+impl FrameDriver {
+    /// Renders a frame.
+    ///
+    /// The `timestamp` is given in milliseconds, using the same time origin
+    /// as `performance.now()` and the timestamps of `requestAnimationFrame()`.
+    /// The maximum frame rate is not applied, since the caller decides when
+    /// frames are rendered.
+    pub fn render_frame(&self, timestamp: f64) -> Result<(), JsValue> {
+        let mut render_engine = self.render_engine.borrow_mut();
+        self.waterfall
+            .borrow_mut()
+            .prepare_render(&mut render_engine, timestamp as f32)?;
+        render_engine.render()
+    }
+
+    /// Returns `true` if a frame should be rendered.
+    ///
+    /// This is a hint that allows the caller to skip rendering frames when
+    /// nothing has changed. See [`Waterfall::needs_redraw`] for the changes
+    /// that are tracked.
+    pub fn needs_redraw(&self) -> bool {
+        self.waterfall.borrow().needs_redraw()
+    }
+}
+
+fn request_animation_frame(f: &Closure<dyn FnMut(f32)>) {
+    web_sys::window()
+        .unwrap()
+        .request_animation_frame(f.as_ref().unchecked_ref())
+        .unwrap();
+}
@@ -0,0 +1,162 @@
+//! Startup configuration.
+//!
+//! The initial view of the waterfall can be configured with data attributes on
+//! the canvas element on which the waterfall is mounted, so that applications
+//! that embed maia-wasm can open it at a specific view instead of the
+//! defaults:
+//!
+//! ```html
+//! <canvas id="canvas" data-zoom="4" data-center-frequency="0.25" data-colormap="Viridis">
+//! </canvas>
+//! ```
+//!
+//! The following attributes are supported. All of them are optional.
+//!
+//! - `data-zoom`: zoom level, between [`Waterfall::MIN_ZOOM`] and
+//!   [`Waterfall::MAX_ZOOM`].
+//! - `data-center-frequency`: center frequency of the view, between -1 and 1,
+//!   in the units of [`Waterfall::set_center_frequency`]. It is clamped so that
+//!   the view does not extend beyond the band edges at the given zoom.
+//! - `data-colormap`: colormap, which can be `Turbo` or `Viridis`.
+//!
+//! The options are applied when the waterfall is mounted, before the first
+//! frame is rendered, so they replace the built-in defaults. Anything that
+//! changes the view afterwards, such as the preferences and sessions applied
+//! by the UI, the remote control commands, or the view synchronization with
+//! other tabs, takes precedence over them. Invalid options make mounting fail
+//! with an error that names the offending attribute.
+
+use crate::render::RenderEngine;
+use crate::ui::colormap::Colormap;
+use crate::waterfall::Waterfall;
+use wasm_bindgen::JsValue;
+use web_sys::Element;
+
+/// Startup options of the waterfall.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct InitOptions {
+    /// Initial zoom level.
+    pub zoom: Option<f32>,
+    /// Initial center frequency, in the units of
+    /// [`Waterfall::set_center_frequency`].
+    pub center_frequency: Option<f32>,
+    /// Look-up table of the initial colormap.
+    pub colormap: Option<&'static [u8]>,
+}
+
+impl InitOptions {
+    /// Reads the startup options from the data attributes of an element.
+    pub fn from_element(element: &Element) -> Result<InitOptions, JsValue> {
+        Ok(InitOptions::parse(|name| element.get_attribute(name))?)
+    }
+
+    /// Parses the startup options.
+    ///
+    /// The `attribute` function returns the value of the attribute with the
+    /// given name, or `None` if the attribute is not present.
+    pub fn parse(attribute: impl Fn(&str) -> Option<String>) -> Result<InitOptions, String> {
+        let number = |name: &str| -> Result<Option<f32>, String> {
+            attribute(name)
+                .map(|value| {
+                    value
+                        .trim()
+                        .parse::<f32>()
+                        .ok()
+                        .filter(|x| x.is_finite())
+                        .ok_or_else(|| format!("{name} is not a valid number: {value}"))
+                })
+                .transpose()
+        };
+        let zoom = number("data-zoom")?;
+        if let Some(zoom) = zoom {
+            if !(Waterfall::MIN_ZOOM..=Waterfall::MAX_ZOOM).contains(&zoom) {
+                return Err(format!(
+                    "data-zoom must be between {} and {}",
+                    Waterfall::MIN_ZOOM,
+                    Waterfall::MAX_ZOOM
+                ));
+            }
+        }
+        let center_frequency = number("data-center-frequency")?;
+        if let Some(center_frequency) = center_frequency {
+            if !(-1.0..=1.0).contains(&center_frequency) {
+                return Err("data-center-frequency must be between -1 and 1".to_string());
+            }
+        }
+        let colormap = attribute("data-colormap")
+            .map(|name| {
+                name.trim()
+                    .parse::<Colormap>()
+                    .map(|colormap| colormap.colormap_as_slice())
+                    .map_err(|_| format!("unknown data-colormap {name}"))
+            })
+            .transpose()?;
+        Ok(InitOptions {
+            zoom,
+            center_frequency,
+            colormap,
+        })
+    }
+
+    /// Applies the startup options to the waterfall.
+    pub fn apply(
+        &self,
+        waterfall: &mut Waterfall,
+        render_engine: &mut RenderEngine,
+    ) -> Result<(), JsValue> {
+        // The zoom is set first, since the center frequency is clamped
+        // according to the zoom.
+        if let Some(zoom) = self.zoom {
+            waterfall.set_zoom(zoom);
+        }
+        if let Some(center_frequency) = self.center_frequency {
+            waterfall.set_center_frequency(center_frequency);
+        }
+        if let Some(colormap) = self.colormap {
+            waterfall.load_colormap(render_engine, colormap)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(attributes: &[(&str, &str)]) -> Result<InitOptions, String> {
+        InitOptions::parse(|name| {
+            attributes
+                .iter()
+                .find(|(n, _)| *n == name)
+                .map(|(_, value)| value.to_string())
+        })
+    }
+
+    #[test]
+    fn valid() {
+        assert_eq!(parse(&[]), Ok(InitOptions::default()));
+        assert_eq!(
+            parse(&[
+                ("data-zoom", "4"),
+                ("data-center-frequency", " -0.25 "),
+                ("data-colormap", "Viridis"),
+            ]),
+            Ok(InitOptions {
+                zoom: Some(4.0),
+                center_frequency: Some(-0.25),
+                colormap: Some(&crate::colormap::viridis::COLORMAP[..]),
+            })
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(parse(&[("data-zoom", "0.5")]).is_err());
+        assert!(parse(&[("data-zoom", "256")]).is_err());
+        assert!(parse(&[("data-zoom", "NaN")]).is_err());
+        assert!(parse(&[("data-zoom", "")]).is_err());
+        assert!(parse(&[("data-center-frequency", "1.5")]).is_err());
+        assert!(parse(&[("data-center-frequency", "center")]).is_err());
+        assert!(parse(&[("data-colormap", "Jet")]).is_err());
+    }
+}
@@ -9,6 +9,8 @@ use std::rc::Rc;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
 
+use crate::frame_driver::FrameDriver;
+use crate::init_options::InitOptions;
 use crate::render::RenderEngine;
 use crate::ui::Ui;
 use crate::waterfall::Waterfall;
@@ -17,8 +19,12 @@ use crate::websocket::WebSocketClient;
 
 pub mod array_view;
 pub mod colormap;
+pub mod frame_driver;
+pub mod init_options;
 pub mod pointer;
+pub mod remote_control;
 pub mod render;
+pub mod tween;
 pub mod ui;
 pub mod waterfall;
 pub mod waterfall_interaction;
@@ -35,17 +41,43 @@ pub fn maia_init() {
 }
 
 /// Mounts the waterfall plot.
+///
+/// The waterfall is rendered in a `requestAnimationFrame()` loop that is
+/// started by this function. The initial view can be configured with data
+/// attributes on the canvas element (see [`init_options`]).
 #[wasm_bindgen]
 pub fn waterfall(id: String) -> Result<(), JsValue> {
+    mount_waterfall(&id)?.start_animation_loop();
+    Ok(())
+}
+
+/// Mounts the waterfall plot for rendering on demand.
+///
+/// Unlike [`waterfall`], this function does not start a
+/// `requestAnimationFrame()` loop. The application is responsible for
+/// rendering frames by calling [`FrameDriver::render_frame`] on the returned
+/// object, and it can use [`FrameDriver::needs_redraw`] to skip frames when
+/// nothing has changed. The initial view is configured as in [`waterfall`].
+#[wasm_bindgen]
+pub fn waterfall_on_demand(id: String) -> Result<FrameDriver, JsValue> {
+    mount_waterfall(&id)
+}
+
+fn mount_waterfall(id: &str) -> Result<FrameDriver, JsValue> {
     let window = Rc::new(web_sys::window().ok_or("unable to get window")?);
     let document = Rc::new(window.document().ok_or("unable to get document")?);
     let canvas = Rc::new(
         document
-            .get_element_by_id(&id)
+            .get_element_by_id(id)
             .ok_or("unable to get #canvas element")?
             .dyn_into::<web_sys::HtmlCanvasElement>()?,
     );
     canvas.style().set_property("cursor", "crosshair")?;
+    // The waterfall handles panning and pinch zoom itself. Without this, the
+    // browser can take over touch gestures on the canvas to scroll or zoom
+    // the page, and it cancels the pointers it uses. This is set here rather
+    // than only in the stylesheet because the canvas can have any id.
+    canvas.style().set_property("touch-action", "none")?;
 
     let render_engine = Rc::new(RefCell::new(RenderEngine::new(
         Rc::clone(&canvas),
@@ -56,6 +88,8 @@ pub fn waterfall(id: String) -> Result<(), JsValue> {
         &mut render_engine.borrow_mut(),
         window.performance().ok_or("unable to get performance")?,
     )?));
+    InitOptions::from_element(&canvas)?
+        .apply(&mut waterfall.borrow_mut(), &mut render_engine.borrow_mut())?;
     WebSocketClient::start(Rc::clone(&waterfall), "ws://127.0.0.1:9001".to_string())?;
     // let ui = Ui::new(
     //     Rc::clone(&window),
@@ -66,34 +100,7 @@ pub fn waterfall(id: String) -> Result<(), JsValue> {
     // let waterfall_interaction =
     //     WaterfallInteraction::new(Rc::clone(&render_engine), canvas, ui, Rc::clone(&waterfall));
     // waterfall_interaction.set_callbacks();
+    // remote_control::RemoteControl::new(waterfall_interaction, ui, Vec::new()).set_callbacks()?;
 
-    let f = Rc::new(RefCell::new(None));
-    let g = f.clone();
-    *g.borrow_mut() = Some(Closure::new(move |dt| {
-        let mut render_engine = render_engine.borrow_mut();
-        if let Err(e) = waterfall
-            .borrow_mut()
-            .prepare_render(&mut render_engine, dt)
-        {
-            web_sys::console::error_1(&e);
-            return;
-        }
-        if let Err(e) = render_engine.render() {
-            web_sys::console::error_1(&e);
-            return;
-        }
-        // Schedule ourselves for another requestAnimationFrame callback.
-        request_animation_frame(f.borrow().as_ref().unwrap());
-    }));
-    // Initial requestAnimationFrame callback.
-    request_animation_frame(g.borrow().as_ref().unwrap());
-
-    Ok(())
-}
-
-fn request_animation_frame(f: &Closure<dyn FnMut(f32)>) {
-    web_sys::window()
-        .unwrap()
-        .request_animation_frame(f.as_ref().unchecked_ref())
-        .unwrap();
+    Ok(FrameDriver::new(render_engine, waterfall))
 }
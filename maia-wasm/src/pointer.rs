@@ -12,8 +12,25 @@ const NUM_POINTERS: usize = 2;
 /// A pointer tracker receives [`PointerEvent`]'s from the web browser, maintains
 /// state information about the pointers that are active, and generates
 /// [`PointerGesture`]'s.
+///
+/// Sometimes the browser does not deliver the pointer up event of a pointer,
+/// for instance if the pointer is released while the window does not have
+/// focus. To avoid these pointers staying active forever, pointers that have
+/// not produced any events for [`PointerTracker::STALE_TIMEOUT`] are dropped
+/// when events of other pointers are received. The application can also drop
+/// all the active pointers with [`PointerTracker::clear`].
 pub struct PointerTracker {
-    slots: [Option<PointerEvent>; NUM_POINTERS],
+    slots: [Option<Pointer>; NUM_POINTERS],
+}
+
+// State of an active pointer, taken from its last event.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) struct Pointer {
+    id: i32,
+    x: i32,
+    y: i32,
+    // Event timestamp in milliseconds
+    timestamp: f64,
 }
 
 /// Pointer gesture.
@@ -52,6 +69,11 @@ pub enum PointerGesture {
 }
 
 impl PointerTracker {
+    /// Time after which a pointer that has not produced any events is dropped.
+    ///
+    /// The time is given in milliseconds.
+    pub const STALE_TIMEOUT: f64 = 10e3;
+
     /// Creates a new pointer tracker.
     pub fn new() -> PointerTracker {
         PointerTracker {
@@ -63,47 +85,72 @@ impl PointerTracker {
     ///
     /// This function should be used as the handler for pointer down events.
     pub fn on_pointer_down(&mut self, event: PointerEvent) {
-        self.record_event(event);
+        self.pointer_down(Pointer::from_event(&event));
+    }
+
+    pub(crate) fn pointer_down(&mut self, pointer: Pointer) {
+        self.drop_stale(&pointer);
+        self.record(pointer);
     }
 
     #[allow(clippy::needless_return)]
-    fn record_event(&mut self, event: PointerEvent) {
-        let pointer_id = event.pointer_id();
+    fn record(&mut self, pointer: Pointer) {
         // Search previous event with same pointer ID.
-        if let Some(slot) = self.slots.iter_mut().find_map(|x| {
-            x.as_mut().and_then(|x| {
-                if x.pointer_id() == pointer_id {
-                    Some(x)
-                } else {
-                    None
-                }
-            })
-        }) {
+        if let Some(slot) = self
+            .slots
+            .iter_mut()
+            .find_map(|x| x.as_mut().filter(|x| x.id == pointer.id))
+        {
             // Replace event with the new one.
-            *slot = event;
+            *slot = pointer;
             return;
         }
         // Search for an empty slot.
         if let Some(slot) = self.slots.iter_mut().find(|x| x.is_none()) {
-            slot.replace(event);
+            slot.replace(pointer);
             return;
         }
         // We found no empty slots, so we cannot handle this pointer (this
         // typically should not happen).
     }
 
+    // Drops the pointers other than the one that produced an event which have
+    // not produced any events in STALE_TIMEOUT. These are most likely pointers
+    // whose pointer up event was missed.
+    //
+    // If all the slots are taken by an ongoing gesture, its pointers are only
+    // dropped if all of them are stale, since a finger held still during a
+    // pinch does not produce any events.
+    fn drop_stale(&mut self, pointer: &Pointer) {
+        let is_stale = |x: &Pointer| {
+            x.id != pointer.id && pointer.timestamp - x.timestamp > Self::STALE_TIMEOUT
+        };
+        if self.num_active_pointers() == NUM_POINTERS && !self.slots.iter().flatten().all(is_stale)
+        {
+            return;
+        }
+        for slot in self.slots.iter_mut() {
+            if slot.as_ref().map_or(false, is_stale) {
+                slot.take();
+            }
+        }
+    }
+
     /// Handler for the pointer up event.
     ///
     /// This function should be used as the handler for pointer up events.
-    #[allow(clippy::needless_return)]
     pub fn on_pointer_up(&mut self, event: PointerEvent) {
-        let pointer_id = event.pointer_id();
+        self.pointer_up(event.pointer_id());
+    }
+
+    #[allow(clippy::needless_return)]
+    fn pointer_up(&mut self, pointer_id: i32) {
         // Search previous event with the same pointer (this typically should be
         // found).
         if let Some(slot) = self
             .slots
             .iter_mut()
-            .find(|x| x.as_ref().map_or(false, |x| x.pointer_id() == pointer_id))
+            .find(|x| x.map_or(false, |x| x.id == pointer_id))
         {
             // Remove event.
             slot.take();
@@ -113,16 +160,19 @@ impl PointerTracker {
         // typically should not happen).
     }
 
-    fn get_event(&self, pointer_id: i32) -> Option<&PointerEvent> {
-        self.slots.iter().find_map(|x| {
-            x.as_ref().and_then(|x| {
-                if x.pointer_id() == pointer_id {
-                    Some(x)
-                } else {
-                    None
-                }
-            })
-        })
+    /// Drops all the active pointers.
+    ///
+    /// This function should be called when the pointer up events of the
+    /// active pointers might not be delivered, such as when the window loses
+    /// focus.
+    pub fn clear(&mut self) {
+        self.slots = Default::default();
+    }
+
+    fn get(&self, pointer_id: i32) -> Option<&Pointer> {
+        self.slots
+            .iter()
+            .find_map(|x| x.as_ref().filter(|x| x.id == pointer_id))
     }
 
     /// Handler for the pointer move event.
@@ -131,16 +181,22 @@ impl PointerTracker {
     ///
     /// If the event produces a corresponding pointer gesture, it is returned.
     pub fn on_pointer_move(&mut self, event: PointerEvent) -> Option<PointerGesture> {
+        self.pointer_move(Pointer::from_event(&event))
+    }
+
+    pub(crate) fn pointer_move(&mut self, pointer: Pointer) -> Option<PointerGesture> {
+        // The other pointers of an ongoing gesture are not dropped, since a
+        // finger held still during a pinch does not produce any events.
+        if self.get(pointer.id).is_none() {
+            self.drop_stale(&pointer);
+            return None;
+        }
         let ret = match self.num_active_pointers() {
-            1 => self
-                .get_event(event.pointer_id())
-                .map(|old_event| self.drag(&event, old_event)),
-            2 => self.pinch(&event),
+            1 => self.get(pointer.id).map(|old| self.drag(&pointer, old)),
+            2 => self.pinch(&pointer),
             _ => None,
         };
-        if ret.is_some() {
-            self.record_event(event);
-        }
+        self.record(pointer);
         ret
     }
 
@@ -158,39 +214,30 @@ impl PointerTracker {
         self.slots.iter().filter(|x| x.is_some()).count()
     }
 
-    fn drag(&self, new: &PointerEvent, old: &PointerEvent) -> PointerGesture {
+    fn drag(&self, new: &Pointer, old: &Pointer) -> PointerGesture {
         PointerGesture::Drag {
-            dx: new.client_x() - old.client_x(),
-            dy: new.client_y() - old.client_y(),
+            dx: new.x - old.x,
+            dy: new.y - old.y,
         }
     }
 
-    fn pinch(&self, event: &PointerEvent) -> Option<PointerGesture> {
-        let pointer_id = event.pointer_id();
-        // This event might not be present in the slots. In that case the pinch
-        // is invalid.
-        let same = self.get_event(pointer_id)?;
-        // There must be another event in the slots, since this is only called
-        // when there are 2 events in the slots.
+    fn pinch(&self, pointer: &Pointer) -> Option<PointerGesture> {
+        // This pointer might not be present in the slots. In that case the
+        // pinch is invalid.
+        let same = self.get(pointer.id)?;
+        // There must be another pointer in the slots, since this is only
+        // called when there are 2 pointers in the slots.
         let other = self
             .slots
             .iter()
-            .find_map(|x| {
-                x.as_ref().and_then(|x| {
-                    if x.pointer_id() != pointer_id {
-                        Some(x)
-                    } else {
-                        None
-                    }
-                })
-            })
+            .find_map(|x| x.as_ref().filter(|x| x.id != pointer.id))
             .unwrap();
-        let same_x = same.client_x() as f32;
-        let same_y = same.client_y() as f32;
-        let other_x = other.client_x() as f32;
-        let other_y = other.client_y() as f32;
-        let new_x = event.client_x() as f32;
-        let new_y = event.client_y() as f32;
+        let same_x = same.x as f32;
+        let same_y = same.y as f32;
+        let other_x = other.x as f32;
+        let other_y = other.y as f32;
+        let new_x = pointer.x as f32;
+        let new_y = pointer.y as f32;
         let min_dilation = 0.5;
         let max_dilation = 2.0;
         let min_distance = 10.0;
@@ -211,14 +258,121 @@ impl PointerTracker {
             1.0
         };
         Some(PointerGesture::Pinch {
-            center: (other.client_x(), other.client_y()),
+            center: (other.x, other.y),
             dilation: (dilation_x, dilation_y),
         })
     }
 }
 
+impl Pointer {
+    pub(crate) fn new(id: i32, x: i32, y: i32, timestamp: f64) -> Pointer {
+        Pointer {
+            id,
+            x,
+            y,
+            timestamp,
+        }
+    }
+
+    fn from_event(event: &PointerEvent) -> Pointer {
+        Pointer::new(
+            event.pointer_id(),
+            event.client_x(),
+            event.client_y(),
+            event.time_stamp(),
+        )
+    }
+}
+
 impl Default for PointerTracker {
     fn default() -> PointerTracker {
         PointerTracker::new()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn pointer(id: i32, x: i32, timestamp: f64) -> Pointer {
+        Pointer {
+            id,
+            x,
+            y: 0,
+            timestamp,
+        }
+    }
+
+    fn is_drag(gesture: Option<PointerGesture>, dx: i32) -> bool {
+        matches!(gesture, Some(PointerGesture::Drag { dx: x, .. }) if x == dx)
+    }
+
+    #[test]
+    fn drag() {
+        let mut tracker = PointerTracker::new();
+        tracker.pointer_down(pointer(1, 100, 0.0));
+        assert!(is_drag(tracker.pointer_move(pointer(1, 110, 10.0)), 10));
+        assert!(is_drag(tracker.pointer_move(pointer(1, 105, 20.0)), -5));
+        tracker.pointer_up(1);
+        assert!(!tracker.has_active_pointers());
+        assert!(tracker.pointer_move(pointer(1, 110, 30.0)).is_none());
+    }
+
+    #[test]
+    fn missed_pointer_up() {
+        let mut tracker = PointerTracker::new();
+        // The pointer up of this pointer is never received
+        tracker.pointer_down(pointer(1, 100, 0.0));
+        // A new gesture shortly after is treated as a pinch
+        tracker.pointer_down(pointer(2, 200, 1e3));
+        assert!(matches!(
+            tracker.pointer_move(pointer(2, 300, 1.1e3)),
+            Some(PointerGesture::Pinch { .. })
+        ));
+        tracker.pointer_up(2);
+        // After the timeout the stale pointer is dropped and a new gesture
+        // is a drag
+        tracker.pointer_down(pointer(3, 200, 20e3));
+        assert_eq!(tracker.num_active_pointers(), 1);
+        assert!(is_drag(tracker.pointer_move(pointer(3, 250, 20.1e3)), 50));
+        tracker.pointer_up(3);
+        assert!(!tracker.has_active_pointers());
+    }
+
+    #[test]
+    fn stationary_pinch_pointer() {
+        let mut tracker = PointerTracker::new();
+        tracker.pointer_down(pointer(1, 100, 0.0));
+        tracker.pointer_down(pointer(2, 200, 0.0));
+        // Pointer 1 is held still for longer than the timeout while pointer
+        // 2 moves
+        for (x, timestamp) in [(210, 5e3), (220, 15e3), (230, 25e3)] {
+            assert!(matches!(
+                tracker.pointer_move(pointer(2, x, timestamp)),
+                Some(PointerGesture::Pinch { .. })
+            ));
+        }
+        assert_eq!(tracker.num_active_pointers(), 2);
+        assert!(matches!(
+            tracker.pointer_move(pointer(1, 90, 30e3)),
+            Some(PointerGesture::Pinch { .. })
+        ));
+        // A third pointer does not break the pinch
+        tracker.pointer_down(pointer(3, 300, 36e3));
+        assert!(matches!(
+            tracker.pointer_move(pointer(2, 240, 36.1e3)),
+            Some(PointerGesture::Pinch { .. })
+        ));
+    }
+
+    #[test]
+    fn clear() {
+        let mut tracker = PointerTracker::new();
+        tracker.pointer_down(pointer(1, 100, 0.0));
+        tracker.pointer_down(pointer(2, 200, 0.0));
+        tracker.clear();
+        assert!(!tracker.has_active_pointers());
+        tracker.pointer_down(pointer(3, 100, 1.0));
+        assert!(is_drag(tracker.pointer_move(pointer(3, 90, 2.0)), -10));
+    }
+}
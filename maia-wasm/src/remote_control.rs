@@ -0,0 +1,319 @@
+//! Remote control over `postMessage`.
+//!
+//! This module implements a command interface that allows a parent window to
+//! control maia-wasm when it is embedded in an iframe. Commands are sent with
+//! `window.postMessage` as JSON strings (or objects) and each command is
+//! answered with a response message posted back to the sender.
+//!
+//! Commands have the following form, where `id` is optional and is copied
+//! into the response:
+//!
+//! ```json
+//! {"id": 1, "command": "set_center_frequency", "center_frequency": 0.5}
+//! {"id": 2, "command": "set_zoom", "zoom": 4.0}
+//! {"id": 3, "command": "set_rx_lo", "frequency": 2400000000}
+//! {"id": 4, "command": "set_colormap", "colormap": "Viridis"}
+//! {"id": 5, "command": "query_view"}
+//! {"id": 6, "command": "auto_range"}
+//! ```
+//!
+//! The center frequency uses the units of
+//! [`Waterfall::set_center_frequency`](crate::waterfall::Waterfall::set_center_frequency).
+//! Responses have the form
+//!
+//! ```json
+//! {"id": 5, "ok": true, "view": {"zoom": 4.0, "center_frequency": 0.5}}
+//! {"id": 4, "ok": false, "error": "unknown colormap Jet"}
+//! {"id": 6, "ok": true, "levels": {"min": 38.0, "max": 83.0}}
+//! ```
+//!
+//! The `view` field is only present in the response to `query_view`, and the
+//! `levels` field, which gives the waterfall levels in dB, is only present in
+//! the response to `auto_range`.
+//!
+//! Only messages from the origins in an allowlist are processed. Messages from
+//! other origins are ignored without sending a response.
+
+use serde::{Deserialize, Serialize};
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{MessageEvent, Window};
+
+use crate::ui::Ui;
+use crate::waterfall_interaction::{ViewState, WaterfallInteraction};
+
+/// Remote control.
+///
+/// The remote control listens to the message events of the window and
+/// executes the commands that it receives from the allowed origins.
+#[derive(Clone)]
+pub struct RemoteControl {
+    interaction: WaterfallInteraction,
+    ui: Ui,
+    allowed_origins: Rc<[String]>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+struct Request {
+    #[serde(default)]
+    id: Option<serde_json::Value>,
+    #[serde(flatten)]
+    command: Command,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "command", rename_all = "snake_case")]
+enum Command {
+    SetCenterFrequency { center_frequency: f32 },
+    SetZoom { zoom: f32 },
+    SetRxLo { frequency: u64 },
+    SetColormap { colormap: String },
+    QueryView,
+    AutoRange,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+struct Response {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<serde_json::Value>,
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    view: Option<ViewState>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    levels: Option<Levels>,
+}
+
+#[derive(Serialize, Debug, Copy, Clone, PartialEq)]
+struct Levels {
+    min: f32,
+    max: f32,
+}
+
+impl RemoteControl {
+    /// Creates a remote control.
+    ///
+    /// Only the messages whose origin (for instance,
+    /// `"https://dashboard.example.com"`) is listed in `allowed_origins` are
+    /// processed. If the list is empty, the remote control does nothing.
+    ///
+    /// After this function returns, it is necessary to call
+    /// [`RemoteControl::set_callbacks`] to start listening to messages.
+    pub fn new(
+        interaction: WaterfallInteraction,
+        ui: Ui,
+        allowed_origins: Vec<String>,
+    ) -> RemoteControl {
+        RemoteControl {
+            interaction,
+            ui,
+            allowed_origins: allowed_origins.into(),
+        }
+    }
+
+    /// Registers the message event listener of the window.
+    pub fn set_callbacks(&self) -> Result<(), JsValue> {
+        if self.allowed_origins.is_empty() {
+            return Ok(());
+        }
+        let window = web_sys::window().ok_or("unable to get window")?;
+        // We leak the closure to prevent it from being dropped immediately.
+        window.add_event_listener_with_callback(
+            "message",
+            self.onmessage().into_js_value().unchecked_ref(),
+        )
+    }
+
+    fn onmessage(&self) -> Closure<dyn Fn(MessageEvent)> {
+        let remote = self.clone();
+        Closure::new(move |event: MessageEvent| {
+            let origin = event.origin();
+            if !remote.allowed_origins.iter().any(|o| *o == origin) {
+                return;
+            }
+            let response = match Self::parse(&event.data()) {
+                Ok(request) => {
+                    let mut response = remote.execute(request.command);
+                    response.id = request.id;
+                    response
+                }
+                Err(e) => Response::error(e),
+            };
+            if let Err(e) = Self::respond(&event, &response) {
+                web_sys::console::error_1(&e);
+            }
+        })
+    }
+
+    fn parse(data: &JsValue) -> Result<Request, String> {
+        let json = match data.as_string() {
+            Some(json) => json,
+            None => js_sys::JSON::stringify(data)
+                .ok()
+                .and_then(|s| s.as_string())
+                .ok_or("unable to read message")?,
+        };
+        Self::parse_json(&json)
+    }
+
+    fn parse_json(json: &str) -> Result<Request, String> {
+        serde_json::from_str(json).map_err(|e| format!("invalid command: {e}"))
+    }
+
+    fn execute(&self, command: Command) -> Response {
+        let result = match command {
+            Command::SetCenterFrequency { center_frequency } => {
+                if center_frequency.is_finite() {
+                    let view = self.interaction.view();
+                    self.interaction.set_view(ViewState {
+                        center_frequency,
+                        ..view
+                    });
+                    Ok(())
+                } else {
+                    Err("invalid center frequency".to_string())
+                }
+            }
+            Command::SetZoom { zoom } => {
+                if zoom.is_finite() && zoom > 0.0 {
+                    let view = self.interaction.view();
+                    self.interaction.set_view(ViewState { zoom, ..view });
+                    Ok(())
+                } else {
+                    Err("invalid zoom".to_string())
+                }
+            }
+            Command::SetRxLo { frequency } => self
+                .ui
+                .set_rx_lo_frequency(frequency)
+                .map_err(Self::js_error),
+            Command::SetColormap { colormap } => {
+                self.ui.set_colormap(&colormap).map_err(Self::js_error)
+            }
+            Command::QueryView => {
+                return Response {
+                    view: Some(self.interaction.view()),
+                    ..Response::success()
+                }
+            }
+            Command::AutoRange => match self.ui.auto_range() {
+                Ok((min, max)) => {
+                    return Response {
+                        levels: Some(Levels { min, max }),
+                        ..Response::success()
+                    }
+                }
+                Err(e) => Err(Self::js_error(e)),
+            },
+        };
+        match result {
+            Ok(()) => Response::success(),
+            Err(e) => Response::error(e),
+        }
+    }
+
+    fn js_error(e: JsValue) -> String {
+        e.as_string().unwrap_or_else(|| format!("{e:?}"))
+    }
+
+    fn respond(event: &MessageEvent, response: &Response) -> Result<(), JsValue> {
+        // The source is a WindowProxy of the parent window, which for
+        // cross-origin windows does not pass the instanceof Window check
+        // performed by dyn_into, so an unchecked cast is used.
+        let source = event
+            .source()
+            .ok_or("message has no source")?
+            .unchecked_into::<Window>();
+        let json = serde_json::to_string(response).map_err(|_| "unable to format response")?;
+        source.post_message(&json.into(), &event.origin())
+    }
+}
+
+impl Response {
+    fn success() -> Response {
+        Response {
+            id: None,
+            ok: true,
+            error: None,
+            view: None,
+            levels: None,
+        }
+    }
+
+    fn error(error: String) -> Response {
+        Response {
+            id: None,
+            ok: false,
+            error: Some(error),
+            view: None,
+            levels: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_commands() {
+        let request =
+            RemoteControl::parse_json(r#"{"id": 7, "command": "set_zoom", "zoom": 2.0}"#).unwrap();
+        assert_eq!(request.id, Some(serde_json::json!(7)));
+        assert_eq!(request.command, Command::SetZoom { zoom: 2.0 });
+        let request = RemoteControl::parse_json(r#"{"command": "query_view"}"#).unwrap();
+        assert_eq!(request.id, None);
+        assert_eq!(request.command, Command::QueryView);
+        assert_eq!(
+            RemoteControl::parse_json(r#"{"command": "set_rx_lo", "frequency": 100000000}"#)
+                .unwrap()
+                .command,
+            Command::SetRxLo {
+                frequency: 100_000_000
+            }
+        );
+    }
+
+    #[test]
+    fn parse_invalid_commands() {
+        assert!(RemoteControl::parse_json("").is_err());
+        assert!(RemoteControl::parse_json(r#"{"command": "reboot"}"#).is_err());
+        assert!(RemoteControl::parse_json(r#"{"command": "set_zoom"}"#).is_err());
+        assert!(RemoteControl::parse_json(r#"{"command": "set_rx_lo", "frequency": -1}"#).is_err());
+    }
+
+    #[test]
+    fn format_levels() {
+        let response = Response {
+            levels: Some(Levels {
+                min: 38.0,
+                max: 83.5,
+            }),
+            ..Response::success()
+        };
+        assert_eq!(
+            serde_json::to_string(&response).unwrap(),
+            r#"{"ok":true,"levels":{"min":38.0,"max":83.5}}"#
+        );
+        assert_eq!(
+            RemoteControl::parse_json(r#"{"command": "auto_range"}"#)
+                .unwrap()
+                .command,
+            Command::AutoRange
+        );
+    }
+
+    #[test]
+    fn format_response() {
+        let response = Response {
+            id: Some(serde_json::json!("a")),
+            ..Response::error("failed".to_string())
+        };
+        assert_eq!(
+            serde_json::to_string(&response).unwrap(),
+            r#"{"id":"a","ok":false,"error":"failed"}"#
+        );
+    }
+}
@@ -9,8 +9,9 @@ use std::rc::Rc;
 use web_sys::{WebGl2RenderingContext, WebGlProgram, WebGlVertexArrayObject};
 
 pub use engine::{
-    CanvasDims, RenderEngine, TextsDimensions, Texture, TextureBuilder, TextureInternalFormat,
-    TextureMagFilter, TextureMinFilter, TextureParameter, TextureWrap, VaoBuilder,
+    CanvasDims, Capabilities, RenderEngine, TextsDimensions, Texture, TextureBuilder,
+    TextureInternalFormat, TextureMagFilter, TextureMinFilter, TextureParameter, TextureWrap,
+    VaoBuilder,
 };
 pub use uniform::{Uniform, UniformType, UniformValue};
 
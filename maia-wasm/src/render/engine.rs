@@ -33,6 +33,19 @@ pub struct RenderEngine {
     current: Current,
     objects: Vec<RenderObject>,
     text_render: TextRender,
+    frame_limiter: FrameLimiter,
+    // Maximum device pixel ratio used for the canvas backing store
+    max_pixel_ratio: Option<f64>,
+}
+
+// Limits the rate at which frames are rendered. Frames are requested with
+// requestAnimationFrame, and some of them are skipped to stay below the
+// maximum frame rate.
+#[derive(Debug, Default)]
+struct FrameLimiter {
+    // Minimum time between frames, in milliseconds
+    min_interval: Option<f64>,
+    last_frame: Option<f64>,
 }
 
 #[derive(Debug)]
@@ -62,11 +75,25 @@ pub struct CanvasDims {
 }
 
 impl CanvasDims {
-    fn from_canvas_and_window(canvas: &HtmlCanvasElement, window: &web_sys::Window) -> CanvasDims {
+    fn from_canvas_and_window(
+        canvas: &HtmlCanvasElement,
+        window: &web_sys::Window,
+        max_pixel_ratio: Option<f64>,
+    ) -> CanvasDims {
         CanvasDims {
             width: canvas.client_width() as u32,
             height: canvas.client_height() as u32,
-            device_pixel_ratio: window.device_pixel_ratio(),
+            device_pixel_ratio: Self::capped_pixel_ratio(
+                window.device_pixel_ratio(),
+                max_pixel_ratio,
+            ),
+        }
+    }
+
+    fn capped_pixel_ratio(device_pixel_ratio: f64, max_pixel_ratio: Option<f64>) -> f64 {
+        match max_pixel_ratio {
+            Some(max) if max > 0.0 => device_pixel_ratio.min(max),
+            _ => device_pixel_ratio,
         }
     }
 
@@ -83,6 +110,14 @@ impl CanvasDims {
         (self.width, self.height)
     }
 
+    /// Returns the device pixel ratio.
+    ///
+    /// This is the ratio between the size of a device pixel and the size of a
+    /// CSS pixel.
+    pub fn device_pixel_ratio(&self) -> f64 {
+        self.device_pixel_ratio
+    }
+
     fn set_viewport(&self, gl: &WebGl2RenderingContext) {
         let (w, h) = self.device_pixels();
         gl.viewport(0, 0, w as i32, h as i32);
@@ -96,6 +131,57 @@ impl CanvasDims {
     }
 }
 
+/// Browser and WebGL2 capabilities.
+///
+/// This structure is returned by [`RenderEngine::capabilities`]. It is used
+/// for diagnostics, and to understand the limits imposed by the browser and
+/// the GPU.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Capabilities {
+    /// WebGL version string.
+    pub version: String,
+    /// GLSL version string.
+    pub shading_language_version: String,
+    /// GPU vendor.
+    ///
+    /// If the browser supports the `WEBGL_debug_renderer_info` extension, this
+    /// is the unmasked vendor.
+    pub vendor: String,
+    /// GPU renderer.
+    ///
+    /// If the browser supports the `WEBGL_debug_renderer_info` extension, this
+    /// is the unmasked renderer.
+    pub renderer: String,
+    /// Maximum texture width and height.
+    ///
+    /// This bounds the FFT size and the waterfall history depth.
+    pub max_texture_size: u32,
+    /// Number of texture units available to the fragment shader.
+    pub max_texture_image_units: u32,
+    /// Whether the WebGPU API is available in the browser.
+    pub webgpu_available: bool,
+}
+
+impl std::fmt::Display for Capabilities {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        writeln!(f, "WebGL version: {}", self.version)?;
+        writeln!(f, "GLSL version: {}", self.shading_language_version)?;
+        writeln!(f, "Vendor: {}", self.vendor)?;
+        writeln!(f, "Renderer: {}", self.renderer)?;
+        writeln!(f, "Max texture size: {}", self.max_texture_size)?;
+        writeln!(f, "Texture units: {}", self.max_texture_image_units)?;
+        write!(
+            f,
+            "WebGPU: {}",
+            if self.webgpu_available {
+                "available"
+            } else {
+                "not available"
+            }
+        )
+    }
+}
+
 impl Textures {
     fn new(gl: &WebGl2RenderingContext) -> Result<Textures, JsValue> {
         let num_textures =
@@ -141,6 +227,31 @@ impl Textures {
     }
 }
 
+impl FrameLimiter {
+    // The frame rate cannot be set below this, so that the lines received
+    // between two frames always fit in the waterfall texture.
+    const MIN_FRAME_RATE: f32 = 1.0;
+    // Tolerance in milliseconds for the jitter of the requestAnimationFrame
+    // timestamps, which would otherwise cause frames to be skipped when the
+    // maximum frame rate divides the display refresh rate.
+    const TOLERANCE: f64 = 1.0;
+
+    fn set_max_frame_rate(&mut self, rate: Option<f32>) {
+        self.min_interval = rate.map(|rate| 1e3 / f64::from(rate.max(Self::MIN_FRAME_RATE)));
+    }
+
+    fn frame_due(&mut self, timestamp: f64) -> bool {
+        let due = match (self.min_interval, self.last_frame) {
+            (Some(interval), Some(last)) => timestamp - last >= interval - Self::TOLERANCE,
+            _ => true,
+        };
+        if due {
+            self.last_frame = Some(timestamp);
+        }
+        due
+    }
+}
+
 impl Current {
     fn new(gl: &WebGl2RenderingContext) -> Result<Current, JsValue> {
         Ok(Current {
@@ -175,7 +286,7 @@ mod render_engine {
                 .alpha(false)
                 .antialias(true)
                 .power_preference(web_sys::WebGlPowerPreference::LowPower);
-            let canvas_dims = CanvasDims::from_canvas_and_window(&canvas, &window);
+            let canvas_dims = CanvasDims::from_canvas_and_window(&canvas, &window, None);
             let current = Current::new(&gl)?;
 
             // We use pre-multiplied alpha to obtain correct results with bilinear
@@ -195,6 +306,8 @@ mod render_engine {
                 current,
                 objects: Vec::new(),
                 text_render: TextRender::new(document)?,
+                frame_limiter: FrameLimiter::default(),
+                max_pixel_ratio: None,
             })
         }
 
@@ -214,6 +327,45 @@ mod render_engine {
             Ok(())
         }
 
+        /// Sets the maximum frame rate.
+        ///
+        /// The rate is given in frames per second. If it is `None`, there is
+        /// no limit and a frame can be rendered on each animation frame. The
+        /// maximum frame rate cannot be lower than 1 frame per second.
+        ///
+        /// The frame rate only limits how often the scene is rendered. Data can
+        /// still be added to the render objects at any rate, and it is shown
+        /// in the next frame that is rendered.
+        pub fn set_max_frame_rate(&mut self, rate: Option<f32>) {
+            self.frame_limiter.set_max_frame_rate(rate);
+        }
+
+        /// Sets the maximum device pixel ratio.
+        ///
+        /// The device pixel ratio used to size the canvas backing store is
+        /// capped to this value, so that on high density displays a lower
+        /// resolution can be used to render faster. If it is `None`, the
+        /// device pixel ratio of the display is used.
+        ///
+        /// The new maximum is applied on the next call to
+        /// [`RenderEngine::resize_canvas`].
+        pub fn set_max_pixel_ratio(&mut self, ratio: Option<f64>) {
+            self.max_pixel_ratio = ratio;
+        }
+
+        /// Returns `true` if a frame should be rendered now.
+        ///
+        /// This function should be called on each animation frame, with the
+        /// timestamp given by `requestAnimationFrame()`, in milliseconds. It
+        /// returns `false` if rendering a frame would exceed the maximum frame
+        /// rate set with [`RenderEngine::set_max_frame_rate`]. Since animation
+        /// frames keep being requested when frames are skipped, the latest
+        /// state of the scene is always rendered after at most one frame
+        /// interval.
+        pub fn frame_due(&mut self, timestamp: f64) -> bool {
+            self.frame_limiter.frame_due(timestamp)
+        }
+
         /// Compiles a WebGL2 program.
         ///
         /// This function compiles the vertex and fragment shaders given in
@@ -301,6 +453,58 @@ mod render_engine {
             TextureBuilder::new(self)
         }
 
+        /// Returns the HTML canvas element used for the render output.
+        pub fn canvas(&self) -> &HtmlCanvasElement {
+            &self.canvas
+        }
+
+        /// Returns the capabilities of the browser and the WebGL2 context.
+        pub fn capabilities(&self) -> Result<Capabilities, JsValue> {
+            // Constants of the WEBGL_debug_renderer_info extension
+            const UNMASKED_VENDOR_WEBGL: u32 = 0x9245;
+            const UNMASKED_RENDERER_WEBGL: u32 = 0x9246;
+            let get_string = |parameter| -> Result<String, JsValue> {
+                Ok(self
+                    .gl
+                    .get_parameter(parameter)?
+                    .as_string()
+                    .unwrap_or_default())
+            };
+            let get_u32 = |parameter| -> Result<u32, JsValue> {
+                Ok(self
+                    .gl
+                    .get_parameter(parameter)?
+                    .as_f64()
+                    .unwrap_or_default() as u32)
+            };
+            let debug_info = self
+                .gl
+                .get_extension("WEBGL_debug_renderer_info")?
+                .is_some();
+            let (vendor, renderer) = if debug_info {
+                (UNMASKED_VENDOR_WEBGL, UNMASKED_RENDERER_WEBGL)
+            } else {
+                (
+                    WebGl2RenderingContext::VENDOR,
+                    WebGl2RenderingContext::RENDERER,
+                )
+            };
+            let webgpu_available =
+                js_sys::Reflect::get(&self.window.navigator(), &JsValue::from_str("gpu"))
+                    .map_or(false, |gpu| !gpu.is_undefined() && !gpu.is_null());
+            Ok(Capabilities {
+                version: get_string(WebGl2RenderingContext::VERSION)?,
+                shading_language_version: get_string(
+                    WebGl2RenderingContext::SHADING_LANGUAGE_VERSION,
+                )?,
+                vendor: get_string(vendor)?,
+                renderer: get_string(renderer)?,
+                max_texture_size: get_u32(WebGl2RenderingContext::MAX_TEXTURE_SIZE)?,
+                max_texture_image_units: get_u32(WebGl2RenderingContext::MAX_TEXTURE_IMAGE_UNITS)?,
+                webgpu_available,
+            })
+        }
+
         /// Returns the current canvas dimensions.
         pub fn canvas_dims(&self) -> CanvasDims {
             self.canvas_dims
@@ -313,7 +517,11 @@ mod render_engine {
         /// called whenever any of these parameters change, in order to update
         /// the render engine accordingly.
         pub fn resize_canvas(&mut self) -> Result<(), JsValue> {
-            self.canvas_dims = CanvasDims::from_canvas_and_window(&self.canvas, &self.window);
+            self.canvas_dims = CanvasDims::from_canvas_and_window(
+                &self.canvas,
+                &self.window,
+                self.max_pixel_ratio,
+            );
             self.canvas_dims.set_canvas(&self.canvas)?;
             self.canvas_dims.set_viewport(&self.gl);
             Ok(())
@@ -432,3 +640,42 @@ impl Current {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn frame_limiter() {
+        let mut limiter = FrameLimiter::default();
+        assert!(limiter.frame_due(0.0));
+        assert!(limiter.frame_due(1.0));
+        limiter.set_max_frame_rate(Some(20.0));
+        assert!(limiter.frame_due(16.7));
+        assert!(!limiter.frame_due(33.4));
+        assert!(!limiter.frame_due(50.1));
+        // 66.8 - 16.7 is slightly above 50 ms
+        assert!(limiter.frame_due(66.8));
+        // Jitter of the timestamps is tolerated
+        assert!(limiter.frame_due(116.3));
+        limiter.set_max_frame_rate(None);
+        assert!(limiter.frame_due(117.0));
+    }
+
+    #[test]
+    fn capped_pixel_ratio() {
+        assert_eq!(CanvasDims::capped_pixel_ratio(3.0, None), 3.0);
+        assert_eq!(CanvasDims::capped_pixel_ratio(3.0, Some(1.5)), 1.5);
+        assert_eq!(CanvasDims::capped_pixel_ratio(1.0, Some(1.5)), 1.0);
+        assert_eq!(CanvasDims::capped_pixel_ratio(2.0, Some(0.0)), 2.0);
+    }
+
+    #[test]
+    fn frame_limiter_minimum_rate() {
+        let mut limiter = FrameLimiter::default();
+        limiter.set_max_frame_rate(Some(0.01));
+        assert!(limiter.frame_due(0.0));
+        assert!(!limiter.frame_due(500.0));
+        assert!(limiter.frame_due(1000.0));
+    }
+}
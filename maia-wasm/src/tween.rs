@@ -0,0 +1,289 @@
+//! Tweened animations.
+//!
+//! This module contains the easing curves and the tween helper that are
+//! shared by all the animated transitions of the waterfall, such as the
+//! navigation jumps and the zoom and pan buttons, as well as the momentum
+//! helper used to continue a gesture after it ends.
+
+use serde::{Deserialize, Serialize};
+
+/// Easing curve.
+///
+/// The easing curve maps the elapsed fraction of the duration of an
+/// animation to the fraction of the change that has been applied.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum Easing {
+    /// No animation.
+    ///
+    /// The change is applied immediately.
+    None,
+    /// Constant speed.
+    Linear,
+    /// Cubic curve that accelerates at the beginning and decelerates at the
+    /// end.
+    EaseInOut,
+    /// Cubic curve that decelerates at the end.
+    EaseOut,
+}
+
+impl Easing {
+    /// Evaluates the easing curve.
+    ///
+    /// The fraction `t` is clamped to the interval [0, 1]. The curve gives 0
+    /// for `t = 0` and 1 for `t = 1`.
+    pub fn apply(&self, t: f32) -> f32 {
+        let t = if t.is_nan() { 1.0 } else { t.clamp(0.0, 1.0) };
+        match self {
+            Easing::None => 1.0,
+            Easing::Linear => t,
+            Easing::EaseInOut => {
+                if t < 0.5 {
+                    4.0 * t * t * t
+                } else {
+                    1.0 - 0.5 * (2.0 - 2.0 * t).powi(3)
+                }
+            }
+            Easing::EaseOut => 1.0 - (1.0 - t).powi(3),
+        }
+    }
+}
+
+impl std::str::FromStr for Easing {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Easing, ()> {
+        Ok(match s {
+            "None" => Easing::None,
+            "Linear" => Easing::Linear,
+            "Ease in-out" => Easing::EaseInOut,
+            "Ease out" => Easing::EaseOut,
+            _ => return Err(()),
+        })
+    }
+}
+
+impl std::fmt::Display for Easing {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        write!(
+            f,
+            "{}",
+            match self {
+                Easing::None => "None",
+                Easing::Linear => "Linear",
+                Easing::EaseInOut => "Ease in-out",
+                Easing::EaseOut => "Ease out",
+            }
+        )
+    }
+}
+
+/// Tween.
+///
+/// A tween describes an animation between two values of type `T` that starts
+/// at a given time and lasts a given duration. Times are given in
+/// milliseconds, using the same clock for all of them (typically
+/// `performance.now()` or the timestamp of a `requestAnimationFrame`
+/// callback).
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Tween<T> {
+    from: T,
+    to: T,
+    start_time: f64,
+    duration: f64,
+    easing: Easing,
+}
+
+impl<T: Copy> Tween<T> {
+    /// Creates a new tween.
+    pub fn new(from: T, to: T, start_time: f64, duration: f64, easing: Easing) -> Tween<T> {
+        Tween {
+            from,
+            to,
+            start_time,
+            duration,
+            easing,
+        }
+    }
+
+    /// Returns the initial value.
+    pub fn from(&self) -> T {
+        self.from
+    }
+
+    /// Returns the final value.
+    pub fn to(&self) -> T {
+        self.to
+    }
+
+    /// Returns the eased progress of the animation at time `now`.
+    ///
+    /// The progress is 0 at the start of the animation and 1 when it has
+    /// finished.
+    pub fn progress(&self, now: f64) -> f32 {
+        if self.duration <= 0.0 {
+            return 1.0;
+        }
+        let t = ((now - self.start_time) / self.duration) as f32;
+        self.easing.apply(t)
+    }
+
+    /// Returns `true` if the animation has finished at time `now`.
+    pub fn is_finished(&self, now: f64) -> bool {
+        self.easing == Easing::None || now >= self.start_time + self.duration
+    }
+}
+
+/// Momentum.
+///
+/// Momentum describes a motion whose rate decays exponentially with time, as
+/// if slowed down by friction. It is used to continue a gesture after the
+/// pointers are released. Times are given in milliseconds, as in [`Tween`],
+/// and the rate is given in units per millisecond.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Momentum {
+    rate: f64,
+    last_time: f64,
+    time_constant: f64,
+}
+
+impl Momentum {
+    // Rate below which the motion is considered to have stopped, in units per
+    // millisecond.
+    const MIN_RATE: f64 = 1e-5;
+
+    /// Creates a new momentum.
+    ///
+    /// The motion starts at `start_time` with an initial `rate`, which decays
+    /// with the time constant `time_constant`.
+    pub fn new(rate: f64, start_time: f64, time_constant: f64) -> Momentum {
+        Momentum {
+            rate,
+            last_time: start_time,
+            time_constant,
+        }
+    }
+
+    /// Advances the motion to time `now`.
+    ///
+    /// Returns the displacement since the previous call (or since the start
+    /// of the motion). The displacement is zero if `now` is not later than
+    /// the previous time.
+    pub fn step(&mut self, now: f64) -> f64 {
+        let elapsed = now - self.last_time;
+        if elapsed.is_nan() || elapsed <= 0.0 || self.time_constant <= 0.0 {
+            return 0.0;
+        }
+        let decay = (-elapsed / self.time_constant).exp();
+        // Integral of the decaying rate over the elapsed time
+        let displacement = self.rate * self.time_constant * (1.0 - decay);
+        self.rate *= decay;
+        self.last_time = now;
+        displacement
+    }
+
+    /// Returns `true` if the motion has stopped.
+    pub fn is_finished(&self) -> bool {
+        !(self.rate.abs() >= Self::MIN_RATE) || self.time_constant <= 0.0
+    }
+}
+
+/// Linear interpolation between `a` and `b`.
+///
+/// The result is `a` for `t = 0` and `b` for `t = 1`.
+pub fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const EASINGS: [Easing; 3] = [Easing::Linear, Easing::EaseInOut, Easing::EaseOut];
+
+    #[test]
+    fn easing_endpoints() {
+        for easing in EASINGS {
+            assert_eq!(easing.apply(0.0), 0.0);
+            assert_eq!(easing.apply(1.0), 1.0);
+            assert_eq!(easing.apply(-0.5), 0.0);
+            assert_eq!(easing.apply(1.5), 1.0);
+            assert_eq!(easing.apply(f32::NAN), 1.0);
+        }
+        assert_eq!(Easing::None.apply(0.0), 1.0);
+    }
+
+    #[test]
+    fn easing_monotonic() {
+        for easing in EASINGS {
+            let mut previous = 0.0;
+            for j in 1..=100 {
+                let value = easing.apply(j as f32 / 100.0);
+                assert!(value >= previous);
+                previous = value;
+            }
+        }
+    }
+
+    #[test]
+    fn ease_in_out_symmetric() {
+        assert!((Easing::EaseInOut.apply(0.5) - 0.5).abs() < 1e-6);
+        for j in 0..=50 {
+            let t = j as f32 / 100.0;
+            let a = Easing::EaseInOut.apply(t);
+            let b = Easing::EaseInOut.apply(1.0 - t);
+            assert!((a + b - 1.0).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn tween_progress() {
+        let tween = Tween::new(0.0, 10.0, 100.0, 200.0, Easing::Linear);
+        assert_eq!(tween.progress(100.0), 0.0);
+        assert_eq!(tween.progress(200.0), 0.5);
+        assert_eq!(tween.progress(400.0), 1.0);
+        assert!(!tween.is_finished(299.0));
+        assert!(tween.is_finished(300.0));
+        assert_eq!(lerp(tween.from(), tween.to(), tween.progress(200.0)), 5.0);
+        let tween = Tween::new(0.0, 10.0, 100.0, 200.0, Easing::None);
+        assert!(tween.is_finished(100.0));
+        assert_eq!(tween.progress(100.0), 1.0);
+    }
+
+    #[test]
+    fn easing_names() {
+        for easing in [
+            Easing::None,
+            Easing::Linear,
+            Easing::EaseInOut,
+            Easing::EaseOut,
+        ] {
+            assert_eq!(easing.to_string().parse::<Easing>(), Ok(easing));
+        }
+    }
+
+    #[test]
+    fn momentum_total_displacement() {
+        let mut momentum = Momentum::new(0.01, 0.0, 200.0);
+        let mut total = 0.0;
+        let mut now = 0.0;
+        while !momentum.is_finished() {
+            now += 16.7;
+            total += momentum.step(now);
+        }
+        // The total displacement is the initial rate times the time constant,
+        // regardless of the frame times.
+        assert!((total - 2.0).abs() < 0.01);
+        assert!(now < 2000.0);
+        assert_eq!(momentum.step(now), 0.0);
+    }
+
+    #[test]
+    fn momentum_invalid() {
+        let mut momentum = Momentum::new(0.01, 100.0, 200.0);
+        assert_eq!(momentum.step(50.0), 0.0);
+        assert_eq!(momentum.step(f64::NAN), 0.0);
+        assert!(!momentum.is_finished());
+        assert!(Momentum::new(f64::NAN, 0.0, 200.0).is_finished());
+        assert!(Momentum::new(0.01, 0.0, 0.0).is_finished());
+    }
+}
@@ -5,29 +5,48 @@
 //! other operations that are performed client-side (such as changing the
 //! waterfall levels or colormap).
 
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
 use wasm_bindgen::{closure::Closure, JsCast, JsValue};
 use wasm_bindgen_futures::{future_to_promise, JsFuture};
 use web_sys::{
-    Document, HtmlButtonElement, HtmlDialogElement, HtmlInputElement, HtmlSelectElement, Response,
-    Window,
+    Document, HtmlButtonElement, HtmlCanvasElement, HtmlDialogElement, HtmlElement,
+    HtmlInputElement, HtmlOptionElement, HtmlSelectElement, Response, Window,
 };
 
 use crate::render::RenderEngine;
-use crate::waterfall::Waterfall;
+use crate::waterfall::{Cursor, Waterfall};
+use crate::waterfall_interaction::{Action, Keymap, WaterfallInteraction};
 
 use active::IsElementActive;
-use input::{EnumInput, InputElement, NumberInput, TextInput};
+use input::{CheckboxInput, EnumInput, InputElement, NumberInput, TextInput};
 use patch::json_patch;
 
+pub use number_format::{DecimalSeparator, FrequencyUnit, NumberFormat, TimeUnit};
+
 mod active;
-mod colormap;
+mod annotations;
+mod calibration;
+mod clipping;
+pub(crate) mod colormap;
+mod display_presets;
+mod export;
 mod input;
 #[macro_use]
 mod macros;
+mod number_format;
 mod patch;
 mod preferences;
+mod processing;
+mod profiles;
+pub(crate) mod scan;
+mod scan_plot;
+mod session;
+mod storage;
+mod strip_chart;
+mod tone;
+mod tuning_knob;
+mod view_log;
 
 const API_URL: &str = "/api";
 const AD9361_URL: &str = "/api/ad9361";
@@ -35,6 +54,7 @@ const SPECTROMETER_URL: &str = "/api/spectrometer";
 const RECORDER_URL: &str = "/api/recorder";
 const RECORDING_METADATA_URL: &str = "/api/recording/metadata";
 const TIME_URL: &str = "/api/time";
+const HEALTH_URL: &str = "/api/health";
 
 /// User interface.
 ///
@@ -46,8 +66,33 @@ pub struct Ui {
     document: Rc<Document>,
     elements: Elements,
     preferences: Rc<RefCell<preferences::Preferences>>,
+    profiles: Rc<RefCell<profiles::Profiles>>,
+    display_presets: Rc<RefCell<display_presets::DisplayPresets>>,
     render_engine: Rc<RefCell<RenderEngine>>,
     waterfall: Rc<RefCell<Waterfall>>,
+    toast_timeout: Rc<Cell<Option<i32>>>,
+    scan_state: Rc<Cell<scan::ScanState>>,
+    scan_steps: Rc<RefCell<Vec<scan::ScanStep>>>,
+    scan_plot: Rc<scan_plot::ScanPlot>,
+    annotations: Rc<annotations::Annotations>,
+    tone: Rc<RefCell<Option<tone::Tone>>>,
+    tone_interval: Rc<Cell<Option<i32>>>,
+    perf_overlay_interval: Rc<Cell<Option<i32>>>,
+    cursor_readout_interval: Rc<Cell<Option<i32>>>,
+    number_format: Rc<Cell<NumberFormat>>,
+    reticle_readout_interval: Rc<Cell<Option<i32>>>,
+    power_history_chart: Rc<strip_chart::StripChart>,
+    power_history_interval: Rc<Cell<Option<i32>>>,
+    tuning_knob: Rc<RefCell<tuning_knob::TuningKnob>>,
+    tuning_knob_interval: Rc<Cell<Option<i32>>>,
+    view_log: Rc<RefCell<view_log::ViewLog>>,
+    view_log_activity: Rc<Cell<view_log::Activity>>,
+    // RX LO frequency before the last large retune, and timeout handle to
+    // hide the undo toast.
+    retune_undo: Rc<Cell<Option<(u64, i32)>>>,
+    clipping_detector: Rc<RefCell<clipping::ClippingDetector>>,
+    keymap: Rc<RefCell<Keymap>>,
+    calibration: Rc<RefCell<calibration::Calibration>>,
 }
 
 // Defines the 'struct Elements' and its constructor
@@ -58,8 +103,75 @@ ui_elements! {
     recording_dialog: HtmlDialogElement => Rc<HtmlDialogElement>,
     waterfall_min: HtmlInputElement => NumberInput<f32>,
     waterfall_max: HtmlInputElement => NumberInput<f32>,
+    auto_range_button: HtmlButtonElement => Rc<HtmlButtonElement>,
+    waterfall_gamma: HtmlInputElement => NumberInput<f32>,
+    waterfall_persistence: HtmlInputElement => NumberInput<f32>,
+    max_frame_rate: HtmlInputElement => NumberInput<f32>,
+    max_pixel_ratio: HtmlInputElement => NumberInput<f64>,
+    line_height: HtmlInputElement => NumberInput<u32>,
+    view_span: HtmlInputElement => NumberInput<f64, input::KHzPresentation>,
+    keymap: HtmlInputElement => TextInput,
+    perf_overlay: HtmlInputElement => CheckboxInput,
+    perf_overlay_text: HtmlElement => Rc<HtmlElement>,
+    shortcut_help: HtmlElement => Rc<HtmlElement>,
+    spectral_interpolation: HtmlInputElement => CheckboxInput,
+    linear_light_colormap: HtmlInputElement => CheckboxInput,
+    freeze_on_measurement: HtmlInputElement => CheckboxInput,
+    over_range_color_enabled: HtmlInputElement => CheckboxInput,
+    over_range_color: HtmlInputElement => TextInput,
+    under_range_color_enabled: HtmlInputElement => CheckboxInput,
+    under_range_color: HtmlInputElement => TextInput,
+    reticle: HtmlInputElement => CheckboxInput,
+    reticle_color: HtmlInputElement => TextInput,
+    bin_grid: HtmlInputElement => CheckboxInput,
+    smooth_scroll: HtmlInputElement => CheckboxInput,
+    frequency_axis_flipped: HtmlInputElement => CheckboxInput,
+    animation_easing: HtmlSelectElement => EnumInput<crate::tween::Easing>,
+    decimal_separator: HtmlSelectElement => EnumInput<DecimalSeparator>,
+    frequency_unit: HtmlSelectElement => EnumInput<FrequencyUnit>,
+    time_unit: HtmlSelectElement => EnumInput<TimeUnit>,
+    snap_zoom: HtmlInputElement => CheckboxInput,
+    overscan: HtmlInputElement => NumberInput<f32>,
+    view_sync: HtmlInputElement => CheckboxInput,
     ad9361_rx_lo_frequency: HtmlInputElement
         => NumberInput<u64, input::MHzPresentation>,
+    rx_lo_wheel_step: HtmlInputElement => NumberInput<u32, input::KHzPresentation>,
+    rx_lo_keep_visible: HtmlInputElement => CheckboxInput,
+    rx_lo_keep_visible_margin: HtmlInputElement => NumberInput<f32>,
+    retune_guard: HtmlInputElement => NumberInput<f32>,
+    max_retune_step: HtmlInputElement => NumberInput<u64, input::MHzPresentation>,
+    tuning_knob_enabled: HtmlInputElement => CheckboxInput,
+    tuning_knob_step: HtmlInputElement => NumberInput<u32, input::KHzPresentation>,
+    tone_enabled: HtmlInputElement => CheckboxInput,
+    tone_mapping: HtmlSelectElement => EnumInput<tone::ToneMapping>,
+    tone_min_db: HtmlInputElement => NumberInput<f32>,
+    tone_max_db: HtmlInputElement => NumberInput<f32>,
+    comb_origin: HtmlInputElement => NumberInput<u64, input::MHzPresentation>,
+    comb_spacing: HtmlInputElement => NumberInput<u32, input::KHzPresentation>,
+    comb_count: HtmlInputElement => NumberInput<u32>,
+    peak_tracking: HtmlInputElement => CheckboxInput,
+    peak_tracking_hysteresis: HtmlInputElement => NumberInput<f32>,
+    peak_tracking_min_snr: HtmlInputElement => NumberInput<f32>,
+    dc_exclusion_bins: HtmlInputElement => NumberInput<u32>,
+    scan_start: HtmlInputElement => NumberInput<u64, input::MHzPresentation>,
+    scan_stop: HtmlInputElement => NumberInput<u64, input::MHzPresentation>,
+    scan_step: HtmlInputElement => NumberInput<u32, input::KHzPresentation>,
+    scan_dwell: HtmlInputElement => NumberInput<u32>,
+    scan_edge_mask: HtmlInputElement => NumberInput<f32>,
+    scan_button: HtmlButtonElement => Rc<HtmlButtonElement>,
+    scan_pause_button: HtmlButtonElement => Rc<HtmlButtonElement>,
+    scan_status: HtmlElement => Rc<HtmlElement>,
+    scan_results_button: HtmlButtonElement => Rc<HtmlButtonElement>,
+    scan_plot_dialog: HtmlDialogElement => Rc<HtmlDialogElement>,
+    scan_plot_canvas: HtmlCanvasElement => Rc<HtmlCanvasElement>,
+    scan_plot_log_frequency: HtmlInputElement => CheckboxInput,
+    scan_export_csv_button: HtmlButtonElement => Rc<HtmlButtonElement>,
+    scan_export_png_button: HtmlButtonElement => Rc<HtmlButtonElement>,
+    annotation_canvas: HtmlCanvasElement => Rc<HtmlCanvasElement>,
+    annotate_button: HtmlButtonElement => Rc<HtmlButtonElement>,
+    annotation_tool: HtmlSelectElement => EnumInput<annotations::AnnotationTool>,
+    annotation_color: HtmlInputElement => TextInput,
+    annotation_clear_button: HtmlButtonElement => Rc<HtmlButtonElement>,
     ad9361_sampling_frequency: HtmlInputElement
         => NumberInput<u32, input::MHzPresentation>,
     ad9361_rx_rf_bandwidth: HtmlInputElement
@@ -72,6 +184,68 @@ ui_elements! {
     recording_metadata_description: HtmlInputElement => TextInput,
     recording_metadata_author: HtmlInputElement => TextInput,
     recorder_mode: HtmlSelectElement => EnumInput<maia_json::RecorderMode>,
+    noise_floor: HtmlElement => Rc<HtmlElement>,
+    clipping_threshold: HtmlInputElement => NumberInput<f32>,
+    calibrated_power: HtmlInputElement => CheckboxInput,
+    calibration_offset: HtmlInputElement => NumberInput<f32>,
+    calibration_extrapolation: HtmlSelectElement => EnumInput<calibration::Extrapolation>,
+    calibration_table_load_button: HtmlButtonElement => Rc<HtmlButtonElement>,
+    calibration_table_clear_button: HtmlButtonElement => Rc<HtmlButtonElement>,
+    calibration_table_file: HtmlInputElement => Rc<HtmlInputElement>,
+    noise_floor_unit: HtmlElement => Rc<HtmlElement>,
+    clipping_warning: HtmlElement => Rc<HtmlElement>,
+    health_status: HtmlElement => Rc<HtmlElement>,
+    processing_status: HtmlElement => Rc<HtmlElement>,
+    export_button: HtmlButtonElement => Rc<HtmlButtonElement>,
+    export_clipboard_button: HtmlButtonElement => Rc<HtmlButtonElement>,
+    export_properties_button: HtmlButtonElement => Rc<HtmlButtonElement>,
+    export_dialog: HtmlDialogElement => Rc<HtmlDialogElement>,
+    export_overlay_text: HtmlInputElement => TextInput,
+    export_overlay_position: HtmlSelectElement => EnumInput<export::OverlayPosition>,
+    export_overlay_font_size: HtmlInputElement => NumberInput<u32>,
+    toast: HtmlElement => Rc<HtmlElement>,
+    ruler_readout: HtmlElement => Rc<HtmlElement>,
+    cursor_readout: HtmlElement => Rc<HtmlElement>,
+    reticle_readout: HtmlElement => Rc<HtmlElement>,
+    power_history: HtmlElement => Rc<HtmlElement>,
+    power_history_title: HtmlElement => Rc<HtmlElement>,
+    power_history_canvas: HtmlCanvasElement => Rc<HtmlCanvasElement>,
+    power_history_window: HtmlInputElement => NumberInput<u32>,
+    power_history_clear_button: HtmlButtonElement => Rc<HtmlButtonElement>,
+    power_history_close_button: HtmlButtonElement => Rc<HtmlButtonElement>,
+    safe_area: HtmlElement => Rc<HtmlElement>,
+    retune_undo: HtmlElement => Rc<HtmlElement>,
+    retune_undo_text: HtmlElement => Rc<HtmlElement>,
+    retune_undo_button: HtmlButtonElement => Rc<HtmlButtonElement>,
+    session_save_button: HtmlButtonElement => Rc<HtmlButtonElement>,
+    session_load_button: HtmlButtonElement => Rc<HtmlButtonElement>,
+    session_file: HtmlInputElement => Rc<HtmlInputElement>,
+    view_log_record_button: HtmlButtonElement => Rc<HtmlButtonElement>,
+    view_log_replay_button: HtmlButtonElement => Rc<HtmlButtonElement>,
+    view_log_speed: HtmlInputElement => NumberInput<f64>,
+    view_log_export_button: HtmlButtonElement => Rc<HtmlButtonElement>,
+    view_log_import_button: HtmlButtonElement => Rc<HtmlButtonElement>,
+    view_log_file: HtmlInputElement => Rc<HtmlInputElement>,
+    profile_select: HtmlSelectElement => Rc<HtmlSelectElement>,
+    profile_apply_button: HtmlButtonElement => Rc<HtmlButtonElement>,
+    profile_save_button: HtmlButtonElement => Rc<HtmlButtonElement>,
+    profile_delete_button: HtmlButtonElement => Rc<HtmlButtonElement>,
+    profile_export_button: HtmlButtonElement => Rc<HtmlButtonElement>,
+    profile_import_button: HtmlButtonElement => Rc<HtmlButtonElement>,
+    profile_file: HtmlInputElement => Rc<HtmlInputElement>,
+    display_preset_select: HtmlSelectElement => Rc<HtmlSelectElement>,
+    display_preset_apply_button: HtmlButtonElement => Rc<HtmlButtonElement>,
+    display_preset_save_button: HtmlButtonElement => Rc<HtmlButtonElement>,
+    display_preset_delete_button: HtmlButtonElement => Rc<HtmlButtonElement>,
+    diagnostics_button: HtmlButtonElement => Rc<HtmlButtonElement>,
+    diagnostics_dialog: HtmlDialogElement => Rc<HtmlDialogElement>,
+    diagnostics_text: HtmlElement => Rc<HtmlElement>,
+    processing_button: HtmlButtonElement => Rc<HtmlButtonElement>,
+    processing_dialog: HtmlDialogElement => Rc<HtmlDialogElement>,
+    processing_text: HtmlElement => Rc<HtmlElement>,
+    compact_layout: HtmlInputElement => CheckboxInput,
+    controls: HtmlElement => Rc<HtmlElement>,
+    controls_button: HtmlButtonElement => Rc<HtmlButtonElement>,
 }
 
 impl Ui {
@@ -83,14 +257,49 @@ impl Ui {
         waterfall: Rc<RefCell<Waterfall>>,
     ) -> Result<Ui, JsValue> {
         let elements = Elements::new(&document)?;
-        let preferences = Rc::new(RefCell::new(preferences::Preferences::new(&window)?));
+        let scan_plot = scan_plot::ScanPlot::new(Rc::clone(&elements.scan_plot_canvas));
+        let annotations = annotations::Annotations::new(Rc::clone(&elements.annotation_canvas));
+        let power_history_chart = Rc::new(strip_chart::StripChart::new(Rc::clone(
+            &elements.power_history_canvas,
+        )));
+        let storage = Rc::new(storage::Storage::local(&window));
+        let preferences = preferences::Preferences::new(Rc::clone(&storage));
+        let preferences = Rc::new(RefCell::new(preferences));
+        let profiles = Rc::new(RefCell::new(profiles::Profiles::new(Rc::clone(&storage))));
+        let display_presets = Rc::new(RefCell::new(display_presets::DisplayPresets::new(
+            Rc::clone(&storage),
+        )));
+        let calibration = Rc::new(RefCell::new(calibration::Calibration::new(storage)));
         let ui = Ui {
             window,
             document,
             elements,
             preferences,
+            profiles,
+            display_presets,
             render_engine,
             waterfall,
+            toast_timeout: Rc::new(Cell::new(None)),
+            scan_state: Rc::new(Cell::new(scan::ScanState::Stopped)),
+            scan_steps: Rc::new(RefCell::new(Vec::new())),
+            scan_plot,
+            annotations,
+            tone: Rc::new(RefCell::new(None)),
+            tone_interval: Rc::new(Cell::new(None)),
+            perf_overlay_interval: Rc::new(Cell::new(None)),
+            cursor_readout_interval: Rc::new(Cell::new(None)),
+            number_format: Rc::new(Cell::new(NumberFormat::default())),
+            reticle_readout_interval: Rc::new(Cell::new(None)),
+            power_history_chart,
+            power_history_interval: Rc::new(Cell::new(None)),
+            tuning_knob: Rc::new(RefCell::new(tuning_knob::TuningKnob::new())),
+            tuning_knob_interval: Rc::new(Cell::new(None)),
+            view_log: Rc::new(RefCell::new(view_log::ViewLog::new())),
+            view_log_activity: Rc::new(Cell::new(view_log::Activity::Idle)),
+            retune_undo: Rc::new(Cell::new(None)),
+            clipping_detector: Rc::new(RefCell::new(clipping::ClippingDetector::new())),
+            keymap: Rc::new(RefCell::new(Keymap::default())),
+            calibration,
         };
         ui.set_callbacks()?;
         ui.preferences.borrow().apply(&ui)?;
@@ -98,10 +307,20 @@ impl Ui {
     }
 
     fn set_callbacks(&self) -> Result<(), JsValue> {
-        self.resize_canvas()();
+        self.update_layout()?;
         self.window
             .set_onresize(Some(self.onresize().into_js_value().unchecked_ref()));
         self.set_api_get_periodic(1000)?;
+        self.set_health_get_periodic(10000)?;
+        self.scan_plot.set_callbacks();
+        self.annotations.set_callbacks();
+        self.update_profile_select(None)?;
+        self.update_display_preset_select(None)?;
+        self.update_view_log_buttons();
+        if self.has_url_param("perf") {
+            self.elements.perf_overlay.set(&true);
+            self.set_perf_overlay(true)?;
+        }
 
         set_on!(
             change,
@@ -109,7 +328,65 @@ impl Ui {
             colormap_select,
             waterfall_min,
             waterfall_max,
+            waterfall_gamma,
+            waterfall_persistence,
+            max_frame_rate,
+            max_pixel_ratio,
+            line_height,
+            view_span,
+            keymap,
+            perf_overlay,
+            spectral_interpolation,
+            linear_light_colormap,
+            freeze_on_measurement,
+            over_range_color_enabled,
+            over_range_color,
+            under_range_color_enabled,
+            under_range_color,
+            reticle,
+            reticle_color,
+            bin_grid,
+            smooth_scroll,
+            frequency_axis_flipped,
+            animation_easing,
+            decimal_separator,
+            frequency_unit,
+            time_unit,
+            snap_zoom,
+            overscan,
+            view_sync,
             ad9361_rx_lo_frequency,
+            rx_lo_wheel_step,
+            rx_lo_keep_visible,
+            rx_lo_keep_visible_margin,
+            retune_guard,
+            max_retune_step,
+            tuning_knob_enabled,
+            tuning_knob_step,
+            power_history_window,
+            clipping_threshold,
+            calibrated_power,
+            calibration_offset,
+            calibration_extrapolation,
+            tone_enabled,
+            tone_mapping,
+            tone_min_db,
+            tone_max_db,
+            comb_origin,
+            comb_spacing,
+            comb_count,
+            peak_tracking,
+            peak_tracking_hysteresis,
+            peak_tracking_min_snr,
+            dc_exclusion_bins,
+            scan_start,
+            scan_stop,
+            scan_step,
+            scan_dwell,
+            scan_edge_mask,
+            scan_plot_log_frequency,
+            annotation_tool,
+            annotation_color,
             ad9361_sampling_frequency,
             ad9361_rx_rf_bandwidth,
             ad9361_rx_gain,
@@ -118,25 +395,664 @@ impl Ui {
             recording_metadata_filename,
             recording_metadata_description,
             recording_metadata_author,
-            recorder_mode
+            recorder_mode,
+            export_overlay_text,
+            export_overlay_position,
+            export_overlay_font_size,
+            compact_layout,
+            session_file,
+            view_log_file,
+            profile_file,
+            display_preset_select,
+            calibration_table_file
         );
 
-        set_on!(click, self, recorder_button, recording_properties_button);
+        set_on!(
+            click,
+            self,
+            auto_range_button,
+            recorder_button,
+            recording_properties_button,
+            export_button,
+            export_clipboard_button,
+            export_properties_button,
+            controls_button,
+            session_save_button,
+            session_load_button,
+            view_log_record_button,
+            view_log_replay_button,
+            view_log_export_button,
+            view_log_import_button,
+            profile_apply_button,
+            profile_save_button,
+            profile_delete_button,
+            profile_export_button,
+            profile_import_button,
+            display_preset_apply_button,
+            display_preset_save_button,
+            display_preset_delete_button,
+            diagnostics_button,
+            processing_button,
+            scan_button,
+            scan_pause_button,
+            scan_results_button,
+            scan_export_csv_button,
+            scan_export_png_button,
+            annotate_button,
+            annotation_clear_button,
+            retune_undo_button,
+            power_history_clear_button,
+            power_history_close_button,
+            calibration_table_load_button,
+            calibration_table_clear_button
+        );
 
         Ok(())
     }
 
     /// Sets the value of the RX LO frequency UI element.
+    ///
+    /// The RX LO marker flashes when the device acknowledges the new
+    /// frequency, or in a different color if the retune fails.
     pub fn set_rx_lo_frequency(&self, freq: u64) -> Result<(), JsValue> {
         self.elements.ad9361_rx_lo_frequency.set(&freq);
-        self.elements
+        let request = self
+            .elements
             .ad9361_rx_lo_frequency
             .onchange()
             .unwrap()
             .call0(&JsValue::NULL)?;
+        self.confirm_rx_lo_retune(request);
+        Ok(())
+    }
+
+    // Flashes the RX LO marker once the retune request completes, so that the
+    // user can see whether the device has acknowledged the new frequency. The
+    // request is the value returned by the onchange closure of the RX LO
+    // frequency element, which is a promise that resolves when the PATCH
+    // request completes, or null if the frequency was rejected before making
+    // any request.
+    fn confirm_rx_lo_retune(&self, request: JsValue) {
+        let waterfall = Rc::clone(&self.waterfall);
+        let Ok(request) = request.dyn_into::<js_sys::Promise>() else {
+            // This can be called while the waterfall is borrowed, in which
+            // case the flash is skipped.
+            if let Ok(mut waterfall) = waterfall.try_borrow_mut() {
+                waterfall.flash_rx_lo_marker(false);
+            }
+            return;
+        };
+        let _ = future_to_promise(async move {
+            let result = JsFuture::from(request).await;
+            if let Ok(mut waterfall) = waterfall.try_borrow_mut() {
+                waterfall.flash_rx_lo_marker(result.is_ok());
+            }
+            result
+        });
+    }
+
+    /// Returns the RX LO frequency in Hz.
+    ///
+    /// This is the frequency shown in the UI, which is updated as soon as the
+    /// frequency is set, before the waterfall receives the new frequency from
+    /// the device. `None` is returned if the frequency is not known yet.
+    pub fn rx_lo_frequency(&self) -> Option<u64> {
+        self.elements.ad9361_rx_lo_frequency.get()
+    }
+
+    /// Tunes the RX LO frequency in response to a user interaction.
+    ///
+    /// This works as [`Ui::set_rx_lo_frequency`], but if a maximum retune
+    /// step is set, the change of frequency is limited to that step, moving
+    /// towards `freq`, and a toast shows the frequency that was set and the
+    /// step limit. The frequency that was actually set is returned.
+    pub fn tune_rx_lo_frequency(&self, freq: u64) -> Result<u64, JsValue> {
+        // The current frequency is taken from the UI element rather than from
+        // the waterfall, since this can be called while the waterfall is
+        // borrowed.
+        let current_freq = self.elements.ad9361_rx_lo_frequency.get();
+        let freq = match (self.max_retune_step(), current_freq) {
+            (Some(max_step), Some(current_freq)) if freq.abs_diff(current_freq) > max_step => {
+                let limited = if freq > current_freq {
+                    current_freq + max_step
+                } else {
+                    current_freq.saturating_sub(max_step)
+                };
+                let format = self.number_format();
+                self.show_toast(&format!(
+                    "RX freq {} (step limited to {})",
+                    format.frequency(limited as f64, FrequencyUnit::MHz, 3),
+                    format.frequency(max_step as f64, FrequencyUnit::MHz, 3)
+                ))?;
+                limited
+            }
+            _ => freq,
+        };
+        self.set_rx_lo_frequency(freq)?;
+        Ok(freq)
+    }
+
+    /// Tunes the RX LO frequency by a jump in response to a user interaction,
+    /// such as dragging the waterfall beyond the band edges.
+    ///
+    /// This works as [`Ui::tune_rx_lo_frequency`], but if the retune guard is
+    /// enabled and the frequency changes by more than the fraction of the band
+    /// set in it, a toast that allows undoing the retune is shown. The
+    /// frequency that was actually set is returned.
+    pub fn jump_rx_lo_frequency(&self, freq: u64) -> Result<u64, JsValue> {
+        // The current frequency and sampling rate are taken from the UI
+        // elements rather than from the waterfall, since this can be called
+        // while the waterfall is borrowed.
+        let current_freq = self.elements.ad9361_rx_lo_frequency.get();
+        let samp_rate = self.elements.ad9361_sampling_frequency.get();
+        let freq = self.tune_rx_lo_frequency(freq)?;
+        if let (Some(fraction), Some(current_freq), Some(samp_rate)) =
+            (self.retune_guard_fraction(), current_freq, samp_rate)
+        {
+            if freq.abs_diff(current_freq) as f64 > fraction * f64::from(samp_rate) {
+                self.show_retune_undo(current_freq, freq)?;
+            }
+        }
+        Ok(freq)
+    }
+
+    // Returns the maximum change of the RX LO frequency in a single retune, in
+    // Hz, or None if retunes are not limited.
+    fn max_retune_step(&self) -> Option<u64> {
+        self.elements
+            .max_retune_step
+            .get()
+            .filter(|&step| step != 0)
+    }
+
+    // Returns the fraction of the band above which retunes can be undone, or
+    // None if the retune guard is disabled.
+    fn retune_guard_fraction(&self) -> Option<f64> {
+        self.elements
+            .retune_guard
+            .get()
+            .filter(|&percent| percent > 0.0)
+            .map(|percent| f64::from(percent) / 100.0)
+    }
+
+    fn show_retune_undo(&self, previous_freq: u64, freq: u64) -> Result<(), JsValue> {
+        const DURATION_MS: i32 = 5000;
+        if let Some((_, handle)) = self.retune_undo.take() {
+            self.window.clear_timeout_with_handle(handle);
+        }
+        let format = self.number_format();
+        self.elements
+            .retune_undo_text
+            .set_text_content(Some(&format!(
+                "RX freq {} (was {})",
+                format.frequency(freq as f64, FrequencyUnit::MHz, 3),
+                format.frequency(previous_freq as f64, FrequencyUnit::MHz, 3)
+            )));
+        self.elements
+            .retune_undo
+            .class_list()
+            .toggle_with_force("retune_undo_visible", true)?;
+        let ui = self.clone();
+        let hide = Closure::once_into_js(move || {
+            ui.retune_undo.set(None);
+            ui.hide_retune_undo();
+        });
+        let handle = self
+            .window
+            .set_timeout_with_callback_and_timeout_and_arguments_0(
+                hide.unchecked_ref(),
+                DURATION_MS,
+            )?;
+        self.retune_undo.set(Some((previous_freq, handle)));
+        Ok(())
+    }
+
+    fn hide_retune_undo(&self) {
+        if let Err(e) = self
+            .elements
+            .retune_undo
+            .class_list()
+            .toggle_with_force("retune_undo_visible", false)
+        {
+            web_sys::console::error_1(&e);
+        }
+    }
+
+    fn retune_undo_button_onclick(&self) -> Closure<dyn Fn()> {
+        let ui = self.clone();
+        Closure::new(move || {
+            let Some((previous_freq, handle)) = ui.retune_undo.take() else {
+                return;
+            };
+            ui.window.clear_timeout_with_handle(handle);
+            ui.hide_retune_undo();
+            if let Err(e) = ui.set_rx_lo_frequency(previous_freq) {
+                web_sys::console::error_1(&e);
+            }
+        })
+    }
+
+    /// Selects the next colormap.
+    ///
+    /// The colormaps are cycled in the order in which they appear in the
+    /// colormap selector, wrapping around after the last one. The name of the
+    /// new colormap is shown briefly on screen.
+    pub fn cycle_colormap(&self) -> Result<(), JsValue> {
+        let colormap = self
+            .elements
+            .colormap_select
+            .get()
+            .unwrap_or(colormap::Colormap::Turbo)
+            .next();
+        self.select_colormap(colormap)?;
+        self.show_toast(&format!("Colormap: {colormap}"))
+    }
+
+    /// Selects a colormap by name.
+    ///
+    /// The name is one of those shown in the colormap selector, such as
+    /// `"Turbo"`. An error is returned if there is no colormap with this name.
+    pub fn set_colormap(&self, name: &str) -> Result<(), JsValue> {
+        let colormap = name
+            .parse()
+            .map_err(|_| format!("unknown colormap {name}"))?;
+        self.select_colormap(colormap)
+    }
+
+    fn select_colormap(&self, colormap: colormap::Colormap) -> Result<(), JsValue> {
+        self.elements.colormap_select.set(&colormap);
+        self.elements
+            .colormap_select
+            .onchange()
+            .unwrap()
+            .call0(&JsValue::NULL)?;
+        Ok(())
+    }
+
+    /// Sets the waterfall levels automatically.
+    ///
+    /// The minimum and maximum levels are computed once from the part of the
+    /// last spectrum lines that is visible, so that the noise floor is shown
+    /// dark and the strongest signals are not saturated. The levels are
+    /// rounded to whole dB and set in the UI as if the user had entered them.
+    /// The levels that have been set are returned, in dB. An error is returned
+    /// if no spectrum lines have been received yet.
+    pub fn auto_range(&self) -> Result<(f32, f32), JsValue> {
+        let (min, max) = self
+            .waterfall
+            .borrow()
+            .compute_auto_range()
+            .ok_or("no spectrum data to compute the levels")?;
+        // The level inputs only accept non-negative whole numbers.
+        let min = min.floor().max(0.0);
+        let max = max.ceil().max(min + 1.0);
+        for (element, value) in [
+            (&self.elements.waterfall_min, min),
+            (&self.elements.waterfall_max, max),
+        ] {
+            element.set(&value);
+            element.onchange().unwrap().call0(&JsValue::NULL)?;
+        }
+        Ok((min, max))
+    }
+
+    fn auto_range_button_onclick(&self) -> Closure<dyn Fn()> {
+        let ui = self.clone();
+        Closure::new(move || {
+            let result = match ui.auto_range() {
+                Ok((min, max)) => {
+                    let format = ui.number_format();
+                    ui.show_toast(&format!(
+                        "Levels {} to {} dB",
+                        format.number(f64::from(min), 0),
+                        format.number(f64::from(max), 0)
+                    ))
+                }
+                Err(e) => ui.show_toast(&e.as_string().unwrap_or_default()),
+            };
+            if let Err(e) = result {
+                web_sys::console::error_1(&e);
+            }
+        })
+    }
+
+    /// Shows a short message on screen.
+    ///
+    /// The message is hidden automatically after a short time.
+    pub fn show_toast(&self, text: &str) -> Result<(), JsValue> {
+        const DURATION_MS: i32 = 1500;
+        if let Some(handle) = self.toast_timeout.take() {
+            self.window.clear_timeout_with_handle(handle);
+        }
+        let toast = Rc::clone(&self.elements.toast);
+        toast.set_text_content(Some(text));
+        toast.set_class_name("toast_visible");
+        let hide = Closure::once_into_js(move || toast.set_class_name(""));
+        let handle = self
+            .window
+            .set_timeout_with_callback_and_timeout_and_arguments_0(
+                hide.unchecked_ref(),
+                DURATION_MS,
+            )?;
+        self.toast_timeout.set(Some(handle));
+        Ok(())
+    }
+
+    /// Shows the ruler measurement readout.
+    ///
+    /// The readout is shown next to the position given by `x` and `y` in CSS
+    /// pixels. It is moved to the other side of the position if it would not
+    /// fit within the safe area of the screen, which excludes notches and
+    /// rounded corners.
+    pub fn show_ruler_readout(&self, text: &str, x: i32, y: i32) -> Result<(), JsValue> {
+        // Offset in CSS pixels between the position and the readout, so that
+        // the readout is not hidden by the pointer.
+        const OFFSET: f64 = 12.0;
+        let readout = &self.elements.ruler_readout;
+        readout.set_text_content(Some(text));
+        readout.set_class_name("ruler_readout_visible");
+        // The size of the readout is only known once it is visible.
+        let size = (
+            f64::from(readout.offset_width()),
+            f64::from(readout.offset_height()),
+        );
+        let viewport = (
+            self.window.inner_width()?.as_f64().unwrap_or(0.0),
+            self.window.inner_height()?.as_f64().unwrap_or(0.0),
+        );
+        let [top, right, bottom, left] = self.safe_area_insets()?;
+        let place = |pos: f64, size: f64, start: f64, end: f64| {
+            let after = pos + OFFSET;
+            if after + size <= end {
+                after
+            } else {
+                (pos - OFFSET - size).max(start)
+            }
+        };
+        let style = readout.style();
+        style.set_property(
+            "left",
+            &format!(
+                "{}px",
+                place(f64::from(x), size.0, left, viewport.0 - right)
+            ),
+        )?;
+        style.set_property(
+            "top",
+            &format!(
+                "{}px",
+                place(f64::from(y), size.1, top, viewport.1 - bottom)
+            ),
+        )?;
+        Ok(())
+    }
+
+    // Returns the safe area insets (top, right, bottom, left) in CSS pixels.
+    // These are read from an invisible element whose padding is given by the
+    // CSS safe-area-inset environment variables.
+    fn safe_area_insets(&self) -> Result<[f64; 4], JsValue> {
+        let Some(style) = self.window.get_computed_style(&self.elements.safe_area)? else {
+            return Ok([0.0; 4]);
+        };
+        let mut insets = [0.0; 4];
+        for (inset, side) in insets.iter_mut().zip(["top", "right", "bottom", "left"]) {
+            let value = style.get_property_value(&format!("padding-{side}"))?;
+            *inset = value
+                .strip_suffix("px")
+                .and_then(|x| x.parse().ok())
+                .unwrap_or(0.0);
+        }
+        Ok(insets)
+    }
+
+    /// Hides the ruler measurement readout.
+    pub fn hide_ruler_readout(&self) {
+        self.elements.ruler_readout.set_class_name("");
+    }
+
+    /// Updates the readout of the measurement cursors.
+    ///
+    /// The readout shows the frequency and the power at each cursor that is
+    /// placed, and the differences between both cursors. If the passband is
+    /// placed, its center frequency, bandwidth and power are also shown. While
+    /// any cursor or the passband is placed, the readout is updated
+    /// periodically, so that it follows the power of the signals. The readout
+    /// is hidden when neither the cursors nor the passband are placed.
+    pub fn update_cursor_readout(&self) -> Result<(), JsValue> {
+        // Interval in milliseconds between updates of the readout.
+        const UPDATE_INTERVAL_MS: i32 = 250;
+        let text = self.cursor_readout_text();
+        let readout = &self.elements.cursor_readout;
+        readout.set_text_content(text.as_deref());
+        readout
+            .class_list()
+            .toggle_with_force("cursor_readout_visible", text.is_some())?;
+        match (text.is_some(), self.cursor_readout_interval.get()) {
+            (true, None) => {
+                let ui = self.clone();
+                let update = Closure::<dyn Fn()>::new(move || {
+                    if let Err(e) = ui.update_cursor_readout() {
+                        web_sys::console::error_1(&e);
+                    }
+                });
+                let handle = self
+                    .window
+                    .set_interval_with_callback_and_timeout_and_arguments_0(
+                        update.into_js_value().unchecked_ref(),
+                        UPDATE_INTERVAL_MS,
+                    )?;
+                self.cursor_readout_interval.set(Some(handle));
+            }
+            (false, Some(handle)) => {
+                self.window.clear_interval_with_handle(handle);
+                self.cursor_readout_interval.set(None);
+            }
+            _ => (),
+        }
         Ok(())
     }
 
+    /// Shows the power history of a frequency.
+    ///
+    /// The frequency is given in the same units as the waterfall center
+    /// frequency. A strip chart that plots the power of the frequency over
+    /// the time window set in the UI is shown, replacing any previous
+    /// history.
+    pub fn start_power_history(&self, frequency: f32) -> Result<(), JsValue> {
+        // Interval in milliseconds between updates of the chart.
+        const UPDATE_INTERVAL_MS: i32 = 100;
+        let window_ms = self.power_history_window_ms();
+        let frequency = {
+            let mut waterfall = self.waterfall.borrow_mut();
+            waterfall.start_power_history(frequency, window_ms);
+            let Some(history) = waterfall.get_power_history() else {
+                return Ok(());
+            };
+            history.frequency()
+        };
+        self.elements
+            .power_history_title
+            .set_text_content(Some(&format!(
+                "Power at {}",
+                self.number_format()
+                    .frequency(frequency, FrequencyUnit::MHz, 6)
+            )));
+        self.elements
+            .power_history
+            .class_list()
+            .add_1("power_history_visible")?;
+        if self.power_history_interval.get().is_none() {
+            let ui = self.clone();
+            let update = Closure::<dyn Fn()>::new(move || {
+                if let Err(e) = ui.draw_power_history() {
+                    web_sys::console::error_1(&e);
+                }
+            });
+            let handle = self
+                .window
+                .set_interval_with_callback_and_timeout_and_arguments_0(
+                    update.into_js_value().unchecked_ref(),
+                    UPDATE_INTERVAL_MS,
+                )?;
+            self.power_history_interval.set(Some(handle));
+        }
+        self.draw_power_history()
+    }
+
+    /// Hides the power history and stops recording it.
+    pub fn stop_power_history(&self) -> Result<(), JsValue> {
+        if let Some(handle) = self.power_history_interval.take() {
+            self.window.clear_interval_with_handle(handle);
+        }
+        self.waterfall.borrow_mut().stop_power_history();
+        self.elements
+            .power_history
+            .class_list()
+            .remove_1("power_history_visible")
+    }
+
+    fn draw_power_history(&self) -> Result<(), JsValue> {
+        self.power_history_chart.draw(
+            self.waterfall.borrow().get_power_history(),
+            self.elements.waterfall_min.get().unwrap_or(35.0),
+            self.elements.waterfall_max.get().unwrap_or(85.0),
+            &self.number_format(),
+        )
+    }
+
+    // Length of the time window of the power history, in milliseconds.
+    fn power_history_window_ms(&self) -> f64 {
+        f64::from(self.elements.power_history_window.get().unwrap_or(60)) * 1e3
+    }
+
+    fn power_history_window_onchange(&self) -> Closure<dyn Fn()> {
+        let ui = self.clone();
+        Closure::new(move || {
+            let element = &ui.elements.power_history_window;
+            if !element.report_validity() {
+                return;
+            }
+            let Some(value) = element.get() else {
+                ui.window
+                    .alert_with_message("Invalid value for power_history_window")
+                    .unwrap();
+                return;
+            };
+            ui.waterfall
+                .borrow_mut()
+                .set_power_history_window(ui.power_history_window_ms());
+            // try_borrow_mut prevents trying to update the preferences as a
+            // consequence of the Preferences::apply_client calling this
+            // closure
+            if let Ok(mut p) = ui.preferences.try_borrow_mut() {
+                if let Err(e) = p.update_power_history_window(&value) {
+                    web_sys::console::error_1(&e);
+                }
+            }
+        })
+    }
+
+    fn power_history_clear_button_onclick(&self) -> Closure<dyn Fn()> {
+        let ui = self.clone();
+        Closure::new(move || {
+            ui.waterfall.borrow_mut().clear_power_history();
+            if let Err(e) = ui.draw_power_history() {
+                web_sys::console::error_1(&e);
+            }
+        })
+    }
+
+    fn power_history_close_button_onclick(&self) -> Closure<dyn Fn()> {
+        let ui = self.clone();
+        Closure::new(move || {
+            if let Err(e) = ui.stop_power_history() {
+                web_sys::console::error_1(&e);
+            }
+        })
+    }
+
+    // Formats the readout of the measurement cursors, or returns None if no
+    // cursors are placed.
+    fn cursor_readout_text(&self) -> Option<String> {
+        let waterfall = self.waterfall.borrow();
+        let format = self.number_format();
+        let calibration = self.power_calibration();
+        let unit = if calibration.is_some() { "dBm" } else { "dB" };
+        let readings = [Cursor::A, Cursor::B].map(|cursor| {
+            let frequency = waterfall.get_cursor_frequency(cursor)?;
+            let power = waterfall
+                .get_cursor_power(cursor)
+                .map(|power| power + calibration.unwrap_or(0.0));
+            Some((frequency, power))
+        });
+        let mut lines = Vec::new();
+        for (name, reading) in ["A", "B"].iter().zip(&readings) {
+            if let Some((frequency, power)) = reading {
+                let power = match power {
+                    Some(power) => format!("{} {unit}", format.number(f64::from(*power), 1)),
+                    None => "--".to_string(),
+                };
+                lines.push(format!(
+                    "{name} {} {power}",
+                    format.frequency(*frequency, FrequencyUnit::MHz, 6)
+                ));
+            }
+        }
+        if let [Some((freq_a, power_a)), Some((freq_b, power_b))] = readings {
+            let mut line = format!(
+                "B-A Δf {}",
+                format.frequency_delta(freq_b - freq_a, FrequencyUnit::KHz, 3)
+            );
+            if let (Some(power_a), Some(power_b)) = (power_a, power_b) {
+                line.push_str(&format!(
+                    " ΔP {} dB",
+                    format.signed_number(f64::from(power_b - power_a), 1)
+                ));
+            }
+            lines.push(line);
+        }
+        if let Some((center, bandwidth)) = waterfall.get_passband() {
+            let power = match waterfall.get_passband_power() {
+                Some(power) => format!(
+                    "{} {unit}",
+                    format.number(f64::from(power + calibration.unwrap_or(0.0)), 1)
+                ),
+                None => "--".to_string(),
+            };
+            lines.push(format!(
+                "PB {} BW {} {power}",
+                format.frequency(center, FrequencyUnit::MHz, 6),
+                format.frequency(bandwidth, FrequencyUnit::KHz, 3)
+            ));
+        }
+        Some(lines.join("\n")).filter(|_| !lines.is_empty())
+    }
+
+    /// Returns the RX LO frequency step used for wheel tuning.
+    ///
+    /// The step is given in Hz. If wheel tuning is disabled, which is indicated
+    /// by setting the step to zero in the UI, `None` is returned.
+    pub fn rx_lo_wheel_step(&self) -> Option<u32> {
+        self.elements
+            .rx_lo_wheel_step
+            .get()
+            .filter(|&step| step != 0)
+    }
+
+    /// Returns the format of the numbers shown in the UI.
+    ///
+    /// The format uses the decimal separator and the units chosen in the
+    /// settings.
+    pub fn number_format(&self) -> NumberFormat {
+        self.number_format.get()
+    }
+
+    /// Returns `true` if the synchronization of the waterfall view with other
+    /// browser tabs is enabled.
+    pub fn view_sync_enabled(&self) -> bool {
+        self.elements.view_sync.get().unwrap_or(false)
+    }
+
     impl_section!(
         spectrometer,
         maia_json::Spectrometer,
@@ -182,6 +1098,27 @@ impl Ui {
         Ok(())
     }
 
+    // The device health is polled separately from the API and at a lower rate,
+    // since it changes slowly and reading it requires accessing several IIO
+    // devices.
+    fn set_health_get_periodic(&self, interval_ms: i32) -> Result<(), JsValue> {
+        let ui = self.clone();
+        let handler = Closure::<dyn Fn() -> js_sys::Promise>::new(move || {
+            let ui = ui.clone();
+            future_to_promise(async move {
+                let health = ui.get_health().await?;
+                ui.update_health_status(&health)?;
+                Ok(JsValue::NULL)
+            })
+        });
+        let handler_ = handler.into_js_value();
+        let handler = handler_.unchecked_ref();
+        self.window.set_timeout_with_callback(handler)?;
+        self.window
+            .set_interval_with_callback_and_timeout_and_arguments_0(handler, interval_ms)?;
+        Ok(())
+    }
+
     fn resize_canvas(&self) -> impl Fn() {
         let render_engine = Rc::clone(&self.render_engine);
         let waterfall = Rc::clone(&self.waterfall);
@@ -193,17 +1130,53 @@ impl Ui {
     }
 
     fn onresize(&self) -> Closure<dyn Fn()> {
-        Closure::new(self.resize_canvas())
+        let ui = self.clone();
+        Closure::new(move || {
+            if let Err(e) = ui.update_layout() {
+                web_sys::console::error_1(&e);
+            }
+        })
+    }
+
+    // Selects between the full and the compact layout and resizes the canvas
+    // accordingly. The compact layout is used if it is enabled in the UI or if
+    // the viewport is narrow.
+    //
+    // The view (zoom and center frequency) is stored in the waterfall and is
+    // not modified by resizing the canvas, so it is preserved when the layout
+    // changes.
+    fn update_layout(&self) -> Result<(), JsValue> {
+        // Maximum viewport width in CSS pixels for which the compact layout is
+        // selected automatically.
+        const COMPACT_MAX_WIDTH: f64 = 600.0;
+        let narrow = self
+            .window
+            .inner_width()?
+            .as_f64()
+            .map_or(false, |width| width <= COMPACT_MAX_WIDTH);
+        let compact = narrow || self.elements.compact_layout.get().unwrap_or(false);
+        let body = self.document.body().ok_or("unable to get body")?;
+        body.class_list().toggle_with_force("compact", compact)?;
+        if !compact {
+            self.elements
+                .controls
+                .class_list()
+                .remove_1("controls_open")?;
+        }
+        self.resize_canvas()();
+        self.resize_annotations()
     }
 
     fn colormap_select_onchange(&self) -> Closure<dyn Fn()> {
         let ui = self.clone();
         Closure::new(move || {
             let colormap = ui.elements.colormap_select.get().unwrap();
-            let mut render_engine = ui.render_engine.borrow_mut();
             ui.waterfall
                 .borrow()
-                .load_colormap(&mut render_engine, colormap.colormap_as_slice())
+                .load_colormap(
+                    &mut ui.render_engine.borrow_mut(),
+                    colormap.colormap_as_slice(),
+                )
                 .unwrap();
             // try_borrow_mut prevents trying to update the preferences as a
             // consequence of the Preferences::apply_client calling this
@@ -213,71 +1186,686 @@ impl Ui {
                     web_sys::console::error_1(&e);
                 }
             }
+            // Each colormap has its own gamma, which is restored when the
+            // colormap is selected.
+            let gamma = ui.preferences.borrow().colormap_gamma(colormap);
+            if ui.elements.waterfall_gamma.get() != Some(gamma) {
+                ui.elements.waterfall_gamma.set(&gamma);
+                if let Err(e) = ui
+                    .elements
+                    .waterfall_gamma
+                    .onchange()
+                    .unwrap()
+                    .call0(&JsValue::NULL)
+                {
+                    web_sys::console::error_1(&e);
+                }
+            }
         })
     }
 
-    waterfallminmax_onchange!(waterfall_min);
-    waterfallminmax_onchange!(waterfall_max);
+    fn waterfall_gamma_onchange(&self) -> Closure<dyn Fn()> {
+        let ui = self.clone();
+        Closure::new(move || {
+            let element = &ui.elements.waterfall_gamma;
+            if !element.report_validity() {
+                return;
+            }
+            let Some(gamma) = element.get() else {
+                ui.window
+                    .alert_with_message("Invalid value for waterfall_gamma")
+                    .unwrap();
+                return;
+            };
+            ui.waterfall.borrow_mut().set_waterfall_gamma(gamma);
+            // try_borrow_mut prevents trying to update the preferences as a
+            // consequence of the Preferences::apply_client calling this
+            // function
+            if let Ok(mut p) = ui.preferences.try_borrow_mut() {
+                if let Err(e) = p.update_waterfall_gamma(&gamma) {
+                    web_sys::console::error_1(&e);
+                }
+                // The gamma is also recorded for the selected colormap, so
+                // that it is restored when the colormap is selected again.
+                if let Some(colormap) = ui.elements.colormap_select.get() {
+                    if let Err(e) = p.update_colormap_gamma(colormap, gamma) {
+                        web_sys::console::error_1(&e);
+                    }
+                }
+            }
+        })
+    }
 
-    async fn get_api_update_elements(&self) -> Result<(), JsValue> {
-        let json = self.get_api().await?;
-        self.update_ad9361_inactive_elements(&json.ad9361)?;
-        self.update_spectrometer_inactive_elements(&json.spectrometer);
-        self.update_waterfall_rate(&json.spectrometer);
-        self.update_recorder_button(&json.recorder);
-        self.update_recording_metadata_inactive_elements(&json.recording_metadata);
-        self.update_recorder_inactive_elements(&json.recorder);
+    fn over_range_color_enabled_onchange(&self) -> Closure<dyn Fn()> {
+        self.range_colors_onchange()
+    }
 
-        // This potentially takes some time to complete, since it might have to
-        // do a fetch call to PATCH the server time. We do this last.
-        self.update_server_time(&json.time).await?;
+    fn over_range_color_onchange(&self) -> Closure<dyn Fn()> {
+        self.range_colors_onchange()
+    }
 
-        Ok(())
+    fn under_range_color_enabled_onchange(&self) -> Closure<dyn Fn()> {
+        self.range_colors_onchange()
     }
 
-    async fn get_api(&self) -> Result<maia_json::Api, JsValue> {
-        let response = JsFuture::from(self.window.fetch_with_str(API_URL))
-            .await?
-            .dyn_into::<Response>()?;
-        Self::response_to_json(response).await
+    fn under_range_color_onchange(&self) -> Closure<dyn Fn()> {
+        self.range_colors_onchange()
     }
 
-    async fn response_to_json<T>(response: Response) -> Result<T, JsValue>
-    where
-        for<'a> T: serde::Deserialize<'a>,
-    {
-        let text = JsFuture::from(response.text()?).await?;
-        let json = serde_json::from_str(
-            &text
-                .as_string()
-                .ok_or("unable to convert fetch text to string")?,
-        )
-        .map_err(|_| format!("unable to parse {} JSON", std::any::type_name::<T>()))?;
-        Ok(json)
+    // Applies the colors of the out of range values to the waterfall. Each
+    // color is only used while its checkbox is checked.
+    fn range_colors_onchange(&self) -> Closure<dyn Fn()> {
+        let ui = self.clone();
+        Closure::new(move || {
+            let elements = &ui.elements;
+            let color = |enabled: &CheckboxInput, color: &TextInput| {
+                let enabled = enabled.get().unwrap_or(false);
+                let color = color.get().unwrap_or_default();
+                (enabled, color)
+            };
+            let over = color(
+                &elements.over_range_color_enabled,
+                &elements.over_range_color,
+            );
+            let under = color(
+                &elements.under_range_color_enabled,
+                &elements.under_range_color,
+            );
+            {
+                let parse = |(enabled, color): &(bool, String)| {
+                    colormap::parse_hex_color(color).filter(|_| *enabled)
+                };
+                let mut waterfall = ui.waterfall.borrow_mut();
+                waterfall.set_over_range_color(parse(&over));
+                waterfall.set_under_range_color(parse(&under));
+            }
+            // try_borrow_mut prevents trying to update the preferences as a
+            // consequence of the Preferences::apply_client calling this
+            // function
+            if let Ok(mut p) = ui.preferences.try_borrow_mut() {
+                let results = [
+                    p.update_over_range_color_enabled(&over.0),
+                    p.update_over_range_color(&over.1),
+                    p.update_under_range_color_enabled(&under.0),
+                    p.update_under_range_color(&under.1),
+                ];
+                for result in results {
+                    if let Err(e) = result {
+                        web_sys::console::error_1(&e);
+                    }
+                }
+            }
+        })
     }
 
-    // The ad9361 is not implemented via impl_section! because it needs custom
-    // update element functions that call update_waterfall_ad9361 and a custom
-    // patch-update that calls the spectrometer onchange closure.
-    async fn patch_ad9361_update_elements(
-        &self,
-        json: &maia_json::PatchAd9361,
-    ) -> Result<(), JsValue> {
-        let json_output = self.patch_ad9361(json).await?;
-        self.update_ad9361_all_elements(&json_output)?;
-        if json.sampling_frequency.is_some() {
-            // The spectrometer needs to be updated also. To do this, we fake an
-            // onchange event for the spectrometer_rate input element.
-            self.elements
-                .spectrometer_output_sampling_frequency
-                .onchange()
-                .unwrap()
-                .call0(&JsValue::NULL)?;
-        }
-        Ok(())
+    fn reticle_onchange(&self) -> Closure<dyn Fn()> {
+        self.reticle_settings_onchange()
     }
 
-    fn update_ad9361_inactive_elements(&self, json: &maia_json::Ad9361) -> Result<(), JsValue> {
+    fn reticle_color_onchange(&self) -> Closure<dyn Fn()> {
+        self.reticle_settings_onchange()
+    }
+
+    // Applies the reticle settings to the waterfall and shows or hides the
+    // center readout accordingly.
+    fn reticle_settings_onchange(&self) -> Closure<dyn Fn()> {
+        let ui = self.clone();
+        Closure::new(move || {
+            let enabled = ui.elements.reticle.get().unwrap_or(false);
+            let color = ui.elements.reticle_color.get().unwrap_or_default();
+            ui.waterfall
+                .borrow_mut()
+                .set_reticle(colormap::parse_hex_color(&color).filter(|_| enabled));
+            if let Err(e) = ui.set_reticle_readout(enabled) {
+                web_sys::console::error_1(&e);
+            }
+            // try_borrow_mut prevents trying to update the preferences as a
+            // consequence of the Preferences::apply_client calling this
+            // function
+            if let Ok(mut p) = ui.preferences.try_borrow_mut() {
+                let results = [p.update_reticle(&enabled), p.update_reticle_color(&color)];
+                for result in results {
+                    if let Err(e) = result {
+                        web_sys::console::error_1(&e);
+                    }
+                }
+            }
+        })
+    }
+
+    // Shows or hides the readout of the frequency and power at the center of
+    // the view, which is drawn next to the reticle. While shown, the readout
+    // is updated periodically, so that it follows the view and the power of
+    // the signals.
+    fn set_reticle_readout(&self, enabled: bool) -> Result<(), JsValue> {
+        // Interval in milliseconds between updates of the readout.
+        const UPDATE_INTERVAL_MS: i32 = 250;
+        if let Some(handle) = self.reticle_readout_interval.take() {
+            self.window.clear_interval_with_handle(handle);
+        }
+        let readout = &self.elements.reticle_readout;
+        readout.set_text_content(None);
+        readout
+            .class_list()
+            .toggle_with_force("reticle_readout_visible", enabled)?;
+        if enabled {
+            self.update_reticle_readout();
+            let ui = self.clone();
+            let update = Closure::<dyn Fn()>::new(move || ui.update_reticle_readout());
+            let handle = self
+                .window
+                .set_interval_with_callback_and_timeout_and_arguments_0(
+                    update.into_js_value().unchecked_ref(),
+                    UPDATE_INTERVAL_MS,
+                )?;
+            self.reticle_readout_interval.set(Some(handle));
+        }
+        Ok(())
+    }
+
+    fn update_reticle_readout(&self) {
+        let waterfall = self.waterfall.borrow();
+        let format = self.number_format();
+        let calibration = self.power_calibration();
+        let unit = if calibration.is_some() { "dBm" } else { "dB" };
+        let power = match waterfall.get_display_center_power() {
+            Some(power) => format!(
+                "{} {unit}",
+                format.number(f64::from(power + calibration.unwrap_or(0.0)), 1)
+            ),
+            None => "--".to_string(),
+        };
+        let text = format!(
+            "{} {power}",
+            format.frequency(waterfall.get_display_center_hz(), FrequencyUnit::MHz, 6)
+        );
+        self.elements.reticle_readout.set_text_content(Some(&text));
+    }
+
+    fn decimal_separator_onchange(&self) -> Closure<dyn Fn()> {
+        self.number_format_onchange()
+    }
+
+    fn frequency_unit_onchange(&self) -> Closure<dyn Fn()> {
+        self.number_format_onchange()
+    }
+
+    fn time_unit_onchange(&self) -> Closure<dyn Fn()> {
+        self.number_format_onchange()
+    }
+
+    // Updates the number format from the settings. The readouts use the new
+    // format the next time that they are updated.
+    fn number_format_onchange(&self) -> Closure<dyn Fn()> {
+        let ui = self.clone();
+        Closure::new(move || {
+            let elements = &ui.elements;
+            let separator = elements.decimal_separator.get().unwrap_or_default();
+            let frequency_unit = elements.frequency_unit.get().unwrap_or_default();
+            let time_unit = elements.time_unit.get().unwrap_or_default();
+            // The separator of the browser language is found by formatting a
+            // number with it.
+            let browser_number = ui
+                .window
+                .navigator()
+                .language()
+                .map(|language| String::from(js_sys::Number::from(1.5).to_locale_string(&language)))
+                .unwrap_or_default();
+            let format = NumberFormat::new(
+                separator.is_comma(&browser_number),
+                frequency_unit,
+                time_unit,
+            );
+            ui.number_format.set(format);
+            if let Err(e) = ui.waterfall.borrow_mut().set_decimal_separator(
+                format.decimal_separator(),
+                &mut ui.render_engine.borrow_mut(),
+            ) {
+                web_sys::console::error_1(&e);
+            }
+            // try_borrow_mut prevents trying to update the preferences as a
+            // consequence of the Preferences::apply_client calling this
+            // function
+            if let Ok(mut p) = ui.preferences.try_borrow_mut() {
+                let results = [
+                    p.update_decimal_separator(&separator),
+                    p.update_frequency_unit(&frequency_unit),
+                    p.update_time_unit(&time_unit),
+                ];
+                for result in results {
+                    if let Err(e) = result {
+                        web_sys::console::error_1(&e);
+                    }
+                }
+            }
+        })
+    }
+
+    fn keymap_onchange(&self) -> Closure<dyn Fn()> {
+        let ui = self.clone();
+        Closure::new(move || {
+            let text = ui.elements.keymap.get().unwrap_or_default();
+            let keymap = match Keymap::parse(&text) {
+                Ok(keymap) => keymap,
+                Err(e) => {
+                    ui.window
+                        .alert_with_message(&format!("Invalid keymap: {e}"))
+                        .unwrap();
+                    return;
+                }
+            };
+            let conflicts = keymap
+                .conflicts()
+                .iter()
+                .map(|(key, actions)| {
+                    let names = actions.iter().map(|a| a.name()).collect::<Vec<_>>();
+                    format!("{} ({})", Keymap::key_name(key), names.join(", "))
+                })
+                .collect::<Vec<_>>();
+            if !conflicts.is_empty() {
+                // Only the first action bound to each key is performed, so
+                // the keymap is still usable.
+                if let Err(e) =
+                    ui.show_toast(&format!("Conflicting keys: {}", conflicts.join("; ")))
+                {
+                    web_sys::console::error_1(&e);
+                }
+            }
+            ui.update_shortcut_titles(&keymap);
+            ui.keymap.replace(keymap);
+            // try_borrow_mut prevents trying to update the preferences as a
+            // consequence of the Preferences::apply_client calling this
+            // function
+            if let Ok(mut p) = ui.preferences.try_borrow_mut() {
+                if let Err(e) = p.update_keymap(&text) {
+                    web_sys::console::error_1(&e);
+                }
+            }
+        })
+    }
+
+    fn perf_overlay_onchange(&self) -> Closure<dyn Fn()> {
+        let ui = self.clone();
+        Closure::new(move || {
+            let enabled = ui.elements.perf_overlay.get().unwrap();
+            if let Err(e) = ui.set_perf_overlay(enabled) {
+                web_sys::console::error_1(&e);
+            }
+        })
+    }
+
+    /// Toggles the performance overlay.
+    ///
+    /// The overlay shows the rendered frames per second, the rate at which
+    /// spectrum lines are received, the number of dropped frames and lines,
+    /// and the latency between the reception of a line and its rendering. The
+    /// overlay can also be enabled by loading the page with a `perf` URL
+    /// parameter.
+    pub fn toggle_perf_overlay(&self) -> Result<(), JsValue> {
+        let enabled = !self.elements.perf_overlay.get().unwrap_or(false);
+        self.elements.perf_overlay.set(&enabled);
+        self.set_perf_overlay(enabled)
+    }
+
+    // Enables or disables the performance overlay. The statistics are only
+    // collected while the overlay is enabled.
+    fn set_perf_overlay(&self, enabled: bool) -> Result<(), JsValue> {
+        // Interval in milliseconds between updates of the overlay.
+        const UPDATE_INTERVAL_MS: i32 = 1000;
+        if let Some(handle) = self.perf_overlay_interval.take() {
+            self.window.clear_interval_with_handle(handle);
+        }
+        self.waterfall.borrow_mut().set_perf_stats_enabled(enabled);
+        let text = &self.elements.perf_overlay_text;
+        text.set_text_content(None);
+        text.class_list()
+            .toggle_with_force("perf_overlay_visible", enabled)?;
+        if enabled {
+            let ui = self.clone();
+            let update = Closure::<dyn Fn()>::new(move || {
+                if let Some(report) = ui.waterfall.borrow_mut().take_perf_report() {
+                    ui.elements
+                        .perf_overlay_text
+                        .set_text_content(Some(&report.to_string()));
+                }
+            });
+            let handle = self
+                .window
+                .set_interval_with_callback_and_timeout_and_arguments_0(
+                    update.into_js_value().unchecked_ref(),
+                    UPDATE_INTERVAL_MS,
+                )?;
+            self.perf_overlay_interval.set(Some(handle));
+        }
+        Ok(())
+    }
+
+    // Returns true if the page URL has a query parameter with the given name.
+    fn has_url_param(&self, name: &str) -> bool {
+        self.window
+            .location()
+            .search()
+            .unwrap_or_default()
+            .trim_start_matches('?')
+            .split('&')
+            .any(|param| param.split('=').next() == Some(name))
+    }
+
+    // Updates the titles of the UI elements that have a keyboard shortcut, so
+    // that they show the key bound to the shortcut.
+    fn update_shortcut_titles(&self, keymap: &Keymap) {
+        for action in Action::ALL {
+            let Some(element) = action
+                .element_id()
+                .and_then(|id| self.document.get_element_by_id(id))
+                .and_then(|element| element.dyn_into::<HtmlElement>().ok())
+            else {
+                continue;
+            };
+            let title = match keymap.key(action) {
+                Some(key) => format!("{} ({})", action.description(), Keymap::key_name(key)),
+                None => action.description().to_string(),
+            };
+            element.set_title(&title);
+        }
+    }
+
+    /// Shows or hides the keyboard shortcut help.
+    ///
+    /// The help lists the actions that have a key bound in the keymap,
+    /// together with their keys.
+    pub fn toggle_shortcut_help(&self) -> Result<(), JsValue> {
+        let help = &self.elements.shortcut_help;
+        let visible = !help.class_list().contains("shortcut_help_visible");
+        if visible {
+            let keymap = self.keymap.borrow();
+            let text = Action::ALL
+                .iter()
+                .filter_map(|&action| {
+                    let key = Keymap::key_name(keymap.key(action)?);
+                    Some(format!("{key:<10} {}", action.description()))
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            help.set_text_content(Some(&text));
+        }
+        help.class_list()
+            .toggle_with_force("shortcut_help_visible", visible)?;
+        Ok(())
+    }
+
+    /// Pauses or resumes the waterfall.
+    ///
+    /// While paused, the waterfall stops adding spectrum lines, so that the
+    /// spectrum on the screen can be examined. The lines received while
+    /// paused are lost.
+    pub fn toggle_paused(&self) -> Result<(), JsValue> {
+        let paused = {
+            let mut waterfall = self.waterfall.borrow_mut();
+            let paused = !waterfall.is_paused();
+            waterfall.set_paused(paused);
+            paused
+        };
+        self.show_toast(if paused {
+            "Waterfall paused"
+        } else {
+            "Waterfall resumed"
+        })
+    }
+
+    /// Returns the action bound to a key in the keymap.
+    ///
+    /// The `key` should be the value of the `key` property of a keyboard
+    /// event. If no action is bound to the key, `None` is returned.
+    pub fn keymap_action(&self, key: &str) -> Option<Action> {
+        self.keymap.borrow().action(key)
+    }
+
+    fn compact_layout_onchange(&self) -> Closure<dyn Fn()> {
+        let ui = self.clone();
+        Closure::new(move || {
+            let compact = ui.elements.compact_layout.get().unwrap_or(false);
+            if let Err(e) = ui.update_layout() {
+                web_sys::console::error_1(&e);
+            }
+            // try_borrow_mut prevents trying to update the preferences as a
+            // consequence of the Preferences::apply_client calling this
+            // function
+            if let Ok(mut p) = ui.preferences.try_borrow_mut() {
+                if let Err(e) = p.update_compact_layout(&compact) {
+                    web_sys::console::error_1(&e);
+                }
+            }
+        })
+    }
+
+    fn max_frame_rate_onchange(&self) -> Closure<dyn Fn()> {
+        let ui = self.clone();
+        Closure::new(move || {
+            let element = &ui.elements.max_frame_rate;
+            if !element.report_validity() {
+                return;
+            }
+            let Some(rate) = element.get() else {
+                return;
+            };
+            // A rate of zero removes the limit.
+            ui.render_engine
+                .borrow_mut()
+                .set_max_frame_rate(Some(rate).filter(|&rate| rate > 0.0));
+            // try_borrow_mut prevents trying to update the preferences as a
+            // consequence of the Preferences::apply_client calling this
+            // function
+            if let Ok(mut p) = ui.preferences.try_borrow_mut() {
+                if let Err(e) = p.update_max_frame_rate(&rate) {
+                    web_sys::console::error_1(&e);
+                }
+            }
+        })
+    }
+
+    fn max_pixel_ratio_onchange(&self) -> Closure<dyn Fn()> {
+        let ui = self.clone();
+        Closure::new(move || {
+            let element = &ui.elements.max_pixel_ratio;
+            if !element.report_validity() {
+                return;
+            }
+            let Some(ratio) = element.get() else {
+                return;
+            };
+            // A ratio of zero uses the native device pixel ratio. The canvas
+            // is resized to apply the new ratio, which preserves the view.
+            ui.render_engine
+                .borrow_mut()
+                .set_max_pixel_ratio(Some(ratio).filter(|&ratio| ratio > 0.0));
+            ui.resize_canvas()();
+            if let Err(e) = ui.resize_annotations() {
+                web_sys::console::error_1(&e);
+            }
+            // try_borrow_mut prevents trying to update the preferences as a
+            // consequence of the Preferences::apply_client calling this
+            // function
+            if let Ok(mut p) = ui.preferences.try_borrow_mut() {
+                if let Err(e) = p.update_max_pixel_ratio(&ratio) {
+                    web_sys::console::error_1(&e);
+                }
+            }
+        })
+    }
+
+    fn view_span_onchange(&self) -> Closure<dyn Fn()> {
+        let ui = self.clone();
+        Closure::new(move || {
+            let element = &ui.elements.view_span;
+            match element.get().filter(|span| span.is_finite() && *span > 0.0) {
+                Some(span) => ui.waterfall.borrow_mut().set_span_hz(span),
+                None => {
+                    if let Err(e) = ui.window.alert_with_message("Invalid value for span") {
+                        web_sys::console::error_1(&e);
+                    }
+                }
+            }
+            // The span that is actually shown can be different because of the
+            // zoom limits.
+            ui.update_view_span();
+        })
+    }
+
+    // Shows the current span of the view, unless the user is editing it.
+    fn update_view_span(&self) {
+        if self.document.is_element_active("view_span") {
+            return;
+        }
+        let span = self.waterfall.borrow().get_span_hz();
+        self.elements.view_span.set(&span);
+    }
+
+    fn controls_button_onclick(&self) -> Closure<dyn Fn()> {
+        let ui = self.clone();
+        Closure::new(move || {
+            // The controls drawer is overlaid on top of the waterfall, so the
+            // canvas does not need to be resized when it is opened or closed.
+            let open = ui
+                .elements
+                .controls
+                .class_list()
+                .toggle("controls_open")
+                .unwrap();
+            ui.elements.controls_button.set_text_content(Some(if open {
+                "Hide controls"
+            } else {
+                "Controls"
+            }));
+        })
+    }
+
+    waterfall_onchange!(waterfall_min);
+    waterfall_onchange!(waterfall_max);
+    waterfall_onchange!(waterfall_persistence);
+    waterfall_onchange!(spectral_interpolation);
+    waterfall_onchange!(linear_light_colormap);
+    waterfall_onchange!(freeze_on_measurement);
+    waterfall_onchange!(bin_grid);
+    waterfall_onchange!(smooth_scroll);
+    waterfall_onchange!(line_height);
+    waterfall_onchange!(frequency_axis_flipped);
+    waterfall_onchange!(animation_easing);
+    waterfall_onchange!(snap_zoom);
+    waterfall_onchange!(overscan);
+    waterfall_onchange!(rx_lo_keep_visible);
+    waterfall_onchange!(rx_lo_keep_visible_margin);
+    waterfall_onchange!(comb_origin);
+    waterfall_onchange!(comb_spacing);
+    waterfall_onchange!(comb_count);
+    waterfall_onchange!(peak_tracking);
+    waterfall_onchange!(peak_tracking_hysteresis);
+    waterfall_onchange!(peak_tracking_min_snr);
+    waterfall_onchange!(dc_exclusion_bins);
+    preference_onchange!(rx_lo_wheel_step);
+    preference_onchange!(retune_guard);
+    preference_onchange!(max_retune_step);
+    preference_onchange!(tuning_knob_step);
+    preference_onchange!(clipping_threshold);
+    preference_onchange!(calibrated_power);
+    preference_onchange!(calibration_offset);
+    preference_onchange!(tone_mapping);
+    preference_onchange!(tone_min_db);
+    preference_onchange!(tone_max_db);
+    preference_onchange!(scan_start);
+    preference_onchange!(scan_stop);
+    preference_onchange!(scan_step);
+    preference_onchange!(scan_dwell);
+    preference_onchange!(view_sync);
+    preference_onchange!(export_overlay_text);
+    preference_onchange!(export_overlay_position);
+    preference_onchange!(export_overlay_font_size);
+
+    async fn get_api_update_elements(&self) -> Result<(), JsValue> {
+        // The status bar and the span are updated first because they do not
+        // depend on the API response. The span can change by zooming on the
+        // waterfall or when the sampling frequency changes.
+        self.update_status_bar();
+        self.update_view_span();
+        let json = self.get_api().await?;
+        self.update_ad9361_inactive_elements(&json.ad9361)?;
+        self.update_spectrometer_inactive_elements(&json.spectrometer);
+        self.update_waterfall_rate(&json.spectrometer);
+        self.update_processing_status(&json.spectrometer);
+        self.update_recorder_button(&json.recorder);
+        self.update_recording_metadata_inactive_elements(&json.recording_metadata);
+        self.update_recorder_inactive_elements(&json.recorder);
+
+        // This potentially takes some time to complete, since it might have to
+        // do a fetch call to PATCH the server time. We do this last.
+        self.update_server_time(&json.time).await?;
+
+        Ok(())
+    }
+
+    async fn get_api(&self) -> Result<maia_json::Api, JsValue> {
+        let response = JsFuture::from(self.window.fetch_with_str(API_URL))
+            .await?
+            .dyn_into::<Response>()?;
+        Self::response_to_json(response).await
+    }
+
+    async fn get_health(&self) -> Result<maia_json::Health, JsValue> {
+        let response = JsFuture::from(self.window.fetch_with_str(HEALTH_URL))
+            .await?
+            .dyn_into::<Response>()?;
+        if !response.ok() {
+            return Err(format!("GET {HEALTH_URL} failed: {}", response.status()).into());
+        }
+        Self::response_to_json(response).await
+    }
+
+    async fn get_spectrometer(&self) -> Result<maia_json::Spectrometer, JsValue> {
+        let response = JsFuture::from(self.window.fetch_with_str(SPECTROMETER_URL))
+            .await?
+            .dyn_into::<Response>()?;
+        if !response.ok() {
+            return Err(format!("GET {SPECTROMETER_URL} failed: {}", response.status()).into());
+        }
+        Self::response_to_json(response).await
+    }
+
+    async fn response_to_json<T>(response: Response) -> Result<T, JsValue>
+    where
+        for<'a> T: serde::Deserialize<'a>,
+    {
+        let text = JsFuture::from(response.text()?).await?;
+        let json = serde_json::from_str(
+            &text
+                .as_string()
+                .ok_or("unable to convert fetch text to string")?,
+        )
+        .map_err(|_| format!("unable to parse {} JSON", std::any::type_name::<T>()))?;
+        Ok(json)
+    }
+
+    // The ad9361 is not implemented via impl_section! because it needs custom
+    // update element functions that call update_waterfall_ad9361 and a custom
+    // patch-update that calls the spectrometer onchange closure.
+    async fn patch_ad9361_update_elements(
+        &self,
+        json: &maia_json::PatchAd9361,
+    ) -> Result<(), JsValue> {
+        let json_output = self.patch_ad9361(json).await?;
+        self.update_ad9361_all_elements(&json_output)?;
+        if json.sampling_frequency.is_some() {
+            // The spectrometer needs to be updated also. To do this, we fake an
+            // onchange event for the spectrometer_rate input element.
+            self.elements
+                .spectrometer_output_sampling_frequency
+                .onchange()
+                .unwrap()
+                .call0(&JsValue::NULL)?;
+        }
+        Ok(())
+    }
+
+    fn update_ad9361_inactive_elements(&self, json: &maia_json::Ad9361) -> Result<(), JsValue> {
         set_values_if_inactive!(
             self,
             json,
@@ -376,23 +1964,1670 @@ impl Ui {
         })
     }
 
-    fn update_waterfall_rate(&self, json: &maia_json::Spectrometer) {
-        self.waterfall
-            .borrow_mut()
-            .set_waterfall_update_rate(json.output_sampling_frequency as f32);
+    fn export_button_onclick(&self) -> Closure<dyn Fn()> {
+        let ui = self.clone();
+        Closure::new(move || {
+            if let Err(e) = ui.export_png() {
+                web_sys::console::error_1(&e);
+            }
+        })
     }
 
-    impl_patch!(time, maia_json::PatchTime, maia_json::Time, TIME_URL);
+    fn export_clipboard_button_onclick(&self) -> Closure<dyn Fn() -> JsValue> {
+        let ui = self.clone();
+        Closure::new(move || {
+            let ui = ui.clone();
+            future_to_promise(async move {
+                let message = match ui.copy_png_to_clipboard().await {
+                    Ok(()) => "Image copied to clipboard".to_string(),
+                    Err(e) if export::is_permission_denied(&e) => {
+                        "Unable to copy image: clipboard permission denied".to_string()
+                    }
+                    Err(e) => {
+                        web_sys::console::error_1(&e);
+                        format!(
+                            "Unable to copy image: {}",
+                            e.as_string()
+                                .unwrap_or_else(|| "clipboard error".to_string())
+                        )
+                    }
+                };
+                ui.show_toast(&message)?;
+                Ok(JsValue::NULL)
+            })
+            .into()
+        })
+    }
 
-    async fn update_server_time(&self, json: &maia_json::Time) -> Result<(), JsValue> {
-        let threshold = 1000.0; // update server time if off by more than 1 sec
-        let milliseconds = js_sys::Date::now();
-        if (milliseconds - json.time).abs() >= threshold {
-            let patch = maia_json::PatchTime {
-                time: Some(milliseconds),
-            };
-            self.patch_time(&patch).await?;
-        }
+    fn export_properties_button_onclick(&self) -> Closure<dyn Fn()> {
+        let ui = self.clone();
+        Closure::new(move || {
+            ui.elements.export_dialog.show_modal().unwrap();
+        })
+    }
+
+    /// Exports the waterfall as a PNG image.
+    ///
+    /// The image is rendered with the current view and with the overlay text
+    /// configured in the export dialog, and downloaded as a PNG file.
+    pub fn export_png(&self) -> Result<(), JsValue> {
+        let canvas = self.export_canvas()?;
+        export::download_png(&self.document, &canvas, &export::png_filename())
+    }
+
+    /// Copies the waterfall to the clipboard as a PNG image.
+    ///
+    /// The image is the same as the one exported by [`Ui::export_png`]. An
+    /// error is returned if the browser does not support writing images to
+    /// the clipboard or if the permission to do so is denied.
+    pub async fn copy_png_to_clipboard(&self) -> Result<(), JsValue> {
+        let canvas = self.export_canvas()?;
+        export::copy_png_to_clipboard(&self.window, &canvas).await
+    }
+
+    /// Saves the current session to a file.
+    ///
+    /// The session contains the waterfall view and all the preferences, and it
+    /// is downloaded as a JSON file that can be loaded with
+    /// [`Ui::load_session`].
+    pub fn save_session(&self) -> Result<(), JsValue> {
+        let session = session::Session::new(
+            Some(self.current_view()),
+            self.preferences.borrow().to_json(),
+        );
+        export::download_json(
+            &self.document,
+            &session.to_json(),
+            &export::timestamped_filename("maia-sdr-session", "json"),
+        )
+    }
+
+    /// Loads a session from a JSON document.
+    ///
+    /// Documents produced by older versions are migrated to the current
+    /// version. Preferences that are missing from the document keep their
+    /// current value. If the document or its preferences are invalid, an
+    /// error is returned and nothing is modified. An invalid view is ignored.
+    pub fn load_session(&self, json: &str) -> Result<(), JsValue> {
+        let session = session::Session::from_json(json)?;
+        if !session.preferences.is_null() {
+            self.preferences
+                .borrow_mut()
+                .merge_json(&session.preferences)?;
+            self.preferences.borrow().apply(self)?;
+        }
+        if let Some(view) = session.view {
+            if view.is_valid() {
+                let mut waterfall = self.waterfall.borrow_mut();
+                waterfall.set_zoom(view.zoom);
+                waterfall.set_center_frequency(view.center_frequency);
+            } else {
+                web_sys::console::error_1(&"ignoring invalid view in session".into());
+            }
+        }
+        Ok(())
+    }
+
+    fn current_view(&self) -> session::View {
+        let waterfall = self.waterfall.borrow();
+        session::View {
+            zoom: waterfall.get_zoom(),
+            center_frequency: waterfall.get_center_frequency(),
+        }
+    }
+
+    fn session_save_button_onclick(&self) -> Closure<dyn Fn()> {
+        let ui = self.clone();
+        Closure::new(move || {
+            if let Err(e) = ui.save_session() {
+                web_sys::console::error_1(&e);
+            }
+        })
+    }
+
+    // Reads the file selected in a hidden file input as text and passes it to
+    // load. The file inputs are hidden and opened by clicking them from a
+    // button, and their value is cleared after reading the file, so that the
+    // same file can be loaded again. Returns the promise of the read.
+    fn read_file_input(
+        input: Rc<HtmlInputElement>,
+        load: impl FnOnce(String) -> Result<(), JsValue> + 'static,
+    ) -> JsValue {
+        future_to_promise(async move {
+            let Some(file) = input.files().and_then(|files| files.get(0)) else {
+                return Ok(JsValue::NULL);
+            };
+            let text = JsFuture::from(file.text()).await?;
+            input.set_value("");
+            let text = text
+                .as_string()
+                .ok_or_else(|| format!("unable to read file {}", file.name()))?;
+            load(text)?;
+            Ok(JsValue::NULL)
+        })
+        .into()
+    }
+
+    fn session_load_button_onclick(&self) -> Closure<dyn Fn()> {
+        let ui = self.clone();
+        Closure::new(move || ui.elements.session_file.click())
+    }
+
+    fn session_file_onchange(&self) -> Closure<dyn Fn() -> JsValue> {
+        let ui = self.clone();
+        Closure::new(move || {
+            let ui = ui.clone();
+            let input = Rc::clone(&ui.elements.session_file);
+            Self::read_file_input(input, move |text| match ui.load_session(&text) {
+                Ok(()) => ui.show_toast("Session loaded"),
+                Err(e) => ui.window.alert_with_message(&format!(
+                    "Unable to load session: {}",
+                    e.as_string().unwrap_or_default()
+                )),
+            })
+        })
+    }
+
+    /// Starts recording the view log.
+    ///
+    /// While recording, the zoom and center frequency of the waterfall view,
+    /// the RX LO frequency and the colormap are sampled periodically, and each
+    /// change is recorded with a timestamp. Any previous log is discarded. A
+    /// replay in progress is stopped.
+    pub fn start_view_log_recording(&self) -> Result<(), JsValue> {
+        // Interval in milliseconds between samples of the state.
+        const SAMPLE_INTERVAL_MS: i32 = 100;
+        self.stop_view_log();
+        let start = self.now()?;
+        self.view_log.replace(view_log::ViewLog::new());
+        let ui = self.clone();
+        let sample = Closure::<dyn Fn()>::new(move || {
+            if let Err(e) = ui.sample_view_log() {
+                web_sys::console::error_1(&e);
+            }
+        });
+        let interval = self
+            .window
+            .set_interval_with_callback_and_timeout_and_arguments_0(
+                sample.into_js_value().unchecked_ref(),
+                SAMPLE_INTERVAL_MS,
+            )?;
+        self.view_log_activity
+            .set(view_log::Activity::Recording { start, interval });
+        self.sample_view_log()?;
+        self.update_view_log_buttons();
+        Ok(())
+    }
+
+    /// Stops recording or replaying the view log.
+    ///
+    /// The recorded log is kept, so that it can be exported or replayed.
+    pub fn stop_view_log(&self) {
+        match self.view_log_activity.replace(view_log::Activity::Idle) {
+            view_log::Activity::Idle => (),
+            view_log::Activity::Recording { interval, .. } => {
+                self.window.clear_interval_with_handle(interval)
+            }
+            view_log::Activity::Replaying { timeout } => {
+                self.window.clear_timeout_with_handle(timeout)
+            }
+        }
+        self.update_view_log_buttons();
+    }
+
+    /// Replays the view log.
+    ///
+    /// The recorded changes are applied with the timing with which they were
+    /// recorded, scaled by the replay speed set in the UI. The speed can be
+    /// changed during the replay. Applying a change of RX LO frequency retunes
+    /// the device. A recording in progress is stopped.
+    pub fn replay_view_log(&self) -> Result<(), JsValue> {
+        self.stop_view_log();
+        if self.view_log.borrow().is_empty() {
+            return Err("the view log is empty".into());
+        }
+        self.replay_view_log_entry(0)
+    }
+
+    // Applies an entry of the view log and schedules the next one.
+    fn replay_view_log_entry(&self, index: usize) -> Result<(), JsValue> {
+        let entry = self.view_log.borrow().entries[index];
+        {
+            let mut waterfall = self.waterfall.borrow_mut();
+            waterfall.set_zoom(entry.state.view.zoom);
+            waterfall.set_center_frequency(entry.state.view.center_frequency);
+        }
+        if let Some(freq) = entry.state.rx_lo_frequency {
+            if self.elements.ad9361_rx_lo_frequency.get() != Some(freq) {
+                self.set_rx_lo_frequency(freq)?;
+            }
+        }
+        if let Some(colormap) = entry.state.colormap {
+            if self.elements.colormap_select.get() != Some(colormap) {
+                self.select_colormap(colormap)?;
+            }
+        }
+        let speed = self
+            .elements
+            .view_log_speed
+            .get()
+            .filter(|&speed| speed > 0.0)
+            .unwrap_or(1.0);
+        let Some(delay) = self.view_log.borrow().delay_after(index, speed) else {
+            self.stop_view_log();
+            return self.show_toast("View log replay finished");
+        };
+        let ui = self.clone();
+        let next = Closure::once_into_js(move || {
+            if let Err(e) = ui.replay_view_log_entry(index + 1) {
+                web_sys::console::error_1(&e);
+                ui.stop_view_log();
+            }
+        });
+        let timeout = self
+            .window
+            .set_timeout_with_callback_and_timeout_and_arguments_0(
+                next.unchecked_ref(),
+                delay.round() as i32,
+            )?;
+        self.view_log_activity
+            .set(view_log::Activity::Replaying { timeout });
+        self.update_view_log_buttons();
+        Ok(())
+    }
+
+    // Records the current state in the view log if it has changed.
+    fn sample_view_log(&self) -> Result<(), JsValue> {
+        let view_log::Activity::Recording { start, .. } = self.view_log_activity.get() else {
+            return Ok(());
+        };
+        let (zoom, center_frequency) = self.waterfall.borrow().get_view_target();
+        let state = view_log::ViewLogState {
+            view: session::View {
+                zoom,
+                center_frequency,
+            },
+            rx_lo_frequency: self.elements.ad9361_rx_lo_frequency.get(),
+            colormap: self.elements.colormap_select.get(),
+        };
+        let time_ms = self.now()? - start;
+        if self.view_log.borrow_mut().record(time_ms, state) {
+            self.update_view_log_buttons();
+        }
+        Ok(())
+    }
+
+    /// Exports the view log to a JSON file.
+    ///
+    /// The file can be imported with [`Ui::import_view_log`].
+    pub fn export_view_log(&self) -> Result<(), JsValue> {
+        let json = self.view_log.borrow().to_json();
+        export::download_json(
+            &self.document,
+            &json,
+            &export::timestamped_filename("maia-sdr-view-log", "json"),
+        )
+    }
+
+    /// Imports a view log from a JSON document.
+    ///
+    /// The imported log replaces the current log. If the document is invalid,
+    /// an error is returned and the current log is kept.
+    pub fn import_view_log(&self, json: &str) -> Result<(), JsValue> {
+        let log = view_log::ViewLog::from_json(json)?;
+        self.stop_view_log();
+        self.view_log.replace(log);
+        self.update_view_log_buttons();
+        Ok(())
+    }
+
+    fn update_view_log_buttons(&self) {
+        let activity = self.view_log_activity.get();
+        let recording = matches!(activity, view_log::Activity::Recording { .. });
+        let replaying = matches!(activity, view_log::Activity::Replaying { .. });
+        let empty = self.view_log.borrow().is_empty();
+        let elements = &self.elements;
+        elements
+            .view_log_record_button
+            .set_text_content(Some(if recording {
+                "Stop recording"
+            } else {
+                "Record view"
+            }));
+        elements
+            .view_log_replay_button
+            .set_text_content(Some(if replaying { "Stop replay" } else { "Replay" }));
+        elements
+            .view_log_replay_button
+            .set_disabled(recording || (empty && !replaying));
+        elements
+            .view_log_export_button
+            .set_disabled(empty || recording);
+    }
+
+    // Returns the performance.now() timestamp, in milliseconds.
+    fn now(&self) -> Result<f64, JsValue> {
+        Ok(self
+            .window
+            .performance()
+            .ok_or("unable to get performance")?
+            .now())
+    }
+
+    fn view_log_record_button_onclick(&self) -> Closure<dyn Fn()> {
+        let ui = self.clone();
+        Closure::new(move || {
+            if matches!(
+                ui.view_log_activity.get(),
+                view_log::Activity::Recording { .. }
+            ) {
+                ui.stop_view_log();
+            } else if let Err(e) = ui.start_view_log_recording() {
+                web_sys::console::error_1(&e);
+            }
+        })
+    }
+
+    fn view_log_replay_button_onclick(&self) -> Closure<dyn Fn()> {
+        let ui = self.clone();
+        Closure::new(move || {
+            if matches!(
+                ui.view_log_activity.get(),
+                view_log::Activity::Replaying { .. }
+            ) {
+                ui.stop_view_log();
+            } else if let Err(e) = ui.replay_view_log() {
+                web_sys::console::error_1(&e);
+            }
+        })
+    }
+
+    fn view_log_export_button_onclick(&self) -> Closure<dyn Fn()> {
+        let ui = self.clone();
+        Closure::new(move || {
+            if let Err(e) = ui.export_view_log() {
+                web_sys::console::error_1(&e);
+            }
+        })
+    }
+
+    fn view_log_import_button_onclick(&self) -> Closure<dyn Fn()> {
+        let ui = self.clone();
+        Closure::new(move || ui.elements.view_log_file.click())
+    }
+
+    fn view_log_file_onchange(&self) -> Closure<dyn Fn() -> JsValue> {
+        let ui = self.clone();
+        Closure::new(move || {
+            let ui = ui.clone();
+            let input = Rc::clone(&ui.elements.view_log_file);
+            Self::read_file_input(input, move |text| match ui.import_view_log(&text) {
+                Ok(()) => ui.show_toast("View log loaded"),
+                Err(e) => ui.window.alert_with_message(&format!(
+                    "Unable to load view log: {}",
+                    e.as_string().unwrap_or_default()
+                )),
+            })
+        })
+    }
+
+    /// Saves the current settings and view as a named profile.
+    ///
+    /// The profile stores the device settings (RX frequency, sampling
+    /// frequency, bandwidth and gain), the spectrum rate, the colormap and
+    /// waterfall levels, and the waterfall view. A profile with the same name
+    /// is replaced. The profiles are kept in the browser local storage.
+    pub fn save_profile(&self, name: &str) -> Result<(), JsValue> {
+        let profile = profiles::Profile::new(
+            &self.preferences.borrow().to_json(),
+            Some(self.current_view()),
+        );
+        self.profiles
+            .borrow_mut()
+            .update(|set| set.insert(name, profile))?;
+        self.update_profile_select(Some(name))
+    }
+
+    /// Applies a named profile.
+    ///
+    /// The settings stored in the profile are sent to the device and the
+    /// display is updated with the colormap, waterfall levels and view of the
+    /// profile. Settings not stored in the profile are not modified. If the
+    /// profile does not exist or any of its settings is invalid, an error is
+    /// returned and nothing is modified.
+    pub fn apply_profile(&self, name: &str) -> Result<(), JsValue> {
+        let profile = self
+            .profiles
+            .borrow()
+            .set()
+            .get(name)
+            .cloned()
+            .ok_or_else(|| format!("unknown profile {name}"))?;
+        profile.validate()?;
+        self.preferences
+            .borrow_mut()
+            .merge_json(&serde_json::Value::Object(profile.preferences))?;
+        self.preferences.borrow().apply(self)?;
+        if let Some(view) = profile.view {
+            let mut waterfall = self.waterfall.borrow_mut();
+            waterfall.set_zoom(view.zoom);
+            waterfall.set_center_frequency(view.center_frequency);
+        }
+        Ok(())
+    }
+
+    /// Deletes a named profile.
+    ///
+    /// Returns `true` if the profile existed.
+    pub fn delete_profile(&self, name: &str) -> Result<bool, JsValue> {
+        let removed = self
+            .profiles
+            .borrow_mut()
+            .update(|set| Ok(set.remove(name)))?;
+        self.update_profile_select(None)?;
+        Ok(removed)
+    }
+
+    /// Exports all the profiles to a JSON file.
+    ///
+    /// The file can be imported with [`Ui::import_profiles`].
+    pub fn export_profiles(&self) -> Result<(), JsValue> {
+        let json = self.profiles.borrow().set().to_json();
+        export::download_json(
+            &self.document,
+            &json,
+            &export::timestamped_filename("maia-sdr-profiles", "json"),
+        )
+    }
+
+    /// Imports profiles from a JSON document.
+    ///
+    /// The imported profiles are added to the existing ones, replacing those
+    /// with the same name. If the document is invalid, an error is returned
+    /// and no profiles are imported. Returns the number of imported profiles.
+    pub fn import_profiles(&self, json: &str) -> Result<usize, JsValue> {
+        let imported = profiles::ProfileSet::from_json(json)?;
+        let count = self
+            .profiles
+            .borrow_mut()
+            .update(|set| Ok(set.merge(imported)))?;
+        self.update_profile_select(None)?;
+        Ok(count)
+    }
+
+    // Fills the profile selector with the names of the profiles, selecting the
+    // given profile or keeping the current selection if it still exists.
+    fn update_profile_select(&self, selected: Option<&str>) -> Result<(), JsValue> {
+        let select = &self.elements.profile_select;
+        let selected = selected
+            .map(|name| name.to_string())
+            .unwrap_or_else(|| select.value());
+        select.set_length(0);
+        for name in self.profiles.borrow().set().names() {
+            select.add_with_html_option_element(&HtmlOptionElement::new_with_text_and_value(
+                name, name,
+            )?)?;
+        }
+        select.set_value(&selected);
+        let empty = select.length() == 0;
+        self.elements.profile_apply_button.set_disabled(empty);
+        self.elements.profile_delete_button.set_disabled(empty);
+        Ok(())
+    }
+
+    // Returns the name of the profile selected in the profile selector.
+    fn selected_profile(&self) -> Option<String> {
+        Some(self.elements.profile_select.value()).filter(|name| !name.is_empty())
+    }
+
+    fn profile_apply_button_onclick(&self) -> Closure<dyn Fn()> {
+        let ui = self.clone();
+        Closure::new(move || {
+            let Some(name) = ui.selected_profile() else {
+                return;
+            };
+            let result = match ui.apply_profile(&name) {
+                Ok(()) => ui.show_toast(&format!("Profile {name} applied")),
+                Err(e) => ui.window.alert_with_message(&format!(
+                    "Unable to apply profile: {}",
+                    e.as_string().unwrap_or_default()
+                )),
+            };
+            if let Err(e) = result {
+                web_sys::console::error_1(&e);
+            }
+        })
+    }
+
+    fn profile_save_button_onclick(&self) -> Closure<dyn Fn()> {
+        let ui = self.clone();
+        Closure::new(move || {
+            let result = (|| {
+                let default = ui.selected_profile().unwrap_or_default();
+                let Some(name) = ui
+                    .window
+                    .prompt_with_message_and_default("Profile name", &default)?
+                else {
+                    return Ok(());
+                };
+                let name = name.trim();
+                if ui.profiles.borrow().set().get(name).is_some()
+                    && !ui
+                        .window
+                        .confirm_with_message(&format!("Replace profile {name}?"))?
+                {
+                    return Ok(());
+                }
+                match ui.save_profile(name) {
+                    Ok(()) => ui.show_toast(&format!("Profile {name} saved")),
+                    Err(e) => ui.window.alert_with_message(&format!(
+                        "Unable to save profile: {}",
+                        e.as_string().unwrap_or_default()
+                    )),
+                }
+            })();
+            if let Err(e) = result {
+                web_sys::console::error_1(&e);
+            }
+        })
+    }
+
+    fn profile_delete_button_onclick(&self) -> Closure<dyn Fn()> {
+        let ui = self.clone();
+        Closure::new(move || {
+            let Some(name) = ui.selected_profile() else {
+                return;
+            };
+            let result = (|| {
+                if ui
+                    .window
+                    .confirm_with_message(&format!("Delete profile {name}?"))?
+                {
+                    ui.delete_profile(&name)?;
+                }
+                Ok::<(), JsValue>(())
+            })();
+            if let Err(e) = result {
+                web_sys::console::error_1(&e);
+            }
+        })
+    }
+
+    fn profile_export_button_onclick(&self) -> Closure<dyn Fn()> {
+        let ui = self.clone();
+        Closure::new(move || {
+            if let Err(e) = ui.export_profiles() {
+                web_sys::console::error_1(&e);
+            }
+        })
+    }
+
+    fn profile_import_button_onclick(&self) -> Closure<dyn Fn()> {
+        let ui = self.clone();
+        Closure::new(move || ui.elements.profile_file.click())
+    }
+
+    fn profile_file_onchange(&self) -> Closure<dyn Fn() -> JsValue> {
+        let ui = self.clone();
+        Closure::new(move || {
+            let ui = ui.clone();
+            let input = Rc::clone(&ui.elements.profile_file);
+            Self::read_file_input(input, move |text| match ui.import_profiles(&text) {
+                Ok(count) => ui.show_toast(&format!("{count} profiles imported")),
+                Err(e) => ui.window.alert_with_message(&format!(
+                    "Unable to import profiles: {}",
+                    e.as_string().unwrap_or_default()
+                )),
+            })
+        })
+    }
+
+    /// Saves the current display settings as a named preset.
+    ///
+    /// The preset stores the colormap, the waterfall levels, the gamma and the
+    /// scaling of the colormap (whether it is interpolated in linear light). A
+    /// preset with the same name is replaced, but the built-in presets cannot
+    /// be replaced. The presets are kept in the browser local storage.
+    pub fn save_display_preset(&self, name: &str) -> Result<(), JsValue> {
+        let preset = display_presets::DisplayPreset {
+            colormap_select: self
+                .elements
+                .colormap_select
+                .get()
+                .ok_or("invalid colormap")?,
+            waterfall_min: self
+                .elements
+                .waterfall_min
+                .get()
+                .ok_or("invalid minimum level")?,
+            waterfall_max: self
+                .elements
+                .waterfall_max
+                .get()
+                .ok_or("invalid maximum level")?,
+            waterfall_gamma: self.elements.waterfall_gamma.get().ok_or("invalid gamma")?,
+            linear_light_colormap: self.elements.linear_light_colormap.get().unwrap_or(false),
+        };
+        self.display_presets.borrow_mut().insert(name, preset)?;
+        self.update_display_preset_select(Some(name))
+    }
+
+    /// Applies a named display preset.
+    ///
+    /// The colormap, the waterfall levels, the gamma and the colormap scaling
+    /// of the preset are set in the UI and applied to the waterfall together,
+    /// so that no frame is rendered with only some of them changed. The gamma
+    /// of the preset becomes the gamma of its colormap.
+    pub fn apply_display_preset(&self, name: &str) -> Result<(), JsValue> {
+        let preset = self
+            .display_presets
+            .borrow()
+            .get(name)
+            .ok_or_else(|| format!("unknown display preset {name}"))?;
+        preset.validate()?;
+        // The colormap is selected first, since selecting it restores its
+        // gamma, which is then replaced by the gamma of the preset. All the
+        // onchange closures run synchronously, before the next frame.
+        self.select_colormap(preset.colormap_select)?;
+        self.elements.waterfall_min.set(&preset.waterfall_min);
+        self.elements.waterfall_max.set(&preset.waterfall_max);
+        self.elements.waterfall_gamma.set(&preset.waterfall_gamma);
+        self.elements
+            .linear_light_colormap
+            .set(&preset.linear_light_colormap);
+        for onchange in [
+            self.elements.waterfall_min.onchange(),
+            self.elements.waterfall_max.onchange(),
+            self.elements.waterfall_gamma.onchange(),
+            self.elements.linear_light_colormap.onchange(),
+        ] {
+            onchange.unwrap().call0(&JsValue::NULL)?;
+        }
+        Ok(())
+    }
+
+    /// Deletes a named display preset.
+    ///
+    /// Returns `true` if the preset existed. The built-in presets cannot be
+    /// deleted.
+    pub fn delete_display_preset(&self, name: &str) -> Result<bool, JsValue> {
+        let removed = self.display_presets.borrow_mut().remove(name);
+        self.update_display_preset_select(None)?;
+        Ok(removed)
+    }
+
+    // Fills the display preset selector with the names of the presets,
+    // selecting the given preset or keeping the current selection if it still
+    // exists.
+    fn update_display_preset_select(&self, selected: Option<&str>) -> Result<(), JsValue> {
+        let select = &self.elements.display_preset_select;
+        let selected = selected
+            .map(|name| name.to_string())
+            .unwrap_or_else(|| select.value());
+        select.set_length(0);
+        for name in self.display_presets.borrow().names() {
+            select.add_with_html_option_element(&HtmlOptionElement::new_with_text_and_value(
+                name, name,
+            )?)?;
+        }
+        if !selected.is_empty() {
+            select.set_value(&selected);
+        }
+        self.update_display_preset_delete_button();
+        Ok(())
+    }
+
+    // The built-in presets cannot be deleted.
+    fn update_display_preset_delete_button(&self) {
+        let built_in = self.selected_display_preset().map_or(true, |name| {
+            display_presets::DisplayPresets::is_built_in(&name)
+        });
+        self.elements
+            .display_preset_delete_button
+            .set_disabled(built_in);
+    }
+
+    // Returns the name of the preset selected in the display preset selector.
+    fn selected_display_preset(&self) -> Option<String> {
+        Some(self.elements.display_preset_select.value()).filter(|name| !name.is_empty())
+    }
+
+    fn display_preset_select_onchange(&self) -> Closure<dyn Fn()> {
+        let ui = self.clone();
+        Closure::new(move || ui.update_display_preset_delete_button())
+    }
+
+    fn display_preset_apply_button_onclick(&self) -> Closure<dyn Fn()> {
+        let ui = self.clone();
+        Closure::new(move || {
+            let Some(name) = ui.selected_display_preset() else {
+                return;
+            };
+            let result = match ui.apply_display_preset(&name) {
+                Ok(()) => ui.show_toast(&format!("Display preset {name} applied")),
+                Err(e) => ui.window.alert_with_message(&format!(
+                    "Unable to apply display preset: {}",
+                    e.as_string().unwrap_or_default()
+                )),
+            };
+            if let Err(e) = result {
+                web_sys::console::error_1(&e);
+            }
+        })
+    }
+
+    fn display_preset_save_button_onclick(&self) -> Closure<dyn Fn()> {
+        let ui = self.clone();
+        Closure::new(move || {
+            let result = (|| {
+                let default = ui
+                    .selected_display_preset()
+                    .filter(|name| !display_presets::DisplayPresets::is_built_in(name))
+                    .unwrap_or_default();
+                let Some(name) = ui
+                    .window
+                    .prompt_with_message_and_default("Display preset name", &default)?
+                else {
+                    return Ok(());
+                };
+                let name = name.trim();
+                if !display_presets::DisplayPresets::is_built_in(name)
+                    && ui.display_presets.borrow().get(name).is_some()
+                    && !ui
+                        .window
+                        .confirm_with_message(&format!("Replace display preset {name}?"))?
+                {
+                    return Ok(());
+                }
+                match ui.save_display_preset(name) {
+                    Ok(()) => ui.show_toast(&format!("Display preset {name} saved")),
+                    Err(e) => ui.window.alert_with_message(&format!(
+                        "Unable to save display preset: {}",
+                        e.as_string().unwrap_or_default()
+                    )),
+                }
+            })();
+            if let Err(e) = result {
+                web_sys::console::error_1(&e);
+            }
+        })
+    }
+
+    fn display_preset_delete_button_onclick(&self) -> Closure<dyn Fn()> {
+        let ui = self.clone();
+        Closure::new(move || {
+            let Some(name) = ui.selected_display_preset() else {
+                return;
+            };
+            let result = (|| {
+                if ui
+                    .window
+                    .confirm_with_message(&format!("Delete display preset {name}?"))?
+                {
+                    ui.delete_display_preset(&name)?;
+                }
+                Ok::<(), JsValue>(())
+            })();
+            if let Err(e) = result {
+                web_sys::console::error_1(&e);
+            }
+        })
+    }
+
+    fn diagnostics_button_onclick(&self) -> Closure<dyn Fn()> {
+        let ui = self.clone();
+        Closure::new(move || {
+            let text = match ui.render_engine.borrow().capabilities() {
+                Ok(capabilities) => capabilities.to_string(),
+                Err(e) => format!(
+                    "Unable to query capabilities: {}",
+                    e.as_string().unwrap_or_default()
+                ),
+            };
+            ui.elements.diagnostics_text.set_text_content(Some(&text));
+            ui.elements.diagnostics_dialog.show_modal().unwrap();
+        })
+    }
+
+    fn processing_button_onclick(&self) -> Closure<dyn Fn() -> JsValue> {
+        let ui = self.clone();
+        Closure::new(move || {
+            let ui = ui.clone();
+            future_to_promise(async move {
+                let text = match ui.get_spectrometer().await {
+                    Ok(json) => processing::describe(&json, &ui.number_format()),
+                    Err(e) => format!(
+                        "Unable to get the spectrometer parameters: {}",
+                        e.as_string().unwrap_or_default()
+                    ),
+                };
+                ui.elements.processing_text.set_text_content(Some(&text));
+                ui.elements.processing_dialog.show_modal()?;
+                Ok(JsValue::NULL)
+            })
+            .into()
+        })
+    }
+
+    /// Starts a scan.
+    ///
+    /// The scan steps the RX LO frequency from the start to the stop frequency
+    /// configured in the UI, dwelling at each step for the configured time.
+    /// The peaks of the spectrum lines received at each step are captured, so
+    /// that the band covered by the scan can be surveyed. An error is returned
+    /// if a scan is already running or if the scan settings are invalid.
+    pub fn start_scan(&self) -> Result<(), JsValue> {
+        if self.scan_state.get() != scan::ScanState::Stopped {
+            return Err("a scan is already running".into());
+        }
+        let plan = scan::ScanPlan::new(
+            self.elements.scan_start.get().ok_or("invalid scan start")?,
+            self.elements.scan_stop.get().ok_or("invalid scan stop")?,
+            self.elements
+                .scan_step
+                .get()
+                .ok_or("invalid scan step")?
+                .into(),
+        )?;
+        let dwell_ms = self.elements.scan_dwell.get().ok_or("invalid scan dwell")?;
+        self.scan_steps.borrow_mut().clear();
+        self.set_scan_state(scan::ScanState::Running);
+        self.waterfall
+            .borrow_mut()
+            .set_edge_mask(Some(self.scan_edge_fraction() as f32));
+        let ui = self.clone();
+        let _ = future_to_promise(async move {
+            let result = ui.run_scan(plan, dwell_ms).await;
+            {
+                let mut waterfall = ui.waterfall.borrow_mut();
+                waterfall.take_peak_capture();
+                waterfall.set_edge_mask(None);
+            }
+            ui.set_scan_state(scan::ScanState::Stopped);
+            result.map(|()| JsValue::NULL)
+        });
+        Ok(())
+    }
+
+    /// Stops the scan.
+    ///
+    /// The scan stops after the current step. The peaks captured in the
+    /// previous steps are kept.
+    pub fn stop_scan(&self) {
+        if matches!(
+            self.scan_state.get(),
+            scan::ScanState::Running | scan::ScanState::Paused
+        ) {
+            self.set_scan_state(scan::ScanState::Stopping);
+        }
+    }
+
+    /// Pauses or resumes the scan.
+    ///
+    /// When the scan is paused, it stays at the current step until it is
+    /// resumed.
+    pub fn toggle_scan_pause(&self) {
+        match self.scan_state.get() {
+            scan::ScanState::Running => self.set_scan_state(scan::ScanState::Paused),
+            scan::ScanState::Paused => self.set_scan_state(scan::ScanState::Running),
+            scan::ScanState::Stopped | scan::ScanState::Stopping => (),
+        }
+    }
+
+    // Fraction of the band masked at each edge of the scan steps, which is set
+    // in the UI as a percentage.
+    fn scan_edge_fraction(&self) -> f64 {
+        f64::from(self.elements.scan_edge_mask.get().unwrap_or(0.0)) * 0.01
+    }
+
+    fn scan_edge_mask_onchange(&self) -> Closure<dyn Fn()> {
+        let ui = self.clone();
+        Closure::new(move || {
+            let element = &ui.elements.scan_edge_mask;
+            if !element.report_validity() {
+                return;
+            }
+            let Some(value) = element.get() else {
+                return;
+            };
+            // The mask shown in the waterfall is updated if a scan is in
+            // progress. The mask is applied to the results when they are
+            // shown, so the results of a finished scan can be stitched again
+            // with a different mask.
+            if ui.scan_state.get() != scan::ScanState::Stopped {
+                ui.waterfall
+                    .borrow_mut()
+                    .set_edge_mask(Some(ui.scan_edge_fraction() as f32));
+            }
+            // try_borrow_mut prevents trying to update the preferences as a
+            // consequence of the Preferences::apply_client calling this
+            // function
+            if let Ok(mut p) = ui.preferences.try_borrow_mut() {
+                if let Err(e) = p.update_scan_edge_mask(&value) {
+                    web_sys::console::error_1(&e);
+                }
+            }
+        })
+    }
+
+    fn scan_plot_log_frequency_onchange(&self) -> Closure<dyn Fn()> {
+        let ui = self.clone();
+        Closure::new(move || {
+            let Some(value) = ui.elements.scan_plot_log_frequency.get() else {
+                return;
+            };
+            if let Err(e) = ui.scan_plot.set_log_frequency(value) {
+                web_sys::console::error_1(&e);
+            }
+            // try_borrow_mut prevents trying to update the preferences as a
+            // consequence of the Preferences::apply_client calling this
+            // function
+            if let Ok(mut p) = ui.preferences.try_borrow_mut() {
+                if let Err(e) = p.update_scan_plot_log_frequency(&value) {
+                    web_sys::console::error_1(&e);
+                }
+            }
+        })
+    }
+
+    fn set_scan_state(&self, state: scan::ScanState) {
+        self.scan_state.set(state);
+        let (button, pause, status) = match state {
+            scan::ScanState::Stopped => ("Scan", "Pause", None),
+            scan::ScanState::Running => ("Stop scan", "Pause", None),
+            scan::ScanState::Paused => ("Stop scan", "Resume", Some("Paused")),
+            scan::ScanState::Stopping => ("Stop scan", "Pause", Some("Stopping")),
+        };
+        self.elements.scan_button.set_text_content(Some(button));
+        self.elements
+            .scan_pause_button
+            .set_text_content(Some(pause));
+        self.elements.scan_pause_button.set_disabled(matches!(
+            state,
+            scan::ScanState::Stopped | scan::ScanState::Stopping
+        ));
+        if let Some(status) = status {
+            self.elements.scan_status.set_text_content(Some(status));
+        }
+    }
+
+    async fn run_scan(&self, plan: scan::ScanPlan, dwell_ms: u32) -> Result<(), JsValue> {
+        // Polling interval used while the scan is paused.
+        const PAUSE_POLL_MS: u32 = 100;
+        let num_steps = plan.num_steps();
+        let mut failed = 0;
+        for n in 0..num_steps {
+            while self.scan_state.get() == scan::ScanState::Paused {
+                self.sleep(PAUSE_POLL_MS).await?;
+            }
+            if self.scan_state.get() == scan::ScanState::Stopping {
+                break;
+            }
+            // n is always a valid step
+            let frequency = plan.frequency(n).unwrap();
+            self.elements.scan_status.set_text_content(Some(&format!(
+                "Step {}/{num_steps}: {}",
+                n + 1,
+                self.number_format()
+                    .frequency(frequency as f64, FrequencyUnit::MHz, 3)
+            )));
+            let patch = maia_json::PatchAd9361 {
+                rx_lo_frequency: Some(frequency),
+                ..Default::default()
+            };
+            // A step that fails to tune is skipped, so that a frequency that
+            // the device rejects does not abort the whole scan.
+            if let Err(e) = self.patch_ad9361_update_elements(&patch).await {
+                web_sys::console::error_1(&e);
+                failed += 1;
+                continue;
+            }
+            // The capture is started once the device has been tuned, so that
+            // it does not include spectrum lines of the previous step.
+            self.waterfall.borrow_mut().start_peak_capture();
+            self.sleep(dwell_ms).await?;
+            let mut waterfall = self.waterfall.borrow_mut();
+            if let Some(peaks) = waterfall.take_peak_capture() {
+                let (_, samp_rate) = waterfall.get_freq_samprate();
+                self.scan_steps.borrow_mut().push(scan::ScanStep {
+                    rx_lo_frequency: frequency,
+                    samp_rate,
+                    peaks,
+                });
+            }
+        }
+        let captured = self.scan_steps.borrow().len();
+        let status = if failed == 0 {
+            format!("Scan finished: {captured} steps")
+        } else {
+            format!("Scan finished: {captured} steps, {failed} failed")
+        };
+        self.elements.scan_status.set_text_content(Some(&status));
+        Ok(())
+    }
+
+    async fn sleep(&self, milliseconds: u32) -> Result<(), JsValue> {
+        let window = Rc::clone(&self.window);
+        let mut result = Ok(());
+        let promise = js_sys::Promise::new(&mut |resolve, _| {
+            result = window
+                .set_timeout_with_callback_and_timeout_and_arguments_0(
+                    &resolve,
+                    milliseconds.try_into().unwrap_or(i32::MAX),
+                )
+                .map(|_| ());
+        });
+        result?;
+        JsFuture::from(promise).await?;
+        Ok(())
+    }
+
+    // The tuning knob is not stored in the preferences, since browsers only
+    // expose gamepads to the page after the user interacts with them.
+    fn tuning_knob_enabled_onchange(&self) -> Closure<dyn Fn()> {
+        let ui = self.clone();
+        Closure::new(move || {
+            let result = if ui.elements.tuning_knob_enabled.get().unwrap_or(false) {
+                ui.start_tuning_knob()
+            } else {
+                ui.stop_tuning_knob();
+                Ok(())
+            };
+            if let Err(e) = result {
+                web_sys::console::error_1(&e);
+                ui.stop_tuning_knob();
+                ui.elements.tuning_knob_enabled.set(&false);
+                ui.window
+                    .alert_with_message(
+                        "Gamepads are not available. They might be blocked by the browser, \
+                         which only allows them in pages served over HTTPS.",
+                    )
+                    .unwrap();
+            }
+        })
+    }
+
+    // Starts polling the gamepads to tune the RX LO frequency with them.
+    fn start_tuning_knob(&self) -> Result<(), JsValue> {
+        // Interval in milliseconds between polls of the gamepads. This is
+        // short so that the pulses of rotary encoders are not missed.
+        const POLL_INTERVAL_MS: i32 = 20;
+        self.stop_tuning_knob();
+        // Fails if gamepads are not allowed, which happens in insecure
+        // contexts and when the page has a permissions policy that blocks
+        // them.
+        let gamepads = self.window.navigator().get_gamepads()?;
+        if !gamepads.iter().any(|pad| !pad.is_null()) {
+            self.show_toast("Press a button on the controller to connect it")?;
+        }
+        let ui = self.clone();
+        let poll = Closure::<dyn Fn()>::new(move || {
+            if let Err(e) = ui.poll_tuning_knob() {
+                web_sys::console::error_1(&e);
+            }
+        });
+        let handle = self
+            .window
+            .set_interval_with_callback_and_timeout_and_arguments_0(
+                poll.into_js_value().unchecked_ref(),
+                POLL_INTERVAL_MS,
+            )?;
+        self.tuning_knob_interval.set(Some(handle));
+        Ok(())
+    }
+
+    fn stop_tuning_knob(&self) {
+        if let Some(handle) = self.tuning_knob_interval.take() {
+            self.window.clear_interval_with_handle(handle);
+        }
+        self.tuning_knob.borrow_mut().reset();
+    }
+
+    fn poll_tuning_knob(&self) -> Result<(), JsValue> {
+        let pads = self
+            .window
+            .navigator()
+            .get_gamepads()?
+            .iter()
+            // Disconnected gamepads are given as null
+            .filter_map(|pad| pad.dyn_into::<web_sys::Gamepad>().ok())
+            .filter(|pad| pad.connected())
+            .map(|pad| tuning_knob::PadInput {
+                index: pad.index(),
+                id: pad.id(),
+                buttons: pad
+                    .buttons()
+                    .iter()
+                    .map(|button| {
+                        button
+                            .dyn_into::<web_sys::GamepadButton>()
+                            .is_ok_and(|button| button.pressed())
+                    })
+                    .collect(),
+                axes: pad
+                    .axes()
+                    .iter()
+                    .map(|axis| axis.as_f64().unwrap_or(0.0))
+                    .collect(),
+            })
+            .collect::<Vec<_>>();
+        let update = self.tuning_knob.borrow_mut().update(pads);
+        for id in &update.connected {
+            self.show_toast(&format!("Tuning with {id}"))?;
+        }
+        for id in &update.disconnected {
+            self.show_toast(&format!("{id} disconnected"))?;
+        }
+        let Some(step) = self
+            .elements
+            .tuning_knob_step
+            .get()
+            .filter(|&step| step != 0)
+        else {
+            return Ok(());
+        };
+        let Some(mut freq) = self.elements.ad9361_rx_lo_frequency.get().map(|f| f as f64) else {
+            return Ok(());
+        };
+        if update.steps == 0 {
+            return Ok(());
+        }
+        for _ in 0..update.steps.unsigned_abs() {
+            freq = WaterfallInteraction::wheel_tuning_frequency(
+                freq,
+                f64::from(step),
+                update.steps > 0,
+            );
+        }
+        if freq.is_finite() && freq > 0.0 {
+            self.tune_rx_lo_frequency(freq as u64)?;
+        }
+        Ok(())
+    }
+
+    // The tone is not stored in the preferences, since browsers only allow
+    // starting audio in response to a user action.
+    fn tone_enabled_onchange(&self) -> Closure<dyn Fn()> {
+        let ui = self.clone();
+        Closure::new(move || {
+            let result = if ui.elements.tone_enabled.get().unwrap_or(false) {
+                ui.start_tone()
+            } else {
+                ui.stop_tone();
+                Ok(())
+            };
+            if let Err(e) = result {
+                web_sys::console::error_1(&e);
+                ui.stop_tone();
+                ui.elements.tone_enabled.set(&false);
+            }
+        })
+    }
+
+    // Starts the signal strength tone, which tracks the power at the RX LO
+    // frequency.
+    fn start_tone(&self) -> Result<(), JsValue> {
+        // Interval in milliseconds between updates of the tone.
+        const UPDATE_INTERVAL_MS: i32 = 50;
+        self.stop_tone();
+        self.tone.replace(Some(tone::Tone::new()?));
+        let ui = self.clone();
+        let update = Closure::<dyn Fn()>::new(move || {
+            if let Err(e) = ui.update_tone() {
+                web_sys::console::error_1(&e);
+            }
+        });
+        let handle = self
+            .window
+            .set_interval_with_callback_and_timeout_and_arguments_0(
+                update.into_js_value().unchecked_ref(),
+                UPDATE_INTERVAL_MS,
+            )?;
+        self.tone_interval.set(Some(handle));
+        Ok(())
+    }
+
+    // Stops the signal strength tone. Dropping the tone closes its audio
+    // context.
+    fn stop_tone(&self) {
+        if let Some(handle) = self.tone_interval.take() {
+            self.window.clear_interval_with_handle(handle);
+        }
+        self.tone.replace(None);
+    }
+
+    fn update_tone(&self) -> Result<(), JsValue> {
+        let tone = self.tone.borrow();
+        let Some(tone) = tone.as_ref() else {
+            return Ok(());
+        };
+        let Some(power) = self.waterfall.borrow().get_rx_lo_power() else {
+            return Ok(());
+        };
+        let mapping = self
+            .elements
+            .tone_mapping
+            .get()
+            .unwrap_or(tone::ToneMapping::Pitch);
+        tone.set(mapping.parameters(
+            power,
+            self.elements.tone_min_db.get().unwrap_or(35.0),
+            self.elements.tone_max_db.get().unwrap_or(85.0),
+        ))
+    }
+
+    fn scan_button_onclick(&self) -> Closure<dyn Fn()> {
+        let ui = self.clone();
+        Closure::new(move || {
+            if ui.scan_state.get() != scan::ScanState::Stopped {
+                // A scan that is stopping cannot be restarted until its
+                // current step finishes.
+                ui.stop_scan();
+            } else if let Err(e) = ui.start_scan() {
+                ui.window
+                    .alert_with_message(&format!(
+                        "Unable to start scan: {}",
+                        e.as_string().unwrap_or_default()
+                    ))
+                    .unwrap();
+            }
+        })
+    }
+
+    fn scan_pause_button_onclick(&self) -> Closure<dyn Fn()> {
+        let ui = self.clone();
+        Closure::new(move || ui.toggle_scan_pause())
+    }
+
+    /// Shows the wide-band spectrum obtained in the last scan.
+    ///
+    /// The peaks captured at each step of the scan are stitched into a single
+    /// spectrum spanning the scanned range, which is shown in a dialog with its
+    /// own zoom and pan. An error is returned if there are no scan results.
+    pub fn show_scan_results(&self) -> Result<(), JsValue> {
+        let spectrum = scan::StitchedSpectrum::from_steps(
+            &self.scan_steps.borrow(),
+            self.scan_edge_fraction(),
+            self.waterfall.borrow().get_dc_exclusion_bins(),
+        )
+        .ok_or("there are no scan results")?;
+        self.scan_plot.set_number_format(self.number_format());
+        self.scan_plot.set_levels(
+            self.elements.waterfall_min.get().unwrap_or(35.0),
+            self.elements.waterfall_max.get().unwrap_or(85.0),
+        )?;
+        self.scan_plot.set_spectrum(Some(spectrum))?;
+        self.elements.scan_plot_dialog.show_modal()
+    }
+
+    fn scan_results_button_onclick(&self) -> Closure<dyn Fn()> {
+        let ui = self.clone();
+        Closure::new(move || {
+            if let Err(e) = ui.show_scan_results() {
+                ui.window
+                    .alert_with_message(&format!(
+                        "Unable to show scan results: {}",
+                        e.as_string().unwrap_or_default()
+                    ))
+                    .unwrap();
+            }
+        })
+    }
+
+    fn scan_export_csv_button_onclick(&self) -> Closure<dyn Fn()> {
+        let ui = self.clone();
+        Closure::new(move || {
+            let spectrum = ui.scan_plot.spectrum();
+            let Some(spectrum) = spectrum.as_ref() else {
+                return;
+            };
+            if let Err(e) = export::download_csv(
+                &ui.document,
+                &spectrum.to_csv(ui.power_calibration()),
+                &export::timestamped_filename("maia-sdr-scan", "csv"),
+            ) {
+                web_sys::console::error_1(&e);
+            }
+        })
+    }
+
+    fn scan_export_png_button_onclick(&self) -> Closure<dyn Fn()> {
+        let ui = self.clone();
+        Closure::new(move || {
+            if let Err(e) = export::download_png(
+                &ui.document,
+                ui.scan_plot.canvas(),
+                &export::timestamped_filename("maia-sdr-scan", "png"),
+            ) {
+                web_sys::console::error_1(&e);
+            }
+        })
+    }
+
+    fn export_canvas(&self) -> Result<web_sys::HtmlCanvasElement, JsValue> {
+        let text = self.elements.export_overlay_text.get().unwrap_or_default();
+        let overlay = export::Overlay {
+            text: text.trim(),
+            position: self
+                .elements
+                .export_overlay_position
+                .get()
+                .unwrap_or(export::OverlayPosition::BottomLeft),
+            font_size: self.elements.export_overlay_font_size.get().unwrap_or(16),
+        };
+        let annotations = Some(self.annotations.canvas())
+            .filter(|_| self.is_annotating() && !self.annotations.is_empty());
+        export::render_to_canvas(
+            &self.document,
+            &mut self.render_engine.borrow_mut(),
+            &overlay,
+            annotations,
+        )
+    }
+
+    /// Returns `true` if the waterfall is being annotated.
+    ///
+    /// See [`Ui::set_annotating`].
+    pub fn is_annotating(&self) -> bool {
+        self.elements
+            .annotation_canvas
+            .class_list()
+            .contains("annotating")
+    }
+
+    /// Starts or stops annotating the waterfall.
+    ///
+    /// While annotating, the waterfall is frozen and the pointer draws
+    /// freehand lines, rectangles or text over it instead of panning and
+    /// zooming. The annotations are included in the exported images. They
+    /// are cleared when annotating stops or when the waterfall canvas changes
+    /// size, since they refer to the frozen image.
+    pub fn set_annotating(&self, enable: bool) -> Result<(), JsValue> {
+        self.elements
+            .annotation_canvas
+            .class_list()
+            .toggle_with_force("annotating", enable)?;
+        self.waterfall.borrow_mut().set_frozen(enable);
+        self.elements
+            .annotate_button
+            .set_text_content(Some(if enable { "Exit annotate" } else { "Annotate" }));
+        if enable {
+            // The canvas is only shown while annotating, so its size is
+            // unknown until now.
+            self.resize_annotations()
+        } else {
+            self.annotations.clear()
+        }
+    }
+
+    // Resizes the annotation canvas to match the waterfall canvas, which is
+    // needed after the waterfall canvas is resized. The annotations are
+    // cleared if the size on the screen changes.
+    fn resize_annotations(&self) -> Result<(), JsValue> {
+        if !self.is_annotating() {
+            return Ok(());
+        }
+        let device_pixel_ratio = self
+            .render_engine
+            .borrow()
+            .canvas_dims()
+            .device_pixel_ratio();
+        self.annotations.resize(device_pixel_ratio)
+    }
+
+    fn annotate_button_onclick(&self) -> Closure<dyn Fn()> {
+        let ui = self.clone();
+        Closure::new(move || {
+            let enable = !ui.is_annotating();
+            if let Err(e) = ui.set_annotating(enable) {
+                web_sys::console::error_1(&e);
+            }
+            let message = if enable {
+                "Annotating (waterfall frozen)"
+            } else {
+                "Annotations cleared"
+            };
+            if let Err(e) = ui.show_toast(message) {
+                web_sys::console::error_1(&e);
+            }
+        })
+    }
+
+    fn annotation_clear_button_onclick(&self) -> Closure<dyn Fn()> {
+        let ui = self.clone();
+        Closure::new(move || {
+            if let Err(e) = ui.annotations.clear() {
+                web_sys::console::error_1(&e);
+            }
+        })
+    }
+
+    fn annotation_tool_onchange(&self) -> Closure<dyn Fn()> {
+        self.annotation_settings_onchange()
+    }
+
+    fn annotation_color_onchange(&self) -> Closure<dyn Fn()> {
+        self.annotation_settings_onchange()
+    }
+
+    // Applies the tool and color used for the next annotations.
+    fn annotation_settings_onchange(&self) -> Closure<dyn Fn()> {
+        let ui = self.clone();
+        Closure::new(move || {
+            let tool = ui
+                .elements
+                .annotation_tool
+                .get()
+                .unwrap_or(annotations::AnnotationTool::Freehand);
+            let color = ui.elements.annotation_color.get().unwrap_or_default();
+            ui.annotations.set_tool(tool);
+            ui.annotations.set_color(&color);
+            // try_borrow_mut prevents trying to update the preferences as a
+            // consequence of the Preferences::apply_client calling this
+            // function
+            if let Ok(mut p) = ui.preferences.try_borrow_mut() {
+                let results = [
+                    p.update_annotation_tool(&tool),
+                    p.update_annotation_color(&color),
+                ];
+                for result in results {
+                    if let Err(e) = result {
+                        web_sys::console::error_1(&e);
+                    }
+                }
+            }
+        })
+    }
+
+    fn update_waterfall_rate(&self, json: &maia_json::Spectrometer) {
+        self.waterfall
+            .borrow_mut()
+            .set_waterfall_update_rate(json.output_sampling_frequency as f32);
+    }
+
+    fn update_processing_status(&self, json: &maia_json::Spectrometer) {
+        let text = processing::summary(json, &self.number_format());
+        if self.elements.processing_status.text_content().as_deref() != Some(text.as_str()) {
+            self.elements
+                .processing_status
+                .set_text_content(Some(&text));
+        }
+    }
+
+    impl_patch!(time, maia_json::PatchTime, maia_json::Time, TIME_URL);
+
+    async fn update_server_time(&self, json: &maia_json::Time) -> Result<(), JsValue> {
+        let threshold = 1000.0; // update server time if off by more than 1 sec
+        let milliseconds = js_sys::Date::now();
+        if (milliseconds - json.time).abs() >= threshold {
+            let patch = maia_json::PatchTime {
+                time: Some(milliseconds),
+            };
+            self.patch_time(&patch).await?;
+        }
+        Ok(())
+    }
+
+    fn update_status_bar(&self) {
+        let noise_floor_db = self.waterfall.borrow().get_noise_floor();
+        let calibration = self.power_calibration();
+        let noise_floor = match noise_floor_db {
+            Some(x) => self
+                .number_format()
+                .number(f64::from(x + calibration.unwrap_or(0.0)), 1),
+            None => "--".to_string(),
+        };
+        if self.elements.noise_floor.inner_html() != noise_floor {
+            self.elements.noise_floor.set_inner_html(&noise_floor);
+        }
+        let unit = if calibration.is_some() { "dBm" } else { "dB" };
+        if self.elements.noise_floor_unit.inner_html() != unit {
+            self.elements.noise_floor_unit.set_inner_html(unit);
+        }
+        // The clipping warning uses the noise floor at the ADC, since the
+        // calibration table does not change the power at the ADC.
+        let uncorrected_noise_floor_db = self.waterfall.borrow().get_uncorrected_noise_floor();
+        self.update_clipping_warning(uncorrected_noise_floor_db);
+    }
+
+    // Returns the offset in dB that converts the power to dBm, or None if the
+    // calibration is disabled. The frequency dependent correction table is
+    // not included, since it is applied by the waterfall to the spectrum
+    // lines.
+    fn power_calibration(&self) -> Option<f32> {
+        if !self.elements.calibrated_power.get().unwrap_or(false) {
+            return None;
+        }
+        Some(self.elements.calibration_offset.get().unwrap_or(0.0))
+    }
+
+    // Applies the correction table to the spectrum lines in the waterfall.
+    fn update_power_correction(&self) {
+        let table = self.calibration.borrow().table().clone();
+        let correction: Option<Box<dyn Fn(f64) -> f32>> = if table.is_empty() {
+            None
+        } else {
+            let extrapolation = self
+                .elements
+                .calibration_extrapolation
+                .get()
+                .unwrap_or(calibration::Extrapolation::Hold);
+            Some(Box::new(move |frequency| {
+                table.offset(frequency, extrapolation)
+            }))
+        };
+        self.waterfall.borrow_mut().set_power_correction(correction);
+    }
+
+    fn calibration_extrapolation_onchange(&self) -> Closure<dyn Fn()> {
+        let ui = self.clone();
+        Closure::new(move || {
+            let Some(value) = ui.elements.calibration_extrapolation.get() else {
+                return;
+            };
+            ui.update_power_correction();
+            // try_borrow_mut prevents trying to update the preferences as a
+            // consequence of the Preferences::apply_client calling this
+            // function
+            if let Ok(mut p) = ui.preferences.try_borrow_mut() {
+                if let Err(e) = p.update_calibration_extrapolation(&value) {
+                    web_sys::console::error_1(&e);
+                }
+            }
+        })
+    }
+
+    fn calibration_table_load_button_onclick(&self) -> Closure<dyn Fn()> {
+        let ui = self.clone();
+        Closure::new(move || ui.elements.calibration_table_file.click())
+    }
+
+    fn calibration_table_file_onchange(&self) -> Closure<dyn Fn() -> JsValue> {
+        let ui = self.clone();
+        Closure::new(move || {
+            let ui = ui.clone();
+            let input = Rc::clone(&ui.elements.calibration_table_file);
+            Self::read_file_input(
+                input,
+                move |text| match calibration::CorrectionTable::from_csv(&text) {
+                    Ok(table) => {
+                        let len = table.len();
+                        ui.calibration.borrow_mut().set_table(table);
+                        ui.update_power_correction();
+                        ui.show_toast(&format!("Calibration table with {len} entries loaded"))
+                    }
+                    Err(e) => ui
+                        .window
+                        .alert_with_message(&format!("Unable to load calibration table: {e}")),
+                },
+            )
+        })
+    }
+
+    fn calibration_table_clear_button_onclick(&self) -> Closure<dyn Fn()> {
+        let ui = self.clone();
+        Closure::new(move || {
+            ui.calibration
+                .borrow_mut()
+                .set_table(calibration::CorrectionTable::default());
+            ui.update_power_correction();
+            if let Err(e) = ui.show_toast("Calibration table cleared") {
+                web_sys::console::error_1(&e);
+            }
+        })
+    }
+
+    // Shows a warning if the noise floor suggests that the ADC is clipping.
+    fn update_clipping_warning(&self, noise_floor: Option<f32>) {
+        // A threshold of zero disables the warning.
+        let threshold = self
+            .elements
+            .clipping_threshold
+            .get()
+            .filter(|&threshold| threshold > 0.0);
+        let clipping = self
+            .clipping_detector
+            .borrow_mut()
+            .update(noise_floor, threshold);
+        let _ = self
+            .elements
+            .clipping_warning
+            .class_list()
+            .toggle_with_force("clipping_warning_visible", clipping);
+    }
+
+    fn update_health_status(&self, health: &maia_json::Health) -> Result<(), JsValue> {
+        // Temperature in degrees Celsius above which the health status is
+        // shown as a warning.
+        const HIGH_TEMPERATURE: f64 = 80.0;
+        let format = self.number_format();
+        // Fields that the device does not report are omitted.
+        let fields = [
+            health
+                .fpga_temperature
+                .map(|x| format!("FPGA {} \u{b0}C", format.number(x, 1))),
+            health
+                .ad9361_temperature
+                .map(|x| format!("AD9361 {} \u{b0}C", format.number(x, 1))),
+            health
+                .cpu_load
+                .map(|x| format!("CPU load {}", format.number(x, 2))),
+            health
+                .memory_usage
+                .map(|x| format!("Memory {}%", format.number(100.0 * x, 0))),
+        ];
+        let text = fields.into_iter().flatten().collect::<Vec<_>>().join(", ");
+        if self.elements.health_status.text_content().as_deref() != Some(text.as_str()) {
+            self.elements.health_status.set_text_content(Some(&text));
+        }
+        let high_temperature = [health.fpga_temperature, health.ad9361_temperature]
+            .into_iter()
+            .flatten()
+            .any(|x| x >= HIGH_TEMPERATURE);
+        self.elements
+            .health_status
+            .class_list()
+            .toggle_with_force("health_warning", high_temperature)?;
         Ok(())
     }
 
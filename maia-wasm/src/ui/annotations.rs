@@ -0,0 +1,306 @@
+use serde::{Deserialize, Serialize};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement, PointerEvent, WheelEvent};
+
+// Annotations drawn over the waterfall while it is frozen.
+//
+// The annotations are drawn in a 2D canvas that is placed over the waterfall
+// canvas. They are given in screen space, in CSS pixels, so they refer to the
+// image that is on the screen rather than to frequencies and times. While
+// annotating, the annotation canvas receives the pointer events, so the
+// waterfall cannot be panned or zoomed with the pointer.
+pub struct Annotations {
+    canvas: Rc<HtmlCanvasElement>,
+    annotations: RefCell<Vec<Annotation>>,
+    tool: Cell<AnnotationTool>,
+    color: RefCell<String>,
+    // Pointer ID of the annotation being drawn, which is the last one
+    drawing: Cell<Option<i32>>,
+    // Size of the canvas in CSS pixels when the annotations were drawn
+    client_size: Cell<(i32, i32)>,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum AnnotationTool {
+    Freehand,
+    Rectangle,
+    Text,
+}
+
+impl std::str::FromStr for AnnotationTool {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<AnnotationTool, ()> {
+        Ok(match s {
+            "Freehand" => AnnotationTool::Freehand,
+            "Rectangle" => AnnotationTool::Rectangle,
+            "Text" => AnnotationTool::Text,
+            _ => return Err(()),
+        })
+    }
+}
+
+impl std::fmt::Display for AnnotationTool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        write!(
+            f,
+            "{}",
+            match self {
+                AnnotationTool::Freehand => "Freehand",
+                AnnotationTool::Rectangle => "Rectangle",
+                AnnotationTool::Text => "Text",
+            }
+        )
+    }
+}
+
+// Annotation, with coordinates in CSS pixels relative to the canvas.
+#[derive(Debug, Clone, PartialEq)]
+enum Annotation {
+    Freehand {
+        points: Vec<(f64, f64)>,
+        color: String,
+    },
+    Rectangle {
+        start: (f64, f64),
+        end: (f64, f64),
+        color: String,
+    },
+    Text {
+        position: (f64, f64),
+        text: String,
+        color: String,
+    },
+}
+
+impl Annotations {
+    // Width of the lines in CSS pixels.
+    const LINE_WIDTH: f64 = 2.0;
+    // Font size of the text in CSS pixels.
+    const FONT_SIZE: f64 = 16.0;
+
+    pub fn new(canvas: Rc<HtmlCanvasElement>) -> Rc<Annotations> {
+        Rc::new(Annotations {
+            canvas,
+            annotations: RefCell::new(Vec::new()),
+            tool: Cell::new(AnnotationTool::Freehand),
+            color: RefCell::new("#ffff00".to_string()),
+            drawing: Cell::new(None),
+            client_size: Cell::new((0, 0)),
+        })
+    }
+
+    pub fn set_callbacks(self: &Rc<Self>) {
+        self.canvas
+            .set_onwheel(Some(Self::onwheel().into_js_value().unchecked_ref()));
+        self.canvas
+            .set_onpointerdown(Some(self.onpointerdown().into_js_value().unchecked_ref()));
+        self.canvas
+            .set_onpointermove(Some(self.onpointermove().into_js_value().unchecked_ref()));
+        let onpointerup = self.onpointerup().into_js_value();
+        self.canvas
+            .set_onpointerup(Some(onpointerup.unchecked_ref()));
+        self.canvas
+            .set_onpointercancel(Some(onpointerup.unchecked_ref()));
+    }
+
+    // Sets the tool used for the next annotations.
+    pub fn set_tool(&self, tool: AnnotationTool) {
+        self.tool.set(tool);
+    }
+
+    // Sets the color used for the next annotations, as a CSS color.
+    pub fn set_color(&self, color: &str) {
+        self.color.replace(color.to_string());
+    }
+
+    pub fn canvas(&self) -> &HtmlCanvasElement {
+        &self.canvas
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.annotations.borrow().is_empty()
+    }
+
+    pub fn clear(&self) -> Result<(), JsValue> {
+        self.drawing.set(None);
+        self.annotations.borrow_mut().clear();
+        self.draw()
+    }
+
+    // Resizes the canvas to its size on the screen in device pixels, using the
+    // device pixel ratio of the waterfall canvas, so that the annotations have
+    // the same resolution as the waterfall in the exported images. The canvas
+    // must be shown.
+    //
+    // If the size of the canvas on the screen has changed, the annotations
+    // are cleared, since the waterfall image does not scale with the canvas
+    // and they would no longer line up with it.
+    pub fn resize(&self, device_pixel_ratio: f64) -> Result<(), JsValue> {
+        let client_size = (self.canvas.client_width(), self.canvas.client_height());
+        if self.client_size.replace(client_size) != client_size {
+            self.drawing.set(None);
+            self.annotations.borrow_mut().clear();
+        }
+        let width = (f64::from(self.canvas.client_width()) * device_pixel_ratio).round();
+        let height = (f64::from(self.canvas.client_height()) * device_pixel_ratio).round();
+        self.canvas.set_width(width as u32);
+        self.canvas.set_height(height as u32);
+        self.draw()
+    }
+
+    pub fn draw(&self) -> Result<(), JsValue> {
+        let context = self
+            .canvas
+            .get_context("2d")?
+            .ok_or("unable to get 2d context")?
+            .dyn_into::<CanvasRenderingContext2d>()?;
+        let width = f64::from(self.canvas.width());
+        let height = f64::from(self.canvas.height());
+        context.set_transform(1.0, 0.0, 0.0, 1.0, 0.0, 0.0)?;
+        context.clear_rect(0.0, 0.0, width, height);
+        let client_width = self.canvas.client_width();
+        if client_width <= 0 {
+            return Ok(());
+        }
+        // The annotations are given in CSS pixels.
+        let scale = width / f64::from(client_width);
+        context.scale(scale, scale)?;
+        context.set_line_width(Self::LINE_WIDTH);
+        context.set_line_cap("round");
+        context.set_line_join("round");
+        context.set_font(&format!("bold {}px sans", Self::FONT_SIZE));
+        context.set_text_align("left");
+        context.set_text_baseline("middle");
+        for annotation in self.annotations.borrow().iter() {
+            Self::draw_annotation(&context, annotation)?;
+        }
+        Ok(())
+    }
+
+    fn draw_annotation(
+        context: &CanvasRenderingContext2d,
+        annotation: &Annotation,
+    ) -> Result<(), JsValue> {
+        match annotation {
+            Annotation::Freehand { points, color } => {
+                let Some(&(x, y)) = points.first() else {
+                    return Ok(());
+                };
+                context.set_stroke_style(&JsValue::from_str(color));
+                context.begin_path();
+                context.move_to(x, y);
+                for &(x, y) in points.iter().skip(1) {
+                    context.line_to(x, y);
+                }
+                context.stroke();
+            }
+            Annotation::Rectangle { start, end, color } => {
+                context.set_stroke_style(&JsValue::from_str(color));
+                context.stroke_rect(start.0, start.1, end.0 - start.0, end.1 - start.1);
+            }
+            Annotation::Text {
+                position,
+                text,
+                color,
+            } => {
+                // An outline makes the text readable regardless of the
+                // waterfall colors.
+                context.set_line_width(0.2 * Self::FONT_SIZE);
+                context.set_stroke_style(&JsValue::from_str("black"));
+                context.stroke_text(text, position.0, position.1)?;
+                context.set_fill_style(&JsValue::from_str(color));
+                context.fill_text(text, position.0, position.1)?;
+                context.set_line_width(Self::LINE_WIDTH);
+            }
+        }
+        Ok(())
+    }
+
+    // Asks for the text of a text annotation. Returns None if the user
+    // cancels or enters an empty text.
+    fn prompt_text() -> Option<String> {
+        let text = web_sys::window()?
+            .prompt_with_message("Annotation text")
+            .ok()
+            .flatten()?;
+        let text = text.trim();
+        (!text.is_empty()).then(|| text.to_string())
+    }
+
+    fn onwheel() -> Closure<dyn Fn(WheelEvent)> {
+        // The wheel does not zoom the waterfall while annotating.
+        Closure::new(move |event: WheelEvent| event.prevent_default())
+    }
+
+    fn onpointerdown(self: &Rc<Self>) -> Closure<dyn Fn(PointerEvent)> {
+        let annotations = Rc::clone(self);
+        Closure::new(move |event: PointerEvent| {
+            if annotations.drawing.get().is_some() {
+                return;
+            }
+            let point = (f64::from(event.offset_x()), f64::from(event.offset_y()));
+            let color = annotations.color.borrow().clone();
+            let annotation = match annotations.tool.get() {
+                AnnotationTool::Freehand => Annotation::Freehand {
+                    points: vec![point],
+                    color,
+                },
+                AnnotationTool::Rectangle => Annotation::Rectangle {
+                    start: point,
+                    end: point,
+                    color,
+                },
+                AnnotationTool::Text => {
+                    let Some(text) = Self::prompt_text() else {
+                        return;
+                    };
+                    annotations.annotations.borrow_mut().push(Annotation::Text {
+                        position: point,
+                        text,
+                        color,
+                    });
+                    if let Err(e) = annotations.draw() {
+                        web_sys::console::error_1(&e);
+                    }
+                    return;
+                }
+            };
+            let _ = annotations.canvas.set_pointer_capture(event.pointer_id());
+            annotations.drawing.set(Some(event.pointer_id()));
+            annotations.annotations.borrow_mut().push(annotation);
+            if let Err(e) = annotations.draw() {
+                web_sys::console::error_1(&e);
+            }
+        })
+    }
+
+    fn onpointermove(self: &Rc<Self>) -> Closure<dyn Fn(PointerEvent)> {
+        let annotations = Rc::clone(self);
+        Closure::new(move |event: PointerEvent| {
+            if annotations.drawing.get() != Some(event.pointer_id()) {
+                return;
+            }
+            let point = (f64::from(event.offset_x()), f64::from(event.offset_y()));
+            match annotations.annotations.borrow_mut().last_mut() {
+                Some(Annotation::Freehand { points, .. }) => points.push(point),
+                Some(Annotation::Rectangle { end, .. }) => *end = point,
+                _ => return,
+            }
+            if let Err(e) = annotations.draw() {
+                web_sys::console::error_1(&e);
+            }
+        })
+    }
+
+    fn onpointerup(self: &Rc<Self>) -> Closure<dyn Fn(PointerEvent)> {
+        let annotations = Rc::clone(self);
+        Closure::new(move |event: PointerEvent| {
+            if annotations.drawing.get() == Some(event.pointer_id()) {
+                annotations.drawing.set(None);
+            }
+        })
+    }
+}
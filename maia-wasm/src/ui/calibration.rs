@@ -0,0 +1,214 @@
+use serde::{Deserialize, Serialize};
+use std::rc::Rc;
+
+use super::storage::Storage;
+
+const CALIBRATION_TABLE_KEY: &str = "calibration_table";
+
+// Frequency dependent correction of the power, which compensates for the
+// frequency response of the antenna, cables and front-end. The table maps
+// frequencies to power offsets, which are added to the power of the spectrum
+// lines. Between the frequencies in the table the offset is interpolated
+// linearly.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CorrectionTable {
+    // Frequencies in Hz and offsets in dB, sorted by frequency
+    points: Vec<(f64, f32)>,
+}
+
+// How the correction is computed for frequencies outside the table.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum Extrapolation {
+    // Use the offset of the nearest entry
+    Hold,
+    // Extend the line through the two nearest entries
+    Linear,
+}
+
+// Power calibration. The offset, whether the calibration is enabled and the
+// extrapolation are preferences, but the correction table is stored
+// separately in localStorage, since it can be large.
+pub struct Calibration {
+    storage: Rc<Storage>,
+    table: CorrectionTable,
+}
+
+impl CorrectionTable {
+    // Parses a table given in CSV format, with one "frequency_hz,offset_db"
+    // row per entry. An optional header row, empty lines, and lines starting
+    // with '#' are ignored. Each frequency can only appear once, since the
+    // interpolation between two entries with the same frequency is undefined.
+    pub fn from_csv(csv: &str) -> Result<CorrectionTable, String> {
+        let mut points = Vec::new();
+        for (number, line) in csv.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let parse = || -> Option<(f64, f32)> {
+                let (frequency, offset) = line.split_once(',')?;
+                let frequency: f64 = frequency.trim().parse().ok()?;
+                let offset: f32 = offset.trim().parse().ok()?;
+                Some((frequency, offset)).filter(|_| frequency.is_finite() && offset.is_finite())
+            };
+            match parse() {
+                Some(point) => points.push(point),
+                // The header can only be the first line
+                None if number == 0 => continue,
+                None => return Err(format!("invalid entry in line {}: {line}", number + 1)),
+            }
+        }
+        if points.is_empty() {
+            return Err("the table has no entries".to_string());
+        }
+        points.sort_by(|a, b| a.0.total_cmp(&b.0));
+        if let Some(pair) = points.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+            return Err(format!("duplicate entry for frequency {}", pair[0].0));
+        }
+        Ok(CorrectionTable { points })
+    }
+
+    // Formats the table in CSV format, as accepted by from_csv.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("frequency_hz,offset_db\n");
+        for (frequency, offset) in &self.points {
+            csv.push_str(&format!("{frequency},{offset}\n"));
+        }
+        csv
+    }
+
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    // Returns the offset in dB at a frequency given in Hz. An empty table has
+    // no offset.
+    pub fn offset(&self, frequency: f64, extrapolation: Extrapolation) -> f32 {
+        let points = &self.points;
+        let n = points.len();
+        let index = points.partition_point(|&(f, _)| f < frequency);
+        let segment = match (index, extrapolation) {
+            (_, _) if n == 0 => return 0.0,
+            (0, Extrapolation::Hold) => return points[0].1,
+            (i, Extrapolation::Hold) if i == n => return points[n - 1].1,
+            // A single entry cannot be extrapolated linearly
+            _ if n == 1 => return points[0].1,
+            (0, Extrapolation::Linear) => 1,
+            (i, Extrapolation::Linear) if i == n => n - 1,
+            (i, _) => i,
+        };
+        let (f0, y0) = points[segment - 1];
+        let (f1, y1) = points[segment];
+        let t = ((frequency - f0) / (f1 - f0)) as f32;
+        y0 + t * (y1 - y0)
+    }
+}
+
+impl std::str::FromStr for Extrapolation {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Extrapolation, ()> {
+        Ok(match s {
+            "Hold" => Extrapolation::Hold,
+            "Linear" => Extrapolation::Linear,
+            _ => return Err(()),
+        })
+    }
+}
+
+impl std::fmt::Display for Extrapolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        write!(
+            f,
+            "{}",
+            match self {
+                Extrapolation::Hold => "Hold",
+                Extrapolation::Linear => "Linear",
+            }
+        )
+    }
+}
+
+impl Calibration {
+    pub fn new(storage: Rc<Storage>) -> Calibration {
+        let table = storage
+            .get_parsed(
+                CALIBRATION_TABLE_KEY,
+                "calibration table",
+                CorrectionTable::from_csv,
+            )
+            .unwrap_or_default();
+        Calibration { storage, table }
+    }
+
+    pub fn table(&self) -> &CorrectionTable {
+        &self.table
+    }
+
+    // Replaces the correction table and stores it. An empty table removes
+    // the frequency dependent correction.
+    pub fn set_table(&mut self, table: CorrectionTable) {
+        if table.is_empty() {
+            self.storage.remove_item(CALIBRATION_TABLE_KEY);
+        } else {
+            self.storage
+                .set_item(CALIBRATION_TABLE_KEY, &table.to_csv());
+        }
+        self.table = table;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_csv() {
+        let table =
+            CorrectionTable::from_csv("frequency_hz,offset_db\n# comment\n2e9, -20\n\n1e9,-10.5\n")
+                .unwrap();
+        assert_eq!(table.points, vec![(1e9, -10.5), (2e9, -20.0)]);
+        assert_eq!(CorrectionTable::from_csv(&table.to_csv()).unwrap(), table);
+        assert!(CorrectionTable::from_csv("").is_err());
+        assert!(CorrectionTable::from_csv("frequency_hz,offset_db\n").is_err());
+        assert!(CorrectionTable::from_csv("1e9,-10\n2e9\n").is_err());
+        assert!(CorrectionTable::from_csv("1e9,NaN\n").is_err());
+        // Duplicated frequencies would divide by zero when extrapolating
+        assert!(CorrectionTable::from_csv("1e9,-10\n1e9,-12\n2e9,-20\n").is_err());
+        assert!(CorrectionTable::from_csv("1e9,-10\n2e9,-20\n2000e6,-22\n").is_err());
+    }
+
+    #[test]
+    fn offset() {
+        use Extrapolation::*;
+        assert_eq!(CorrectionTable::default().offset(1e9, Hold), 0.0);
+        assert_eq!(CorrectionTable::default().offset(1e9, Linear), 0.0);
+        let table = CorrectionTable::from_csv("1e9,-10\n2e9,-20\n3e9,-20\n").unwrap();
+        for extrapolation in [Hold, Linear] {
+            assert_eq!(table.offset(1e9, extrapolation), -10.0);
+            assert_eq!(table.offset(1.25e9, extrapolation), -12.5);
+            assert_eq!(table.offset(2e9, extrapolation), -20.0);
+            assert_eq!(table.offset(2.5e9, extrapolation), -20.0);
+        }
+        assert_eq!(table.offset(0.5e9, Hold), -10.0);
+        assert_eq!(table.offset(0.5e9, Linear), -5.0);
+        assert_eq!(table.offset(4e9, Hold), -20.0);
+        assert_eq!(table.offset(4e9, Linear), -20.0);
+        let table = CorrectionTable::from_csv("1e9,-10\n").unwrap();
+        assert_eq!(table.offset(2e9, Linear), -10.0);
+    }
+
+    #[test]
+    fn extrapolation_from_str() {
+        for extrapolation in [Extrapolation::Hold, Extrapolation::Linear] {
+            assert_eq!(
+                extrapolation.to_string().parse::<Extrapolation>(),
+                Ok(extrapolation)
+            );
+        }
+    }
+}
@@ -0,0 +1,93 @@
+// Detection of ADC clipping.
+//
+// When the ADC clips, the distortion spreads power over the whole band, so the
+// noise floor rises. Clipping is detected when the noise floor estimated by
+// the waterfall stays above a threshold. The detection is debounced, so that
+// brief transients do not show the warning, and the warning is kept for a
+// while after the noise floor goes down, so that it does not flicker when the
+// noise floor is close to the threshold.
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ClippingDetector {
+    active: bool,
+    // Number of consecutive updates that disagree with the current state
+    count: u32,
+}
+
+impl ClippingDetector {
+    // Number of consecutive updates with the noise floor above the threshold
+    // needed to activate the warning.
+    const ACTIVATE_COUNT: u32 = 3;
+    // Number of consecutive updates with the noise floor below the threshold
+    // needed to deactivate the warning.
+    const DEACTIVATE_COUNT: u32 = 5;
+
+    pub fn new() -> ClippingDetector {
+        ClippingDetector::default()
+    }
+
+    // Updates the detector with a new noise floor estimate. The threshold is
+    // None if the detection is disabled. Returns true if clipping is detected.
+    pub fn update(&mut self, noise_floor: Option<f32>, threshold: Option<f32>) -> bool {
+        let above = match (noise_floor, threshold) {
+            (Some(noise_floor), Some(threshold)) => noise_floor > threshold,
+            _ => false,
+        };
+        if threshold.is_none() {
+            // Disabling the detection hides the warning immediately.
+            self.active = false;
+            self.count = 0;
+        } else if above != self.active {
+            self.count += 1;
+            let needed = if above {
+                Self::ACTIVATE_COUNT
+            } else {
+                Self::DEACTIVATE_COUNT
+            };
+            if self.count >= needed {
+                self.active = above;
+                self.count = 0;
+            }
+        } else {
+            self.count = 0;
+        }
+        self.active
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn debounce() {
+        let mut detector = ClippingDetector::new();
+        let threshold = Some(60.0);
+        assert!(!detector.update(Some(65.0), threshold));
+        assert!(!detector.update(Some(65.0), threshold));
+        // A transient below the threshold restarts the count
+        assert!(!detector.update(Some(50.0), threshold));
+        assert!(!detector.update(Some(65.0), threshold));
+        assert!(!detector.update(Some(65.0), threshold));
+        assert!(detector.update(Some(65.0), threshold));
+        for _ in 0..4 {
+            assert!(detector.update(Some(50.0), threshold));
+        }
+        assert!(detector.update(Some(65.0), threshold));
+        for _ in 0..4 {
+            assert!(detector.update(Some(50.0), threshold));
+        }
+        assert!(!detector.update(Some(50.0), threshold));
+    }
+
+    #[test]
+    fn disabled() {
+        let mut detector = ClippingDetector::new();
+        for _ in 0..3 {
+            detector.update(Some(65.0), Some(60.0));
+        }
+        assert!(detector.update(Some(65.0), Some(60.0)));
+        assert!(!detector.update(Some(65.0), None));
+        assert!(!detector.update(None, Some(60.0)));
+    }
+}
@@ -7,7 +7,14 @@ pub enum Colormap {
 }
 
 impl Colormap {
-    pub fn colormap_as_slice(&self) -> &[u8] {
+    pub const ALL: [Colormap; 2] = [Colormap::Turbo, Colormap::Viridis];
+
+    pub fn next(&self) -> Colormap {
+        let n = Self::ALL.iter().position(|c| c == self).unwrap();
+        Self::ALL[(n + 1) % Self::ALL.len()]
+    }
+
+    pub fn colormap_as_slice(&self) -> &'static [u8] {
         match self {
             Colormap::Turbo => &crate::colormap::turbo::COLORMAP,
             Colormap::Viridis => &crate::colormap::viridis::COLORMAP,
@@ -15,6 +22,21 @@ impl Colormap {
     }
 }
 
+// Parses a color given in the "#rrggbb" format used by color input elements.
+// The components are returned between 0 and 1.
+pub fn parse_hex_color(color: &str) -> Option<(f32, f32, f32)> {
+    let hex = color.strip_prefix('#')?;
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+    let component = |j: usize| {
+        u8::from_str_radix(&hex[2 * j..2 * j + 2], 16)
+            .ok()
+            .map(|x| f32::from(x) / 255.0)
+    };
+    Some((component(0)?, component(1)?, component(2)?))
+}
+
 impl std::str::FromStr for Colormap {
     type Err = ();
 
@@ -39,3 +61,18 @@ impl std::fmt::Display for Colormap {
         )
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn hex_color() {
+        assert_eq!(parse_hex_color("#ff0000"), Some((1.0, 0.0, 0.0)));
+        assert_eq!(parse_hex_color("#00FF33"), Some((0.0, 1.0, 0.2)));
+        assert_eq!(parse_hex_color("ff0000"), None);
+        assert_eq!(parse_hex_color("#f00"), None);
+        assert_eq!(parse_hex_color("#ff00zz"), None);
+        assert_eq!(parse_hex_color("#ff00é"), None);
+    }
+}
@@ -0,0 +1,225 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use super::colormap::Colormap;
+use super::storage::{self, Storage};
+
+const DISPLAY_PRESETS_KEY: &str = "display_presets";
+
+// A named combination of display settings. The fields use the names and
+// formats of the corresponding preferences.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+pub struct DisplayPreset {
+    pub colormap_select: Colormap,
+    pub waterfall_min: f32,
+    pub waterfall_max: f32,
+    pub waterfall_gamma: f32,
+    // Scaling of the colormap, which is interpolated in linear light if
+    // enabled. Presets saved before this was added interpolate in sRGB.
+    #[serde(default)]
+    pub linear_light_colormap: bool,
+}
+
+// Presets that are always available and cannot be replaced or deleted.
+const BUILT_IN: [(&str, DisplayPreset); 3] = [
+    (
+        "Overview",
+        DisplayPreset {
+            colormap_select: Colormap::Turbo,
+            waterfall_min: 35.0,
+            waterfall_max: 85.0,
+            waterfall_gamma: 1.0,
+            linear_light_colormap: false,
+        },
+    ),
+    (
+        "Weak signals",
+        DisplayPreset {
+            colormap_select: Colormap::Turbo,
+            waterfall_min: 30.0,
+            waterfall_max: 55.0,
+            waterfall_gamma: 0.7,
+            linear_light_colormap: true,
+        },
+    ),
+    (
+        "Wide dynamic range",
+        DisplayPreset {
+            colormap_select: Colormap::Viridis,
+            waterfall_min: 25.0,
+            waterfall_max: 105.0,
+            waterfall_gamma: 1.0,
+            linear_light_colormap: false,
+        },
+    ),
+];
+
+// Display presets. The presets saved by the user are stored in localStorage.
+pub struct DisplayPresets {
+    storage: Rc<Storage>,
+    user: BTreeMap<String, DisplayPreset>,
+}
+
+impl DisplayPreset {
+    // Checks that the levels are valid, with the same limits as the waterfall
+    // level and gamma inputs.
+    pub fn validate(&self) -> Result<(), String> {
+        if !(self.waterfall_min >= 0.0 && self.waterfall_min < self.waterfall_max) {
+            return Err("the minimum level must be below the maximum level".to_string());
+        }
+        if !(0.1..=10.0).contains(&self.waterfall_gamma) {
+            return Err("the gamma must be between 0.1 and 10".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl DisplayPresets {
+    pub fn new(storage: Rc<Storage>) -> DisplayPresets {
+        let user = storage
+            .get_parsed(DISPLAY_PRESETS_KEY, "display presets", Self::parse)
+            .unwrap_or_default();
+        DisplayPresets { storage, user }
+    }
+
+    fn parse(data: &str) -> Result<BTreeMap<String, DisplayPreset>, String> {
+        let user: BTreeMap<String, DisplayPreset> =
+            serde_json::from_str(data).map_err(|e| format!("invalid display presets: {e}"))?;
+        for (name, preset) in &user {
+            Self::validate_name(name)?;
+            preset
+                .validate()
+                .map_err(|e| format!("display preset {name}: {e}"))?;
+        }
+        Ok(user)
+    }
+
+    fn validate_name(name: &str) -> Result<(), String> {
+        storage::validate_name(name, "preset")?;
+        if Self::is_built_in(name) {
+            return Err(format!("{name} is a built-in preset"));
+        }
+        Ok(())
+    }
+
+    pub fn is_built_in(name: &str) -> bool {
+        BUILT_IN.iter().any(|(n, _)| *n == name)
+    }
+
+    // Returns the names of the presets, with the built-in presets first.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        BUILT_IN
+            .iter()
+            .map(|(name, _)| *name)
+            .chain(self.user.keys().map(|name| name.as_str()))
+    }
+
+    pub fn get(&self, name: &str) -> Option<DisplayPreset> {
+        BUILT_IN
+            .iter()
+            .find_map(|(n, preset)| Some(*preset).filter(|_| *n == name))
+            .or_else(|| self.user.get(name).copied())
+    }
+
+    // Saves a preset, replacing any user preset with the same name.
+    pub fn insert(&mut self, name: &str, preset: DisplayPreset) -> Result<(), String> {
+        Self::validate_name(name)?;
+        preset.validate()?;
+        self.user.insert(name.to_string(), preset);
+        self.store();
+        Ok(())
+    }
+
+    // Deletes a user preset. Returns true if the preset existed.
+    pub fn remove(&mut self, name: &str) -> bool {
+        let removed = self.user.remove(name).is_some();
+        if removed {
+            self.store();
+        }
+        removed
+    }
+
+    fn store(&self) {
+        self.storage.set_item(
+            DISPLAY_PRESETS_KEY,
+            &serde_json::to_string(&self.user).unwrap(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn preset() -> DisplayPreset {
+        DisplayPreset {
+            colormap_select: Colormap::Viridis,
+            waterfall_min: 40.0,
+            waterfall_max: 70.0,
+            waterfall_gamma: 1.5,
+            linear_light_colormap: true,
+        }
+    }
+
+    #[test]
+    fn built_in_presets_are_valid() {
+        for (name, preset) in BUILT_IN {
+            assert_eq!(preset.validate(), Ok(()), "{name}");
+        }
+    }
+
+    #[test]
+    fn names() {
+        let mut presets = DisplayPresets::new(Rc::new(Storage::memory()));
+        presets.insert("Satellites", preset()).unwrap();
+        assert_eq!(
+            presets.names().collect::<Vec<_>>(),
+            [
+                "Overview",
+                "Weak signals",
+                "Wide dynamic range",
+                "Satellites"
+            ]
+        );
+        assert_eq!(presets.get("Satellites"), Some(preset()));
+        assert_eq!(presets.get("Overview").unwrap().waterfall_min, 35.0);
+        assert!(presets.get("Unknown").is_none());
+    }
+
+    #[test]
+    fn built_in_presets_are_read_only() {
+        let mut presets = DisplayPresets::new(Rc::new(Storage::memory()));
+        assert!(presets.insert("Overview", preset()).is_err());
+        assert!(!presets.remove("Overview"));
+        assert!(presets.get("Overview").is_some());
+    }
+
+    #[test]
+    fn invalid() {
+        let mut presets = DisplayPresets::new(Rc::new(Storage::memory()));
+        assert!(presets.insert(" x", preset()).is_err());
+        let inverted = DisplayPreset {
+            waterfall_min: 70.0,
+            waterfall_max: 40.0,
+            ..preset()
+        };
+        assert!(presets.insert("x", inverted).is_err());
+        assert!(DisplayPresets::parse("{\"x\": 1}").is_err());
+        // The scaling mode is optional
+        let presets = DisplayPresets::parse(
+            "{\"x\": {\"colormap_select\": \"Turbo\", \"waterfall_min\": 30,              \"waterfall_max\": 60, \"waterfall_gamma\": 1}}",
+        )
+        .unwrap();
+        assert!(!presets["x"].linear_light_colormap);
+    }
+
+    #[test]
+    fn persistence() {
+        let storage = Rc::new(Storage::memory());
+        let mut presets = DisplayPresets::new(Rc::clone(&storage));
+        presets.insert("Satellites", preset()).unwrap();
+        let presets = DisplayPresets::new(storage);
+        assert_eq!(presets.get("Satellites"), Some(preset()));
+    }
+}
@@ -0,0 +1,235 @@
+use js_sys::{Array, Function, Object, Promise, Reflect};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{CanvasRenderingContext2d, Document, HtmlCanvasElement, HtmlElement, Window};
+
+use crate::render::RenderEngine;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum OverlayPosition {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl std::str::FromStr for OverlayPosition {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<OverlayPosition, ()> {
+        Ok(match s {
+            "Top left" => OverlayPosition::TopLeft,
+            "Top right" => OverlayPosition::TopRight,
+            "Bottom left" => OverlayPosition::BottomLeft,
+            "Bottom right" => OverlayPosition::BottomRight,
+            _ => return Err(()),
+        })
+    }
+}
+
+impl std::fmt::Display for OverlayPosition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        write!(
+            f,
+            "{}",
+            match self {
+                OverlayPosition::TopLeft => "Top left",
+                OverlayPosition::TopRight => "Top right",
+                OverlayPosition::BottomLeft => "Bottom left",
+                OverlayPosition::BottomRight => "Bottom right",
+            }
+        )
+    }
+}
+
+pub struct Overlay<'a> {
+    pub text: &'a str,
+    pub position: OverlayPosition,
+    // Font size in CSS pixels
+    pub font_size: u32,
+}
+
+// Renders the scene and returns a 2D canvas containing a copy of the WebGL2
+// canvas with the annotations, if given, and the overlay text drawn on top of
+// it. The canvas has the size of the WebGL2 canvas in device pixels. The
+// annotations canvas covers the WebGL2 canvas, so it is scaled to its size.
+pub fn render_to_canvas(
+    document: &Document,
+    engine: &mut RenderEngine,
+    overlay: &Overlay<'_>,
+    annotations: Option<&HtmlCanvasElement>,
+) -> Result<HtmlCanvasElement, JsValue> {
+    // The WebGL2 drawing buffer is cleared after it is composited, so we need
+    // to render the scene again in order to copy it in this same task.
+    engine.render()?;
+    let dims = engine.canvas_dims();
+    let (width, height) = dims.device_pixels();
+    let canvas = document
+        .create_element("canvas")?
+        .dyn_into::<HtmlCanvasElement>()?;
+    canvas.set_width(width);
+    canvas.set_height(height);
+    let context = canvas
+        .get_context("2d")?
+        .ok_or("unable to get 2d context")?
+        .dyn_into::<CanvasRenderingContext2d>()?;
+    context.draw_image_with_html_canvas_element(engine.canvas(), 0.0, 0.0)?;
+    if let Some(annotations) = annotations {
+        context.draw_image_with_html_canvas_element_and_dw_and_dh(
+            annotations,
+            0.0,
+            0.0,
+            f64::from(width),
+            f64::from(height),
+        )?;
+    }
+    if !overlay.text.is_empty() {
+        draw_overlay(&context, overlay, dims.device_pixel_ratio(), width, height)?;
+    }
+    Ok(canvas)
+}
+
+fn draw_overlay(
+    context: &CanvasRenderingContext2d,
+    overlay: &Overlay<'_>,
+    device_pixel_ratio: f64,
+    width: u32,
+    height: u32,
+) -> Result<(), JsValue> {
+    // The font is scaled by the device pixel ratio, since the exported image
+    // has the size of the canvas in device pixels.
+    let font_px = f64::from(overlay.font_size) * device_pixel_ratio;
+    let margin = 0.5 * font_px;
+    let (x, align) = match overlay.position {
+        OverlayPosition::TopLeft | OverlayPosition::BottomLeft => (margin, "left"),
+        OverlayPosition::TopRight | OverlayPosition::BottomRight => {
+            (f64::from(width) - margin, "right")
+        }
+    };
+    let (y, baseline) = match overlay.position {
+        OverlayPosition::TopLeft | OverlayPosition::TopRight => (margin, "top"),
+        OverlayPosition::BottomLeft | OverlayPosition::BottomRight => {
+            (f64::from(height) - margin, "bottom")
+        }
+    };
+    context.set_font(&format!("bold {font_px}px sans"));
+    context.set_text_align(align);
+    context.set_text_baseline(baseline);
+    // An outline makes the text readable regardless of the waterfall colors.
+    context.set_line_width(0.2 * font_px);
+    context.set_stroke_style(&JsValue::from_str("black"));
+    context.stroke_text(overlay.text, x, y)?;
+    context.set_fill_style(&JsValue::from_str("white"));
+    context.fill_text(overlay.text, x, y)?;
+    Ok(())
+}
+
+// Triggers the download of a canvas as a PNG file.
+pub fn download_png(
+    document: &Document,
+    canvas: &HtmlCanvasElement,
+    filename: &str,
+) -> Result<(), JsValue> {
+    let url = canvas.to_data_url_with_type("image/png")?;
+    download_url(document, &url, filename)
+}
+
+// Copies a canvas to the clipboard as a PNG image, using the asynchronous
+// Clipboard API. The API is accessed through js_sys::Reflect, since the
+// web-sys bindings for it are unstable.
+//
+// The clipboard item is created with a promise of the PNG blob, so that the
+// clipboard is written before the first await. Some browsers only allow
+// writing to the clipboard in the same task as the user action.
+pub async fn copy_png_to_clipboard(
+    window: &Window,
+    canvas: &HtmlCanvasElement,
+) -> Result<(), JsValue> {
+    let clipboard = Reflect::get(&window.navigator(), &"clipboard".into())?;
+    let clipboard_item = Reflect::get(window, &"ClipboardItem".into())?;
+    if clipboard.is_undefined() || clipboard_item.is_undefined() {
+        return Err("the Clipboard API is not available".into());
+    }
+    let blob = Promise::new(&mut |resolve, reject| {
+        let reject_ = reject.clone();
+        let callback = Closure::once_into_js(move |blob: JsValue| {
+            let _ = if blob.is_null() {
+                reject.call1(&JsValue::NULL, &"unable to encode PNG".into())
+            } else {
+                resolve.call1(&JsValue::NULL, &blob)
+            };
+        });
+        if let Err(e) = canvas.to_blob(callback.unchecked_ref()) {
+            let _ = reject_.call1(&JsValue::NULL, &e);
+        }
+    });
+    let data = Object::new();
+    Reflect::set(&data, &"image/png".into(), &blob)?;
+    let item = Reflect::construct(clipboard_item.unchecked_ref(), &Array::of1(&data))?;
+    let write = Reflect::get(&clipboard, &"write".into())?.dyn_into::<Function>()?;
+    let promise = write
+        .call1(&clipboard, &Array::of1(&item))?
+        .dyn_into::<Promise>()?;
+    JsFuture::from(promise).await?;
+    Ok(())
+}
+
+// Returns true if an error returned by the Clipboard API is due to the
+// permission to write to the clipboard being denied.
+pub fn is_permission_denied(error: &JsValue) -> bool {
+    Reflect::get(error, &"name".into())
+        .ok()
+        .and_then(|name| name.as_string())
+        .map_or(false, |name| name == "NotAllowedError")
+}
+
+// Triggers the download of a JSON document.
+pub fn download_json(document: &Document, json: &str, filename: &str) -> Result<(), JsValue> {
+    download_text(document, "application/json", json, filename)
+}
+
+// Triggers the download of a CSV document.
+pub fn download_csv(document: &Document, csv: &str, filename: &str) -> Result<(), JsValue> {
+    download_text(document, "text/csv", csv, filename)
+}
+
+fn download_text(
+    document: &Document,
+    media_type: &str,
+    text: &str,
+    filename: &str,
+) -> Result<(), JsValue> {
+    let url = format!(
+        "data:{media_type};charset=utf-8,{}",
+        js_sys::encode_uri_component(text)
+    );
+    download_url(document, &url, filename)
+}
+
+fn download_url(document: &Document, url: &str, filename: &str) -> Result<(), JsValue> {
+    let anchor = document.create_element("a")?.dyn_into::<HtmlElement>()?;
+    anchor.set_attribute("href", url)?;
+    anchor.set_attribute("download", filename)?;
+    anchor.click();
+    Ok(())
+}
+
+pub fn png_filename() -> String {
+    timestamped_filename("maia-sdr", "png")
+}
+
+// Returns a filename of the form prefix-timestamp.extension, where the
+// timestamp is the current time in ISO format with only alphanumeric
+// characters.
+pub fn timestamped_filename(prefix: &str, extension: &str) -> String {
+    let date = js_sys::Date::new_0();
+    let timestamp: String = date
+        .to_iso_string()
+        .as_string()
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect();
+    format!("{prefix}-{timestamp}.{extension}")
+}
@@ -29,6 +29,7 @@ macro_rules! presentation {
 presentation!(DefaultPresentation, 1.0, None);
 presentation!(IntegerPresentation, 1.0, Some(1.0));
 presentation!(MHzPresentation, 1e6, Some(1e3));
+presentation!(KHzPresentation, 1e3, Some(1.0));
 
 #[derive(Clone)]
 pub struct NumberInput<T, P = DefaultPresentation> {
@@ -137,6 +138,37 @@ impl InputElement<HtmlInputElement> for TextInput {
     }
 }
 
+#[derive(Clone)]
+pub struct CheckboxInput {
+    element: Rc<HtmlInputElement>,
+}
+
+impl From<Rc<HtmlInputElement>> for CheckboxInput {
+    fn from(element: Rc<HtmlInputElement>) -> CheckboxInput {
+        CheckboxInput { element }
+    }
+}
+
+impl Deref for CheckboxInput {
+    type Target = HtmlInputElement;
+
+    fn deref(&self) -> &HtmlInputElement {
+        &self.element
+    }
+}
+
+impl InputElement<HtmlInputElement> for CheckboxInput {
+    type T = bool;
+
+    fn get(&self) -> Option<bool> {
+        Some(self.element.checked())
+    }
+
+    fn set(&self, value: &bool) {
+        self.element.set_checked(*value)
+    }
+}
+
 pub struct EnumInput<E> {
     element: Rc<HtmlSelectElement>,
     _phantom: std::marker::PhantomData<E>,
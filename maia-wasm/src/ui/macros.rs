@@ -25,30 +25,61 @@ macro_rules! ui_elements {
     }
 }
 
-macro_rules! waterfallminmax_onchange {
-    ($minmax:ident) => {
+macro_rules! waterfall_onchange {
+    ($setting:ident) => {
         paste::paste! {
-            fn [<$minmax _onchange>](&self) -> Closure<dyn Fn()> {
+            fn [<$setting _onchange>](&self) -> Closure<dyn Fn()> {
                 let ui = self.clone();
                 Closure::new(move || {
-                    let element = &ui.elements.$minmax;
+                    let element = &ui.elements.$setting;
                     if !element.report_validity() {
                         return;
                     }
                     if let Some(value) = element.get() {
-                        ui.waterfall.borrow_mut().[<set_ $minmax>](value);
+                        ui.waterfall.borrow_mut().[<set_ $setting>](value);
                         // try_borrow_mut prevents trying to update the
                         // preferences as a consequence of the
                         // Preferences::apply_client calling this closure
                         if let Ok(mut p) = ui.preferences.try_borrow_mut() {
-                            if let Err(e) = p.[<update_ $minmax>](&value) {
+                            if let Err(e) = p.[<update_ $setting>](&value) {
                                 web_sys::console::error_1(&e);
                             }
                         }
                     } else {
                         ui.window
                             .alert_with_message(concat!("Invalid value for ",
-                                                        stringify!($minmax)))
+                                                        stringify!($setting)))
+                            .unwrap();
+                    }
+                })
+            }
+        }
+    };
+}
+
+macro_rules! preference_onchange {
+    ($setting:ident) => {
+        paste::paste! {
+            fn [<$setting _onchange>](&self) -> Closure<dyn Fn()> {
+                let ui = self.clone();
+                Closure::new(move || {
+                    let element = &ui.elements.$setting;
+                    if !element.report_validity() {
+                        return;
+                    }
+                    if let Some(value) = element.get() {
+                        // try_borrow_mut prevents trying to update the
+                        // preferences as a consequence of the
+                        // Preferences::apply_client calling this closure
+                        if let Ok(mut p) = ui.preferences.try_borrow_mut() {
+                            if let Err(e) = p.[<update_ $setting>](&value) {
+                                web_sys::console::error_1(&e);
+                            }
+                        }
+                    } else {
+                        ui.window
+                            .alert_with_message(concat!("Invalid value for ",
+                                                        stringify!($setting)))
                             .unwrap();
                     }
                 })
@@ -0,0 +1,432 @@
+//! Formatting of the numbers shown in the UI.
+//!
+//! The readouts, the ruler, the status bar and the labels of the plots format
+//! their frequencies, times and other values with a [`NumberFormat`], which
+//! applies the decimal separator and units chosen in the settings. Each call
+//! site gives the unit and number of decimals that it uses by default. If the
+//! user has chosen a different unit, the value is converted to it, and the
+//! number of decimals is adjusted so that the resolution is kept.
+//!
+//! Machine-readable exports, such as the CSV files of the scan results, do not
+//! use this formatter. They always use a point as decimal separator and Hz as
+//! unit, so that they can be read by other programs regardless of the locale.
+
+use serde::{Deserialize, Serialize};
+
+/// Decimal separator setting.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Default, Serialize, Deserialize)]
+pub enum DecimalSeparator {
+    /// Use the separator of the browser language.
+    #[default]
+    BrowserDefault,
+    /// Use a point.
+    Point,
+    /// Use a comma.
+    Comma,
+}
+
+impl DecimalSeparator {
+    /// Returns `true` if the separator is a comma.
+    ///
+    /// For [`DecimalSeparator::BrowserDefault`], `formatted` should contain
+    /// the number 1.5 formatted with the browser language (for instance, with
+    /// `Number.prototype.toLocaleString`), and the separator is a comma if it
+    /// appears in the formatted number.
+    pub fn is_comma(self, formatted: &str) -> bool {
+        match self {
+            DecimalSeparator::BrowserDefault => formatted.contains(','),
+            DecimalSeparator::Point => false,
+            DecimalSeparator::Comma => true,
+        }
+    }
+}
+
+impl std::str::FromStr for DecimalSeparator {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<DecimalSeparator, ()> {
+        Ok(match s {
+            "Browser default" => DecimalSeparator::BrowserDefault,
+            "Point" => DecimalSeparator::Point,
+            "Comma" => DecimalSeparator::Comma,
+            _ => return Err(()),
+        })
+    }
+}
+
+impl std::fmt::Display for DecimalSeparator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        write!(
+            f,
+            "{}",
+            match self {
+                DecimalSeparator::BrowserDefault => "Browser default",
+                DecimalSeparator::Point => "Point",
+                DecimalSeparator::Comma => "Comma",
+            }
+        )
+    }
+}
+
+/// Frequency unit.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Default, Serialize, Deserialize)]
+pub enum FrequencyUnit {
+    /// Unit chosen according to the value.
+    ///
+    /// As a setting, this uses the unit of each call site.
+    #[default]
+    Auto,
+    /// Hz.
+    Hz,
+    /// kHz.
+    KHz,
+    /// MHz.
+    MHz,
+    /// GHz.
+    GHz,
+}
+
+impl FrequencyUnit {
+    /// Returns the unit that is used to show a frequency in Hz.
+    ///
+    /// Frequencies of at least 1 MHz are shown in MHz, frequencies of at least
+    /// 1 kHz in kHz, and smaller frequencies in Hz. The sign of the frequency
+    /// is ignored.
+    pub fn for_frequency(hz: f64) -> FrequencyUnit {
+        if hz.abs() >= 1e6 {
+            FrequencyUnit::MHz
+        } else if hz.abs() >= 1e3 {
+            FrequencyUnit::KHz
+        } else {
+            FrequencyUnit::Hz
+        }
+    }
+
+    // Exponent of the unit in Hz.
+    fn exponent(self) -> i32 {
+        match self {
+            FrequencyUnit::Auto | FrequencyUnit::Hz => 0,
+            FrequencyUnit::KHz => 3,
+            FrequencyUnit::MHz => 6,
+            FrequencyUnit::GHz => 9,
+        }
+    }
+
+    fn symbol(self) -> &'static str {
+        match self {
+            FrequencyUnit::Auto | FrequencyUnit::Hz => "Hz",
+            FrequencyUnit::KHz => "kHz",
+            FrequencyUnit::MHz => "MHz",
+            FrequencyUnit::GHz => "GHz",
+        }
+    }
+}
+
+impl std::str::FromStr for FrequencyUnit {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<FrequencyUnit, ()> {
+        Ok(match s {
+            "Auto" => FrequencyUnit::Auto,
+            "Hz" => FrequencyUnit::Hz,
+            "kHz" => FrequencyUnit::KHz,
+            "MHz" => FrequencyUnit::MHz,
+            "GHz" => FrequencyUnit::GHz,
+            _ => return Err(()),
+        })
+    }
+}
+
+impl std::fmt::Display for FrequencyUnit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        match self {
+            FrequencyUnit::Auto => write!(f, "Auto"),
+            unit => write!(f, "{}", unit.symbol()),
+        }
+    }
+}
+
+/// Time unit.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Default, Serialize, Deserialize)]
+pub enum TimeUnit {
+    /// Unit chosen according to the value.
+    ///
+    /// As a setting, this uses the unit of each call site.
+    #[default]
+    Auto,
+    /// Seconds.
+    S,
+    /// Milliseconds.
+    Ms,
+}
+
+impl TimeUnit {
+    /// Returns the unit that is used to show a time in seconds.
+    ///
+    /// Times of at least one second are shown in seconds, and shorter times in
+    /// milliseconds. The sign of the time is ignored.
+    pub fn for_time(seconds: f64) -> TimeUnit {
+        if seconds.abs() >= 1.0 {
+            TimeUnit::S
+        } else {
+            TimeUnit::Ms
+        }
+    }
+
+    // Exponent of the unit in seconds.
+    fn exponent(self) -> i32 {
+        match self {
+            TimeUnit::Auto | TimeUnit::S => 0,
+            TimeUnit::Ms => -3,
+        }
+    }
+
+    fn symbol(self) -> &'static str {
+        match self {
+            TimeUnit::Auto | TimeUnit::S => "s",
+            TimeUnit::Ms => "ms",
+        }
+    }
+}
+
+impl std::str::FromStr for TimeUnit {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<TimeUnit, ()> {
+        Ok(match s {
+            "Auto" => TimeUnit::Auto,
+            "s" => TimeUnit::S,
+            "ms" => TimeUnit::Ms,
+            _ => return Err(()),
+        })
+    }
+}
+
+impl std::fmt::Display for TimeUnit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        match self {
+            TimeUnit::Auto => write!(f, "Auto"),
+            unit => write!(f, "{}", unit.symbol()),
+        }
+    }
+}
+
+/// Number format.
+///
+/// The default format uses a point as decimal separator and the units of
+/// each call site.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Default)]
+pub struct NumberFormat {
+    decimal_comma: bool,
+    frequency_unit: FrequencyUnit,
+    time_unit: TimeUnit,
+}
+
+impl NumberFormat {
+    /// Creates a number format.
+    ///
+    /// If `decimal_comma` is `true`, a comma is used as decimal separator
+    /// instead of a point. The units given as [`FrequencyUnit::Auto`] and
+    /// [`TimeUnit::Auto`] use the units of each call site.
+    pub fn new(decimal_comma: bool, frequency_unit: FrequencyUnit, time_unit: TimeUnit) -> Self {
+        NumberFormat {
+            decimal_comma,
+            frequency_unit,
+            time_unit,
+        }
+    }
+
+    /// Returns the decimal separator.
+    pub fn decimal_separator(&self) -> char {
+        if self.decimal_comma {
+            ','
+        } else {
+            '.'
+        }
+    }
+
+    /// Formats a number with a number of decimals.
+    pub fn number(&self, value: f64, decimals: usize) -> String {
+        self.localize(format!("{value:.decimals$}"))
+    }
+
+    /// Formats a number with a number of decimals, always including its sign.
+    pub fn signed_number(&self, value: f64, decimals: usize) -> String {
+        self.localize(format!("{value:+.decimals$}"))
+    }
+
+    /// Formats a frequency given in Hz.
+    ///
+    /// The frequency is shown in `unit` with `decimals` decimals, unless
+    /// another unit has been chosen for the format. [`FrequencyUnit::Auto`]
+    /// selects the unit with [`FrequencyUnit::for_frequency`].
+    pub fn frequency(&self, hz: f64, unit: FrequencyUnit, decimals: usize) -> String {
+        self.format_frequency(hz, unit, decimals, false)
+    }
+
+    /// Formats a frequency difference given in Hz, always including its sign.
+    ///
+    /// This works as [`frequency`](NumberFormat::frequency).
+    pub fn frequency_delta(&self, hz: f64, unit: FrequencyUnit, decimals: usize) -> String {
+        self.format_frequency(hz, unit, decimals, true)
+    }
+
+    /// Formats a time given in seconds.
+    ///
+    /// The time is shown in `unit` with `decimals` decimals, unless another
+    /// unit has been chosen for the format. [`TimeUnit::Auto`] selects the
+    /// unit with [`TimeUnit::for_time`].
+    pub fn time(&self, seconds: f64, unit: TimeUnit, decimals: usize) -> String {
+        self.format_time(seconds, unit, decimals, false)
+    }
+
+    /// Formats a time difference given in seconds, always including its sign.
+    ///
+    /// This works as [`time`](NumberFormat::time).
+    pub fn time_delta(&self, seconds: f64, unit: TimeUnit, decimals: usize) -> String {
+        self.format_time(seconds, unit, decimals, true)
+    }
+
+    fn format_frequency(
+        &self,
+        hz: f64,
+        unit: FrequencyUnit,
+        decimals: usize,
+        signed: bool,
+    ) -> String {
+        let unit = match unit {
+            FrequencyUnit::Auto => FrequencyUnit::for_frequency(hz),
+            unit => unit,
+        };
+        let target = match self.frequency_unit {
+            FrequencyUnit::Auto => unit,
+            target => target,
+        };
+        let decimals = Self::convert_decimals(decimals, unit.exponent(), target.exponent());
+        self.with_unit(
+            hz * 10.0_f64.powi(-target.exponent()),
+            decimals,
+            signed,
+            target.symbol(),
+        )
+    }
+
+    fn format_time(&self, seconds: f64, unit: TimeUnit, decimals: usize, signed: bool) -> String {
+        let unit = match unit {
+            TimeUnit::Auto => TimeUnit::for_time(seconds),
+            unit => unit,
+        };
+        let target = match self.time_unit {
+            TimeUnit::Auto => unit,
+            target => target,
+        };
+        let decimals = Self::convert_decimals(decimals, unit.exponent(), target.exponent());
+        self.with_unit(
+            seconds * 10.0_f64.powi(-target.exponent()),
+            decimals,
+            signed,
+            target.symbol(),
+        )
+    }
+
+    fn with_unit(&self, value: f64, decimals: usize, signed: bool, symbol: &str) -> String {
+        let number = if signed {
+            self.signed_number(value, decimals)
+        } else {
+            self.number(value, decimals)
+        };
+        format!("{number} {symbol}")
+    }
+
+    // Number of decimals that keeps the resolution of a value shown with some
+    // decimals in a unit when it is shown in another unit. The units are given
+    // by their exponents.
+    fn convert_decimals(decimals: usize, from: i32, to: i32) -> usize {
+        (decimals as i32 + to - from).max(0) as usize
+    }
+
+    fn localize(&self, formatted: String) -> String {
+        if self.decimal_comma {
+            formatted.replace('.', ",")
+        } else {
+            formatted
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn default_format() {
+        let format = NumberFormat::default();
+        assert_eq!(format.number(1234.5678, 2), "1234.57");
+        assert_eq!(format.signed_number(1.5, 1), "+1.5");
+        assert_eq!(
+            format.frequency(2.4e9, FrequencyUnit::MHz, 6),
+            "2400.000000 MHz"
+        );
+        assert_eq!(format.frequency(15e3, FrequencyUnit::Auto, 3), "15.000 kHz");
+        assert_eq!(
+            format.frequency_delta(-1.5e6, FrequencyUnit::Auto, 3),
+            "-1.500 MHz"
+        );
+        assert_eq!(format.time(1.0 / 30.0, TimeUnit::Auto, 3), "33.333 ms");
+        assert_eq!(format.time_delta(2.5, TimeUnit::S, 2), "+2.50 s");
+    }
+
+    #[test]
+    fn english_locale() {
+        // en-US: point as decimal separator
+        let comma = DecimalSeparator::BrowserDefault.is_comma("1.5");
+        assert!(!comma);
+        let format = NumberFormat::new(comma, FrequencyUnit::KHz, TimeUnit::Auto);
+        // The resolution of 1 Hz is kept when converting from MHz to kHz
+        assert_eq!(
+            format.frequency(100.123456e6, FrequencyUnit::MHz, 6),
+            "100123.456 kHz"
+        );
+        assert_eq!(format.frequency(500.0, FrequencyUnit::Hz, 0), "0.500 kHz");
+    }
+
+    #[test]
+    fn german_locale() {
+        // de-DE: comma as decimal separator
+        let comma = DecimalSeparator::BrowserDefault.is_comma("1,5");
+        assert!(comma);
+        let format = NumberFormat::new(comma, FrequencyUnit::Hz, TimeUnit::Ms);
+        assert_eq!(format.decimal_separator(), ',');
+        assert_eq!(format.number(-12.25, 1), "-12,2");
+        // Converting to a smaller unit drops the decimals that are not needed
+        assert_eq!(
+            format.frequency(100.123456e6, FrequencyUnit::MHz, 6),
+            "100123456 Hz"
+        );
+        assert_eq!(format.time_delta(-0.25, TimeUnit::S, 2), "-250 ms");
+        assert_eq!(format.time(1.5, TimeUnit::S, 3), "1500 ms");
+        let format = NumberFormat::new(comma, FrequencyUnit::Auto, TimeUnit::Auto);
+        assert_eq!(
+            format.frequency(433.92e6, FrequencyUnit::MHz, 3),
+            "433,920 MHz"
+        );
+    }
+
+    #[test]
+    fn settings() {
+        assert!(!DecimalSeparator::Point.is_comma("1,5"));
+        assert!(DecimalSeparator::Comma.is_comma("1.5"));
+        for unit in [
+            FrequencyUnit::Auto,
+            FrequencyUnit::Hz,
+            FrequencyUnit::KHz,
+            FrequencyUnit::MHz,
+            FrequencyUnit::GHz,
+        ] {
+            assert_eq!(unit.to_string().parse(), Ok(unit));
+        }
+        for unit in [TimeUnit::Auto, TimeUnit::S, TimeUnit::Ms] {
+            assert_eq!(unit.to_string().parse(), Ok(unit));
+        }
+    }
+}
@@ -1,22 +1,34 @@
+use super::colormap::Colormap;
 use super::input::InputElement;
+use super::storage::Storage;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::rc::Rc;
 use wasm_bindgen::JsValue;
-use web_sys::{Storage, Window};
 
 const PREFERENCES_KEY: &str = "preferences";
 
 pub struct Preferences {
-    storage: Option<Storage>,
+    storage: Rc<Storage>,
     data: PreferenceData,
 }
 
+// The preferences listed before the semicolon correspond to UI elements with
+// the same name, and the ones listed after it are not shown in the UI.
 macro_rules! impl_preference_data {
-    {$($name:ident : $ty:ty = $default:expr,)*} => {
+    {$($name:ident : $ty:ty = $default:expr,)*
+     ; $($other_name:ident : $other_ty:ty = $other_default:expr,)*} => {
+        // serde(default) allows preferences stored by older versions, which
+        // lack some of the fields, to be loaded without discarding them.
         #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+        #[serde(default)]
         struct PreferenceData {
             $(
                 $name: $ty,
             )*
+            $(
+                $other_name: $other_ty,
+            )*
         }
 
         impl Default for PreferenceData {
@@ -25,6 +37,9 @@ macro_rules! impl_preference_data {
                     $(
                         $name: $default,
                     )*
+                    $(
+                        $other_name: $other_default,
+                    )*
                 }
             }
         }
@@ -59,10 +74,69 @@ macro_rules! impl_preference_data {
 }
 
 impl_preference_data! {
-    colormap_select: super::colormap::Colormap = super::colormap::Colormap::Turbo,
+    colormap_select: Colormap = Colormap::Turbo,
     waterfall_min: f32 = 35.0,
     waterfall_max: f32 = 85.0,
+    waterfall_gamma: f32 = 1.0,
+    waterfall_persistence: f32 = 0.0,
+    max_frame_rate: f32 = 0.0,
+    max_pixel_ratio: f64 = 0.0,
+    line_height: u32 = 0,
+    keymap: String = "".to_string(),
+    spectral_interpolation: bool = false,
+    linear_light_colormap: bool = false,
+    freeze_on_measurement: bool = false,
+    over_range_color_enabled: bool = false,
+    over_range_color: String = "#ff0000".to_string(),
+    under_range_color_enabled: bool = false,
+    under_range_color: String = "#808080".to_string(),
+    reticle: bool = false,
+    reticle_color: String = "#ffffff".to_string(),
+    bin_grid: bool = false,
+    smooth_scroll: bool = false,
+    frequency_axis_flipped: bool = false,
+    animation_easing: crate::tween::Easing = crate::tween::Easing::EaseInOut,
+    decimal_separator: super::number_format::DecimalSeparator =
+        super::number_format::DecimalSeparator::BrowserDefault,
+    frequency_unit: super::number_format::FrequencyUnit =
+        super::number_format::FrequencyUnit::Auto,
+    time_unit: super::number_format::TimeUnit = super::number_format::TimeUnit::Auto,
+    snap_zoom: bool = false,
+    overscan: f32 = 0.0,
+    view_sync: bool = false,
+    compact_layout: bool = false,
     ad9361_rx_lo_frequency: u64 = 2_400_000_000,
+    rx_lo_wheel_step: u32 = 100_000,
+    rx_lo_keep_visible: bool = false,
+    rx_lo_keep_visible_margin: f32 = 10.0,
+    retune_guard: f32 = 0.0,
+    max_retune_step: u64 = 0,
+    tuning_knob_step: u32 = 10_000,
+    power_history_window: u32 = 60,
+    clipping_threshold: f32 = 65.0,
+    calibrated_power: bool = false,
+    calibration_offset: f32 = 0.0,
+    calibration_extrapolation: super::calibration::Extrapolation =
+        super::calibration::Extrapolation::Hold,
+    tone_mapping: super::tone::ToneMapping = super::tone::ToneMapping::Pitch,
+    tone_min_db: f32 = 35.0,
+    tone_max_db: f32 = 85.0,
+    comb_origin: u64 = 2_400_000_000,
+    comb_spacing: u32 = 1_000_000,
+    comb_count: u32 = 0,
+    peak_tracking: bool = false,
+    peak_tracking_hysteresis: f32 = 3.0,
+    peak_tracking_min_snr: f32 = 10.0,
+    dc_exclusion_bins: u32 = 1,
+    scan_start: u64 = 2_400_000_000,
+    scan_stop: u64 = 2_500_000_000,
+    scan_step: u32 = 20_000_000,
+    scan_dwell: u32 = 500,
+    scan_edge_mask: f32 = 10.0,
+    scan_plot_log_frequency: bool = false,
+    annotation_tool: super::annotations::AnnotationTool =
+        super::annotations::AnnotationTool::Freehand,
+    annotation_color: String = "#ffff00".to_string(),
     ad9361_sampling_frequency: u32 = 61_440_000,
     ad9361_rx_rf_bandwidth: u32 = 56_000_000,
     ad9361_rx_gain_mode: maia_json::Ad9361GainMode = maia_json::Ad9361GainMode::SlowAttack,
@@ -72,34 +146,86 @@ impl_preference_data! {
     recording_metadata_description: String = "".to_string(),
     recording_metadata_author: String = "".to_string(),
     recorder_mode: maia_json::RecorderMode = maia_json::RecorderMode::IQ12bit,
+    export_overlay_text: String = "".to_string(),
+    export_overlay_position: super::export::OverlayPosition =
+        super::export::OverlayPosition::BottomLeft,
+    export_overlay_font_size: u32 = 16,
+    ;
+    // Waterfall gamma of each colormap, which is restored when the colormap
+    // is selected
+    colormap_gamma: HashMap<Colormap, f32> = HashMap::new(),
+}
+
+impl PreferenceData {
+    // Records the gamma of the selected colormap. This keeps colormap_gamma
+    // consistent with preferences stored by older versions or imported from
+    // a profile, which can set waterfall_gamma without it.
+    fn sync_colormap_gamma(&mut self) {
+        self.colormap_gamma
+            .insert(self.colormap_select, self.waterfall_gamma);
+    }
 }
 
 impl Preferences {
-    pub fn new(window: &Window) -> Result<Preferences, JsValue> {
-        let storage = window.local_storage()?;
-        let data = match &storage {
-            Some(storage) => match storage.get_item(PREFERENCES_KEY)? {
-                Some(data) => match serde_json::from_str(&data) {
-                    Ok(x) => x,
-                    Err(_) => {
-                        web_sys::console::error_1(&"preferences corrupted; removing".into());
-                        storage.remove_item(PREFERENCES_KEY)?;
-                        PreferenceData::default()
-                    }
-                },
-                None => PreferenceData::default(),
-            },
-            None => PreferenceData::default(),
-        };
-        Ok(Preferences { storage, data })
+    pub fn new(storage: Rc<Storage>) -> Preferences {
+        let mut data: PreferenceData = storage
+            .get_parsed(PREFERENCES_KEY, "preferences", |data| {
+                serde_json::from_str(data)
+            })
+            .unwrap_or_default();
+        data.sync_colormap_gamma();
+        Preferences { storage, data }
     }
 
-    fn store(&self) -> Result<(), JsValue> {
-        if let Some(storage) = self.storage.as_ref() {
-            let data = serde_json::to_string(&self.data).unwrap();
-            storage.set_item(PREFERENCES_KEY, &data)
+    // Returns the waterfall gamma of a colormap. Colormaps whose gamma has not
+    // been changed use the default gamma.
+    pub fn colormap_gamma(&self, colormap: Colormap) -> f32 {
+        self.data
+            .colormap_gamma
+            .get(&colormap)
+            .copied()
+            .unwrap_or_else(|| PreferenceData::default().waterfall_gamma)
+    }
+
+    // Records the waterfall gamma of a colormap.
+    pub fn update_colormap_gamma(&mut self, colormap: Colormap, gamma: f32) -> Result<(), JsValue> {
+        if self.data.colormap_gamma.insert(colormap, gamma) != Some(gamma) {
+            self.store()
         } else {
             Ok(())
         }
     }
+
+    // Returns the preferences as a JSON object.
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::to_value(&self.data).unwrap()
+    }
+
+    // Replaces the preferences with those given in a JSON object and stores
+    // them. Preferences that are missing from the object keep their current
+    // value, and unknown keys are ignored. If any of the values is invalid,
+    // an error is returned and the preferences are not modified.
+    pub fn merge_json(&mut self, json: &serde_json::Value) -> Result<(), JsValue> {
+        let json = json
+            .as_object()
+            .ok_or("preferences are not a JSON object")?;
+        let mut merged = self.to_json();
+        // to_json always gives an object, since PreferenceData is a struct
+        let merged_object = merged.as_object_mut().unwrap();
+        for (key, value) in json {
+            if merged_object.contains_key(key) {
+                merged_object.insert(key.clone(), value.clone());
+            }
+        }
+        self.data =
+            serde_json::from_value(merged).map_err(|e| format!("invalid preferences: {e}"))?;
+        self.data.sync_colormap_gamma();
+        self.store()
+    }
+
+    fn store(&self) -> Result<(), JsValue> {
+        let data = serde_json::to_string(&self.data).unwrap();
+        self.storage.set_item(PREFERENCES_KEY, &data);
+        Ok(())
+    }
 }
@@ -0,0 +1,178 @@
+use super::number_format::{FrequencyUnit, NumberFormat, TimeUnit};
+
+// Describes the FFT processing done by the spectrometer of the device, with
+// one "name: value" line per parameter. Parameters that the device does not
+// report, and those derived from them, are omitted.
+pub fn describe(json: &maia_json::Spectrometer, format: &NumberFormat) -> String {
+    let rows = [
+        Some(("FFT size", json.fft_size.to_string())),
+        json.window.clone().map(|window| ("Window", window)),
+        json.overlap
+            .map(|overlap| ("Overlap", format!("{}%", format.number(100.0 * overlap, 0)))),
+        Some((
+            "On-device averaging",
+            format!("{} FFTs", json.number_integrations),
+        )),
+        Some(("Bin width", format_frequency(bin_width(json), format))),
+        resolution_bandwidth(json).map(|rbw| ("RBW", format_frequency(rbw, format))),
+        integration_time(json).map(|t| ("Integration time", format_time(t, format))),
+        Some((
+            "Spectrum rate",
+            format!(
+                "{} lines/s",
+                format.number(json.output_sampling_frequency, 1)
+            ),
+        )),
+    ];
+    rows.into_iter()
+        .flatten()
+        .filter(|(_, value)| !value.is_empty())
+        .map(|(name, value)| format!("{name}: {value}\n"))
+        .collect()
+}
+
+// Short summary of the resolution bandwidth and integration time, which is
+// shown in the status bar.
+pub fn summary(json: &maia_json::Spectrometer, format: &NumberFormat) -> String {
+    let fields = [
+        resolution_bandwidth(json).map(|rbw| format!("RBW {}", format_frequency(rbw, format))),
+        integration_time(json).map(|t| format!("\u{3c4} {}", format_time(t, format))),
+    ];
+    fields.into_iter().flatten().collect::<Vec<_>>().join(", ")
+}
+
+// Spacing between FFT bins in Hz.
+fn bin_width(json: &maia_json::Spectrometer) -> f64 {
+    json.input_sampling_frequency / f64::from(json.fft_size)
+}
+
+// Resolution bandwidth in Hz, which is the equivalent noise bandwidth of the
+// FFT bins. It is None if the window is not reported or not known.
+fn resolution_bandwidth(json: &maia_json::Spectrometer) -> Option<f64> {
+    Some(bin_width(json) * equivalent_noise_bandwidth(json.window.as_deref()?)?)
+}
+
+// Duration in seconds of the samples integrated in each spectrum line.
+// Consecutive FFTs are spaced by a fraction (1 - overlap) of the FFT size, so
+// this is None if the overlap is not reported.
+fn integration_time(json: &maia_json::Spectrometer) -> Option<f64> {
+    let overlap = json.overlap?;
+    Some(
+        f64::from(json.number_integrations) * f64::from(json.fft_size) * (1.0 - overlap)
+            / json.input_sampling_frequency,
+    )
+}
+
+// Equivalent noise bandwidth of a window, in FFT bins. The window name is
+// matched ignoring case, spaces, hyphens and underscores.
+fn equivalent_noise_bandwidth(window: &str) -> Option<f64> {
+    let name: String = window
+        .chars()
+        .filter(|c| !matches!(c, ' ' | '-' | '_'))
+        .collect::<String>()
+        .to_lowercase();
+    Some(match name.as_str() {
+        "rectangular" | "boxcar" => 1.0,
+        "hann" | "hanning" => 1.5,
+        "hamming" => 1.363,
+        "blackman" => 1.727,
+        "blackmanharris" => 2.004,
+        "flattop" => 3.770,
+        _ => return None,
+    })
+}
+
+fn format_time(seconds: f64, format: &NumberFormat) -> String {
+    format.time(seconds, TimeUnit::Auto, 3)
+}
+
+fn format_frequency(hz: f64, format: &NumberFormat) -> String {
+    match FrequencyUnit::for_frequency(hz) {
+        FrequencyUnit::Hz => format.frequency(hz, FrequencyUnit::Hz, 1),
+        unit => format.frequency(hz, unit, 3),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn spectrometer(window: Option<&str>, overlap: Option<f64>) -> maia_json::Spectrometer {
+        maia_json::Spectrometer {
+            input_sampling_frequency: 61.44e6,
+            output_sampling_frequency: 30.0,
+            number_integrations: 500,
+            fft_size: 4096,
+            window: window.map(String::from),
+            overlap,
+        }
+    }
+
+    #[test]
+    fn all_parameters() {
+        let text = describe(
+            &spectrometer(Some("Blackman-Harris"), Some(0.0)),
+            &NumberFormat::default(),
+        );
+        assert_eq!(
+            text,
+            "FFT size: 4096\n\
+             Window: Blackman-Harris\n\
+             Overlap: 0%\n\
+             On-device averaging: 500 FFTs\n\
+             Bin width: 15.000 kHz\n\
+             RBW: 30.060 kHz\n\
+             Integration time: 33.333 ms\n\
+             Spectrum rate: 30.0 lines/s\n"
+        );
+    }
+
+    #[test]
+    fn unreported_parameters() {
+        let text = describe(&spectrometer(None, None), &NumberFormat::default());
+        assert!(!text.contains("Window"));
+        assert!(!text.contains("Overlap"));
+        assert!(!text.contains("RBW"));
+        assert!(!text.contains("Integration time"));
+        assert!(text.contains("Bin width: 15.000 kHz\n"));
+        assert_eq!(
+            summary(&spectrometer(None, None), &NumberFormat::default()),
+            ""
+        );
+    }
+
+    #[test]
+    fn resolution_bandwidth_window() {
+        for (window, enbw) in [
+            ("Blackman-Harris", 2.004),
+            ("hann", 1.5),
+            ("Flat top", 3.77),
+        ] {
+            let rbw = resolution_bandwidth(&spectrometer(Some(window), None)).unwrap();
+            assert!((rbw - 15e3 * enbw).abs() < 1e-6);
+        }
+        assert!(resolution_bandwidth(&spectrometer(Some("Kaiser"), None)).is_none());
+    }
+
+    #[test]
+    fn integration_time_overlap() {
+        let t = integration_time(&spectrometer(None, Some(0.5))).unwrap();
+        assert!((t - 500.0 * 2048.0 / 61.44e6).abs() < 1e-12);
+        assert_eq!(
+            summary(
+                &spectrometer(Some("Blackman-Harris"), Some(0.0)),
+                &NumberFormat::default()
+            ),
+            "RBW 30.060 kHz, \u{3c4} 33.333 ms"
+        );
+    }
+
+    #[test]
+    fn decimal_comma() {
+        let format = NumberFormat::new(true, FrequencyUnit::Auto, TimeUnit::Auto);
+        assert_eq!(
+            summary(&spectrometer(Some("Blackman-Harris"), Some(0.0)), &format),
+            "RBW 30,060 kHz, \u{3c4} 33,333 ms"
+        );
+    }
+}
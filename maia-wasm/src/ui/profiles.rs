@@ -0,0 +1,269 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::rc::Rc;
+use wasm_bindgen::JsValue;
+
+use super::session::View;
+use super::storage::{self, Storage};
+
+const PROFILES_KEY: &str = "profiles";
+
+// Version of the profile documents produced by this code. It must be increased
+// whenever the format changes in a way that requires migrating older
+// documents.
+pub const PROFILES_VERSION: u64 = 1;
+
+// Preferences that are stored in a profile. These are the device settings and
+// the display settings that depend on the use case. Other preferences, such as
+// the layout, are not changed when a profile is applied.
+pub const PROFILE_PREFERENCES: &[&str] = &[
+    "ad9361_rx_lo_frequency",
+    "ad9361_sampling_frequency",
+    "ad9361_rx_rf_bandwidth",
+    "ad9361_rx_gain_mode",
+    "ad9361_rx_gain",
+    "spectrometer_output_sampling_frequency",
+    "colormap_select",
+    "waterfall_min",
+    "waterfall_max",
+    "waterfall_gamma",
+];
+
+// A named configuration that can be applied in one action.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Profile {
+    #[serde(default)]
+    pub view: Option<View>,
+    // Values of some of the PROFILE_PREFERENCES, using the same format as the
+    // preferences.
+    pub preferences: serde_json::Map<String, serde_json::Value>,
+}
+
+// A set of profiles, as stored in localStorage and in exported files.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ProfileSet {
+    version: u64,
+    profiles: BTreeMap<String, Profile>,
+}
+
+// Profiles stored in localStorage.
+pub struct Profiles {
+    storage: Rc<Storage>,
+    set: ProfileSet,
+}
+
+impl Profile {
+    // Creates a profile from the preferences, which are given as a JSON
+    // object, and the view. Preferences that are not PROFILE_PREFERENCES are
+    // not included.
+    pub fn new(preferences: &serde_json::Value, view: Option<View>) -> Profile {
+        let preferences = preferences
+            .as_object()
+            .map(|preferences| {
+                preferences
+                    .iter()
+                    .filter(|(key, _)| PROFILE_PREFERENCES.contains(&key.as_str()))
+                    .map(|(key, value)| (key.clone(), value.clone()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        Profile { view, preferences }
+    }
+
+    // Checks that the profile only contains PROFILE_PREFERENCES and that its
+    // view is valid. The values of the preferences are validated when the
+    // profile is applied.
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(key) = self
+            .preferences
+            .keys()
+            .find(|key| !PROFILE_PREFERENCES.contains(&key.as_str()))
+        {
+            return Err(format!("unsupported setting {key}"));
+        }
+        if let Some(view) = &self.view {
+            if !view.is_valid() {
+                return Err("invalid view".to_string());
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ProfileSet {
+    pub fn new() -> ProfileSet {
+        ProfileSet {
+            version: PROFILES_VERSION,
+            profiles: BTreeMap::new(),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    // Parses a profile document and validates all its profiles.
+    pub fn from_json(json: &str) -> Result<ProfileSet, String> {
+        let set: ProfileSet =
+            serde_json::from_str(json).map_err(|e| format!("invalid profiles: {e}"))?;
+        if set.version > PROFILES_VERSION {
+            return Err(format!(
+                "profiles version {} is newer than the supported version \
+                 {PROFILES_VERSION}",
+                set.version
+            ));
+        }
+        for (name, profile) in &set.profiles {
+            Self::validate_name(name)?;
+            profile
+                .validate()
+                .map_err(|e| format!("profile {name}: {e}"))?;
+        }
+        Ok(set)
+    }
+
+    fn validate_name(name: &str) -> Result<(), String> {
+        storage::validate_name(name, "profile")
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.profiles.keys().map(|name| name.as_str())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Profile> {
+        self.profiles.get(name)
+    }
+
+    // Inserts a profile, replacing any profile with the same name.
+    pub fn insert(&mut self, name: &str, profile: Profile) -> Result<(), String> {
+        Self::validate_name(name)?;
+        profile.validate()?;
+        self.profiles.insert(name.to_string(), profile);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.profiles.remove(name).is_some()
+    }
+
+    // Adds the profiles of another set, replacing those with the same name.
+    // Returns the number of profiles added.
+    pub fn merge(&mut self, other: ProfileSet) -> usize {
+        let count = other.profiles.len();
+        self.profiles.extend(other.profiles);
+        count
+    }
+}
+
+impl Default for ProfileSet {
+    fn default() -> ProfileSet {
+        ProfileSet::new()
+    }
+}
+
+impl Profiles {
+    pub fn new(storage: Rc<Storage>) -> Profiles {
+        let set = storage
+            .get_parsed(PROFILES_KEY, "profiles", ProfileSet::from_json)
+            .unwrap_or_else(ProfileSet::new);
+        Profiles { storage, set }
+    }
+
+    pub fn set(&self) -> &ProfileSet {
+        &self.set
+    }
+
+    // Modifies the profiles and stores them.
+    pub fn update<T>(
+        &mut self,
+        f: impl FnOnce(&mut ProfileSet) -> Result<T, String>,
+    ) -> Result<T, JsValue> {
+        let result = f(&mut self.set)?;
+        self.store();
+        Ok(result)
+    }
+
+    fn store(&self) {
+        self.storage
+            .set_item(PROFILES_KEY, &serde_json::to_string(&self.set).unwrap());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn profile() -> Profile {
+        Profile::new(
+            &serde_json::json!({
+                "ad9361_rx_gain": 50.0,
+                "colormap_select": "Viridis",
+                "compact_layout": true,
+            }),
+            Some(View {
+                zoom: 2.0,
+                center_frequency: 0.5,
+            }),
+        )
+    }
+
+    #[test]
+    fn only_profile_preferences() {
+        let profile = profile();
+        assert_eq!(profile.preferences.len(), 2);
+        assert!(!profile.preferences.contains_key("compact_layout"));
+        assert_eq!(profile.validate(), Ok(()));
+    }
+
+    #[test]
+    fn roundtrip() {
+        let mut set = ProfileSet::new();
+        set.insert("HF", profile()).unwrap();
+        set.insert("2.4 GHz", profile()).unwrap();
+        let parsed = ProfileSet::from_json(&set.to_json()).unwrap();
+        assert_eq!(parsed, set);
+        assert_eq!(parsed.names().collect::<Vec<_>>(), ["2.4 GHz", "HF"]);
+    }
+
+    #[test]
+    fn invalid_names() {
+        let mut set = ProfileSet::new();
+        assert!(set.insert("", profile()).is_err());
+        assert!(set.insert("  ", profile()).is_err());
+        assert!(set.insert(" HF", profile()).is_err());
+        assert!(set
+            .insert(&"x".repeat(storage::MAX_NAME_LENGTH + 1), profile())
+            .is_err());
+        assert!(set.names().next().is_none());
+    }
+
+    #[test]
+    fn invalid_documents() {
+        assert!(ProfileSet::from_json("").is_err());
+        assert!(ProfileSet::from_json(r#"{"version": 2, "profiles": {}}"#).is_err());
+        assert!(ProfileSet::from_json(
+            r#"{"version": 1, "profiles": {"a": {"preferences": {"compact_layout": true}}}}"#
+        )
+        .is_err());
+        assert!(ProfileSet::from_json(
+            r#"{"version": 1, "profiles": {"a": {"view": {"zoom": 0.5, "center_frequency": 0.0},
+                "preferences": {}}}}"#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn merge_replaces() {
+        let mut set = ProfileSet::new();
+        set.insert("HF", profile()).unwrap();
+        let mut other = ProfileSet::new();
+        let mut replacement = profile();
+        replacement.view = None;
+        other.insert("HF", replacement.clone()).unwrap();
+        other.insert("VHF", profile()).unwrap();
+        assert_eq!(set.merge(other), 2);
+        assert_eq!(set.get("HF"), Some(&replacement));
+        assert!(set.remove("VHF"));
+        assert!(!set.remove("VHF"));
+    }
+}
@@ -0,0 +1,338 @@
+use crate::waterfall::Waterfall;
+
+// Maximum number of steps in a scan. This limits the memory used by the peaks
+// captured during the scan.
+pub const MAX_STEPS: usize = 1000;
+
+// A scan plan: the RX LO frequencies visited by a scan.
+//
+// The frequencies go from start to stop in increments of step. The stop
+// frequency is only included if it is a whole number of steps above the
+// start.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct ScanPlan {
+    // Frequencies in Hz
+    start: u64,
+    stop: u64,
+    step: u64,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ScanState {
+    Stopped,
+    Running,
+    Paused,
+    // A stop has been requested, but the current step has not finished yet.
+    Stopping,
+}
+
+// The peaks captured at one step of a scan.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScanStep {
+    // RX LO frequency in Hz
+    pub rx_lo_frequency: u64,
+    // Sampling frequency in samples per second
+    pub samp_rate: f64,
+    // Maximum of each FFT bin over the spectrum lines received during the
+    // dwell, using the same units as the waterfall texture.
+    pub peaks: Vec<f32>,
+}
+
+impl ScanPlan {
+    pub fn new(start: u64, stop: u64, step: u64) -> Result<ScanPlan, String> {
+        if step == 0 {
+            return Err("the scan step must be positive".to_string());
+        }
+        if stop < start {
+            return Err("the scan stop frequency must not be below the start".to_string());
+        }
+        let plan = ScanPlan { start, stop, step };
+        if plan.num_steps() > MAX_STEPS {
+            return Err(format!("the scan cannot have more than {MAX_STEPS} steps"));
+        }
+        Ok(plan)
+    }
+
+    pub fn num_steps(&self) -> usize {
+        usize::try_from((self.stop - self.start) / self.step)
+            .map_or(usize::MAX, |n| n.saturating_add(1))
+    }
+
+    // Returns the RX LO frequency of step n, or None if the scan has fewer
+    // steps.
+    pub fn frequency(&self, n: usize) -> Option<u64> {
+        if n >= self.num_steps() {
+            return None;
+        }
+        Some(self.start + n as u64 * self.step)
+    }
+}
+
+// A wide-band spectrum obtained by stitching the peaks captured at each step
+// of a scan.
+//
+// The spectrum maps absolute frequencies to power using bins of constant
+// width. A configurable fraction of the bins at each band edge of each step
+// is masked, since these are affected by the roll-off of the analog and
+// decimation filters. Where the remaining
+// parts of several steps overlap, the maximum power is kept, since the steps
+// contain peaks. Bins that are not covered by any step have no data.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StitchedSpectrum {
+    // Frequency of the center of the first bin in Hz
+    start: f64,
+    // Width of each bin in Hz
+    resolution: f64,
+    // Power of each bin in dB. Bins without data are NaN.
+    power_db: Vec<f32>,
+}
+
+impl StitchedSpectrum {
+    // Maximum fraction of the band that can be masked at each edge of a
+    // step. Larger fractions are clamped to this value.
+    pub const MAX_EDGE_FRACTION: f64 = 0.49;
+    // Maximum number of bins. If the scan covers more bins than this with the
+    // resolution of the FFT, the resolution is reduced.
+    const MAX_BINS: usize = 1 << 20;
+
+    // Stitches the steps of a scan, masking edge_fraction of the band at each
+    // edge of each step. The dc_exclusion_bins around the DC bin of each step
+    // are also masked (see Waterfall::set_dc_exclusion_bins). Returns None if
+    // there are no steps.
+    pub fn from_steps(
+        steps: &[ScanStep],
+        edge_fraction: f64,
+        dc_exclusion_bins: u32,
+    ) -> Option<StitchedSpectrum> {
+        let edge_fraction = if edge_fraction.is_nan() {
+            0.0
+        } else {
+            edge_fraction.clamp(0.0, Self::MAX_EDGE_FRACTION)
+        };
+        let steps: Vec<&ScanStep> = steps
+            .iter()
+            .filter(|step| !step.peaks.is_empty() && step.samp_rate > 0.0)
+            .collect();
+        let mut start = f64::INFINITY;
+        let mut stop = f64::NEG_INFINITY;
+        let mut resolution = f64::INFINITY;
+        for step in &steps {
+            let bins = Self::kept_bins(step, edge_fraction);
+            if bins.is_empty() {
+                continue;
+            }
+            start = start.min(Self::bin_frequency(step, bins.start));
+            stop = stop.max(Self::bin_frequency(step, bins.end - 1));
+            resolution = resolution.min(step.samp_rate / step.peaks.len() as f64);
+        }
+        if !start.is_finite() {
+            return None;
+        }
+        let mut num_bins = ((stop - start) / resolution).round() as usize + 1;
+        if num_bins > Self::MAX_BINS {
+            resolution *= num_bins as f64 / Self::MAX_BINS as f64;
+            num_bins = ((stop - start) / resolution).round() as usize + 1;
+        }
+        let mut power_db = vec![f32::NAN; num_bins];
+        for step in &steps {
+            let excluded = Waterfall::dc_exclusion_range(step.peaks.len(), dc_exclusion_bins);
+            for k in Self::kept_bins(step, edge_fraction).filter(|k| !excluded.contains(k)) {
+                let index = ((Self::bin_frequency(step, k) - start) / resolution).round() as usize;
+                if let Some(power) = power_db.get_mut(index) {
+                    // The peaks are given in log10 power units. NaNs are
+                    // replaced by the other value by f32::max.
+                    *power = power.max(10.0 * step.peaks[k]);
+                }
+            }
+        }
+        Some(StitchedSpectrum {
+            start,
+            resolution,
+            power_db,
+        })
+    }
+
+    // FFT bins of a step that are kept, masking those near the band edges.
+    fn kept_bins(step: &ScanStep, edge_fraction: f64) -> std::ops::Range<usize> {
+        let n = step.peaks.len();
+        // At least one bin is kept, even if the fraction rounds up to half
+        // of the bins.
+        let first = ((n as f64 * edge_fraction).ceil() as usize).min(n.saturating_sub(1) / 2);
+        first..n.saturating_sub(first)
+    }
+
+    // Frequency of the center of an FFT bin of a step in Hz. FFT bin n/2
+    // corresponds to the RX LO frequency.
+    fn bin_frequency(step: &ScanStep, k: usize) -> f64 {
+        let n = step.peaks.len();
+        let bin_hz = step.samp_rate / n as f64;
+        step.rx_lo_frequency as f64 + (k as f64 - (n / 2) as f64) * bin_hz
+    }
+
+    // Returns the frequency of the center of a bin in Hz.
+    pub fn frequency(&self, index: usize) -> f64 {
+        self.start + index as f64 * self.resolution
+    }
+
+    // Returns the frequencies of the centers of the first and last bins in Hz.
+    pub fn frequency_range(&self) -> (f64, f64) {
+        (self.start, self.frequency(self.power_db.len() - 1))
+    }
+
+    pub fn resolution(&self) -> f64 {
+        self.resolution
+    }
+
+    pub fn power_db(&self) -> &[f32] {
+        &self.power_db
+    }
+
+    // Formats the spectrum as CSV, with one row per bin that has data. If a
+    // calibration offset in dB is given, it is added to the power, which is
+    // then given in dBm.
+    pub fn to_csv(&self, calibration: Option<f32>) -> String {
+        let mut csv = String::from(match calibration {
+            Some(_) => "frequency_hz,power_dbm\n",
+            None => "frequency_hz,power_db\n",
+        });
+        for (index, power) in self.power_db.iter().enumerate() {
+            if power.is_finite() {
+                let frequency = self.frequency(index);
+                let power = power + calibration.unwrap_or(0.0);
+                csv.push_str(&format!("{frequency:.0},{power:.2}\n"));
+            }
+        }
+        csv
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn frequencies() {
+        let plan = ScanPlan::new(100_000_000, 130_000_000, 10_000_000).unwrap();
+        assert_eq!(plan.num_steps(), 4);
+        assert_eq!(plan.frequency(0), Some(100_000_000));
+        assert_eq!(plan.frequency(3), Some(130_000_000));
+        assert_eq!(plan.frequency(4), None);
+    }
+
+    #[test]
+    fn stop_not_multiple_of_step() {
+        let plan = ScanPlan::new(100_000_000, 125_000_000, 10_000_000).unwrap();
+        assert_eq!(plan.num_steps(), 3);
+        assert_eq!(plan.frequency(2), Some(120_000_000));
+    }
+
+    #[test]
+    fn single_step() {
+        let plan = ScanPlan::new(100_000_000, 100_000_000, 1).unwrap();
+        assert_eq!(plan.num_steps(), 1);
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(ScanPlan::new(100, 200, 0).is_err());
+        assert!(ScanPlan::new(200, 100, 10).is_err());
+        assert!(ScanPlan::new(0, 10_000, 1).is_err());
+    }
+
+    fn step(rx_lo_frequency: u64, peaks: Vec<f32>) -> ScanStep {
+        ScanStep {
+            rx_lo_frequency,
+            // 1 MHz bins
+            samp_rate: 1e6 * peaks.len() as f64,
+            peaks,
+        }
+    }
+
+    #[test]
+    fn stitch_single_step() {
+        let stitched =
+            StitchedSpectrum::from_steps(&[step(100_000_000, vec![1.0; 10])], 0.1, 0).unwrap();
+        // The first and last bins are dropped.
+        assert_eq!(stitched.frequency_range(), (96e6, 103e6));
+        assert_eq!(stitched.resolution(), 1e6);
+        assert_eq!(stitched.power_db(), &[10.0; 8]);
+    }
+
+    #[test]
+    fn stitch_overlap_keeps_maximum() {
+        let steps = [
+            step(100_000_000, vec![1.0; 10]),
+            step(104_000_000, vec![2.0; 10]),
+        ];
+        let stitched = StitchedSpectrum::from_steps(&steps, 0.1, 0).unwrap();
+        assert_eq!(stitched.frequency_range(), (96e6, 107e6));
+        let power = stitched.power_db();
+        assert_eq!(power.len(), 12);
+        assert_eq!(power[0], 10.0);
+        assert_eq!(power[3], 10.0);
+        assert_eq!(power[4], 20.0);
+        assert_eq!(power[11], 20.0);
+    }
+
+    #[test]
+    fn stitch_gap() {
+        let steps = [
+            step(100_000_000, vec![1.0; 10]),
+            step(120_000_000, vec![1.0; 10]),
+        ];
+        let stitched = StitchedSpectrum::from_steps(&steps, 0.1, 0).unwrap();
+        let power = stitched.power_db();
+        assert!(power[7].is_finite());
+        assert!(power[8].is_nan());
+        assert!(power[19].is_nan());
+        assert!(power[20].is_finite());
+        let csv = stitched.to_csv(None);
+        assert!(csv.starts_with("frequency_hz,power_db\n96000000,10.00\n"));
+        assert_eq!(csv.lines().count(), 1 + 16);
+        let csv = stitched.to_csv(Some(-30.0));
+        assert!(csv.starts_with("frequency_hz,power_dbm\n96000000,-20.00\n"));
+    }
+
+    #[test]
+    fn stitch_dc_exclusion() {
+        let steps = [step(100_000_000, vec![1.0; 10])];
+        let stitched = StitchedSpectrum::from_steps(&steps, 0.0, 1).unwrap();
+        let power = stitched.power_db();
+        // The DC bin is bin 5, which corresponds to the RX LO frequency
+        assert_eq!(stitched.frequency(5), 100e6);
+        assert!(power[5].is_nan());
+        assert!(power[4].is_finite() && power[6].is_finite());
+        let stitched = StitchedSpectrum::from_steps(&steps, 0.0, 2).unwrap();
+        let power = stitched.power_db();
+        assert!(power[4..7].iter().all(|x| x.is_nan()));
+        assert!(power[3].is_finite() && power[7].is_finite());
+        // Overlapping steps fill the excluded bins of each other
+        let steps = [
+            step(100_000_000, vec![1.0; 10]),
+            step(102_000_000, vec![1.0; 10]),
+        ];
+        let stitched = StitchedSpectrum::from_steps(&steps, 0.0, 1).unwrap();
+        assert!(stitched.power_db().iter().all(|x| x.is_finite()));
+    }
+
+    #[test]
+    fn stitch_empty() {
+        assert!(StitchedSpectrum::from_steps(&[], 0.1, 0).is_none());
+        assert!(StitchedSpectrum::from_steps(&[step(100_000_000, Vec::new())], 0.1, 0).is_none());
+    }
+
+    #[test]
+    fn stitch_edge_fraction() {
+        let steps = [step(100_000_000, vec![1.0; 10])];
+        let stitched = StitchedSpectrum::from_steps(&steps, 0.0, 0).unwrap();
+        assert_eq!(stitched.frequency_range(), (95e6, 104e6));
+        let stitched = StitchedSpectrum::from_steps(&steps, 0.3, 0).unwrap();
+        assert_eq!(stitched.frequency_range(), (98e6, 101e6));
+        // Masking more than half of the band is clamped, so that some bins
+        // are kept.
+        let stitched = StitchedSpectrum::from_steps(&steps, 0.7, 0).unwrap();
+        assert_eq!(stitched.frequency_range(), (99e6, 100e6));
+        let stitched = StitchedSpectrum::from_steps(&steps, f64::NAN, 0).unwrap();
+        assert_eq!(stitched.frequency_range(), (95e6, 104e6));
+    }
+}
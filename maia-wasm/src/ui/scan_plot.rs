@@ -0,0 +1,315 @@
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement, PointerEvent, WheelEvent};
+
+use super::number_format::{FrequencyUnit, NumberFormat};
+use super::scan::StitchedSpectrum;
+
+// Plot of the wide-band spectrum stitched from the results of a scan.
+//
+// The plot is drawn in a 2D canvas and it has its own view, which can be
+// zoomed with the mouse wheel and panned by dragging, independently of the
+// waterfall view.
+//
+// The frequency axis can be linear or logarithmic. Zoom and pan operate in
+// the coordinates of the axis, so that in the logarithmic axis they are
+// uniform across decades. Frequencies of zero or below cannot be shown in
+// the logarithmic axis, so in this case the view starts at the first bin
+// above 0 Hz.
+pub struct ScanPlot {
+    canvas: Rc<HtmlCanvasElement>,
+    spectrum: RefCell<Option<StitchedSpectrum>>,
+    // Frequency range displayed, in Hz
+    view: Cell<(f64, f64)>,
+    // Power range displayed, in dB
+    levels: Cell<(f32, f32)>,
+    // Pointer ID and last x coordinate of an ongoing drag
+    drag: Cell<Option<(i32, i32)>>,
+    // Format of the frequency labels
+    number_format: Cell<NumberFormat>,
+    // Whether the frequency axis is logarithmic
+    log_frequency: Cell<bool>,
+}
+
+impl ScanPlot {
+    // Zoom factor applied by each mouse wheel step.
+    const WHEEL_ZOOM_STEP: f64 = 1.25;
+    // Minimum number of spectrum bins displayed.
+    const MIN_VIEW_BINS: f64 = 16.0;
+    // Height in CSS pixels of the frequency labels.
+    const LABELS_HEIGHT: f64 = 20.0;
+
+    pub fn new(canvas: Rc<HtmlCanvasElement>) -> Rc<ScanPlot> {
+        Rc::new(ScanPlot {
+            canvas,
+            spectrum: RefCell::new(None),
+            view: Cell::new((0.0, 0.0)),
+            levels: Cell::new((35.0, 85.0)),
+            drag: Cell::new(None),
+            number_format: Cell::new(NumberFormat::default()),
+            log_frequency: Cell::new(false),
+        })
+    }
+
+    pub fn set_callbacks(self: &Rc<Self>) {
+        self.canvas
+            .set_onwheel(Some(self.onwheel().into_js_value().unchecked_ref()));
+        self.canvas
+            .set_onpointerdown(Some(self.onpointerdown().into_js_value().unchecked_ref()));
+        self.canvas
+            .set_onpointermove(Some(self.onpointermove().into_js_value().unchecked_ref()));
+        let onpointerup = self.onpointerup().into_js_value();
+        self.canvas
+            .set_onpointerup(Some(onpointerup.unchecked_ref()));
+        self.canvas
+            .set_onpointercancel(Some(onpointerup.unchecked_ref()));
+    }
+
+    // Sets the spectrum and shows all of it.
+    pub fn set_spectrum(&self, spectrum: Option<StitchedSpectrum>) -> Result<(), JsValue> {
+        let range = spectrum.as_ref().map(|s| s.frequency_range());
+        self.spectrum.replace(spectrum);
+        if let Some((start, stop)) = range {
+            self.set_view(start, stop);
+        }
+        self.draw()
+    }
+
+    pub fn spectrum(&self) -> std::cell::Ref<'_, Option<StitchedSpectrum>> {
+        self.spectrum.borrow()
+    }
+
+    // Sets the power range displayed, which is given by the waterfall levels.
+    pub fn set_levels(&self, min_db: f32, max_db: f32) -> Result<(), JsValue> {
+        self.levels.set((min_db, max_db));
+        self.draw()
+    }
+
+    // Sets the format of the frequency labels, which is used the next time
+    // that the plot is drawn.
+    pub fn set_number_format(&self, format: NumberFormat) {
+        self.number_format.set(format);
+    }
+
+    // Selects a linear or logarithmic frequency axis. The current view is
+    // kept as far as the new axis allows it.
+    pub fn set_log_frequency(&self, log_frequency: bool) -> Result<(), JsValue> {
+        if self.log_frequency.replace(log_frequency) == log_frequency {
+            return Ok(());
+        }
+        let (start, stop) = self.view.get();
+        self.set_view(start, stop);
+        self.draw()
+    }
+
+    pub fn canvas(&self) -> &HtmlCanvasElement {
+        &self.canvas
+    }
+
+    pub fn draw(&self) -> Result<(), JsValue> {
+        let context = self
+            .canvas
+            .get_context("2d")?
+            .ok_or("unable to get 2d context")?
+            .dyn_into::<CanvasRenderingContext2d>()?;
+        let width = f64::from(self.canvas.width());
+        let height = f64::from(self.canvas.height());
+        context.set_fill_style(&JsValue::from_str("black"));
+        context.fill_rect(0.0, 0.0, width, height);
+        let spectrum = self.spectrum.borrow();
+        let Some(spectrum) = spectrum.as_ref() else {
+            return Ok(());
+        };
+        let plot_height = height - Self::LABELS_HEIGHT;
+        let (min_db, max_db) = self.levels.get();
+        let power = spectrum.power_db();
+        // Each column of pixels shows the maximum of the bins that it covers,
+        // so that narrow peaks are not lost when zoomed out.
+        context.set_stroke_style(&JsValue::from_str("yellow"));
+        context.set_line_width(1.0);
+        context.begin_path();
+        let mut pen_down = false;
+        for column in 0..self.canvas.width() {
+            let column = f64::from(column);
+            let bins = self.bin_index(spectrum, column / width)
+                ..self.bin_index(spectrum, (column + 1.0) / width);
+            let bins = bins.start.min(power.len())..bins.end.max(bins.start + 1).min(power.len());
+            let value = power[bins].iter().copied().fold(f32::NAN, f32::max);
+            if !value.is_finite() {
+                pen_down = false;
+                continue;
+            }
+            let normalized = f64::from(((value - min_db) / (max_db - min_db)).clamp(0.0, 1.0));
+            let y = plot_height * (1.0 - normalized);
+            if pen_down {
+                context.line_to(column, y);
+            } else {
+                context.move_to(column, y);
+                pen_down = true;
+            }
+        }
+        context.stroke();
+        self.draw_labels(&context, width, height)
+    }
+
+    fn bin_index(&self, spectrum: &StitchedSpectrum, fraction: f64) -> usize {
+        let frequency = self.frequency_at(fraction);
+        let index = ((frequency - spectrum.frequency(0)) / spectrum.resolution()).round();
+        index.max(0.0) as usize
+    }
+
+    fn draw_labels(
+        &self,
+        context: &CanvasRenderingContext2d,
+        width: f64,
+        height: f64,
+    ) -> Result<(), JsValue> {
+        const NUM_LABELS: u32 = 5;
+        let format = self.number_format.get();
+        context.set_fill_style(&JsValue::from_str("white"));
+        context.set_font("12px sans");
+        context.set_text_align("center");
+        context.set_text_baseline("bottom");
+        for j in 0..NUM_LABELS {
+            let fraction = (f64::from(j) + 0.5) / f64::from(NUM_LABELS);
+            let frequency = self.frequency_at(fraction);
+            context.fill_text(
+                &format.frequency(frequency, FrequencyUnit::MHz, 3),
+                fraction * width,
+                height,
+            )?;
+        }
+        Ok(())
+    }
+
+    // Converts a frequency in Hz to a coordinate in the frequency axis.
+    fn to_axis(&self, frequency: f64) -> f64 {
+        if self.log_frequency.get() {
+            frequency.max(f64::MIN_POSITIVE).log10()
+        } else {
+            frequency
+        }
+    }
+
+    // Converts a coordinate in the frequency axis to a frequency in Hz.
+    fn from_axis(&self, coordinate: f64) -> f64 {
+        if self.log_frequency.get() {
+            10.0_f64.powf(coordinate)
+        } else {
+            coordinate
+        }
+    }
+
+    // Returns the frequency in Hz displayed at a fraction of the canvas
+    // width.
+    fn frequency_at(&self, fraction: f64) -> f64 {
+        let (start, stop) = self.view.get();
+        let (start, stop) = (self.to_axis(start), self.to_axis(stop));
+        self.from_axis(start + (stop - start) * fraction)
+    }
+
+    // Sets the view, clamping it to the frequency range of the spectrum.
+    //
+    // The view is clamped in the coordinates of the frequency axis, and then
+    // it is widened if needed so that it spans at least MIN_VIEW_BINS bins.
+    fn set_view(&self, start: f64, stop: f64) {
+        let spectrum = self.spectrum.borrow();
+        let Some(spectrum) = spectrum.as_ref() else {
+            return;
+        };
+        let (min, max) = spectrum.frequency_range();
+        let min = if self.log_frequency.get() {
+            min.max(spectrum.resolution())
+        } else {
+            min
+        };
+        let (axis_min, axis_max) = (self.to_axis(min), self.to_axis(max));
+        let axis_start = self.to_axis(start);
+        let axis_span = (self.to_axis(stop) - axis_start).min(axis_max - axis_min);
+        let axis_start = axis_start.clamp(axis_min, axis_max - axis_span);
+        let start = self.from_axis(axis_start);
+        let stop = self.from_axis(axis_start + axis_span);
+        let min_span = (Self::MIN_VIEW_BINS * spectrum.resolution()).min(max - min);
+        if stop - start < min_span {
+            let stop = (start + min_span).min(max);
+            self.view.set((stop - min_span, stop));
+        } else {
+            self.view.set((start, stop));
+        }
+    }
+
+    // Converts an x coordinate in CSS pixels to a fraction of the canvas
+    // width.
+    fn x_fraction(&self, x: i32) -> f64 {
+        let width = self.canvas.client_width();
+        if width <= 0 {
+            return 0.5;
+        }
+        f64::from(x) / f64::from(width)
+    }
+
+    fn onwheel(self: &Rc<Self>) -> Closure<dyn Fn(WheelEvent)> {
+        let plot = Rc::clone(self);
+        Closure::new(move |event: WheelEvent| {
+            event.prevent_default();
+            let dilation = if event.delta_y() < 0.0 {
+                1.0 / Self::WHEEL_ZOOM_STEP
+            } else if event.delta_y() > 0.0 {
+                Self::WHEEL_ZOOM_STEP
+            } else {
+                return;
+            };
+            // The zoom keeps the frequency under the pointer fixed.
+            let (start, stop) = plot.view.get();
+            let (start, stop) = (plot.to_axis(start), plot.to_axis(stop));
+            let center = start + (stop - start) * plot.x_fraction(event.offset_x());
+            plot.set_view(
+                plot.from_axis(center - (center - start) * dilation),
+                plot.from_axis(center + (stop - center) * dilation),
+            );
+            if let Err(e) = plot.draw() {
+                web_sys::console::error_1(&e);
+            }
+        })
+    }
+
+    fn onpointerdown(self: &Rc<Self>) -> Closure<dyn Fn(PointerEvent)> {
+        let plot = Rc::clone(self);
+        Closure::new(move |event: PointerEvent| {
+            if plot.drag.get().is_none() {
+                let _ = plot.canvas.set_pointer_capture(event.pointer_id());
+                plot.drag.set(Some((event.pointer_id(), event.offset_x())));
+            }
+        })
+    }
+
+    fn onpointermove(self: &Rc<Self>) -> Closure<dyn Fn(PointerEvent)> {
+        let plot = Rc::clone(self);
+        Closure::new(move |event: PointerEvent| {
+            let Some((pointer_id, last_x)) = plot.drag.get() else {
+                return;
+            };
+            if pointer_id != event.pointer_id() {
+                return;
+            }
+            plot.drag.set(Some((pointer_id, event.offset_x())));
+            let (start, stop) = plot.view.get();
+            let (start, stop) = (plot.to_axis(start), plot.to_axis(stop));
+            let shift = (stop - start) * plot.x_fraction(last_x - event.offset_x());
+            plot.set_view(plot.from_axis(start + shift), plot.from_axis(stop + shift));
+            if let Err(e) = plot.draw() {
+                web_sys::console::error_1(&e);
+            }
+        })
+    }
+
+    fn onpointerup(self: &Rc<Self>) -> Closure<dyn Fn(PointerEvent)> {
+        let plot = Rc::clone(self);
+        Closure::new(move |event: PointerEvent| {
+            if matches!(plot.drag.get(), Some((id, _)) if id == event.pointer_id()) {
+                plot.drag.set(None);
+            }
+        })
+    }
+}
@@ -0,0 +1,133 @@
+use serde::{Deserialize, Serialize};
+
+use crate::waterfall::Waterfall;
+
+// Version of the session documents produced by this code. It must be increased
+// whenever the format changes in a way that requires migrating older
+// documents, and a migration step must be added to Session::migrate.
+pub const SESSION_VERSION: u64 = 1;
+
+// A session document.
+//
+// It contains the waterfall view and the preferences (colormap, waterfall
+// levels, display options and device settings), so that a measurement setup
+// can be saved to a file and restored later.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Session {
+    pub version: u64,
+    #[serde(default)]
+    pub view: Option<View>,
+    // The preferences are kept as a JSON object, so that they can be merged
+    // with the current preferences, keeping those that are missing in the
+    // document.
+    #[serde(default)]
+    pub preferences: serde_json::Value,
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+pub struct View {
+    pub zoom: f32,
+    pub center_frequency: f32,
+}
+
+impl View {
+    // Returns true if the view can be applied to the waterfall: the zoom is
+    // within the supported range and the view does not extend beyond the band
+    // edges.
+    pub fn is_valid(&self) -> bool {
+        (Waterfall::MIN_ZOOM..=Waterfall::MAX_ZOOM).contains(&self.zoom)
+            && self.center_frequency.is_finite()
+            && self.center_frequency.abs() <= 1.0 - 1.0 / self.zoom
+    }
+}
+
+impl Session {
+    pub fn new(view: Option<View>, preferences: serde_json::Value) -> Session {
+        Session {
+            version: SESSION_VERSION,
+            view,
+            preferences,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    // Parses a session document, migrating it to the current version if it
+    // was produced by an older version.
+    pub fn from_json(json: &str) -> Result<Session, String> {
+        let value: serde_json::Value =
+            serde_json::from_str(json).map_err(|e| format!("invalid JSON: {e}"))?;
+        let version = value
+            .get("version")
+            .ok_or("not a session file (missing version)")?
+            .as_u64()
+            .ok_or("invalid session version")?;
+        if version > SESSION_VERSION {
+            return Err(format!(
+                "session version {version} is newer than the supported version \
+                 {SESSION_VERSION}"
+            ));
+        }
+        let value = Self::migrate(value, version)?;
+        serde_json::from_value(value).map_err(|e| format!("invalid session: {e}"))
+    }
+
+    // Migrates a session document from an older version to the current
+    // version. Each migration step converts version n into version n + 1.
+    fn migrate(value: serde_json::Value, version: u64) -> Result<serde_json::Value, String> {
+        match version {
+            SESSION_VERSION => Ok(value),
+            _ => Err(format!("unsupported session version {version}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        let session = Session::new(
+            Some(View {
+                zoom: 2.0,
+                center_frequency: 0.25,
+            }),
+            serde_json::json!({ "waterfall_min": 30.0 }),
+        );
+        assert_eq!(Session::from_json(&session.to_json()), Ok(session));
+    }
+
+    #[test]
+    fn partial() {
+        let session = Session::from_json(r#"{"version": 1}"#).unwrap();
+        assert_eq!(session.view, None);
+        assert_eq!(session.preferences, serde_json::Value::Null);
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(Session::from_json("").is_err());
+        assert!(Session::from_json("{}").is_err());
+        assert!(Session::from_json(r#"{"version": "1"}"#).is_err());
+        assert!(Session::from_json(r#"{"version": 0}"#).is_err());
+        assert!(Session::from_json(r#"{"version": 2}"#).is_err());
+        assert!(Session::from_json(r#"{"version": 1, "view": {"zoom": 1.0}}"#).is_err());
+    }
+
+    #[test]
+    fn view_validity() {
+        let view = |zoom, center_frequency| View {
+            zoom,
+            center_frequency,
+        };
+        assert!(view(1.0, 0.0).is_valid());
+        assert!(view(4.0, 0.75).is_valid());
+        assert!(!view(4.0, 0.8).is_valid());
+        assert!(!view(0.5, 0.0).is_valid());
+        assert!(!view(f32::NAN, 0.0).is_valid());
+        assert!(!view(2.0, f32::NAN).is_valid());
+    }
+}
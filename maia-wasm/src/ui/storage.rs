@@ -0,0 +1,263 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use wasm_bindgen::JsValue;
+use web_sys::Window;
+
+// Persistent key-value storage backed by localStorage.
+//
+// Accessing localStorage can fail, for instance in private browsing modes or
+// when the browser has storage disabled. In this case the storage falls back
+// to keeping the data in memory, so the UI still works, but without
+// persistence. The failure is logged only once.
+pub struct Storage<B = web_sys::Storage> {
+    backend: RefCell<Option<B>>,
+    // Copy of all the items written, which is used when the backend is
+    // unavailable
+    memory: RefCell<HashMap<String, String>>,
+    logged: Cell<bool>,
+}
+
+// Maximum length in characters of the names of the items saved by the user,
+// such as profiles and display presets.
+pub const MAX_NAME_LENGTH: usize = 64;
+
+// Checks the name of an item saved by the user. The kind of item, such as
+// "profile", is used in the error messages.
+pub fn validate_name(name: &str, kind: &str) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err(format!("the {kind} name must not be empty"));
+    }
+    if name.trim() != name {
+        return Err(format!("the {kind} name must not begin or end with spaces"));
+    }
+    if name.chars().count() > MAX_NAME_LENGTH {
+        return Err(format!(
+            "the {kind} name cannot have more than {MAX_NAME_LENGTH} characters"
+        ));
+    }
+    Ok(())
+}
+
+// Backend of the storage. This is implemented by localStorage, and allows
+// using a mock in the tests.
+pub trait Backend {
+    fn get_item(&self, key: &str) -> Result<Option<String>, JsValue>;
+    fn set_item(&self, key: &str, value: &str) -> Result<(), JsValue>;
+    fn remove_item(&self, key: &str) -> Result<(), JsValue>;
+
+    // Logs that the backend is unavailable.
+    fn log_unavailable(error: &JsValue) {
+        web_sys::console::warn_2(
+            &"localStorage is unavailable; settings will not be persisted".into(),
+            error,
+        );
+    }
+}
+
+impl Backend for web_sys::Storage {
+    fn get_item(&self, key: &str) -> Result<Option<String>, JsValue> {
+        web_sys::Storage::get_item(self, key)
+    }
+
+    fn set_item(&self, key: &str, value: &str) -> Result<(), JsValue> {
+        web_sys::Storage::set_item(self, key, value)
+    }
+
+    fn remove_item(&self, key: &str) -> Result<(), JsValue> {
+        web_sys::Storage::remove_item(self, key)
+    }
+}
+
+impl Storage {
+    // Opens the localStorage of the window.
+    pub fn local(window: &Window) -> Storage {
+        let storage = Storage::new(None);
+        match window.local_storage() {
+            Ok(Some(backend)) => {
+                storage.backend.replace(Some(backend));
+            }
+            Ok(None) => storage.unavailable(JsValue::NULL),
+            Err(e) => storage.unavailable(e),
+        }
+        storage
+    }
+
+    // Creates a storage that only keeps the data in memory, which is used in
+    // the tests of the users of the storage.
+    #[cfg(test)]
+    pub fn memory() -> Storage {
+        Storage::new(None)
+    }
+}
+
+impl<B: Backend> Storage<B> {
+    fn new(backend: Option<B>) -> Storage<B> {
+        Storage {
+            backend: RefCell::new(backend),
+            memory: RefCell::new(HashMap::new()),
+            logged: Cell::new(false),
+        }
+    }
+
+    pub fn get_item(&self, key: &str) -> Option<String> {
+        let result = self.backend.borrow().as_ref().map(|b| b.get_item(key));
+        match result {
+            Some(Ok(item)) => item,
+            Some(Err(e)) => {
+                self.unavailable(e);
+                self.memory.borrow().get(key).cloned()
+            }
+            None => self.memory.borrow().get(key).cloned(),
+        }
+    }
+
+    // Reads an item and parses it with parse. If the item cannot be parsed, it
+    // is removed, since it would fail to parse every time, and None is
+    // returned. The description of the item is used in the error log.
+    pub fn get_parsed<T, E>(
+        &self,
+        key: &str,
+        description: &str,
+        parse: impl FnOnce(&str) -> Result<T, E>,
+    ) -> Option<T> {
+        let item = self.get_item(key)?;
+        match parse(&item) {
+            Ok(x) => Some(x),
+            Err(_) => {
+                web_sys::console::error_1(&format!("{description} corrupted; removing").into());
+                self.remove_item(key);
+                None
+            }
+        }
+    }
+
+    pub fn set_item(&self, key: &str, value: &str) {
+        self.memory
+            .borrow_mut()
+            .insert(key.to_string(), value.to_string());
+        let result = self
+            .backend
+            .borrow()
+            .as_ref()
+            .map(|b| b.set_item(key, value));
+        if let Some(Err(e)) = result {
+            self.unavailable(e);
+        }
+    }
+
+    pub fn remove_item(&self, key: &str) {
+        self.memory.borrow_mut().remove(key);
+        let result = self.backend.borrow().as_ref().map(|b| b.remove_item(key));
+        if let Some(Err(e)) = result {
+            self.unavailable(e);
+        }
+    }
+
+    // Stops using the backend after it has failed.
+    fn unavailable(&self, error: JsValue) {
+        self.backend.replace(None);
+        if !self.logged.replace(true) {
+            B::log_unavailable(&error);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Default)]
+    struct MockBackend {
+        items: RefCell<HashMap<String, String>>,
+        fail: Cell<bool>,
+    }
+
+    // Number of times that the backend has been logged as unavailable. Each
+    // test runs in its own thread, so the count is not shared between tests.
+    thread_local! {
+        static LOGS: Cell<u32> = const { Cell::new(0) };
+    }
+
+    impl MockBackend {
+        fn result<T>(&self, value: T) -> Result<T, JsValue> {
+            if self.fail.get() {
+                Err(JsValue::NULL)
+            } else {
+                Ok(value)
+            }
+        }
+    }
+
+    impl Backend for &MockBackend {
+        fn get_item(&self, key: &str) -> Result<Option<String>, JsValue> {
+            self.result(())?;
+            Ok(self.items.borrow().get(key).cloned())
+        }
+
+        fn set_item(&self, key: &str, value: &str) -> Result<(), JsValue> {
+            self.result(())?;
+            self.items
+                .borrow_mut()
+                .insert(key.to_string(), value.to_string());
+            Ok(())
+        }
+
+        fn remove_item(&self, key: &str) -> Result<(), JsValue> {
+            self.result(())?;
+            self.items.borrow_mut().remove(key);
+            Ok(())
+        }
+
+        fn log_unavailable(_error: &JsValue) {
+            LOGS.with(|logs| logs.set(logs.get() + 1));
+        }
+    }
+
+    #[test]
+    fn persistent() {
+        let backend = MockBackend::default();
+        let storage = Storage::new(Some(&backend));
+        assert_eq!(storage.get_item("a"), None);
+        storage.set_item("a", "1");
+        assert_eq!(storage.get_item("a").as_deref(), Some("1"));
+        assert_eq!(
+            backend.items.borrow().get("a").map(|x| x.as_str()),
+            Some("1")
+        );
+        storage.remove_item("a");
+        assert_eq!(storage.get_item("a"), None);
+        assert!(backend.items.borrow().is_empty());
+        assert!(storage.backend.borrow().is_some());
+        assert_eq!(LOGS.with(|logs| logs.get()), 0);
+    }
+
+    #[test]
+    fn unavailable() {
+        let backend = MockBackend::default();
+        let storage = Storage::new(Some(&backend));
+        storage.set_item("a", "1");
+        backend.fail.set(true);
+        // The items are kept in memory after the backend fails
+        assert_eq!(storage.get_item("a").as_deref(), Some("1"));
+        assert!(storage.backend.borrow().is_none());
+        storage.set_item("b", "2");
+        assert_eq!(storage.get_item("b").as_deref(), Some("2"));
+        storage.remove_item("a");
+        assert_eq!(storage.get_item("a"), None);
+        // The backend is not used anymore, even if it recovers
+        backend.fail.set(false);
+        storage.set_item("c", "3");
+        assert!(!backend.items.borrow().contains_key("c"));
+        // The failure is logged once
+        assert_eq!(LOGS.with(|logs| logs.get()), 1);
+    }
+
+    #[test]
+    fn no_backend() {
+        let storage = Storage::<&MockBackend>::new(None);
+        assert_eq!(storage.get_item("a"), None);
+        storage.set_item("a", "1");
+        assert_eq!(storage.get_item("a").as_deref(), Some("1"));
+        assert!(storage.backend.borrow().is_none());
+    }
+}
@@ -0,0 +1,98 @@
+use std::rc::Rc;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement};
+
+use super::number_format::{NumberFormat, TimeUnit};
+use crate::waterfall::PowerHistory;
+
+// Strip chart that plots the power history of a frequency.
+//
+// The chart is drawn in a 2D canvas. The time axis spans the window of the
+// history and ends at the most recent sample, which is drawn at the right
+// edge, so the plot scrolls to the left as samples are added.
+pub struct StripChart {
+    canvas: Rc<HtmlCanvasElement>,
+}
+
+impl StripChart {
+    // Height in CSS pixels of the time labels.
+    const LABELS_HEIGHT: f64 = 16.0;
+
+    pub fn new(canvas: Rc<HtmlCanvasElement>) -> StripChart {
+        StripChart { canvas }
+    }
+
+    // Draws the history, using the power range given by the waterfall levels,
+    // in dB. The chart is cleared if there is no history.
+    pub fn draw(
+        &self,
+        history: Option<&PowerHistory>,
+        min_db: f32,
+        max_db: f32,
+        format: &NumberFormat,
+    ) -> Result<(), JsValue> {
+        let context = self
+            .canvas
+            .get_context("2d")?
+            .ok_or("unable to get 2d context")?
+            .dyn_into::<CanvasRenderingContext2d>()?;
+        let width = f64::from(self.canvas.width());
+        let height = f64::from(self.canvas.height());
+        context.set_fill_style(&JsValue::from_str("black"));
+        context.fill_rect(0.0, 0.0, width, height);
+        let Some(history) = history else {
+            return Ok(());
+        };
+        let plot_height = height - Self::LABELS_HEIGHT;
+        let window = history.window();
+        if let Some(end) = history.last_timestamp().filter(|_| window > 0.0) {
+            // Samples in which the frequency was outside of the band are not
+            // drawn, leaving a gap in the line.
+            context.set_stroke_style(&JsValue::from_str("yellow"));
+            context.set_line_width(1.0);
+            context.begin_path();
+            let mut pen_down = false;
+            for (timestamp, power) in history.samples() {
+                if !power.is_finite() {
+                    pen_down = false;
+                    continue;
+                }
+                let x = width * (1.0 - (end - timestamp) / window);
+                let normalized = f64::from(((power - min_db) / (max_db - min_db)).clamp(0.0, 1.0));
+                let y = plot_height * (1.0 - normalized);
+                if pen_down {
+                    context.line_to(x, y);
+                } else {
+                    context.move_to(x, y);
+                    pen_down = true;
+                }
+            }
+            context.stroke();
+        }
+        Self::draw_labels(&context, window, min_db, max_db, width, height, format)
+    }
+
+    fn draw_labels(
+        context: &CanvasRenderingContext2d,
+        window: f64,
+        min_db: f32,
+        max_db: f32,
+        width: f64,
+        height: f64,
+        format: &NumberFormat,
+    ) -> Result<(), JsValue> {
+        let plot_height = height - Self::LABELS_HEIGHT;
+        context.set_fill_style(&JsValue::from_str("white"));
+        context.set_font("12px sans");
+        context.set_text_align("left");
+        context.set_text_baseline("top");
+        let db = |x: f32| format!("{} dB", format.number(f64::from(x), 0));
+        context.fill_text(&db(max_db), 2.0, 2.0)?;
+        context.set_text_baseline("bottom");
+        context.fill_text(&db(min_db), 2.0, plot_height)?;
+        context.fill_text(&format.time(-window * 1e-3, TimeUnit::S, 0), 2.0, height)?;
+        context.set_text_align("right");
+        context.fill_text("now", width - 2.0, height)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,184 @@
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::JsValue;
+use web_sys::{AudioContext, GainNode, OscillatorNode, OscillatorType};
+
+// Audio tone that tracks the power at the RX LO frequency.
+//
+// The tone is played with the Web Audio API. The audio context is closed when
+// the tone is dropped, so that it does not keep using the audio device while
+// the tone is disabled.
+pub struct Tone {
+    context: AudioContext,
+    oscillator: OscillatorNode,
+    gain: GainNode,
+}
+
+// Mapping between power and the parameters of the tone.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum ToneMapping {
+    Pitch,
+    Volume,
+    PitchAndVolume,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ToneParameters {
+    // Frequency in Hz
+    pub frequency: f32,
+    // Linear gain between 0 and 1
+    pub gain: f32,
+}
+
+impl Tone {
+    // Time constant in seconds used to smooth the changes of the tone, which
+    // avoids clicks when the parameters are updated.
+    const TIME_CONSTANT: f64 = 0.05;
+
+    pub fn new() -> Result<Tone, JsValue> {
+        let context = AudioContext::new()?;
+        let oscillator = context.create_oscillator()?;
+        oscillator.set_type(OscillatorType::Sine);
+        let gain = context.create_gain()?;
+        // The tone starts muted until the first update.
+        gain.gain().set_value(0.0);
+        oscillator.connect_with_audio_node(&gain)?;
+        gain.connect_with_audio_node(&context.destination())?;
+        oscillator.start()?;
+        Ok(Tone {
+            context,
+            oscillator,
+            gain,
+        })
+    }
+
+    pub fn set(&self, parameters: ToneParameters) -> Result<(), JsValue> {
+        let now = self.context.current_time();
+        self.oscillator.frequency().set_target_at_time(
+            parameters.frequency,
+            now,
+            Self::TIME_CONSTANT,
+        )?;
+        self.gain
+            .gain()
+            .set_target_at_time(parameters.gain, now, Self::TIME_CONSTANT)?;
+        Ok(())
+    }
+}
+
+impl Drop for Tone {
+    fn drop(&mut self) {
+        let _ = self.oscillator.stop();
+        let _ = self.oscillator.disconnect();
+        let _ = self.gain.disconnect();
+        let _ = self.context.close();
+    }
+}
+
+impl ToneMapping {
+    // Range of frequencies of the tone in Hz.
+    const MIN_FREQUENCY: f32 = 200.0;
+    const MAX_FREQUENCY: f32 = 2000.0;
+    // Range of gains of the tone.
+    const MIN_GAIN: f32 = 0.02;
+    const MAX_GAIN: f32 = 0.5;
+    // Gain used when the volume does not depend on the power.
+    const FIXED_GAIN: f32 = 0.25;
+    // Frequency used when the pitch does not depend on the power.
+    const FIXED_FREQUENCY: f32 = 800.0;
+
+    // Returns the tone parameters for a power in dB. The power is mapped
+    // linearly from the range between min_db and max_db, and it is clamped to
+    // that range. The frequency is interpolated geometrically, so that equal
+    // changes in power give equal changes in perceived pitch.
+    pub fn parameters(&self, power_db: f32, min_db: f32, max_db: f32) -> ToneParameters {
+        let t = ((power_db - min_db) / (max_db - min_db)).clamp(0.0, 1.0);
+        // NaNs (including an empty range) are mapped to the bottom of the
+        // range.
+        let t = if t.is_nan() { 0.0 } else { t };
+        let frequency = Self::MIN_FREQUENCY * (Self::MAX_FREQUENCY / Self::MIN_FREQUENCY).powf(t);
+        let gain = Self::MIN_GAIN + (Self::MAX_GAIN - Self::MIN_GAIN) * t;
+        match self {
+            ToneMapping::Pitch => ToneParameters {
+                frequency,
+                gain: Self::FIXED_GAIN,
+            },
+            ToneMapping::Volume => ToneParameters {
+                frequency: Self::FIXED_FREQUENCY,
+                gain,
+            },
+            ToneMapping::PitchAndVolume => ToneParameters { frequency, gain },
+        }
+    }
+}
+
+impl std::str::FromStr for ToneMapping {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<ToneMapping, ()> {
+        Ok(match s {
+            "Pitch" => ToneMapping::Pitch,
+            "Volume" => ToneMapping::Volume,
+            "Pitch and volume" => ToneMapping::PitchAndVolume,
+            _ => return Err(()),
+        })
+    }
+}
+
+impl std::fmt::Display for ToneMapping {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        write!(
+            f,
+            "{}",
+            match self {
+                ToneMapping::Pitch => "Pitch",
+                ToneMapping::Volume => "Volume",
+                ToneMapping::PitchAndVolume => "Pitch and volume",
+            }
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn pitch_range() {
+        let low = ToneMapping::Pitch.parameters(-100.0, 40.0, 80.0);
+        let high = ToneMapping::Pitch.parameters(100.0, 40.0, 80.0);
+        assert_eq!(low.frequency, ToneMapping::MIN_FREQUENCY);
+        assert!((high.frequency - ToneMapping::MAX_FREQUENCY).abs() < 1e-2);
+        assert_eq!(low.gain, high.gain);
+    }
+
+    #[test]
+    fn volume_range() {
+        let low = ToneMapping::Volume.parameters(40.0, 40.0, 80.0);
+        let mid = ToneMapping::Volume.parameters(60.0, 40.0, 80.0);
+        let high = ToneMapping::Volume.parameters(80.0, 40.0, 80.0);
+        assert_eq!(low.gain, ToneMapping::MIN_GAIN);
+        assert!(mid.gain > low.gain && mid.gain < high.gain);
+        assert_eq!(high.gain, ToneMapping::MAX_GAIN);
+        assert_eq!(low.frequency, high.frequency);
+    }
+
+    #[test]
+    fn invalid_range() {
+        let parameters = ToneMapping::PitchAndVolume.parameters(50.0, 60.0, 60.0);
+        assert!(parameters.frequency.is_finite());
+        assert!(parameters.gain.is_finite());
+        let parameters = ToneMapping::PitchAndVolume.parameters(f32::NAN, 40.0, 80.0);
+        assert_eq!(parameters.frequency, ToneMapping::MIN_FREQUENCY);
+    }
+
+    #[test]
+    fn names() {
+        for mapping in [
+            ToneMapping::Pitch,
+            ToneMapping::Volume,
+            ToneMapping::PitchAndVolume,
+        ] {
+            assert_eq!(mapping.to_string().parse::<ToneMapping>(), Ok(mapping));
+        }
+    }
+}
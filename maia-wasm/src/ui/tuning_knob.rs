@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+
+// Tuning of the RX LO frequency with a gamepad or with a rotary encoder that
+// presents itself as a gamepad. Each press of a tuning button, and each time
+// that the first axis is pushed to one side, counts as a detent, which moves
+// the frequency one step.
+//
+// The gamepads are polled, so this only keeps the state of the buttons and
+// axes in the previous poll, which is used to detect the detents.
+#[derive(Debug, Default)]
+pub struct TuningKnob {
+    // State of each connected gamepad, indexed by gamepad index
+    pads: HashMap<u32, PadState>,
+}
+
+// State of a gamepad as read from the Gamepad API.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PadInput {
+    pub index: u32,
+    pub id: String,
+    pub buttons: Vec<bool>,
+    pub axes: Vec<f64>,
+}
+
+// Result of polling the gamepads.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct KnobUpdate {
+    // Number of steps to tune, which is positive to tune up
+    pub steps: i32,
+    // IDs of the gamepads that have been connected or disconnected since the
+    // previous poll
+    pub connected: Vec<String>,
+    pub disconnected: Vec<String>,
+}
+
+#[derive(Debug)]
+struct PadState {
+    id: String,
+    buttons: Vec<bool>,
+    // Side to which the first axis is pushed (-1, 0 or 1)
+    axis: i32,
+}
+
+impl TuningKnob {
+    // Buttons of the standard gamepad mapping and steps that they tune. The
+    // up and right buttons of the D-pad tune up, and the down and left
+    // buttons tune down.
+    const BUTTONS: [(usize, i32); 4] = [(12, 1), (13, -1), (14, -1), (15, 1)];
+    // The axis counts as pushed when its absolute value goes above the press
+    // threshold, and as released when it goes below the release threshold.
+    const AXIS_PRESS: f64 = 0.5;
+    const AXIS_RELEASE: f64 = 0.25;
+
+    pub fn new() -> TuningKnob {
+        TuningKnob::default()
+    }
+
+    // Forgets the state of all the gamepads.
+    pub fn reset(&mut self) {
+        self.pads.clear();
+    }
+
+    // Updates the state with a poll of the connected gamepads and returns the
+    // number of steps to tune. Buttons that are already pressed and axes that
+    // are already pushed when a gamepad connects do not count as detents.
+    pub fn update(&mut self, pads: impl IntoIterator<Item = PadInput>) -> KnobUpdate {
+        let mut update = KnobUpdate::default();
+        let mut pads = pads
+            .into_iter()
+            .map(|pad| (pad.index, pad))
+            .collect::<HashMap<_, _>>();
+        self.pads.retain(|index, state| {
+            let connected = pads.get(index).is_some_and(|pad| pad.id == state.id);
+            if !connected {
+                update.disconnected.push(state.id.clone());
+            }
+            connected
+        });
+        for (index, pad) in pads.drain() {
+            let axis = Self::axis_side(pad.axes.first().copied(), 0);
+            match self.pads.get_mut(&index) {
+                Some(state) => {
+                    for &(button, steps) in &Self::BUTTONS {
+                        let pressed = pad.buttons.get(button).copied().unwrap_or(false);
+                        let was_pressed = state.buttons.get(button).copied().unwrap_or(false);
+                        if pressed && !was_pressed {
+                            update.steps += steps;
+                        }
+                    }
+                    let axis = Self::axis_side(pad.axes.first().copied(), state.axis);
+                    if axis != 0 && axis != state.axis {
+                        update.steps += axis;
+                    }
+                    state.buttons = pad.buttons;
+                    state.axis = axis;
+                }
+                None => {
+                    update.connected.push(pad.id.clone());
+                    self.pads.insert(
+                        index,
+                        PadState {
+                            id: pad.id,
+                            buttons: pad.buttons,
+                            axis,
+                        },
+                    );
+                }
+            }
+        }
+        update
+    }
+
+    // Returns the side to which the axis is pushed, given the side in the
+    // previous poll.
+    fn axis_side(value: Option<f64>, previous: i32) -> i32 {
+        let Some(value) = value.filter(|x| x.is_finite()) else {
+            return 0;
+        };
+        let threshold = if previous == 0 {
+            Self::AXIS_PRESS
+        } else {
+            Self::AXIS_RELEASE
+        };
+        if value.abs() <= threshold {
+            0
+        } else if value > 0.0 {
+            1
+        } else {
+            -1
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn pad(index: u32, pressed: &[usize], axis: f64) -> PadInput {
+        PadInput {
+            index,
+            id: format!("pad {index}"),
+            buttons: (0..17).map(|j| pressed.contains(&j)).collect(),
+            axes: vec![axis, 0.0],
+        }
+    }
+
+    #[test]
+    fn buttons() {
+        let mut knob = TuningKnob::new();
+        let update = knob.update([pad(0, &[12], 0.0)]);
+        // A button held when the gamepad connects does not tune
+        assert_eq!(update.steps, 0);
+        assert_eq!(update.connected, vec!["pad 0".to_string()]);
+        assert_eq!(knob.update([pad(0, &[12], 0.0)]).steps, 0);
+        assert_eq!(knob.update([pad(0, &[], 0.0)]).steps, 0);
+        assert_eq!(knob.update([pad(0, &[12, 15], 0.0)]).steps, 2);
+        assert_eq!(knob.update([pad(0, &[15, 13], 0.0)]).steps, -1);
+        // Other buttons do not tune
+        assert_eq!(knob.update([pad(0, &[0, 1], 0.0)]).steps, 0);
+    }
+
+    #[test]
+    fn axis() {
+        let mut knob = TuningKnob::new();
+        knob.update([pad(0, &[], 0.0)]);
+        assert_eq!(knob.update([pad(0, &[], 0.4)]).steps, 0);
+        assert_eq!(knob.update([pad(0, &[], 0.9)]).steps, 1);
+        // The axis stays pushed until it goes below the release threshold
+        assert_eq!(knob.update([pad(0, &[], 0.4)]).steps, 0);
+        assert_eq!(knob.update([pad(0, &[], 0.9)]).steps, 0);
+        assert_eq!(knob.update([pad(0, &[], 0.1)]).steps, 0);
+        assert_eq!(knob.update([pad(0, &[], -0.9)]).steps, -1);
+        assert_eq!(knob.update([pad(0, &[], 0.9)]).steps, 1);
+        assert_eq!(knob.update([pad(0, &[], f64::NAN)]).steps, 0);
+    }
+
+    #[test]
+    fn disconnect() {
+        let mut knob = TuningKnob::new();
+        knob.update([pad(0, &[], 0.0), pad(1, &[], 0.0)]);
+        let update = knob.update([pad(1, &[12], 0.0)]);
+        assert_eq!(update.steps, 1);
+        assert_eq!(update.disconnected, vec!["pad 0".to_string()]);
+        // A different gamepad in the same index counts as a new connection
+        let mut other = pad(1, &[], 0.0);
+        other.id = "other".to_string();
+        let update = knob.update([other]);
+        assert_eq!(update.connected, vec!["other".to_string()]);
+        assert_eq!(update.disconnected, vec!["pad 1".to_string()]);
+        knob.reset();
+        assert_eq!(knob.update([]).disconnected, Vec::<String>::new());
+    }
+}
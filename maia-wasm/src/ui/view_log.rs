@@ -0,0 +1,155 @@
+use serde::{Deserialize, Serialize};
+
+use super::colormap::Colormap;
+use super::session::View;
+
+// Version of the view log documents produced by this code.
+pub const VIEW_LOG_VERSION: u64 = 1;
+
+// State recorded in the view log. The view uses the same units as sessions.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+pub struct ViewLogState {
+    #[serde(flatten)]
+    pub view: View,
+    #[serde(default)]
+    pub rx_lo_frequency: Option<u64>,
+    #[serde(default)]
+    pub colormap: Option<Colormap>,
+}
+
+// An entry of the view log. The time is given in milliseconds since the start
+// of the recording.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+pub struct ViewLogEntry {
+    pub time_ms: f64,
+    #[serde(flatten)]
+    pub state: ViewLogState,
+}
+
+// A log of the changes of the view state, which can be exported to a file and
+// replayed.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ViewLog {
+    pub version: u64,
+    pub entries: Vec<ViewLogEntry>,
+}
+
+// What the view log is being used for.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Activity {
+    Idle,
+    // The state is sampled periodically. The start is the performance.now()
+    // timestamp of the start of the recording, and the interval is the handle
+    // of the sampling interval.
+    Recording { start: f64, interval: i32 },
+    // The handle is that of the timeout that applies the next entry.
+    Replaying { timeout: i32 },
+}
+
+impl ViewLog {
+    pub fn new() -> ViewLog {
+        ViewLog {
+            version: VIEW_LOG_VERSION,
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // Records the state at a time since the start of the recording. The state
+    // is only recorded if it is different from the last recorded state.
+    // Returns true if the state was recorded.
+    pub fn record(&mut self, time_ms: f64, state: ViewLogState) -> bool {
+        if self.entries.last().map(|entry| entry.state) == Some(state) {
+            return false;
+        }
+        self.entries.push(ViewLogEntry { time_ms, state });
+        true
+    }
+
+    // Returns the delay in milliseconds between an entry and the next one
+    // when replaying at a speed, or None if it is the last entry.
+    pub fn delay_after(&self, index: usize, speed: f64) -> Option<f64> {
+        let current = self.entries.get(index)?;
+        let next = self.entries.get(index + 1)?;
+        Some((next.time_ms - current.time_ms) / speed)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    // Parses a view log document. The entries must be in chronological order
+    // and have valid views.
+    pub fn from_json(json: &str) -> Result<ViewLog, String> {
+        let log: ViewLog =
+            serde_json::from_str(json).map_err(|e| format!("invalid view log: {e}"))?;
+        if log.version != VIEW_LOG_VERSION {
+            return Err(format!("unsupported view log version {}", log.version));
+        }
+        let mut last_time = 0.0;
+        for entry in &log.entries {
+            if !(entry.time_ms >= last_time && entry.time_ms.is_finite()) {
+                return Err("the view log entries are not in chronological order".to_string());
+            }
+            if !entry.state.view.is_valid() {
+                return Err(format!("invalid view at {} ms", entry.time_ms));
+            }
+            last_time = entry.time_ms;
+        }
+        Ok(log)
+    }
+}
+
+impl Default for ViewLog {
+    fn default() -> ViewLog {
+        ViewLog::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn state(zoom: f32, colormap: Colormap) -> ViewLogState {
+        ViewLogState {
+            view: View {
+                zoom,
+                center_frequency: 0.0,
+            },
+            rx_lo_frequency: Some(2_400_000_000),
+            colormap: Some(colormap),
+        }
+    }
+
+    #[test]
+    fn record() {
+        let mut log = ViewLog::new();
+        assert!(log.record(0.0, state(1.0, Colormap::Turbo)));
+        assert!(!log.record(100.0, state(1.0, Colormap::Turbo)));
+        assert!(log.record(200.0, state(2.0, Colormap::Turbo)));
+        assert!(log.record(1200.0, state(2.0, Colormap::Viridis)));
+        assert_eq!(log.entries.len(), 3);
+        assert_eq!(log.delay_after(0, 1.0), Some(200.0));
+        assert_eq!(log.delay_after(1, 2.0), Some(500.0));
+        assert_eq!(log.delay_after(2, 1.0), None);
+        assert_eq!(ViewLog::from_json(&log.to_json()), Ok(log));
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(ViewLog::from_json("").is_err());
+        assert!(ViewLog::from_json(r#"{"version": 2, "entries": []}"#).is_err());
+        let entry = |time_ms, zoom| {
+            format!(r#"{{"time_ms": {time_ms}, "zoom": {zoom}, "center_frequency": 0.0}}"#)
+        };
+        let log =
+            |entries: &[String]| format!(r#"{{"version": 1, "entries": [{}]}}"#, entries.join(","));
+        let partial = ViewLog::from_json(&log(&[entry(0.0, 1.0)])).unwrap();
+        assert_eq!(partial.entries[0].state.colormap, None);
+        assert!(ViewLog::from_json(&log(&[entry(100.0, 1.0), entry(0.0, 1.0)])).is_err());
+        assert!(ViewLog::from_json(&log(&[entry(0.0, 0.5)])).is_err());
+    }
+}
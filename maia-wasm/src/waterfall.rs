@@ -8,11 +8,25 @@ use crate::render::{
     DrawMode, ProgramSource, RenderEngine, RenderObject, Texture, TextureMagFilter,
     TextureMinFilter, TextureParameter, TextureWrap, Uniform, UniformValue,
 };
+use crate::tween::{lerp, Easing, Momentum, Tween};
+use crate::ui::scan::StitchedSpectrum;
 use std::cell::Cell;
 use std::rc::Rc;
 use wasm_bindgen::prelude::*;
 use web_sys::{Performance, WebGlProgram, WebGlTexture, WebGlVertexArrayObject};
 
+use noise_floor::NoiseFloorEstimator;
+use peak_tracker::PeakTracker;
+pub use perf_stats::PerfReport;
+use perf_stats::PerfStats;
+pub use power_history::PowerHistory;
+
+mod auto_range;
+mod noise_floor;
+mod peak_tracker;
+mod perf_stats;
+mod power_history;
+
 /// Waterfall.
 ///
 /// This object is used to create and add a WebGL2 waterfall display to a
@@ -28,6 +42,8 @@ pub struct Waterfall {
     current_draw_line: usize,
     last_draw_line: usize,
     last_spectrum_timestamp: Option<f32>,
+    // Number of spectrum lines received, saturating at the texture height
+    num_lines: usize,
     waterfall_rate: Option<f32>,
     waterfall_wraps: usize,
     center_freq: f64,
@@ -40,14 +56,101 @@ pub struct Waterfall {
     zoom_levels: Vec<f32>,
     waterfall_min: f32,
     waterfall_max: f32,
+    // Fraction of the power of a spectrum line that persists after one
+    // second. Zero disables persistence.
+    persistence: f32,
+    // Maximum of each FFT bin over the spectrum lines received since the
+    // capture was started
+    peak_capture: Option<Vec<f32>>,
+    // Power of the FFT bin of the RX LO frequency in the last spectrum line
+    rx_lo_power: Option<f32>,
+    // Power of a frequency in the spectrum lines, which is recorded to plot
+    // its level over time
+    power_history: Option<PowerHistory>,
+    // Flash of the RX LO marker that confirms the result of a retune
+    rx_lo_flash: Option<MarkerFlash>,
+    markers: Markers,
+    ruler: Ruler,
+    bin_highlight: BinHighlight,
+    passband_highlight: BinHighlight,
+    retune_progress: RetuneProgress,
+    edge_mask: EdgeMask,
+    comb: Comb,
+    bin_grid: BinGrid,
+    reticle: Reticle,
+    // Height of each waterfall line in CSS pixels, or zero to fit half of the
+    // waterfall texture on the screen
+    line_height: u32,
+    // Animation of the view towards a target (zoom, center frequency)
+    view_tween: Option<Tween<(f32, f32)>>,
+    // Zoom momentum after a pinch gesture, in natural log units of the zoom,
+    // and the frequency around which the zoom is dilated
+    zoom_momentum: Option<(Momentum, f32)>,
+    animation_easing: Easing,
+    // Snap the zoom to powers of two when zoom gestures end
+    snap_zoom: bool,
+    // Maximum distance, as a fraction of the view width, that the view can be
+    // dragged beyond the band edges
+    overscan: f32,
+    // Margin, as a fraction of the view width, within which the view is
+    // panned to keep the RX LO marker visible, or None if the view is not
+    // panned
+    rx_lo_keep_visible: Option<f32>,
+    rx_lo_keep_visible_margin: f32,
+    // Number of FFT bins around DC that are excluded from the measurements
+    dc_exclusion_bins: u32,
+    noise_floor: NoiseFloorEstimator,
+    // Noise floor of the spectrum lines before applying the power correction,
+    // which is only estimated while a correction is set
+    uncorrected_noise_floor: NoiseFloorEstimator,
+    line_width_check: LineWidthCheck,
+    peak_tracking: bool,
+    peak_tracker: PeakTracker,
+    // Absolute frequencies in Hz of the measurement cursors, indexed by
+    // Cursor
+    cursors: [Option<f64>; 2],
+    // Absolute center frequency and bandwidth in Hz of the passband
+    passband: Option<(f64, f64)>,
+    // Stop adding spectrum lines regardless of the measurements
+    frozen: bool,
+    // Stop adding spectrum lines because the user has paused the waterfall
+    paused: bool,
+    // Stop adding spectrum lines while a measurement is shown
+    freeze_on_measurement: bool,
+    // Statistics for the performance overlay, which are only collected while
+    // it is enabled
+    perf_stats: Option<PerfStats>,
+    smooth_scroll: bool,
+    line_timing: LineTiming,
+    // Frequency dependent power correction, which gives the offset in dB to
+    // add at a frequency in Hz
+    power_correction: Option<Box<dyn Fn(f64) -> f32>>,
+    // Offset added to each FFT bin, in log10 power units. This is computed
+    // from power_correction for the current center frequency and sample
+    // rate, and it is empty if there is no correction.
+    bin_correction: Vec<f32>,
+    // Decimal separator of the frequency labels
+    labels_decimal_separator: char,
+    // Set when the view or the objects drawn over the waterfall are changed
+    // outside of prepare_render, and cleared when the next frame is prepared
+    dirty: Cell<bool>,
 }
 
 struct Uniforms {
     time_translation: Rc<Uniform<f32>>,
+    line_height_scale: Rc<Uniform<f32>>,
     center_freq: Rc<Uniform<f32>>,
     zoom: Rc<Uniform<f32>>,
+    axis_direction: Rc<Uniform<f32>>,
     waterfall_scale_add: Rc<Uniform<f32>>,
     waterfall_scale_mult: Rc<Uniform<f32>>,
+    spectral_interpolation: Rc<Uniform<i32>>,
+    linear_light_colormap: Rc<Uniform<i32>>,
+    // Colors of the values above the maximum and below the minimum. A zero
+    // alpha uses the colormap color instead.
+    over_range_color: Rc<Uniform<(f32, f32, f32, f32)>>,
+    under_range_color: Rc<Uniform<(f32, f32, f32, f32)>>,
+    waterfall_gamma: Rc<Uniform<f32>>,
     freq_labels_width: Rc<Uniform<f32>>,
     freq_labels_height: Rc<Uniform<f32>>,
     major_ticks_end: Rc<Uniform<i32>>,
@@ -62,6 +165,161 @@ struct Textures {
 struct Programs {
     frequency_labels: Rc<WebGlProgram>,
     frequency_ticks: Rc<WebGlProgram>,
+    marker: Rc<WebGlProgram>,
+    ruler: Rc<WebGlProgram>,
+    bin_highlight: Rc<WebGlProgram>,
+    retune_progress: Rc<WebGlProgram>,
+    edge_mask: Rc<WebGlProgram>,
+    comb: Rc<WebGlProgram>,
+    bin_grid: Rc<WebGlProgram>,
+    reticle: Rc<WebGlProgram>,
+}
+
+struct Markers {
+    rx_lo: Marker,
+    // Strongest signal followed by the peak tracker
+    peak: Marker,
+    // Measurement cursors, indexed by Cursor
+    cursors: [Marker; 2],
+}
+
+/// Measurement cursor.
+///
+/// The waterfall has two measurement cursors, which are drawn as vertical
+/// lines locked to absolute frequencies.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Cursor {
+    /// Cursor A.
+    A,
+    /// Cursor B.
+    B,
+}
+
+// A vertical line drawn over the waterfall at a given frequency. The frequency
+// uses the same units as the waterfall center frequency.
+struct Marker {
+    frequency: Rc<Uniform<f32>>,
+    color: Rc<Uniform<(f32, f32, f32, f32)>>,
+    num_indices: Rc<Cell<u32>>,
+}
+
+// A flash of a marker, which blends its color towards the flash color in a
+// number of pulses that fade out.
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct MarkerFlash {
+    color: (f32, f32, f32, f32),
+    pulses: u32,
+    // performance.now() timestamp of the start of the flash, in milliseconds
+    start: f64,
+}
+
+// A line drawn over the waterfall between two points, which is used to show a
+// measurement. The x coordinates of the endpoints use the same units as the
+// waterfall center frequency, and the y coordinates are clip-space
+// coordinates.
+struct Ruler {
+    start: Rc<Uniform<(f32, f32)>>,
+    end: Rc<Uniform<(f32, f32)>>,
+    num_indices: Rc<Cell<u32>>,
+}
+
+// A faint band drawn over the FFT bin under the pointer. The edges of the bin
+// use the same units as the waterfall center frequency. The same kind of band
+// is used to draw the passband.
+struct BinHighlight {
+    edges: Rc<Uniform<(f32, f32)>>,
+    // Color of the band, using premultiplied alpha
+    color: Rc<Uniform<(f32, f32, f32, f32)>>,
+    // Minimum half width of the band in clip-space units, which corresponds to
+    // one device pixel. This keeps the band visible when the bins are narrower
+    // than a pixel.
+    min_half_width: Rc<Uniform<f32>>,
+    num_indices: Rc<Cell<u32>>,
+}
+
+// A bar drawn at the edge of the screen that shows how far the waterfall has
+// been dragged beyond the band edge towards retuning the RX frequency.
+struct RetuneProgress {
+    // Screen side of the bar: -1.0 for the left and 1.0 for the right
+    side: Rc<Uniform<f32>>,
+    // Progress between 0.0 and 1.0, which gives the height of the bar
+    progress: Rc<Uniform<f32>>,
+    num_indices: Rc<Cell<u32>>,
+}
+
+// Two dark bands drawn over both edges of the band, which mark the part of
+// the spectrum that is masked when stitching the results of a scan. The width
+// of each band uses the same units as the waterfall center frequency.
+struct EdgeMask {
+    width: Rc<Uniform<f32>>,
+    // Screen side of each band before flipping the frequency axis: -1.0 for
+    // the lower band edge and 1.0 for the upper band edge
+    sides: [Rc<Uniform<f32>>; 2],
+    num_indices: Rc<Cell<u32>>,
+}
+
+// A set of equally spaced vertical lines drawn at absolute frequencies, which
+// is used as a reference for alignment. The uniforms use the same units as the
+// waterfall center frequency, and are recomputed from the absolute
+// frequencies whenever the waterfall center frequency or sample rate change.
+struct Comb {
+    // Frequencies in Hz
+    origin: u64,
+    spacing: u32,
+    count: u32,
+    start: Rc<Uniform<f32>>,
+    step: Rc<Uniform<f32>>,
+    num_indices: Rc<Cell<u32>>,
+}
+
+// Detects spectrum lines whose number of FFT bins does not match the texture
+// width. These can arrive while the FFT size is being changed, and they are
+// dropped instead of being written to the texture.
+#[derive(Default)]
+struct LineWidthCheck {
+    // Number of consecutive lines dropped
+    dropped: usize,
+}
+
+// Measures the interval at which spectrum lines arrive, which is used to
+// scroll the waterfall smoothly between lines. The nominal interval given by
+// the waterfall update rate is only used until lines have been measured, since
+// the lines can arrive at a somewhat different rate.
+#[derive(Default)]
+struct LineTiming {
+    // Smoothed interval between lines in milliseconds
+    interval: Option<f32>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum LineWidth {
+    Valid,
+    // The line must be dropped. first is true for the first line of a run of
+    // mismatched lines.
+    Mismatch { first: bool },
+    // The line is valid and it follows a run of dropped lines.
+    Recovered { dropped: usize },
+}
+
+// Faint vertical lines drawn at the boundaries between FFT bins, which are
+// shown only when the bins are wide enough on the screen. The uniforms use the
+// same units as the waterfall center frequency, and are recomputed from the
+// view on each frame.
+struct BinGrid {
+    enabled: bool,
+    start: Rc<Uniform<f32>>,
+    step: Rc<Uniform<f32>>,
+    num_indices: Rc<Cell<u32>>,
+}
+
+// A crosshair drawn at the center of the canvas, which is used as a reference
+// for alignment. It does not depend on the view.
+struct Reticle {
+    // Half length of the arms in clip-space units, horizontally and vertically
+    half_size: Rc<Uniform<(f32, f32)>>,
+    // Color of the crosshair, using premultiplied alpha
+    color: Rc<Uniform<(f32, f32, f32, f32)>>,
+    num_indices: Rc<Cell<u32>>,
 }
 
 #[derive(Default)]
@@ -76,6 +334,11 @@ impl Waterfall {
     const TEXTURE_WIDTH: usize = 4096;
     const TEXTURE_HEIGHT: usize = 512;
 
+    /// Minimum zoom level, at which the whole band is displayed.
+    pub const MIN_ZOOM: f32 = 1.0;
+    /// Maximum zoom level.
+    pub const MAX_ZOOM: f32 = 128.0;
+
     /// Creates a new waterfall, adding it to the [`RenderEngine`].
     ///
     /// The `performance` parameter should contain a performance object obtained
@@ -84,6 +347,14 @@ impl Waterfall {
         let programs = Programs {
             frequency_labels: Self::frequency_labels_program(engine)?,
             frequency_ticks: Self::frequency_ticks_program(engine)?,
+            marker: Self::marker_program(engine)?,
+            ruler: Self::ruler_program(engine)?,
+            bin_highlight: Self::bin_highlight_program(engine)?,
+            retune_progress: Self::retune_progress_program(engine)?,
+            edge_mask: Self::edge_mask_program(engine)?,
+            comb: Self::comb_program(engine)?,
+            bin_grid: Self::bin_grid_program(engine)?,
+            reticle: Self::reticle_program(engine)?,
         };
         // These default values will be overwritten by the UI
         let samp_rate = 30.72e6;
@@ -99,6 +370,7 @@ impl Waterfall {
             last_draw_line: 0,
             waterfall_wraps: 0,
             last_spectrum_timestamp: None,
+            num_lines: 0,
             waterfall_rate: None,
             center_freq,
             samp_rate,
@@ -109,6 +381,46 @@ impl Waterfall {
             freq_num_idx_ticks: Rc::new(Cell::new(0)),
             waterfall_min: -100.0,
             waterfall_max: 0.0,
+            persistence: 0.0,
+            peak_capture: None,
+            rx_lo_power: None,
+            power_history: None,
+            rx_lo_flash: None,
+            markers: Markers::new(),
+            ruler: Ruler::new(),
+            bin_highlight: BinHighlight::new(BinHighlight::COLOR),
+            passband_highlight: BinHighlight::new(BinHighlight::PASSBAND_COLOR),
+            retune_progress: RetuneProgress::new(),
+            edge_mask: EdgeMask::new(),
+            comb: Comb::new(),
+            bin_grid: BinGrid::new(),
+            reticle: Reticle::new(),
+            line_height: 0,
+            view_tween: None,
+            zoom_momentum: None,
+            animation_easing: Easing::EaseInOut,
+            snap_zoom: false,
+            overscan: 0.0,
+            rx_lo_keep_visible: None,
+            rx_lo_keep_visible_margin: 0.1,
+            dc_exclusion_bins: 1,
+            noise_floor: NoiseFloorEstimator::new(),
+            uncorrected_noise_floor: NoiseFloorEstimator::new(),
+            line_width_check: LineWidthCheck::default(),
+            peak_tracking: false,
+            peak_tracker: PeakTracker::new(),
+            cursors: [None; 2],
+            passband: None,
+            frozen: false,
+            paused: false,
+            freeze_on_measurement: false,
+            perf_stats: None,
+            smooth_scroll: false,
+            line_timing: LineTiming::default(),
+            power_correction: None,
+            bin_correction: Vec::new(),
+            labels_decimal_separator: '.',
+            dirty: Cell::new(true),
         };
 
         w.update_waterfall_scale();
@@ -120,6 +432,68 @@ impl Waterfall {
             w.frequency_labels_object(engine)?;
         engine.add_object(frequency_labels_object);
         engine.add_object(frequency_ticks_object);
+        let marker_vao = Self::marker_vao(engine, &w.programs.marker)?;
+        w.update_rx_lo_marker();
+        engine.add_object(w.markers.rx_lo.render_object(
+            &w.programs.marker,
+            &marker_vao,
+            &w.uniforms,
+        ));
+        engine.add_object(w.markers.peak.render_object(
+            &w.programs.marker,
+            &marker_vao,
+            &w.uniforms,
+        ));
+        for cursor in &w.markers.cursors {
+            engine.add_object(cursor.render_object(&w.programs.marker, &marker_vao, &w.uniforms));
+        }
+        // The ruler is drawn with the marker VAO, since it also consists of a
+        // single line whose endpoints are given by uniforms.
+        engine.add_object(
+            w.ruler
+                .render_object(&w.programs.ruler, &marker_vao, &w.uniforms),
+        );
+        // The passband, the bin highlight and the retune progress bar are
+        // drawn with the same quad VAO, which their vertex shaders transform
+        // as needed.
+        let quad_vao = Self::quad_vao(engine, &w.programs.bin_highlight)?;
+        w.update_bin_highlight_min_width(engine);
+        engine.add_object(w.passband_highlight.render_object(
+            &w.programs.bin_highlight,
+            &quad_vao,
+            &w.uniforms,
+        ));
+        engine.add_object(w.bin_highlight.render_object(
+            &w.programs.bin_highlight,
+            &quad_vao,
+            &w.uniforms,
+        ));
+        engine.add_object(
+            w.retune_progress
+                .render_object(&w.programs.retune_progress, &quad_vao),
+        );
+        for object in w
+            .edge_mask
+            .render_objects(&w.programs.edge_mask, &quad_vao, &w.uniforms)
+        {
+            engine.add_object(object);
+        }
+        let comb_vao = Self::comb_vao(engine, &w.programs.comb)?;
+        engine.add_object(
+            w.comb
+                .render_object(&w.programs.comb, &comb_vao, &w.uniforms),
+        );
+        // The bin grid is also a set of equally spaced lines, so it uses the
+        // same kind of VAO as the comb.
+        let bin_grid_vao = Self::comb_vao(engine, &w.programs.bin_grid)?;
+        engine.add_object(w.bin_grid.render_object(
+            &w.programs.bin_grid,
+            &bin_grid_vao,
+            &w.uniforms,
+        ));
+        let reticle_vao = Self::reticle_vao(engine, &w.programs.reticle)?;
+        w.update_reticle_size(engine);
+        engine.add_object(w.reticle.render_object(&w.programs.reticle, &reticle_vao));
         Ok(w)
     }
 
@@ -127,13 +501,88 @@ impl Waterfall {
     ///
     /// This function updates the waterfall by adding a new spectrum line to
     /// it. The spectrum is given in linear power units.
+    ///
+    /// Lines that do not have the number of FFT bins expected by the waterfall
+    /// are dropped. This can happen briefly while the FFT size is being
+    /// changed.
+    ///
+    /// While the waterfall is frozen (see
+    /// [`is_frozen`](Waterfall::is_frozen)), the lines are dropped.
     pub fn put_waterfall_spectrum(&mut self, spectrum_linear: &js_sys::Float32Array) {
-        self.last_spectrum_timestamp = Some(self.performance.now() as f32);
+        if self.is_frozen() {
+            // The interval to the next line added is not measured, since it
+            // includes the time that the waterfall was frozen.
+            self.last_spectrum_timestamp = None;
+            return;
+        }
+        let width = spectrum_linear.length() as usize;
+        match self.line_width_check.check(width, Self::TEXTURE_WIDTH) {
+            LineWidth::Valid => (),
+            LineWidth::Mismatch { first } => {
+                if let Some(stats) = &mut self.perf_stats {
+                    stats.dropped_line();
+                }
+                if first {
+                    web_sys::console::warn_1(
+                        &format!(
+                            "dropping spectrum lines with {width} bins (expected {})",
+                            Self::TEXTURE_WIDTH
+                        )
+                        .into(),
+                    );
+                }
+                return;
+            }
+            LineWidth::Recovered { dropped } => web_sys::console::log_1(
+                &format!("spectrum lines resumed after dropping {dropped} lines").into(),
+            ),
+        }
+        if let Some(stats) = &mut self.perf_stats {
+            stats.line();
+        }
+        let timestamp = self.performance.now() as f32;
+        let last_timestamp = self.last_spectrum_timestamp.replace(timestamp);
+        let previous_line = self.current_draw_line;
         self.current_draw_line = (self.current_draw_line + 1) % Self::TEXTURE_HEIGHT;
+        self.num_lines = (self.num_lines + 1).min(Self::TEXTURE_HEIGHT);
         let line = self.current_draw_line;
         let spectrum_texture =
             &mut self.texture_map[line * Self::TEXTURE_WIDTH..(line + 1) * Self::TEXTURE_WIDTH];
         spectrum_linear.copy_to(spectrum_texture);
+        // The noise floor is estimated before applying persistence, since
+        // persistence raises the power of the bins after strong signals.
+        let excluded = Self::dc_exclusion_range(Self::TEXTURE_WIDTH, self.dc_exclusion_bins);
+        if !self.bin_correction.is_empty() {
+            self.uncorrected_noise_floor
+                .update(spectrum_texture, excluded.clone(), timestamp);
+        }
+        for (x, &offset) in spectrum_texture.iter_mut().zip(&self.bin_correction) {
+            *x += offset;
+        }
+        self.noise_floor
+            .update(spectrum_texture, excluded, timestamp);
+        // The RX LO frequency is at the DC FFT bin.
+        self.rx_lo_power = Some(spectrum_texture[Self::TEXTURE_WIDTH / 2]);
+        if let Some(peaks) = &mut self.peak_capture {
+            if peaks.is_empty() {
+                peaks.extend_from_slice(spectrum_texture);
+            } else {
+                for (peak, &x) in peaks.iter_mut().zip(spectrum_texture.iter()) {
+                    *peak = peak.max(x);
+                }
+            }
+        }
+        // The peak is tracked before applying persistence, so that the
+        // tracking reacts to the latest line only.
+        self.update_peak_tracker(line);
+        // The power history is also recorded before applying persistence,
+        // since it is used to observe fading.
+        self.update_power_history(line);
+        if let Some(last_timestamp) = last_timestamp {
+            self.line_timing
+                .update(timestamp - last_timestamp, self.nominal_line_interval());
+            self.apply_persistence(previous_line, line, (timestamp - last_timestamp) * 1e-3);
+        }
         // Convert to "dB". We don't include the 10.0 factor to save us a multiplication.
         // This will later be taken into account in the shader.
         // for x in spectrum_texture.iter_mut() {
@@ -141,30 +590,516 @@ impl Waterfall {
         // }
     }
 
+    /// Starts recording the power history of a frequency.
+    ///
+    /// The frequency is given in the same units as the waterfall center
+    /// frequency (see [`set_center_frequency`](Waterfall::set_center_frequency)),
+    /// but it is recorded as an absolute frequency, so the history keeps
+    /// following the same frequency if the RX frequency changes. The power of
+    /// the FFT bin that contains the frequency is recorded in each spectrum
+    /// line, and only the samples in the last `window_ms` milliseconds are
+    /// kept. Any previous history is replaced.
+    pub fn start_power_history(&mut self, frequency: f32, window_ms: f64) {
+        if !frequency.is_finite() {
+            return;
+        }
+        let frequency = Self::units_to_hz(frequency, self.center_freq, self.samp_rate);
+        self.power_history = Some(PowerHistory::new(frequency, window_ms));
+    }
+
+    /// Stops recording the power history and discards it.
+    pub fn stop_power_history(&mut self) {
+        self.power_history = None;
+    }
+
+    /// Removes the samples of the power history.
+    ///
+    /// The recording continues with the same frequency.
+    pub fn clear_power_history(&mut self) {
+        if let Some(history) = &mut self.power_history {
+            history.clear();
+        }
+    }
+
+    /// Sets the time window of the power history, in milliseconds.
+    pub fn set_power_history_window(&mut self, window_ms: f64) {
+        if let Some(history) = &mut self.power_history {
+            history.set_window(window_ms);
+        }
+    }
+
+    /// Returns the power history, if it is being recorded.
+    ///
+    /// The power is given in dB units, using the same scale as the waterfall
+    /// minimum and maximum power values.
+    pub fn get_power_history(&self) -> Option<&PowerHistory> {
+        self.power_history.as_ref()
+    }
+
+    fn update_power_history(&mut self, line: usize) {
+        let Some(history) = &mut self.power_history else {
+            return;
+        };
+        let position = Self::hz_to_units(history.frequency(), self.center_freq, self.samp_rate);
+        let power = Self::units_to_bin(position).map_or(f32::NAN, |bin| {
+            Self::texture_value_to_db(self.texture_map[line * Self::TEXTURE_WIDTH + bin])
+        });
+        history.push(self.performance.now(), power);
+    }
+
+    fn update_peak_tracker(&mut self, line: usize) {
+        if !self.peak_tracking {
+            return;
+        }
+        let visible = self.visible_bins();
+        let noise_floor = self
+            .noise_floor
+            .estimate()
+            .map_or(f32::NEG_INFINITY, Self::texture_value_to_db);
+        let line = &self.texture_map[line * Self::TEXTURE_WIDTH..(line + 1) * Self::TEXTURE_WIDTH];
+        let excluded = Self::dc_exclusion_range(Self::TEXTURE_WIDTH, self.dc_exclusion_bins);
+        // The tracker ignores the bins with a NaN power.
+        let bin = self.peak_tracker.update(
+            |bin| {
+                if excluded.contains(&bin) {
+                    f32::NAN
+                } else {
+                    Self::texture_value_to_db(line[bin])
+                }
+            },
+            visible,
+            noise_floor,
+        );
+        let marker = &self.markers.peak;
+        marker.set_visible(bin.is_some());
+        if let Some(bin) = bin {
+            marker.frequency.set_data(Self::bin_to_units(bin));
+        }
+    }
+
+    // Returns the frequency of the center of an FFT bin, in the same units as
+    // the waterfall center frequency.
+    fn bin_to_units(bin: usize) -> f32 {
+        let bin_width = 2.0 / Self::TEXTURE_WIDTH as f32;
+        -1.0 + (bin as f32 + 0.5) * bin_width
+    }
+
+    // Blends a newly added spectrum line with the previous line, so that
+    // strong signals fade out over several lines instead of disappearing in
+    // the next line. Each bin takes the maximum of its power and the decayed
+    // power of the previous line. The decay is computed from the time elapsed
+    // between both lines, so the fade duration does not depend on the
+    // waterfall update rate, which changes with the spectrometer averaging.
+    fn apply_persistence(&mut self, previous_line: usize, line: usize, elapsed_secs: f32) {
+        if self.persistence <= 0.0 || elapsed_secs <= 0.0 {
+            return;
+        }
+        // The texture stores log10(power), so the decay is an offset.
+        let decay = elapsed_secs * self.persistence.log10();
+        let (previous, current) = if previous_line < line {
+            let (a, b) = self.texture_map.split_at_mut(line * Self::TEXTURE_WIDTH);
+            (
+                &a[previous_line * Self::TEXTURE_WIDTH..(previous_line + 1) * Self::TEXTURE_WIDTH],
+                &mut b[..Self::TEXTURE_WIDTH],
+            )
+        } else {
+            let (a, b) = self
+                .texture_map
+                .split_at_mut(previous_line * Self::TEXTURE_WIDTH);
+            (
+                &b[..Self::TEXTURE_WIDTH],
+                &mut a[line * Self::TEXTURE_WIDTH..(line + 1) * Self::TEXTURE_WIDTH],
+            )
+        };
+        for (x, &p) in current.iter_mut().zip(previous.iter()) {
+            let decayed = p + decay;
+            // NaNs in either line are replaced by the other value.
+            *x = x.max(decayed);
+        }
+    }
+
+    /// Starts capturing the peaks of the spectrum lines.
+    ///
+    /// After calling this function, the maximum of each FFT bin over the
+    /// spectrum lines added to the waterfall is recorded, until the capture is
+    /// finished with [`take_peak_capture`](Waterfall::take_peak_capture). Any
+    /// capture in progress is discarded.
+    pub fn start_peak_capture(&mut self) {
+        self.peak_capture = Some(Vec::new());
+    }
+
+    /// Finishes capturing the peaks of the spectrum lines.
+    ///
+    /// Returns the maximum of each FFT bin over the spectrum lines added since
+    /// [`start_peak_capture`](Waterfall::start_peak_capture) was called. The
+    /// values are given in log10 power units, so they should be multiplied by
+    /// 10 to obtain dB. If no capture was in progress or no spectrum lines
+    /// were added, `None` is returned.
+    pub fn take_peak_capture(&mut self) -> Option<Vec<f32>> {
+        self.peak_capture.take().filter(|peaks| !peaks.is_empty())
+    }
+
+    /// Enables or disables tracking of the strongest signal.
+    ///
+    /// While tracking is enabled, the strongest peak in the visible part of
+    /// each new spectrum line is found and a marker is drawn at its
+    /// frequency. The tracked peak is kept until another peak is stronger by
+    /// more than the hysteresis (see
+    /// [`set_peak_tracking_hysteresis`](Waterfall::set_peak_tracking_hysteresis)),
+    /// and the marker is hidden when no peak is above the noise floor by the
+    /// minimum SNR (see
+    /// [`set_peak_tracking_min_snr`](Waterfall::set_peak_tracking_min_snr)).
+    pub fn set_peak_tracking(&mut self, enable: bool) {
+        self.peak_tracking = enable;
+        self.peak_tracker.reset();
+        self.markers.peak.set_visible(false);
+        self.mark_dirty();
+    }
+
+    /// Sets the hysteresis of the peak tracking.
+    ///
+    /// The hysteresis is the power in dB by which another peak must be
+    /// stronger than the tracked peak for the tracking to move to it.
+    pub fn set_peak_tracking_hysteresis(&mut self, hysteresis_db: f32) {
+        self.peak_tracker.set_hysteresis(hysteresis_db);
+    }
+
+    /// Sets the minimum SNR of the peak tracking.
+    ///
+    /// A peak is only tracked while its power is above the noise floor
+    /// estimate by at least this value, given in dB.
+    pub fn set_peak_tracking_min_snr(&mut self, min_snr_db: f32) {
+        self.peak_tracker.set_min_snr(min_snr_db);
+    }
+
+    /// Returns the frequency of the tracked peak.
+    ///
+    /// The frequency is given in Hz. If peak tracking is disabled or there is
+    /// no peak above the minimum SNR, `None` is returned.
+    pub fn get_tracked_peak(&self) -> Option<f64> {
+        if !self.peak_tracking {
+            return None;
+        }
+        let bin = self.peak_tracker.bin()?;
+        Some(Self::units_to_hz(
+            Self::bin_to_units(bin),
+            self.center_freq,
+            self.samp_rate,
+        ))
+    }
+
+    /// Places a measurement cursor.
+    ///
+    /// The position is given in the same units as the waterfall center
+    /// frequency (see [`set_center_frequency`](Waterfall::set_center_frequency)),
+    /// and it is converted to an absolute frequency, so that the cursor stays
+    /// at the same frequency when the RX frequency changes. Passing `None`
+    /// removes the cursor.
+    pub fn set_cursor_position(&mut self, cursor: Cursor, position: Option<f32>) {
+        self.cursors[cursor as usize] = position
+            .filter(|x| x.is_finite())
+            .map(|x| Self::units_to_hz(x, self.center_freq, self.samp_rate));
+        self.update_cursor_markers();
+    }
+
+    /// Returns the position of a measurement cursor.
+    ///
+    /// The position is given in the same units as the waterfall center
+    /// frequency. If the cursor is not placed, `None` is returned.
+    pub fn get_cursor_position(&self, cursor: Cursor) -> Option<f32> {
+        self.cursors[cursor as usize]
+            .map(|freq| Self::hz_to_units(freq, self.center_freq, self.samp_rate))
+    }
+
+    /// Returns the frequency of a measurement cursor.
+    ///
+    /// The frequency is given in Hz. If the cursor is not placed, `None` is
+    /// returned.
+    pub fn get_cursor_frequency(&self, cursor: Cursor) -> Option<f64> {
+        self.cursors[cursor as usize]
+    }
+
+    /// Returns the power at a measurement cursor.
+    ///
+    /// The power is taken from the FFT bin that contains the cursor in the
+    /// last spectrum line added to the waterfall, and it is given in dB
+    /// units, using the same scale as the waterfall minimum and maximum power
+    /// values. If the cursor is not placed, is outside of the band, or no
+    /// spectrum lines have been added yet, `None` is returned.
+    pub fn get_cursor_power(&self, cursor: Cursor) -> Option<f32> {
+        self.power_at(self.get_cursor_position(cursor)?)
+    }
+
+    // Returns the power in dB of the FFT bin that contains a frequency, given
+    // in the same units as the waterfall center frequency, in the last
+    // spectrum line added to the waterfall.
+    fn power_at(&self, frequency: f32) -> Option<f32> {
+        if self.num_lines == 0 {
+            return None;
+        }
+        let bin = Self::units_to_bin(frequency)?;
+        let line = self.current_draw_line;
+        Some(Self::texture_value_to_db(
+            self.texture_map[line * Self::TEXTURE_WIDTH + bin],
+        ))
+    }
+
+    /// Enables or disables freezing the waterfall during measurements.
+    ///
+    /// When enabled, the waterfall stops adding spectrum lines while the
+    /// ruler, a measurement cursor or the passband is shown, and it resumes when they are
+    /// cleared. This keeps the spectrum stable during the measurement, and
+    /// the power read at the cursors comes from the last line added before
+    /// freezing. The lines received while frozen are lost.
+    pub fn set_freeze_on_measurement(&mut self, enable: bool) {
+        self.freeze_on_measurement = enable;
+    }
+
+    /// Freezes or unfreezes the waterfall.
+    ///
+    /// While frozen, the waterfall stops adding spectrum lines, regardless of
+    /// the measurements shown. This is used to annotate the waterfall. The
+    /// lines received while frozen are lost.
+    pub fn set_frozen(&mut self, frozen: bool) {
+        self.frozen = frozen;
+    }
+
+    /// Pauses or resumes the waterfall.
+    ///
+    /// This works as [`set_frozen`](Waterfall::set_frozen), but it is kept
+    /// separately so that the user can pause the waterfall independently of
+    /// the freezing used for annotations.
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    /// Returns `true` if the waterfall has been paused with
+    /// [`set_paused`](Waterfall::set_paused).
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Returns `true` if the waterfall is frozen.
+    ///
+    /// The waterfall is frozen if this has been requested with
+    /// [`set_frozen`](Waterfall::set_frozen), if it is paused (see
+    /// [`set_paused`](Waterfall::set_paused)) or because of a measurement (see
+    /// [`set_freeze_on_measurement`](Waterfall::set_freeze_on_measurement)).
+    pub fn is_frozen(&self) -> bool {
+        let measuring = self.ruler.num_indices.get() != 0
+            || self.cursors.iter().any(Option::is_some)
+            || self.passband.is_some();
+        self.frozen || self.paused || (self.freeze_on_measurement && measuring)
+    }
+
+    fn update_cursor_markers(&self) {
+        for (cursor, marker) in [Cursor::A, Cursor::B]
+            .into_iter()
+            .zip(&self.markers.cursors)
+        {
+            let position = self.get_cursor_position(cursor);
+            marker.set_visible(position.is_some());
+            if let Some(position) = position {
+                marker.frequency.set_data(position);
+            }
+        }
+        self.mark_dirty();
+    }
+
+    /// Places the passband.
+    ///
+    /// The passband is a region of the spectrum, drawn as a faint band, which
+    /// can be used to measure the power of a signal that occupies several FFT
+    /// bins. Its `center` is given in the same units as the waterfall center
+    /// frequency, and its `bandwidth` is given in Hz. The center is converted
+    /// to an absolute frequency, so that the passband stays at the same
+    /// frequency when the RX frequency changes. Passing a non-finite center or
+    /// a bandwidth that is not a positive finite number removes the passband.
+    pub fn set_passband(&mut self, center: f32, bandwidth: f64) {
+        self.passband =
+            (center.is_finite() && bandwidth.is_finite() && bandwidth > 0.0).then(|| {
+                (
+                    Self::units_to_hz(center, self.center_freq, self.samp_rate),
+                    bandwidth,
+                )
+            });
+        self.update_passband_highlight();
+    }
+
+    /// Changes the bandwidth of the passband.
+    ///
+    /// The bandwidth is given in Hz, and the center frequency of the passband
+    /// is kept. The call is ignored if the passband is not placed or if the
+    /// bandwidth is not a positive finite number.
+    pub fn set_passband_bandwidth(&mut self, bandwidth: f64) {
+        if let Some((_, current)) = self.passband.as_mut() {
+            if bandwidth.is_finite() && bandwidth > 0.0 {
+                *current = bandwidth;
+                self.update_passband_highlight();
+            }
+        }
+    }
+
+    /// Removes the passband.
+    pub fn clear_passband(&mut self) {
+        self.passband = None;
+        self.update_passband_highlight();
+    }
+
+    /// Returns the center frequency and bandwidth of the passband.
+    ///
+    /// Both are given in Hz. If the passband is not placed, `None` is
+    /// returned.
+    pub fn get_passband(&self) -> Option<(f64, f64)> {
+        self.passband
+    }
+
+    /// Returns the edges of the passband.
+    ///
+    /// The edges are given in the same units as the waterfall center
+    /// frequency, ordered from lower to upper frequency. If the passband is
+    /// not placed, `None` is returned.
+    pub fn get_passband_edges(&self) -> Option<(f32, f32)> {
+        let (center, bandwidth) = self.passband?;
+        Some((
+            Self::hz_to_units(center - 0.5 * bandwidth, self.center_freq, self.samp_rate),
+            Self::hz_to_units(center + 0.5 * bandwidth, self.center_freq, self.samp_rate),
+        ))
+    }
+
+    /// Returns the power in the passband.
+    ///
+    /// The power is the sum of the power of the FFT bins whose center lies in
+    /// the passband in the last spectrum line added to the waterfall, and it
+    /// is given in dB units, using the same scale as the waterfall minimum and
+    /// maximum power values. The bins around DC that are excluded from the
+    /// measurements (see [`set_dc_exclusion_bins`](Waterfall::set_dc_exclusion_bins))
+    /// are not added. If the passband is not placed, does not contain any
+    /// bins, or no spectrum lines have been added yet, `None` is returned.
+    pub fn get_passband_power(&self) -> Option<f32> {
+        if self.num_lines == 0 {
+            return None;
+        }
+        let bins = Self::passband_bins(self.get_passband_edges()?);
+        let excluded = Self::dc_exclusion_range(Self::TEXTURE_WIDTH, self.dc_exclusion_bins);
+        let line = self.current_draw_line * Self::TEXTURE_WIDTH;
+        let power = bins
+            .filter(|bin| !excluded.contains(bin))
+            .map(|bin| 10.0_f32.powf(self.texture_map[line + bin]))
+            .sum::<f32>();
+        (power > 0.0).then(|| Self::texture_value_to_db(power.log10()))
+    }
+
+    // Returns the FFT bins whose center lies between the passband edges,
+    // given in the same units as the waterfall center frequency.
+    fn passband_bins((lower, upper): (f32, f32)) -> std::ops::Range<usize> {
+        let bin_width = 2.0 / Self::TEXTURE_WIDTH as f32;
+        // Position of an edge in bins, measured from the center of the first
+        // bin
+        let to_bins = |edge: f32| (edge + 1.0) / bin_width - 0.5;
+        let clamp = |bin: f32| bin.clamp(0.0, Self::TEXTURE_WIDTH as f32) as usize;
+        let start = clamp(to_bins(lower).ceil());
+        let end = clamp(to_bins(upper).floor() + 1.0);
+        start..end.max(start)
+    }
+
+    fn update_passband_highlight(&self) {
+        match self.get_passband_edges() {
+            Some(edges) => {
+                self.passband_highlight.edges.set_data(edges);
+                self.passband_highlight
+                    .num_indices
+                    .set(BinHighlight::NUM_INDICES);
+            }
+            None => self.passband_highlight.num_indices.set(0),
+        }
+        self.mark_dirty();
+    }
+
+    // Returns the FFT bin that contains a frequency given in the same units as
+    // the waterfall center frequency, or None if the frequency is outside the
+    // band.
+    fn units_to_bin(frequency: f32) -> Option<usize> {
+        let bin_width = 2.0 / Self::TEXTURE_WIDTH as f32;
+        let bin = ((frequency + 1.0) / bin_width).floor();
+        if (0.0..Self::TEXTURE_WIDTH as f32).contains(&bin) {
+            Some(bin as usize)
+        } else {
+            None
+        }
+    }
+
+    /// Enables or disables the collection of performance statistics.
+    ///
+    /// While enabled, the waterfall records the rendered frames and the
+    /// received spectrum lines, and the statistics can be obtained with
+    /// [`take_perf_report`](Waterfall::take_perf_report). While disabled,
+    /// nothing is recorded.
+    pub fn set_perf_stats_enabled(&mut self, enabled: bool) {
+        if enabled != self.perf_stats.is_some() {
+            self.perf_stats = Some(PerfStats::new(self.performance.now())).filter(|_| enabled);
+        }
+    }
+
+    /// Returns the performance statistics since the previous report.
+    ///
+    /// The statistics cover the time since the previous call to this function,
+    /// or since they were enabled with
+    /// [`set_perf_stats_enabled`](Waterfall::set_perf_stats_enabled). If the
+    /// statistics are not enabled, `None` is returned.
+    pub fn take_perf_report(&mut self) -> Option<PerfReport> {
+        let now = self.performance.now();
+        self.perf_stats.as_mut().map(|stats| stats.report(now))
+    }
+
+    /// Returns `true` if the waterfall should be rendered again.
+    ///
+    /// This is the case if spectrum lines have been added since the last call
+    /// to [`prepare_render`](Waterfall::prepare_render), if the view, the
+    /// colormap, its scaling or any of the objects drawn over the waterfall
+    /// (markers, cursors, ruler, highlights, etc.) have been changed since
+    /// then, or if an animation of the view, a flash of the RX LO marker or
+    /// smooth scrolling are in progress.
+    pub fn needs_redraw(&self) -> bool {
+        self.dirty.get()
+            || self.current_draw_line != self.last_draw_line
+            || self.view_tween.is_some()
+            || self.zoom_momentum.is_some()
+            || self.rx_lo_flash.is_some()
+            || (self.smooth_scroll && self.last_spectrum_timestamp.is_some())
+    }
+
     /// Updates the waterfall for rendering.
     ///
     /// This function must be called before each call to
     /// [`RenderEngine::render`]. It updates and prepares the waterfall render
     /// objects for rendering. The vale of `dt` should be the timestamp given
     /// to the `request_animation_frame` callback in which this function is
-    /// called. It is currently unused, since the waterfall scroll rate is
-    /// determined by how often
+    /// called, or to [`FrameDriver::render_frame`](crate::frame_driver::FrameDriver::render_frame).
+    /// It is used for the view animations and smooth scrolling. The waterfall
+    /// scroll rate is determined by how often
     /// [`put_waterfall_spectrum`](Waterfall::put_waterfall_spectrum) is called.
     pub fn prepare_render(&mut self, engine: &mut RenderEngine, dt: f32) -> Result<(), JsValue> {
+        self.update_view_animation(f64::from(dt));
+        self.update_zoom_momentum(f64::from(dt));
+        self.keep_rx_lo_visible();
+        self.update_rx_lo_flash(f64::from(dt));
+        self.update_bin_grid(engine);
+        self.update_line_height_scale(engine);
         let draw_lines_coarse = self.current_draw_line as f32;
-        // Fine correction to draw_t_coarse for smooth animation interpolation
-        // between waterfall lines. Only applied when we have the necessary data.
-        let draw_lines_fine = match (self.last_spectrum_timestamp, self.waterfall_rate) {
-            (Some(t0), Some(rate)) => {
-                let elapsed_secs = (dt - t0) * 1e-3;
-                let elapsed_lines = elapsed_secs * rate;
+        // Fine correction to draw_t_coarse for smooth scrolling between
+        // waterfall lines. Only applied when smooth scrolling is enabled and
+        // we have the necessary data. Since the correction is given in lines,
+        // it is also scaled by the line height.
+        let draw_lines_fine = match self.last_spectrum_timestamp {
+            Some(t0) if self.smooth_scroll => self
+                .line_timing
+                .elapsed_lines(dt - t0, self.nominal_line_interval())
                 // Gives a correction between -0.5 and +0.5 lines
-                elapsed_lines.clamp(0.0, 1.0) - 0.5
-            }
+                .map_or(0.0, |elapsed_lines| elapsed_lines - 0.5),
             _ => 0.0,
         };
         let draw_t = (draw_lines_coarse + draw_lines_fine) / Self::TEXTURE_HEIGHT as f32;
-        // TODO use elapsed_ms to effect draw_t. This needs us to know the spectrometer rate.
         self.uniforms.time_translation.set_data(4.0 * draw_t);
 
         let end_draw = self.current_draw_line;
@@ -201,11 +1136,27 @@ impl Waterfall {
             )?;
         }
 
+        if let Some(stats) = &mut self.perf_stats {
+            let now = self.performance.now();
+            let latency = self
+                .last_spectrum_timestamp
+                .filter(|_| end_draw != self.last_draw_line)
+                .map(|t| now - f64::from(t));
+            stats.frame(now, latency);
+        }
+
         self.last_draw_line = end_draw;
+        self.dirty.set(false);
 
         Ok(())
     }
 
+    // Records that the waterfall needs to be rendered again because of a
+    // change that is not tracked otherwise by needs_redraw.
+    fn mark_dirty(&self) {
+        self.dirty.set(true);
+    }
+
     /// Updates the waterfall according to the new dimensions of the canvas.
     ///
     /// This function should be called each time that the canvas size or the
@@ -213,6 +1164,35 @@ impl Waterfall {
     pub fn resize_canvas(&mut self, engine: &mut RenderEngine) -> Result<(), JsValue> {
         // update frequency labels VAOs and texts texture
         self.frequency_labels_vao(engine)?;
+        self.update_bin_highlight_min_width(engine);
+        self.update_reticle_size(engine);
+        self.mark_dirty();
+        Ok(())
+    }
+
+    fn update_bin_highlight_min_width(&self, engine: &RenderEngine) {
+        let (width, _) = engine.canvas_dims().device_pixels();
+        if width != 0 {
+            for highlight in [&self.bin_highlight, &self.passband_highlight] {
+                highlight.min_half_width.set_data(1.0 / width as f32);
+            }
+        }
+    }
+
+    /// Sets the decimal separator of the frequency labels.
+    ///
+    /// The labels use a point by default.
+    pub fn set_decimal_separator(
+        &mut self,
+        separator: char,
+        engine: &mut RenderEngine,
+    ) -> Result<(), JsValue> {
+        if separator != self.labels_decimal_separator {
+            self.labels_decimal_separator = separator;
+            // update frequency labels VAOs and texts texture
+            self.frequency_labels_vao(engine)?;
+            self.mark_dirty();
+        }
         Ok(())
     }
 
@@ -220,22 +1200,178 @@ impl Waterfall {
     ///
     /// The center frequency and sample rate should be given in units of Hz and
     /// samples per second.
+    ///
+    /// If the sample rate is not a positive finite number or the center
+    /// frequency is not finite, the call is ignored and the previous values
+    /// are kept. This can happen before the device has reported its
+    /// configuration, and it prevents the conversions between Hz and waterfall
+    /// units from producing non-finite values.
     pub fn set_freq_samprate(
         &mut self,
         center_freq: f64,
         samp_rate: f64,
         engine: &mut RenderEngine,
     ) -> Result<(), JsValue> {
+        if !center_freq.is_finite() || !samp_rate.is_finite() || samp_rate <= 0.0 {
+            return Ok(());
+        }
         let center_freq = Self::actual_center_freq(center_freq, samp_rate);
         if center_freq != self.center_freq || samp_rate != self.samp_rate {
             self.center_freq = center_freq;
             self.samp_rate = samp_rate;
             // update frequency labels VAOs and texts texture
             self.frequency_labels_vao(engine)?;
+            self.update_rx_lo_marker();
+            self.update_cursor_markers();
+            self.update_passband_highlight();
+            self.update_comb();
+            self.update_bin_correction();
+            self.mark_dirty();
         }
         Ok(())
     }
 
+    /// Sets a frequency dependent power correction.
+    ///
+    /// The correction is a function that gives the offset in dB that is added
+    /// to the power at a frequency in Hz. It is applied to each spectrum line
+    /// when it is added to the waterfall, so it affects the display as well
+    /// as the noise floor, the RX LO power and the peak capture. Lines that
+    /// are already in the waterfall are not modified. Passing `None` removes
+    /// the correction.
+    pub fn set_power_correction(&mut self, correction: Option<Box<dyn Fn(f64) -> f32>>) {
+        self.power_correction = correction;
+        self.uncorrected_noise_floor = NoiseFloorEstimator::new();
+        self.update_bin_correction();
+    }
+
+    fn update_bin_correction(&mut self) {
+        self.bin_correction.clear();
+        let Some(correction) = &self.power_correction else {
+            return;
+        };
+        let bin_width = 2.0 / Self::TEXTURE_WIDTH as f32;
+        self.bin_correction
+            .extend((0..Self::TEXTURE_WIDTH).map(|bin| {
+                let units = -1.0 + (bin as f32 + 0.5) * bin_width;
+                let frequency = Self::units_to_hz(units, self.center_freq, self.samp_rate);
+                // The texture stores log10(power)
+                0.1 * correction(frequency)
+            }));
+    }
+
+    /// Returns the position of the RX LO marker.
+    ///
+    /// The position is given in the same units as the waterfall center
+    /// frequency (see [`set_center_frequency`](Waterfall::set_center_frequency)).
+    pub fn get_rx_lo_marker_position(&self) -> f32 {
+        self.markers.rx_lo.frequency.get_data()
+    }
+
+    /// Flashes the RX LO marker to show the result of a retune.
+    ///
+    /// If `confirmed` is `true`, the marker pulses once in white to show that
+    /// the device has acknowledged the new frequency. Otherwise, it pulses
+    /// several times in orange to show that the retune failed. The flash is
+    /// animated in the calls to [`prepare_render`](Waterfall::prepare_render),
+    /// and a new flash replaces the one in progress.
+    pub fn flash_rx_lo_marker(&mut self, confirmed: bool) {
+        let (color, pulses) = if confirmed {
+            ((1.0, 1.0, 1.0, 1.0), 1)
+        } else {
+            ((1.0, 0.6, 0.0, 1.0), 3)
+        };
+        self.rx_lo_flash = Some(MarkerFlash {
+            color,
+            pulses,
+            start: self.performance.now(),
+        });
+    }
+
+    fn update_rx_lo_flash(&mut self, now: f64) {
+        let Some(flash) = self.rx_lo_flash else {
+            return;
+        };
+        let color = flash.color_at(Markers::RX_LO_COLOR, now);
+        if color.is_none() {
+            self.rx_lo_flash = None;
+        }
+        self.markers
+            .rx_lo
+            .color
+            .set_data(color.unwrap_or(Markers::RX_LO_COLOR));
+    }
+
+    /// Enables or disables keeping the RX LO marker visible.
+    ///
+    /// When enabled, the view is panned as little as needed so that the RX LO
+    /// marker stays inside the view, at a distance from its edges given by the
+    /// margin set with
+    /// [`set_rx_lo_keep_visible_margin`](Waterfall::set_rx_lo_keep_visible_margin).
+    /// The view is only panned when the marker would leave this area, which
+    /// is checked in each call to [`prepare_render`](Waterfall::prepare_render),
+    /// so it applies to panning, zooming, animations and retunes alike.
+    pub fn set_rx_lo_keep_visible(&mut self, enable: bool) {
+        self.rx_lo_keep_visible = Some(self.rx_lo_keep_visible_margin).filter(|_| enable);
+        self.mark_dirty();
+    }
+
+    /// Sets the margin used to keep the RX LO marker visible.
+    ///
+    /// The margin is given as a percentage of the width of the view, and it is
+    /// clamped between 0 and 45%.
+    pub fn set_rx_lo_keep_visible_margin(&mut self, percent: f32) {
+        let margin = if percent.is_nan() {
+            0.0
+        } else {
+            (1e-2 * percent).clamp(0.0, 0.45)
+        };
+        self.rx_lo_keep_visible_margin = margin;
+        if let Some(keep_visible) = &mut self.rx_lo_keep_visible {
+            *keep_visible = margin;
+            self.mark_dirty();
+        }
+    }
+
+    fn keep_rx_lo_visible(&mut self) {
+        let Some(margin) = self.rx_lo_keep_visible else {
+            return;
+        };
+        let zoom = self.get_zoom();
+        let center = self.get_center_frequency();
+        let new_center =
+            Self::keep_visible_center(center, zoom, self.get_rx_lo_marker_position(), margin);
+        if new_center != center {
+            self.uniforms.center_freq.set_data(new_center);
+        }
+    }
+
+    // Center frequency closest to `center` for which `position` is inside the
+    // view, at least `margin` view widths away from its edges. The result is
+    // clamped so that the view does not extend beyond the band edges.
+    fn keep_visible_center(center: f32, zoom: f32, position: f32, margin: f32) -> f32 {
+        if !position.is_finite() {
+            return center;
+        }
+        let half_width = 1.0 / zoom;
+        // Largest distance between the center and the position
+        let max_offset = half_width * (1.0 - 2.0 * margin);
+        let new_center = if position < center - max_offset {
+            position + max_offset
+        } else if position > center + max_offset {
+            position - max_offset
+        } else {
+            return center;
+        };
+        Self::clamp_center_frequency(new_center, zoom)
+    }
+
+    fn update_rx_lo_marker(&mut self) {
+        let (rx_lo, samp_rate) = self.get_freq_samprate();
+        let position = Self::hz_to_units(rx_lo, self.center_freq, samp_rate);
+        self.markers.rx_lo.frequency.set_data(position);
+    }
+
     fn actual_center_freq(center_freq: f64, samp_rate: f64) -> f64 {
         // Take note that the actual center_frequency in the waterfall is not
         // baseband DC, but rather the frequency between the DC FFT bin and one
@@ -309,12 +1445,17 @@ impl Waterfall {
         in vec2 aPosition;
         in vec2 aTextureCoordinates;
         uniform float uTimeTranslation;
+        uniform float uLineHeightScale;
         uniform float uCenterFreq;
         uniform float uZoom;
+        uniform float uAxisDirection;
         out vec2 vTextureCoordinates;
         void main() {
-            gl_Position = vec4(uZoom * (aPosition.x - uCenterFreq),
-                               aPosition.y + uTimeTranslation,
+            // The newest line is drawn at y = -1. The waterfall is stretched
+            // vertically around it to give each line the requested height.
+            float y = aPosition.y + uTimeTranslation;
+            gl_Position = vec4(uAxisDirection * uZoom * (aPosition.x - uCenterFreq),
+                               uLineHeightScale * (y + 1.0) - 1.0,
                                0.0, 1.0);
             vTextureCoordinates = aTextureCoordinates;
         }"#,
@@ -329,16 +1470,69 @@ impl Waterfall {
         uniform sampler2D uColormapSampler;
         uniform float uWaterfallScaleAdd;
         uniform float uWaterfallScaleMult;
+        uniform bool uSpectralInterpolation;
+        uniform bool uLinearLightColormap;
+        // A zero alpha disables the out of range colors
+        uniform vec4 uOverRangeColor;
+        uniform vec4 uUnderRangeColor;
+        uniform float uWaterfallGamma;
         out vec4 color;
+
+        // Conversions between sRGB and linear light, according to the sRGB
+        // transfer function.
+        vec3 srgbToLinear(vec3 c) {
+            return mix(c / 12.92, pow((c + 0.055) / 1.055, vec3(2.4)),
+                       step(0.04045, c));
+        }
+        vec3 linearToSrgb(vec3 c) {
+            return mix(12.92 * c, 1.055 * pow(c, vec3(1.0 / 2.4)) - 0.055,
+                       step(0.0031308, c));
+        }
+
+        // Looks up the colormap, interpolating between its two nearest
+        // entries in linear light. The colormap texture stores sRGB values,
+        // so the texture filtering would interpolate in sRGB space.
+        vec4 colormapLinearLight(float x) {
+            int size = textureSize(uColormapSampler, 0).x;
+            float position = clamp(x * float(size) - 0.5, 0.0, float(size - 1));
+            int index = int(floor(position));
+            int next = min(index + 1, size - 1);
+            vec3 c0 = srgbToLinear(texelFetch(uColormapSampler, ivec2(index, 0), 0).rgb);
+            vec3 c1 = srgbToLinear(texelFetch(uColormapSampler, ivec2(next, 0), 0).rgb);
+            return vec4(linearToSrgb(mix(c0, c1, position - float(index))), 1.0);
+        }
+
         void main() {
-            float power = texture(uSampler, vTextureCoordinates).x;
+            vec2 coordinates = vTextureCoordinates;
+            if (!uSpectralInterpolation) {
+                // Sample at the center of the FFT bin, so that each bin is
+                // displayed with a constant value when zoomed in.
+                float width = float(textureSize(uSampler, 0).x);
+                coordinates.x = (floor(coordinates.x * width) + 0.5) / width;
+            }
+            float power = texture(uSampler, coordinates).x;
 
             // Use polynomial approximation of Turbo colormap
             // color = vec4(TurboColormap(power), 1.0);
 
             // Use colormap texture
             float normalizedPower = uWaterfallScaleMult * (power + uWaterfallScaleAdd);
-            color = texture(uColormapSampler, vec2(normalizedPower, 0.0));
+            if (normalizedPower > 1.0 && uOverRangeColor.a > 0.0) {
+                color = uOverRangeColor;
+                return;
+            }
+            if (normalizedPower < 0.0 && uUnderRangeColor.a > 0.0) {
+                color = uUnderRangeColor;
+                return;
+            }
+            // Gamma is applied to the normalized power after clamping it to
+            // [0, 1], since pow() is undefined for negative values.
+            normalizedPower = pow(clamp(normalizedPower, 0.0, 1.0), uWaterfallGamma);
+            if (uLinearLightColormap) {
+                color = colormapLinearLight(normalizedPower);
+            } else {
+                color = texture(uColormapSampler, vec2(normalizedPower, 0.0));
+            }
         }"#,
             ),
         };
@@ -352,12 +1546,13 @@ impl Waterfall {
         in vec2 aPosition;
         uniform float uCenterFreq;
         uniform float uZoom;
+        uniform float uAxisDirection;
         uniform int uMajorTicksEnd;
         void main() {
             bool majorTick = gl_VertexID < uMajorTicksEnd;
             bool tickStart = (gl_VertexID & 1) == 0;
             float majorTickOffset = majorTick && !tickStart ? 0.02 : 0.0;
-            gl_Position = vec4(uZoom * (aPosition.x - uCenterFreq),
+            gl_Position = vec4(uAxisDirection * uZoom * (aPosition.x - uCenterFreq),
                                aPosition.y + majorTickOffset,
                                0.0, 1.0);
         }"#,
@@ -372,20 +1567,222 @@ impl Waterfall {
         engine.make_program(source)
     }
 
-    fn frequency_labels_program(engine: &RenderEngine) -> Result<Rc<WebGlProgram>, JsValue> {
+    fn marker_program(engine: &RenderEngine) -> Result<Rc<WebGlProgram>, JsValue> {
         let source = ProgramSource {
             vertex_shader: r#"#version 300 es
         in vec2 aPosition;
-        in vec2 aTextureCoordinates;
         uniform float uCenterFreq;
         uniform float uZoom;
-        uniform float uLabelWidth;
-        uniform float uLabelHeight;
-        out vec2 vTextureCoordinates;
+        uniform float uAxisDirection;
+        uniform float uMarkerFreq;
         void main() {
-            float side_offset = (float(gl_VertexID & 1) - 0.5) * uLabelWidth;
-            float vertical_offset = (gl_VertexID & 2) != 0 ? uLabelHeight : 0.0;
-            float center = uZoom * (aPosition.x - uCenterFreq);
+            gl_Position = vec4(uAxisDirection * uZoom * (uMarkerFreq - uCenterFreq),
+                               aPosition.y,
+                               0.0, 1.0);
+        }"#,
+            fragment_shader: r#"#version 300 es
+        precision highp float;
+        uniform vec4 uMarkerColor;
+        out vec4 color;
+        void main() {
+            color = uMarkerColor;
+        }"#,
+        };
+        engine.make_program(source)
+    }
+
+    fn ruler_program(engine: &RenderEngine) -> Result<Rc<WebGlProgram>, JsValue> {
+        let source = ProgramSource {
+            vertex_shader: r#"#version 300 es
+        in vec2 aPosition;
+        uniform float uCenterFreq;
+        uniform float uZoom;
+        uniform float uAxisDirection;
+        uniform vec2 uRulerStart;
+        uniform vec2 uRulerEnd;
+        void main() {
+            vec2 point = gl_VertexID == 0 ? uRulerStart : uRulerEnd;
+            gl_Position = vec4(uAxisDirection * uZoom * (point.x - uCenterFreq),
+                               point.y,
+                               0.0, 1.0);
+        }"#,
+            fragment_shader: r#"#version 300 es
+        precision highp float;
+        out vec4 color;
+        void main() {
+            color = vec4(1.0);
+        }"#,
+        };
+        engine.make_program(source)
+    }
+
+    fn bin_highlight_program(engine: &RenderEngine) -> Result<Rc<WebGlProgram>, JsValue> {
+        let source = ProgramSource {
+            vertex_shader: r#"#version 300 es
+        in vec2 aPosition;
+        uniform float uCenterFreq;
+        uniform float uZoom;
+        uniform float uAxisDirection;
+        uniform vec2 uBinEdges;
+        uniform float uMinHalfWidth;
+        void main() {
+            float binCenter = 0.5 * (uBinEdges.x + uBinEdges.y);
+            float center = uAxisDirection * uZoom * (binCenter - uCenterFreq);
+            float halfWidth = max(0.5 * uZoom * (uBinEdges.y - uBinEdges.x),
+                                  uMinHalfWidth);
+            gl_Position = vec4(center + aPosition.x * halfWidth,
+                               aPosition.y,
+                               0.0, 1.0);
+        }"#,
+            fragment_shader: r#"#version 300 es
+        precision highp float;
+        uniform vec4 uHighlightColor;
+        out vec4 color;
+        void main() {
+            color = uHighlightColor;
+        }"#,
+        };
+        engine.make_program(source)
+    }
+
+    fn retune_progress_program(engine: &RenderEngine) -> Result<Rc<WebGlProgram>, JsValue> {
+        let source = ProgramSource {
+            vertex_shader: r#"#version 300 es
+        in vec2 aPosition;
+        uniform float uEdgeSide;
+        uniform float uProgress;
+        void main() {
+            // The bar has a width of 1% of the screen and grows upwards from
+            // the bottom of the screen.
+            const float width = 0.02;
+            float x = uEdgeSide * (1.0 - 0.5 * (aPosition.x + 1.0) * width);
+            float y = -1.0 + (aPosition.y + 1.0) * uProgress;
+            gl_Position = vec4(x, y, 0.0, 1.0);
+        }"#,
+            fragment_shader: r#"#version 300 es
+        precision highp float;
+        out vec4 color;
+        void main() {
+            // Translucent white, using premultiplied alpha
+            color = vec4(0.6);
+        }"#,
+        };
+        engine.make_program(source)
+    }
+
+    fn edge_mask_program(engine: &RenderEngine) -> Result<Rc<WebGlProgram>, JsValue> {
+        let source = ProgramSource {
+            vertex_shader: r#"#version 300 es
+        in vec2 aPosition;
+        uniform float uCenterFreq;
+        uniform float uZoom;
+        uniform float uAxisDirection;
+        uniform float uMaskSide;
+        uniform float uMaskWidth;
+        void main() {
+            float inner = uMaskSide * (1.0 - uMaskWidth);
+            float x = mix(inner, uMaskSide, 0.5 * (aPosition.x + 1.0));
+            gl_Position = vec4(uAxisDirection * uZoom * (x - uCenterFreq),
+                               aPosition.y,
+                               0.0, 1.0);
+        }"#,
+            fragment_shader: r#"#version 300 es
+        precision highp float;
+        out vec4 color;
+        void main() {
+            // Translucent black, using premultiplied alpha
+            color = vec4(0.0, 0.0, 0.0, 0.5);
+        }"#,
+        };
+        engine.make_program(source)
+    }
+
+    fn comb_program(engine: &RenderEngine) -> Result<Rc<WebGlProgram>, JsValue> {
+        let source = ProgramSource {
+            vertex_shader: r#"#version 300 es
+        in vec2 aPosition;
+        uniform float uCenterFreq;
+        uniform float uZoom;
+        uniform float uAxisDirection;
+        uniform float uCombStart;
+        uniform float uCombStep;
+        void main() {
+            float freq = uCombStart + float(gl_VertexID / 2) * uCombStep;
+            gl_Position = vec4(uAxisDirection * uZoom * (freq - uCenterFreq),
+                               aPosition.y,
+                               0.0, 1.0);
+        }"#,
+            fragment_shader: r#"#version 300 es
+        precision highp float;
+        out vec4 color;
+        void main() {
+            // Translucent yellow, using premultiplied alpha
+            color = vec4(0.5, 0.5, 0.2, 0.5);
+        }"#,
+        };
+        engine.make_program(source)
+    }
+
+    fn bin_grid_program(engine: &RenderEngine) -> Result<Rc<WebGlProgram>, JsValue> {
+        let source = ProgramSource {
+            vertex_shader: r#"#version 300 es
+        in vec2 aPosition;
+        uniform float uCenterFreq;
+        uniform float uZoom;
+        uniform float uAxisDirection;
+        uniform float uBinGridStart;
+        uniform float uBinGridStep;
+        void main() {
+            float freq = uBinGridStart + float(gl_VertexID / 2) * uBinGridStep;
+            gl_Position = vec4(uAxisDirection * uZoom * (freq - uCenterFreq),
+                               aPosition.y,
+                               0.0, 1.0);
+        }"#,
+            fragment_shader: r#"#version 300 es
+        precision highp float;
+        out vec4 color;
+        void main() {
+            // Faint white, using premultiplied alpha
+            color = vec4(0.15, 0.15, 0.15, 0.15);
+        }"#,
+        };
+        engine.make_program(source)
+    }
+
+    fn reticle_program(engine: &RenderEngine) -> Result<Rc<WebGlProgram>, JsValue> {
+        let source = ProgramSource {
+            vertex_shader: r#"#version 300 es
+        in vec2 aPosition;
+        uniform vec2 uReticleHalfSize;
+        void main() {
+            gl_Position = vec4(aPosition * uReticleHalfSize, 0.0, 1.0);
+        }"#,
+            fragment_shader: r#"#version 300 es
+        precision highp float;
+        uniform vec4 uReticleColor;
+        out vec4 color;
+        void main() {
+            color = uReticleColor;
+        }"#,
+        };
+        engine.make_program(source)
+    }
+
+    fn frequency_labels_program(engine: &RenderEngine) -> Result<Rc<WebGlProgram>, JsValue> {
+        let source = ProgramSource {
+            vertex_shader: r#"#version 300 es
+        in vec2 aPosition;
+        in vec2 aTextureCoordinates;
+        uniform float uCenterFreq;
+        uniform float uZoom;
+        uniform float uAxisDirection;
+        uniform float uLabelWidth;
+        uniform float uLabelHeight;
+        out vec2 vTextureCoordinates;
+        void main() {
+            float side_offset = (float(gl_VertexID & 1) - 0.5) * uLabelWidth;
+            float vertical_offset = (gl_VertexID & 2) != 0 ? uLabelHeight : 0.0;
+            float center = uAxisDirection * uZoom * (aPosition.x - uCenterFreq);
             gl_Position = vec4(center + side_offset,
                                aPosition.y + vertical_offset,
                                0.0, 1.0);
@@ -443,6 +1840,87 @@ impl Waterfall {
         Ok(vao)
     }
 
+    fn quad_vao(
+        engine: &mut RenderEngine,
+        program: &WebGlProgram,
+    ) -> Result<Rc<WebGlVertexArrayObject>, JsValue> {
+        // A quad that covers the whole screen. The vertex shaders use the
+        // coordinates to select the corners of the quad they draw.
+        let vertices: [f32; 8] = [
+            -1.0, -1.0, // A
+            1.0, -1.0, // B
+            -1.0, 1.0, // C
+            1.0, 1.0, // D
+        ];
+        let indices: [u16; 6] = [
+            0, 1, 2, // ABC
+            1, 2, 3, // BCD
+        ];
+        let vao = engine
+            .create_vao()?
+            .create_array_buffer(program, "aPosition", 2, &vertices)?
+            .create_element_array_buffer(&indices)?
+            .build();
+        Ok(vao)
+    }
+
+    fn comb_vao(
+        engine: &mut RenderEngine,
+        program: &WebGlProgram,
+    ) -> Result<Rc<WebGlVertexArrayObject>, JsValue> {
+        // Each line is formed by two vertices. The x coordinate is not used by
+        // the vertex shader, since the line positions are computed from
+        // uniforms and the vertex ID.
+        let num_vertices = 2 * Comb::MAX_LINES as usize;
+        let vertices: Vec<f32> = (0..num_vertices)
+            .flat_map(|j| [0.0, if j % 2 == 0 { -1.0 } else { 1.0 }])
+            .collect();
+        let indices: Vec<u16> = (0..num_vertices as u16).collect();
+        let vao = engine
+            .create_vao()?
+            .create_array_buffer(program, "aPosition", 2, &vertices)?
+            .create_element_array_buffer(&indices)?
+            .build();
+        Ok(vao)
+    }
+
+    fn marker_vao(
+        engine: &mut RenderEngine,
+        program: &WebGlProgram,
+    ) -> Result<Rc<WebGlVertexArrayObject>, JsValue> {
+        // The x coordinate is not used by the vertex shader, since the marker
+        // position is given by a uniform.
+        let vertices: [f32; 4] = [0.0, -1.0, 0.0, 1.0];
+        let indices: [u16; Marker::NUM_INDICES as usize] = [0, 1];
+        let vao = engine
+            .create_vao()?
+            .create_array_buffer(program, "aPosition", 2, &vertices)?
+            .create_element_array_buffer(&indices)?
+            .build();
+        Ok(vao)
+    }
+
+    fn reticle_vao(
+        engine: &mut RenderEngine,
+        program: &WebGlProgram,
+    ) -> Result<Rc<WebGlVertexArrayObject>, JsValue> {
+        // A horizontal and a vertical line that cross at the origin. The
+        // vertex shader scales them to the size of the reticle.
+        let vertices: [f32; 8] = [
+            -1.0, 0.0, // left
+            1.0, 0.0, // right
+            0.0, -1.0, // bottom
+            0.0, 1.0, // top
+        ];
+        let indices: [u16; Reticle::NUM_INDICES as usize] = [0, 1, 2, 3];
+        let vao = engine
+            .create_vao()?
+            .create_array_buffer(program, "aPosition", 2, &vertices)?
+            .create_element_array_buffer(&indices)?
+            .build();
+        Ok(vao)
+    }
+
     fn frequency_labels_vao(
         &mut self,
         engine: &mut RenderEngine,
@@ -532,9 +2010,10 @@ impl Waterfall {
 
         let indices_ticks = (0..freqs.len() as u16).into_iter().collect::<Vec<u16>>();
 
+        let separator = self.labels_decimal_separator.to_string();
         let texture_texts = freqs_labels
             .iter()
-            .map(|f| format!("{:.03}", f * 1e-6))
+            .map(|f| format!("{:.03}", f * 1e-6).replace('.', &separator))
             .collect::<Vec<_>>();
         let texts_dimensions =
             engine.render_texts_to_texture(&self.textures.text, &texture_texts, TEXT_HEIGHT_PX)?;
@@ -595,6 +2074,7 @@ impl Waterfall {
     /// defines the colormap (typically, 256 colors are used for the colormap,
     /// so the length of the colormap slice is `3 * 256`).
     pub fn load_colormap(&self, engine: &mut RenderEngine, colormap: &[u8]) -> Result<(), JsValue> {
+        self.mark_dirty();
         self.textures.load_colormap(engine, colormap)
     }
 
@@ -607,8 +2087,202 @@ impl Waterfall {
         )
     }
 
+    /// Sets the easing curve used for the animations of the view.
+    ///
+    /// If the easing is [`Easing::None`], the view changes requested with
+    /// [`animate_view`](Waterfall::animate_view) are applied immediately.
+    pub fn set_animation_easing(&mut self, easing: Easing) {
+        self.animation_easing = easing;
+    }
+
+    /// Animates the view towards a target zoom and center frequency.
+    ///
+    /// The animation is advanced in each call to
+    /// [`prepare_render`](Waterfall::prepare_render). The zoom is interpolated
+    /// geometrically, so that the animation has a constant perceived speed.
+    /// Calling [`set_zoom`](Waterfall::set_zoom) or
+    /// [`set_center_frequency`](Waterfall::set_center_frequency) cancels the
+    /// animation.
+    ///
+    /// The target is clamped as in [`set_zoom`](Waterfall::set_zoom) and
+    /// [`set_center_frequency`](Waterfall::set_center_frequency).
+    pub fn animate_view(&mut self, zoom: f32, center_frequency: f32) {
+        // Duration of the animation in milliseconds.
+        const DURATION_MS: f64 = 250.0;
+        self.zoom_momentum = None;
+        let zoom = Self::clamp_zoom(zoom);
+        let center_frequency = Self::clamp_center_frequency(center_frequency, zoom);
+        if self.animation_easing == Easing::None {
+            self.set_zoom(zoom);
+            self.set_center_frequency(center_frequency);
+            return;
+        }
+        self.view_tween = Some(Tween::new(
+            (self.get_zoom(), self.get_center_frequency()),
+            (zoom, center_frequency),
+            self.performance.now(),
+            DURATION_MS,
+            self.animation_easing,
+        ));
+    }
+
+    /// Returns the target of the view.
+    ///
+    /// If the view is being animated, this returns the zoom and center
+    /// frequency at the end of the animation. Otherwise, it returns the
+    /// current zoom and center frequency.
+    pub fn get_view_target(&self) -> (f32, f32) {
+        match &self.view_tween {
+            Some(tween) => tween.to(),
+            None => (self.get_zoom(), self.get_center_frequency()),
+        }
+    }
+
+    /// Continues zooming with momentum.
+    ///
+    /// This is used to continue a pinch gesture after the fingers are
+    /// lifted. The zoom changes geometrically with an initial `rate`, given in
+    /// natural log units per millisecond, and the rate decays smoothly until
+    /// the zoom stops. The zoom is dilated around the frequency `center`, so
+    /// that it stays at the same place on the screen. The frequency uses the
+    /// same units as the center frequency (see
+    /// [`set_center_frequency`](Waterfall::set_center_frequency)).
+    ///
+    /// The momentum is advanced in each call to
+    /// [`prepare_render`](Waterfall::prepare_render), and it stops when the
+    /// zoom reaches its limits. Calling [`set_zoom`](Waterfall::set_zoom),
+    /// [`set_center_frequency`](Waterfall::set_center_frequency),
+    /// [`animate_view`](Waterfall::animate_view) or
+    /// [`stop_zoom_momentum`](Waterfall::stop_zoom_momentum) cancels the
+    /// momentum.
+    pub fn start_zoom_momentum(&mut self, rate: f32, center: f32) {
+        // Time constant of the decay of the rate, in milliseconds.
+        const TIME_CONSTANT_MS: f64 = 250.0;
+        if !rate.is_finite() || !center.is_finite() {
+            return;
+        }
+        self.view_tween = None;
+        let momentum = Momentum::new(f64::from(rate), self.performance.now(), TIME_CONSTANT_MS);
+        self.zoom_momentum = Some((momentum, center));
+    }
+
+    /// Stops the zoom momentum.
+    ///
+    /// See [`start_zoom_momentum`](Waterfall::start_zoom_momentum).
+    pub fn stop_zoom_momentum(&mut self) {
+        self.zoom_momentum = None;
+    }
+
+    fn update_zoom_momentum(&mut self, now: f64) {
+        let Some((momentum, center)) = &mut self.zoom_momentum else {
+            return;
+        };
+        let center = *center;
+        let dilation = (momentum.step(now) as f32).exp();
+        let finished = momentum.is_finished();
+        let zoom = self.get_zoom();
+        let new_zoom = Self::clamp_zoom(dilation * zoom);
+        if new_zoom != zoom {
+            // The dilation actually applied is smaller if the zoom is clamped.
+            let dilation = new_zoom / zoom;
+            let freq = ((dilation - 1.0) * center + self.get_center_frequency()) / dilation;
+            self.update_zoom(new_zoom);
+            self.uniforms
+                .center_freq
+                .set_data(Self::clamp_center_frequency(freq, new_zoom));
+        }
+        if finished || new_zoom == zoom {
+            self.zoom_momentum = None;
+            // The momentum continues a pinch gesture, so the zoom is settled
+            // when it stops.
+            self.settle_zoom(center);
+        }
+    }
+
+    /// Enables or disables snapping the zoom to powers of two.
+    ///
+    /// When enabled, the zoom eases to the nearest power of two (1x, 2x, 4x,
+    /// and so on) when a zoom gesture ends, so that the ratio between FFT bins
+    /// and pixels is predictable. The zoom is not snapped during the gesture.
+    /// See [`settle_zoom`](Waterfall::settle_zoom).
+    pub fn set_snap_zoom(&mut self, enable: bool) {
+        self.snap_zoom = enable;
+    }
+
+    /// Settles the zoom at the end of a zoom gesture.
+    ///
+    /// If snapping the zoom is enabled, the view is animated to the power of
+    /// two nearest to the current zoom. The zoom is dilated around the
+    /// frequency `fixed`, which stays at the same place on the screen, and
+    /// which uses the same units as the center frequency (see
+    /// [`set_center_frequency`](Waterfall::set_center_frequency)).
+    ///
+    /// Returns `true` if the view is changed.
+    pub fn settle_zoom(&mut self, fixed: f32) -> bool {
+        if !self.snap_zoom || !fixed.is_finite() {
+            return false;
+        }
+        let zoom = self.get_zoom();
+        let new_zoom = Self::snapped_zoom(zoom);
+        if new_zoom == zoom {
+            return false;
+        }
+        let dilation = new_zoom / zoom;
+        let freq = ((dilation - 1.0) * fixed + self.get_center_frequency()) / dilation;
+        self.animate_view(new_zoom, Self::clamp_center_frequency(freq, new_zoom));
+        true
+    }
+
+    // Power of two nearest to a zoom level, in the logarithmic scale. The
+    // limits of the zoom are powers of two, so the result is always valid.
+    fn snapped_zoom(zoom: f32) -> f32 {
+        Self::clamp_zoom(zoom.log2().round().exp2())
+    }
+
+    fn update_view_animation(&mut self, now: f64) {
+        let Some(tween) = self.view_tween else {
+            return;
+        };
+        let t = tween.progress(now);
+        let (zoom0, center0) = tween.from();
+        let (zoom1, center1) = tween.to();
+        let zoom = lerp(zoom0.ln(), zoom1.ln(), t).exp();
+        // The center frequency is clamped so that the view does not extend
+        // beyond the band edges at the intermediate zoom levels. When springing
+        // back from an overscan, the animation starts beyond the band edges,
+        // so the clamp is relaxed by the part of the overscan that remains.
+        let overscan = (center0.abs() - (1.0 - 1.0 / zoom0).max(0.0)).max(0.0) * (1.0 - t);
+        let max_center = (1.0 - 1.0 / zoom).max(0.0) + overscan;
+        let center = lerp(center0, center1, t).clamp(-max_center, max_center);
+        self.update_zoom(zoom);
+        self.uniforms.center_freq.set_data(center);
+        if tween.is_finished(now) {
+            self.update_zoom(zoom1);
+            self.uniforms.center_freq.set_data(center1);
+            self.view_tween = None;
+        }
+    }
+
     /// Sets the zoom level of the waterfall.
+    ///
+    /// The zoom is quietly clamped to the range between
+    /// [`MIN_ZOOM`](Waterfall::MIN_ZOOM) and [`MAX_ZOOM`](Waterfall::MAX_ZOOM),
+    /// and a NaN zoom is replaced by the minimum zoom. The center frequency is
+    /// clamped to the range that is valid for the new zoom, so that the view
+    /// does not extend beyond the band edges.
+    ///
+    /// This cancels any animation and momentum of the view.
     pub fn set_zoom(&mut self, zoom: f32) {
+        self.view_tween = None;
+        self.zoom_momentum = None;
+        let zoom = Self::clamp_zoom(zoom);
+        self.update_zoom(zoom);
+        let center = Self::clamp_center_frequency(self.get_center_frequency(), zoom);
+        self.uniforms.center_freq.set_data(center);
+        self.mark_dirty();
+    }
+
+    fn update_zoom(&mut self, zoom: f32) {
         self.uniforms.zoom.set_data(zoom);
         // TODO: improve search algorithm
         let mut k = 0;
@@ -638,34 +2312,765 @@ impl Waterfall {
     /// This function is used when dragging the waterfall to scroll in
     /// frequency. The `frequency` does not use physical units, but rather has a
     /// value between -1 and 1 that corresponds to screen coordinates.
+    ///
+    /// The frequency is quietly clamped with
+    /// [`clamp_center_frequency`](Waterfall::clamp_center_frequency) using the
+    /// current zoom, so that the view does not extend beyond the band edges.
+    ///
+    /// This cancels any animation and momentum of the view.
     pub fn set_center_frequency(&mut self, frequency: f32) {
+        self.view_tween = None;
+        self.zoom_momentum = None;
+        let frequency = Self::clamp_center_frequency(frequency, self.get_zoom());
         self.uniforms.center_freq.set_data(frequency);
+        self.mark_dirty();
     }
 
-    /// Returns the current center frequency of the waterfall.
+    /// Sets the overscan of the view.
     ///
-    /// The frequency is defined as in the
-    /// [`set_center_frequency`](Waterfall::set_center_frequency) function.
-    pub fn get_center_frequency(&self) -> f32 {
-        self.uniforms.center_freq.get_data()
+    /// The overscan allows dragging the view beyond the band edges, showing an
+    /// empty margin, by up to this percentage of the width of the view. The
+    /// resistance to the drag increases as the view moves further beyond the
+    /// edge, and the view springs back when the gesture ends. See
+    /// [`drag_center_frequency`](Waterfall::drag_center_frequency) and
+    /// [`release_overscan`](Waterfall::release_overscan).
+    ///
+    /// The percentage is clamped between 0 and 25%. With an overscan of zero,
+    /// which is the default, the view stops at the band edges.
+    pub fn set_overscan(&mut self, percent: f32) {
+        self.overscan = if percent.is_nan() {
+            0.0
+        } else {
+            (1e-2 * percent).clamp(0.0, 0.25)
+        };
     }
 
-    /// Sets the waterfall minimum power value.
+    /// Returns `true` if the view can be dragged beyond the band edges.
     ///
-    /// The minimum value is used to scale the colormap. The `value` is in dB
-    /// units.
-    pub fn set_waterfall_min(&mut self, value: f32) {
-        self.waterfall_min = value;
-        self.update_waterfall_scale();
+    /// See [`set_overscan`](Waterfall::set_overscan).
+    pub fn is_overscan_enabled(&self) -> bool {
+        self.overscan > 0.0
     }
 
-    /// Sets the waterfall maximum power value.
+    /// Sets the center frequency of the waterfall during a drag gesture.
     ///
-    /// The maximum value is used to scale the colormap. The `value` is in dB
-    /// units.
-    pub fn set_waterfall_max(&mut self, value: f32) {
-        self.waterfall_max = value;
-        self.update_waterfall_scale();
+    /// This works as
+    /// [`set_center_frequency`](Waterfall::set_center_frequency), except when
+    /// an overscan is set with [`set_overscan`](Waterfall::set_overscan). In
+    /// that case, `frequency` is the center frequency that follows the
+    /// pointer, and the distance by which it goes beyond the band edges is
+    /// compressed as if the view was pulled by a rubber band, so that it never
+    /// exceeds the overscan.
+    ///
+    /// When the gesture ends,
+    /// [`release_overscan`](Waterfall::release_overscan) should be called.
+    pub fn drag_center_frequency(&mut self, frequency: f32) {
+        self.set_center_frequency(frequency);
+        if self.overscan <= 0.0 || !frequency.is_finite() {
+            return;
+        }
+        let clamped = self.get_center_frequency();
+        // The width of the view is 2 / zoom in frequency units.
+        let limit = 2.0 * self.overscan / self.get_zoom();
+        let excess = Self::rubber_band(frequency - clamped, limit);
+        self.uniforms.center_freq.set_data(clamped + excess);
+    }
+
+    /// Returns the view inside the band edges after a drag gesture.
+    ///
+    /// If the view has been dragged beyond the band edges by
+    /// [`drag_center_frequency`](Waterfall::drag_center_frequency), it springs
+    /// back to the nearest band edge. The spring back is animated unless
+    /// `animate` is `false` or the animations are disabled with
+    /// [`set_animation_easing`](Waterfall::set_animation_easing).
+    pub fn release_overscan(&mut self, animate: bool) {
+        // Duration of the spring back in milliseconds.
+        const DURATION_MS: f64 = 300.0;
+        if self.view_tween.is_some() {
+            return;
+        }
+        let zoom = self.get_zoom();
+        let center = self.get_center_frequency();
+        let clamped = Self::clamp_center_frequency(center, zoom);
+        if center == clamped {
+            return;
+        }
+        if !animate || self.animation_easing == Easing::None {
+            self.uniforms.center_freq.set_data(clamped);
+            self.mark_dirty();
+            return;
+        }
+        self.view_tween = Some(Tween::new(
+            (zoom, center),
+            (zoom, clamped),
+            self.performance.now(),
+            DURATION_MS,
+            Easing::EaseOut,
+        ));
+    }
+
+    // Compresses a distance beyond the band edges so that it grows linearly
+    // for short distances and it approaches `limit` for long distances. The
+    // sign of the distance is kept.
+    fn rubber_band(distance: f32, limit: f32) -> f32 {
+        if limit <= 0.0 || distance.is_nan() {
+            return 0.0;
+        }
+        limit * (distance / limit).tanh()
+    }
+
+    /// Clamps a zoom level to the valid range.
+    ///
+    /// The zoom is clamped to the range between
+    /// [`MIN_ZOOM`](Waterfall::MIN_ZOOM) and [`MAX_ZOOM`](Waterfall::MAX_ZOOM).
+    /// A NaN zoom gives the minimum zoom.
+    pub fn clamp_zoom(zoom: f32) -> f32 {
+        if zoom.is_nan() {
+            return Self::MIN_ZOOM;
+        }
+        zoom.clamp(Self::MIN_ZOOM, Self::MAX_ZOOM)
+    }
+
+    /// Clamps a center frequency to the valid range for a zoom level.
+    ///
+    /// The center frequency is clamped so that the view does not extend beyond
+    /// the band edges. If the frequency is NaN or the zoom is not valid, the
+    /// center of the band is returned.
+    pub fn clamp_center_frequency(frequency: f32, zoom: f32) -> f32 {
+        if frequency.is_nan() || !zoom.is_finite() || zoom < Self::MIN_ZOOM {
+            return 0.0;
+        }
+        let max_freq = 1.0 - 1.0 / zoom;
+        frequency.clamp(-max_freq, max_freq)
+    }
+
+    /// Returns the current center frequency of the waterfall.
+    ///
+    /// The frequency is defined as in the
+    /// [`set_center_frequency`](Waterfall::set_center_frequency) function.
+    pub fn get_center_frequency(&self) -> f32 {
+        self.uniforms.center_freq.get_data()
+    }
+
+    /// Sets the center frequency of the waterfall in Hz.
+    ///
+    /// This works as [`set_center_frequency`](Waterfall::set_center_frequency),
+    /// but the frequency is given as an absolute frequency in Hz, using the
+    /// center frequency and sample rate given in
+    /// [`set_freq_samprate`](Waterfall::set_freq_samprate). The frequency is
+    /// clamped so that the view does not extend beyond the band edges. If the
+    /// frequency is not finite, the call is ignored.
+    pub fn set_display_center_hz(&mut self, frequency: f64) {
+        if !frequency.is_finite() {
+            return;
+        }
+        self.set_center_frequency(Self::hz_to_units(
+            frequency,
+            self.center_freq,
+            self.samp_rate,
+        ));
+    }
+
+    /// Returns the current center frequency of the waterfall in Hz.
+    ///
+    /// This is the absolute frequency shown at the center of the screen.
+    pub fn get_display_center_hz(&self) -> f64 {
+        Self::units_to_hz(
+            self.get_center_frequency(),
+            self.center_freq,
+            self.samp_rate,
+        )
+    }
+
+    /// Returns the power at the center of the screen.
+    ///
+    /// The power is taken from the FFT bin that contains the frequency at the
+    /// center of the view in the last spectrum line added to the waterfall,
+    /// and it is given in dB units, using the same scale as the waterfall
+    /// minimum and maximum power values. If no spectrum lines have been added
+    /// yet, `None` is returned.
+    pub fn get_display_center_power(&self) -> Option<f32> {
+        self.power_at(self.get_center_frequency())
+    }
+
+    /// Sets the frequency span of the view in Hz.
+    ///
+    /// The span is the width of the band shown on the screen. The zoom is
+    /// computed from the span and the sample rate given in
+    /// [`set_freq_samprate`](Waterfall::set_freq_samprate), and it is clamped
+    /// as in [`set_zoom`](Waterfall::set_zoom), so the span that is shown can
+    /// differ from the requested one. It can be read back with
+    /// [`get_span_hz`](Waterfall::get_span_hz). The center of the view is kept
+    /// unless it needs to be clamped so that the view does not extend beyond
+    /// the band edges. If the span is not positive and finite, the call is
+    /// ignored.
+    pub fn set_span_hz(&mut self, span: f64) {
+        if let Some(zoom) = Self::span_to_zoom(span, self.samp_rate) {
+            self.set_zoom(zoom);
+        }
+    }
+
+    /// Returns the frequency span of the view in Hz.
+    ///
+    /// This is the width of the band shown on the screen with the current
+    /// zoom.
+    pub fn get_span_hz(&self) -> f64 {
+        self.samp_rate / f64::from(self.get_zoom())
+    }
+
+    // Computes the zoom that shows a span in Hz, clamped to the valid range.
+    fn span_to_zoom(span: f64, samp_rate: f64) -> Option<f32> {
+        if !(span > 0.0 && span.is_finite()) {
+            return None;
+        }
+        Some(Self::clamp_zoom((samp_rate / span) as f32))
+    }
+
+    // Converts an absolute frequency in Hz to the units used by the center
+    // frequency, given the actual center frequency of the waterfall (see
+    // actual_center_freq) and the sample rate.
+    fn hz_to_units(frequency: f64, center_freq: f64, samp_rate: f64) -> f32 {
+        (2.0 * (frequency - center_freq) / samp_rate) as f32
+    }
+
+    // Inverse of hz_to_units.
+    fn units_to_hz(frequency: f32, center_freq: f64, samp_rate: f64) -> f64 {
+        center_freq + 0.5 * f64::from(frequency) * samp_rate
+    }
+
+    /// Flips the frequency axis.
+    ///
+    /// When the frequency axis is flipped, frequency increases from right to
+    /// left. This is the only place where the orientation of the frequency
+    /// axis is defined. Code that converts between screen coordinates and
+    /// frequencies should use
+    /// [`get_axis_direction`](Waterfall::get_axis_direction) to take the
+    /// orientation into account.
+    pub fn set_frequency_axis_flipped(&mut self, flipped: bool) {
+        self.uniforms
+            .axis_direction
+            .set_data(if flipped { -1.0 } else { 1.0 });
+        self.mark_dirty();
+    }
+
+    /// Returns `true` if the frequency axis is flipped.
+    pub fn is_frequency_axis_flipped(&self) -> bool {
+        self.get_axis_direction() < 0.0
+    }
+
+    /// Returns the direction of the frequency axis.
+    ///
+    /// The direction is 1.0 if frequency increases from left to right and -1.0
+    /// if the axis is flipped. The screen coordinate of a `frequency` (as
+    /// defined in [`set_center_frequency`](Waterfall::set_center_frequency))
+    /// is `direction * zoom * (frequency - center_frequency)`.
+    pub fn get_axis_direction(&self) -> f32 {
+        self.uniforms.axis_direction.get_data()
+    }
+
+    /// Shows the ruler.
+    ///
+    /// The ruler is a line drawn between the points `start` and `end`. The x
+    /// coordinate of each point is a frequency given in the same units as the
+    /// waterfall center frequency (see
+    /// [`set_center_frequency`](Waterfall::set_center_frequency)), and the y
+    /// coordinate is given in screen coordinates, between -1 (bottom) and 1
+    /// (top).
+    pub fn set_ruler(&mut self, start: (f32, f32), end: (f32, f32)) {
+        self.ruler.start.set_data(start);
+        self.ruler.end.set_data(end);
+        self.ruler.num_indices.set(Ruler::NUM_INDICES);
+        self.mark_dirty();
+    }
+
+    /// Hides the ruler.
+    pub fn clear_ruler(&mut self) {
+        self.ruler.num_indices.set(0);
+        self.mark_dirty();
+    }
+
+    /// Highlights the FFT bin that contains a frequency.
+    ///
+    /// The `frequency` is given in the same units as the waterfall center
+    /// frequency (see [`set_center_frequency`](Waterfall::set_center_frequency)).
+    /// The highlight covers the bin exactly when the bins are wider than a
+    /// pixel, and it is drawn as a one pixel wide line at the bin center
+    /// otherwise. If `frequency` is `None` or lies outside the waterfall, the
+    /// highlight is hidden.
+    pub fn set_bin_highlight(&mut self, frequency: Option<f32>) {
+        match frequency.and_then(Self::bin_edges) {
+            Some(edges) => {
+                self.bin_highlight.edges.set_data(edges);
+                self.bin_highlight
+                    .num_indices
+                    .set(BinHighlight::NUM_INDICES);
+            }
+            None => self.bin_highlight.num_indices.set(0),
+        }
+        self.mark_dirty();
+    }
+
+    // Returns the edges of the FFT bin that contains a frequency. The
+    // frequencies from -1 to 1 are covered by TEXTURE_WIDTH bins of the same
+    // width.
+    fn bin_edges(frequency: f32) -> Option<(f32, f32)> {
+        if !(-1.0..1.0).contains(&frequency) {
+            return None;
+        }
+        let bin_width = 2.0 / Self::TEXTURE_WIDTH as f32;
+        let bin = ((frequency + 1.0) / bin_width).floor();
+        let start = -1.0 + bin * bin_width;
+        Some((start, start + bin_width))
+    }
+
+    /// Shows the progress towards retuning the RX frequency by dragging.
+    ///
+    /// A bar whose height is proportional to `progress`, which should be
+    /// between 0 and 1, is drawn at the left edge of the screen if `side` is
+    /// negative and at the right edge otherwise. A `progress` of zero hides the
+    /// bar.
+    pub fn set_retune_progress(&mut self, side: f32, progress: f32) {
+        let progress = if progress.is_finite() {
+            progress.clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        self.retune_progress
+            .side
+            .set_data(if side < 0.0 { -1.0 } else { 1.0 });
+        self.retune_progress.progress.set_data(progress);
+        self.retune_progress.num_indices.set(if progress > 0.0 {
+            RetuneProgress::NUM_INDICES
+        } else {
+            0
+        });
+        self.mark_dirty();
+    }
+
+    /// Shows or hides the band edge mask.
+    ///
+    /// The mask darkens the given `fraction` of the band at each band edge,
+    /// which shows the part of the spectrum that is ignored when stitching
+    /// the results of a scan. If `fraction` is `None`, the mask is hidden.
+    /// The fraction is clamped in the same way as when stitching, to the
+    /// range `[0, MAX_EDGE_FRACTION]`.
+    pub fn set_edge_mask(&mut self, fraction: Option<f32>) {
+        match fraction.filter(|x| !x.is_nan()) {
+            Some(fraction) => {
+                // The band spans from -1 to 1, so its width is 2.
+                let width = 2.0 * fraction.clamp(0.0, StitchedSpectrum::MAX_EDGE_FRACTION as f32);
+                self.edge_mask.width.set_data(width);
+                self.edge_mask.num_indices.set(EdgeMask::NUM_INDICES);
+            }
+            None => self.edge_mask.num_indices.set(0),
+        }
+        self.mark_dirty();
+    }
+
+    /// Sets the frequency of the first line of the reference comb.
+    ///
+    /// The frequency is given in Hz.
+    pub fn set_comb_origin(&mut self, origin: u64) {
+        self.comb.origin = origin;
+        self.update_comb();
+    }
+
+    /// Sets the spacing between the lines of the reference comb.
+    ///
+    /// The spacing is given in Hz.
+    pub fn set_comb_spacing(&mut self, spacing: u32) {
+        self.comb.spacing = spacing;
+        self.update_comb();
+    }
+
+    /// Sets the number of lines of the reference comb.
+    ///
+    /// The lines are drawn at the origin and at higher frequencies. A count of
+    /// zero hides the comb. The count is limited to a maximum of 1024 lines.
+    pub fn set_comb_count(&mut self, count: u32) {
+        self.comb.count = count;
+        self.update_comb();
+    }
+
+    fn update_comb(&mut self) {
+        let samp_rate = self.samp_rate;
+        let comb = &self.comb;
+        let start = Self::hz_to_units(comb.origin as f64, self.center_freq, samp_rate);
+        let step = (2.0 * f64::from(comb.spacing) / samp_rate) as f32;
+        // Only the lines that fall inside the waterfall band [-1, 1] are drawn.
+        // The view never extends beyond the band, and the lines outside the
+        // view are clipped when rendering.
+        let (first, num_lines) = if comb.count == 0 {
+            (0.0, 0)
+        } else if step == 0.0 {
+            (0.0, 1)
+        } else {
+            let first = ((-1.0 - start) / step).ceil().max(0.0);
+            let last = ((1.0 - start) / step).floor().min(comb.count as f32 - 1.0);
+            if last >= first {
+                (first, (last - first) as u32 + 1)
+            } else {
+                (0.0, 0)
+            }
+        };
+        comb.start.set_data(start + first * step);
+        comb.step.set_data(step);
+        comb.num_indices.set(2 * num_lines.min(Comb::MAX_LINES));
+        self.mark_dirty();
+    }
+
+    /// Shows or hides the reticle.
+    ///
+    /// The reticle is a small crosshair drawn at the center of the canvas,
+    /// which marks the frequency at the center of the view (see
+    /// [`get_display_center_hz`](Waterfall::get_display_center_hz)). It is
+    /// drawn with the given color, as RGB with components between 0 and 1.
+    /// Passing `None` hides the reticle.
+    pub fn set_reticle(&mut self, color: Option<(f32, f32, f32)>) {
+        match color {
+            Some((r, g, b)) => {
+                self.reticle.color.set_data((r, g, b, 1.0));
+                self.reticle.num_indices.set(Reticle::NUM_INDICES);
+            }
+            None => self.reticle.num_indices.set(0),
+        }
+        self.mark_dirty();
+    }
+
+    fn update_reticle_size(&self, engine: &RenderEngine) {
+        // Length of each arm of the reticle in CSS pixels
+        const ARM_LENGTH: f32 = 12.0;
+        let (width, height) = engine.canvas_dims().css_pixels();
+        if width != 0 && height != 0 {
+            self.reticle.half_size.set_data((
+                2.0 * ARM_LENGTH / width as f32,
+                2.0 * ARM_LENGTH / height as f32,
+            ));
+        }
+    }
+
+    /// Enables or disables the bin grid.
+    ///
+    /// The bin grid consists of faint vertical lines drawn at the boundaries
+    /// between FFT bins. The lines are only drawn when the view is zoomed in
+    /// enough for each bin to be several pixels wide.
+    pub fn set_bin_grid(&mut self, enable: bool) {
+        self.bin_grid.enabled = enable;
+        self.mark_dirty();
+    }
+
+    fn update_bin_grid(&self, engine: &RenderEngine) {
+        let (width, _) = engine.canvas_dims().device_pixels();
+        let lines = if self.bin_grid.enabled {
+            Self::bin_grid_lines(self.get_center_frequency(), self.get_zoom(), width)
+        } else {
+            None
+        };
+        let (start, num_lines) = lines.unwrap_or((0.0, 0));
+        self.bin_grid.start.set_data(start);
+        self.bin_grid
+            .step
+            .set_data(2.0 / Self::TEXTURE_WIDTH as f32);
+        self.bin_grid.num_indices.set(2 * num_lines);
+    }
+
+    // Computes the bin boundaries that are inside the view. Returns the first
+    // boundary and the number of boundaries, or None if the bins are too
+    // narrow on the screen for the boundaries to be drawn. The width of the
+    // canvas is given in device pixels.
+    fn bin_grid_lines(center: f32, zoom: f32, width: u32) -> Option<(f32, u32)> {
+        // Minimum width of a bin in device pixels for the grid to be drawn
+        const MIN_BIN_WIDTH_PX: f32 = 8.0;
+        let bin_width = 2.0 / Self::TEXTURE_WIDTH as f32;
+        let bin_width_px = 0.5 * bin_width * zoom * width as f32;
+        if !(bin_width_px >= MIN_BIN_WIDTH_PX) {
+            return None;
+        }
+        // The band edges, at -1 and 1, are bin boundaries.
+        let first = ((center - 1.0 / zoom + 1.0) / bin_width).ceil();
+        let last = ((center + 1.0 / zoom + 1.0) / bin_width).floor();
+        if last < first {
+            return None;
+        }
+        let num_lines = ((last - first) as u32 + 1).min(Comb::MAX_LINES);
+        Some((first * bin_width - 1.0, num_lines))
+    }
+
+    /// Sets the height of each waterfall line.
+    ///
+    /// The height is given in CSS pixels. Taller lines make slow waterfalls
+    /// fill the screen sooner, at the cost of showing less history. The
+    /// height cannot be made smaller than the one that fits half of the
+    /// waterfall history on the screen, which is also used when `px` is zero
+    /// (the default).
+    pub fn set_line_height(&mut self, px: u32) {
+        self.line_height = px;
+        self.mark_dirty();
+    }
+
+    fn update_line_height_scale(&self, engine: &RenderEngine) {
+        let (_, height) = engine.canvas_dims().css_pixels();
+        self.uniforms
+            .line_height_scale
+            .set_data(Self::line_height_scale(self.line_height, height));
+    }
+
+    // Returns the factor by which the waterfall is stretched vertically to
+    // give each line a height of line_height CSS pixels on a canvas with the
+    // given height. Without stretching, the screen height spans half of the
+    // waterfall texture.
+    fn line_height_scale(line_height: u32, canvas_height: u32) -> f32 {
+        if line_height == 0 || canvas_height == 0 {
+            return 1.0;
+        }
+        let default_height = canvas_height as f32 / (0.5 * Self::TEXTURE_HEIGHT as f32);
+        (line_height as f32 / default_height).max(1.0)
+    }
+
+    /// Returns the time interval spanned by the height of the waterfall.
+    ///
+    /// The interval is given in seconds. It is computed from the waterfall
+    /// update rate, so `None` is returned if the rate has not been set yet.
+    pub fn get_screen_height_duration(&self) -> Option<f32> {
+        // Without stretching, the screen height spans half of the waterfall
+        // texture.
+        let lines = 0.5 * Self::TEXTURE_HEIGHT as f32 / self.uniforms.line_height_scale.get_data();
+        self.waterfall_rate
+            .filter(|&rate| rate > 0.0)
+            .map(|rate| lines / rate)
+    }
+
+    /// Sets the waterfall minimum power value.
+    ///
+    /// The minimum value is used to scale the colormap. The `value` is in dB
+    /// units.
+    pub fn set_waterfall_min(&mut self, value: f32) {
+        self.waterfall_min = value;
+        self.update_waterfall_scale();
+    }
+
+    /// Sets the waterfall maximum power value.
+    ///
+    /// The maximum value is used to scale the colormap. The `value` is in dB
+    /// units.
+    pub fn set_waterfall_max(&mut self, value: f32) {
+        self.waterfall_max = value;
+        self.update_waterfall_scale();
+    }
+
+    /// Sets the waterfall gamma.
+    ///
+    /// The gamma is applied to the power after it has been normalized to the
+    /// range between the waterfall minimum and maximum power values, and
+    /// before applying the colormap. Values larger than one emphasize strong
+    /// signals, and values smaller than one emphasize weak signals. A value of
+    /// one gives a linear mapping between dB and the colormap.
+    pub fn set_waterfall_gamma(&mut self, gamma: f32) {
+        self.uniforms.waterfall_gamma.set_data(gamma);
+        self.mark_dirty();
+    }
+
+    /// Sets the waterfall persistence.
+    ///
+    /// Persistence gives a phosphor-like effect in which strong signals fade
+    /// out over several lines of the waterfall. The `factor` is the fraction
+    /// of the power of a spectrum line that persists after one second, and it
+    /// is clamped to the range `[0, 0.99]`. A value of zero (the default)
+    /// disables persistence, giving the normal scrolling waterfall.
+    ///
+    /// Since the decay is applied according to the time between spectrum
+    /// lines, the duration of the fade does not depend on the waterfall update
+    /// rate. Persistence only affects the lines added after changing it.
+    pub fn set_waterfall_persistence(&mut self, factor: f32) {
+        self.persistence = if factor.is_finite() {
+            factor.clamp(0.0, 0.99)
+        } else {
+            0.0
+        };
+    }
+
+    /// Enables or disables spectral interpolation.
+    ///
+    /// When spectral interpolation is enabled, the waterfall is drawn by
+    /// linearly interpolating between adjacent FFT bins, so that the display
+    /// looks smoother when zoomed in past the FFT resolution. This does not
+    /// increase the actual frequency resolution of the waterfall. When it is
+    /// disabled (the default), each FFT bin is drawn with a constant value.
+    pub fn set_spectral_interpolation(&mut self, enable: bool) {
+        self.uniforms
+            .spectral_interpolation
+            .set_data(i32::from(enable));
+        self.mark_dirty();
+    }
+
+    /// Enables or disables colormap interpolation in linear light.
+    ///
+    /// The colormaps are tables of sRGB colors, and the power values that fall
+    /// between two entries of the table are given a color interpolated from
+    /// both entries. When interpolation in linear light is enabled, the
+    /// entries are converted to linear light, interpolated, and converted back
+    /// to sRGB. Since the sRGB encoding is not linear, this gives blends that
+    /// are slightly brighter and more even than interpolating the sRGB values
+    /// directly, which reduces banding and hue shifts in steep parts of the
+    /// colormap. The difference is subtle, and it is most visible with
+    /// colormaps that have few entries. When it is disabled (the default), the
+    /// sRGB values are interpolated directly.
+    pub fn set_linear_light_colormap(&mut self, enable: bool) {
+        self.uniforms
+            .linear_light_colormap
+            .set_data(i32::from(enable));
+        self.mark_dirty();
+    }
+
+    /// Sets the color of the values above the waterfall maximum.
+    ///
+    /// By default, the values above the maximum are drawn with the color of
+    /// the top of the colormap, so saturated signals are not distinguished
+    /// from signals at the maximum. Setting a color, given as RGB with
+    /// components between 0 and 1, draws these values with that color
+    /// instead, which makes overloaded FFT bins stand out. Passing `None`
+    /// restores the default.
+    pub fn set_over_range_color(&mut self, color: Option<(f32, f32, f32)>) {
+        self.uniforms
+            .over_range_color
+            .set_data(Self::range_color_uniform(color));
+        self.mark_dirty();
+    }
+
+    /// Sets the color of the values below the waterfall minimum.
+    ///
+    /// This works as [`set_over_range_color`](Waterfall::set_over_range_color),
+    /// but for the values that are drawn with the color of the bottom of the
+    /// colormap by default.
+    pub fn set_under_range_color(&mut self, color: Option<(f32, f32, f32)>) {
+        self.uniforms
+            .under_range_color
+            .set_data(Self::range_color_uniform(color));
+        self.mark_dirty();
+    }
+
+    fn range_color_uniform(color: Option<(f32, f32, f32)>) -> (f32, f32, f32, f32) {
+        match color {
+            Some((r, g, b)) => (r, g, b, 1.0),
+            None => (0.0, 0.0, 0.0, 0.0),
+        }
+    }
+
+    /// Returns the power at the RX LO frequency.
+    ///
+    /// The power is taken from the FFT bin that contains the RX LO frequency
+    /// in the last spectrum line added to the waterfall, before applying
+    /// persistence. It is given in dB units, using the same scale as the
+    /// waterfall minimum and maximum power values. If no spectrum lines have
+    /// been added yet, `None` is returned.
+    pub fn get_rx_lo_power(&self) -> Option<f32> {
+        self.rx_lo_power.map(Self::texture_value_to_db)
+    }
+
+    /// Returns the current noise floor estimate.
+    ///
+    /// The noise floor is estimated from the spectrum lines that are added to
+    /// the waterfall, and it is smoothed over time to avoid jitter. The
+    /// estimate is given in dB units, using the same scale as the waterfall
+    /// minimum and maximum power values. If no spectrum lines have been added
+    /// yet, `None` is returned.
+    pub fn get_noise_floor(&self) -> Option<f32> {
+        self.noise_floor.estimate().map(Self::texture_value_to_db)
+    }
+
+    /// Returns the current noise floor estimate without the power correction.
+    ///
+    /// This works as [`get_noise_floor`](Waterfall::get_noise_floor), but the
+    /// estimate does not include the correction set with
+    /// [`set_power_correction`](Waterfall::set_power_correction), so it
+    /// reflects the power at the ADC. This is used to detect ADC clipping.
+    pub fn get_uncorrected_noise_floor(&self) -> Option<f32> {
+        if self.power_correction.is_none() {
+            return self.get_noise_floor();
+        }
+        self.uncorrected_noise_floor
+            .estimate()
+            .map(Self::texture_value_to_db)
+    }
+
+    /// Computes waterfall levels that fit the visible spectrum.
+    ///
+    /// The levels are computed from the part of the last few spectrum lines
+    /// that is visible with the current view. The minimum level is placed
+    /// slightly below the noise floor and the maximum level slightly above
+    /// the strongest signal. The levels are returned in dB, using the same
+    /// scale as the waterfall minimum and maximum power values, but they are
+    /// not applied. If no spectrum lines have been added yet, `None` is
+    /// returned.
+    pub fn compute_auto_range(&self) -> Option<(f32, f32)> {
+        // Number of spectrum lines used to compute the levels
+        const NUM_LINES: usize = 8;
+        let visible = self.visible_bins();
+        let excluded = Self::dc_exclusion_range(Self::TEXTURE_WIDTH, self.dc_exclusion_bins);
+        let mut values = Vec::new();
+        for j in 0..self.num_lines.min(NUM_LINES) {
+            let line = (self.current_draw_line + Self::TEXTURE_HEIGHT - j) % Self::TEXTURE_HEIGHT;
+            let line =
+                &self.texture_map[line * Self::TEXTURE_WIDTH..(line + 1) * Self::TEXTURE_WIDTH];
+            values.extend(
+                visible
+                    .clone()
+                    .filter(|bin| !excluded.contains(bin))
+                    .map(|bin| Self::texture_value_to_db(line[bin])),
+            );
+        }
+        auto_range::levels(&mut values)
+    }
+
+    /// Sets the number of FFT bins around DC excluded from the measurements.
+    ///
+    /// The DC bin usually contains a large spike caused by the LO leakage and
+    /// the DC offset of the receiver, which can skew the measurements that
+    /// combine many bins: the noise floor estimate, the auto range levels, the
+    /// peak tracker and the stitching of scans (see
+    /// [`get_dc_exclusion_bins`](Waterfall::get_dc_exclusion_bins)). A value of
+    /// 0 disables the exclusion, 1 excludes only the DC bin, and larger values
+    /// also exclude `bins - 1` neighbors at each side of the DC bin.
+    ///
+    /// The exclusion only affects these measurements. The DC bin is still
+    /// shown in the waterfall, and the power readouts at a single frequency,
+    /// such as the measurement cursors and the power history, still read it.
+    pub fn set_dc_exclusion_bins(&mut self, bins: u32) {
+        self.dc_exclusion_bins = bins;
+    }
+
+    /// Returns the number of FFT bins around DC excluded from the
+    /// measurements.
+    ///
+    /// See [`set_dc_exclusion_bins`](Waterfall::set_dc_exclusion_bins).
+    pub fn get_dc_exclusion_bins(&self) -> u32 {
+        self.dc_exclusion_bins
+    }
+
+    // Range of FFT bins excluded from the measurements in a spectrum with
+    // num_bins bins, in which the DC bin is num_bins / 2.
+    pub(crate) fn dc_exclusion_range(num_bins: usize, bins: u32) -> std::ops::Range<usize> {
+        let dc = num_bins / 2;
+        if bins == 0 || num_bins == 0 {
+            return dc..dc;
+        }
+        let neighbors = (bins - 1) as usize;
+        dc.saturating_sub(neighbors)..(dc + neighbors + 1).min(num_bins)
+    }
+
+    // Returns the range of FFT bins that is visible with the current view.
+    fn visible_bins(&self) -> std::ops::RangeInclusive<usize> {
+        let bin_width = 2.0 / Self::TEXTURE_WIDTH as f32;
+        let zoom = self.get_zoom();
+        let center = self.get_center_frequency();
+        let bin = |freq: f32| (((freq + 1.0) / bin_width) as usize).min(Self::TEXTURE_WIDTH - 1);
+        bin(center - 1.0 / zoom)..=bin(center + 1.0 / zoom)
+    }
+
+    // Converts a value stored in the waterfall texture to dB. This is
+    // consistent with the scaling done in update_waterfall_scale.
+    fn texture_value_to_db(value: f32) -> f32 {
+        10.0 * value
     }
 
     fn update_waterfall_scale(&mut self) {
@@ -675,18 +3080,436 @@ impl Waterfall {
         self.uniforms
             .waterfall_scale_mult
             .set_data(10.0 / (self.waterfall_max - self.waterfall_min));
+        self.mark_dirty();
     }
 
     /// Sets the waterfall update rate.
     ///
-    /// The waterfall update rate is used for smooth animation interpolation
-    /// between waterfall lines. If the rate is not set, smooth animation
-    /// interpolation is not used.
+    /// The waterfall update rate is used for smooth scrolling between
+    /// waterfall lines until the interval between lines has been measured.
     ///
     /// The rate is indicated in Hz (updates per second).
     pub fn set_waterfall_update_rate(&mut self, rate: f32) {
+        if self.waterfall_rate != Some(rate) {
+            // The measured interval is not valid for the new rate.
+            self.line_timing = LineTiming::default();
+        }
         self.waterfall_rate = Some(rate);
     }
+
+    /// Enables or disables smooth scrolling.
+    ///
+    /// When smooth scrolling is enabled, the waterfall scrolls continuously
+    /// by fractions of a line between the arrival of spectrum lines, instead
+    /// of moving a whole line when each spectrum line arrives. This gives a
+    /// smoother display when lines arrive slower than the display refresh
+    /// rate. When it is disabled (the default), the lines are always drawn at
+    /// exact positions, which is better for measurements.
+    pub fn set_smooth_scroll(&mut self, enable: bool) {
+        self.smooth_scroll = enable;
+    }
+
+    // Nominal interval between spectrum lines in milliseconds, according to
+    // the waterfall update rate.
+    fn nominal_line_interval(&self) -> Option<f32> {
+        self.waterfall_rate
+            .filter(|&rate| rate > 0.0)
+            .map(|rate| 1e3 / rate)
+    }
+}
+
+impl Markers {
+    const RX_LO_COLOR: (f32, f32, f32, f32) = (1.0, 0.25, 0.25, 1.0);
+
+    fn new() -> Markers {
+        let markers = Markers {
+            rx_lo: Marker::new(Self::RX_LO_COLOR),
+            peak: Marker::new((0.25, 1.0, 0.25, 1.0)),
+            cursors: [
+                Marker::new((0.25, 0.85, 1.0, 1.0)),
+                Marker::new((1.0, 0.85, 0.25, 1.0)),
+            ],
+        };
+        // The peak marker is only shown while a peak is tracked, and the
+        // cursors only once they are placed
+        markers.peak.set_visible(false);
+        for cursor in &markers.cursors {
+            cursor.set_visible(false);
+        }
+        markers
+    }
+}
+
+impl Marker {
+    const NUM_INDICES: u32 = 2;
+
+    // The color is given as premultiplied RGBA.
+    fn new(color: (f32, f32, f32, f32)) -> Marker {
+        Marker {
+            frequency: Rc::new(Uniform::new(String::from("uMarkerFreq"), 0.0)),
+            color: Rc::new(Uniform::new(String::from("uMarkerColor"), color)),
+            num_indices: Rc::new(Cell::new(Self::NUM_INDICES)),
+        }
+    }
+
+    fn set_visible(&self, visible: bool) {
+        self.num_indices
+            .set(if visible { Self::NUM_INDICES } else { 0 });
+    }
+
+    fn render_object(
+        &self,
+        program: &Rc<WebGlProgram>,
+        vao: &Rc<WebGlVertexArrayObject>,
+        uniforms: &Uniforms,
+    ) -> RenderObject {
+        RenderObject {
+            program: Rc::clone(program),
+            vao: Rc::clone(vao),
+            draw_mode: DrawMode::Lines,
+            draw_num_indices: Rc::clone(&self.num_indices),
+            draw_offset_elements: Rc::new(Cell::new(0)),
+            uniforms: Box::new([
+                Rc::clone(&uniforms.center_freq) as _,
+                Rc::clone(&uniforms.zoom) as _,
+                Rc::clone(&uniforms.axis_direction) as _,
+                Rc::clone(&self.frequency) as _,
+                Rc::clone(&self.color) as _,
+            ]),
+            textures: Box::new([]),
+        }
+    }
+}
+
+impl MarkerFlash {
+    // Duration of each pulse in milliseconds.
+    const PULSE_MS: f64 = 300.0;
+
+    // Color of the marker at the time `now`, given its normal color. This
+    // returns None once the flash has finished.
+    fn color_at(&self, base: (f32, f32, f32, f32), now: f64) -> Option<(f32, f32, f32, f32)> {
+        let elapsed = (now - self.start) / Self::PULSE_MS;
+        if !elapsed.is_finite() || elapsed >= f64::from(self.pulses) {
+            return None;
+        }
+        // The timestamp of the frame can be slightly earlier than the start.
+        let elapsed = elapsed.max(0.0);
+        // Each pulse starts at the flash color and fades out quadratically.
+        let t = (1.0 - elapsed.fract() as f32).powi(2);
+        Some((
+            lerp(base.0, self.color.0, t),
+            lerp(base.1, self.color.1, t),
+            lerp(base.2, self.color.2, t),
+            lerp(base.3, self.color.3, t),
+        ))
+    }
+}
+
+impl Ruler {
+    const NUM_INDICES: u32 = 2;
+
+    fn new() -> Ruler {
+        Ruler {
+            start: Rc::new(Uniform::new(String::from("uRulerStart"), (0.0, 0.0))),
+            end: Rc::new(Uniform::new(String::from("uRulerEnd"), (0.0, 0.0))),
+            // The ruler is hidden initially
+            num_indices: Rc::new(Cell::new(0)),
+        }
+    }
+
+    fn render_object(
+        &self,
+        program: &Rc<WebGlProgram>,
+        vao: &Rc<WebGlVertexArrayObject>,
+        uniforms: &Uniforms,
+    ) -> RenderObject {
+        RenderObject {
+            program: Rc::clone(program),
+            vao: Rc::clone(vao),
+            draw_mode: DrawMode::Lines,
+            draw_num_indices: Rc::clone(&self.num_indices),
+            draw_offset_elements: Rc::new(Cell::new(0)),
+            uniforms: Box::new([
+                Rc::clone(&uniforms.center_freq) as _,
+                Rc::clone(&uniforms.zoom) as _,
+                Rc::clone(&uniforms.axis_direction) as _,
+                Rc::clone(&self.start) as _,
+                Rc::clone(&self.end) as _,
+            ]),
+            textures: Box::new([]),
+        }
+    }
+}
+
+impl BinHighlight {
+    const NUM_INDICES: u32 = 6;
+    // Faint white
+    const COLOR: (f32, f32, f32, f32) = (0.2, 0.2, 0.2, 0.2);
+    // Faint cyan, which distinguishes the passband from the bin highlight
+    const PASSBAND_COLOR: (f32, f32, f32, f32) = (0.0, 0.15, 0.15, 0.15);
+
+    fn new(color: (f32, f32, f32, f32)) -> BinHighlight {
+        BinHighlight {
+            edges: Rc::new(Uniform::new(String::from("uBinEdges"), (0.0, 0.0))),
+            color: Rc::new(Uniform::new(String::from("uHighlightColor"), color)),
+            min_half_width: Rc::new(Uniform::new(String::from("uMinHalfWidth"), 0.0)),
+            // The highlight is hidden initially
+            num_indices: Rc::new(Cell::new(0)),
+        }
+    }
+
+    fn render_object(
+        &self,
+        program: &Rc<WebGlProgram>,
+        vao: &Rc<WebGlVertexArrayObject>,
+        uniforms: &Uniforms,
+    ) -> RenderObject {
+        RenderObject {
+            program: Rc::clone(program),
+            vao: Rc::clone(vao),
+            draw_mode: DrawMode::Triangles,
+            draw_num_indices: Rc::clone(&self.num_indices),
+            draw_offset_elements: Rc::new(Cell::new(0)),
+            uniforms: Box::new([
+                Rc::clone(&uniforms.center_freq) as _,
+                Rc::clone(&uniforms.zoom) as _,
+                Rc::clone(&uniforms.axis_direction) as _,
+                Rc::clone(&self.edges) as _,
+                Rc::clone(&self.color) as _,
+                Rc::clone(&self.min_half_width) as _,
+            ]),
+            textures: Box::new([]),
+        }
+    }
+}
+
+impl EdgeMask {
+    const NUM_INDICES: u32 = 6;
+
+    fn new() -> EdgeMask {
+        EdgeMask {
+            width: Rc::new(Uniform::new(String::from("uMaskWidth"), 0.0)),
+            sides: [
+                Rc::new(Uniform::new(String::from("uMaskSide"), -1.0)),
+                Rc::new(Uniform::new(String::from("uMaskSide"), 1.0)),
+            ],
+            // The mask is hidden initially
+            num_indices: Rc::new(Cell::new(0)),
+        }
+    }
+
+    // Each band is drawn as a separate render object, which only differs in
+    // the side uniform.
+    fn render_objects(
+        &self,
+        program: &Rc<WebGlProgram>,
+        vao: &Rc<WebGlVertexArrayObject>,
+        uniforms: &Uniforms,
+    ) -> [RenderObject; 2] {
+        self.sides.clone().map(|side| RenderObject {
+            program: Rc::clone(program),
+            vao: Rc::clone(vao),
+            draw_mode: DrawMode::Triangles,
+            draw_num_indices: Rc::clone(&self.num_indices),
+            draw_offset_elements: Rc::new(Cell::new(0)),
+            uniforms: Box::new([
+                Rc::clone(&uniforms.center_freq) as _,
+                Rc::clone(&uniforms.zoom) as _,
+                Rc::clone(&uniforms.axis_direction) as _,
+                Rc::clone(&self.width) as _,
+                side as _,
+            ]),
+            textures: Box::new([]),
+        })
+    }
+}
+
+impl RetuneProgress {
+    const NUM_INDICES: u32 = 6;
+
+    fn new() -> RetuneProgress {
+        RetuneProgress {
+            side: Rc::new(Uniform::new(String::from("uEdgeSide"), 1.0)),
+            progress: Rc::new(Uniform::new(String::from("uProgress"), 0.0)),
+            // The bar is hidden initially
+            num_indices: Rc::new(Cell::new(0)),
+        }
+    }
+
+    fn render_object(
+        &self,
+        program: &Rc<WebGlProgram>,
+        vao: &Rc<WebGlVertexArrayObject>,
+    ) -> RenderObject {
+        RenderObject {
+            program: Rc::clone(program),
+            vao: Rc::clone(vao),
+            draw_mode: DrawMode::Triangles,
+            draw_num_indices: Rc::clone(&self.num_indices),
+            draw_offset_elements: Rc::new(Cell::new(0)),
+            uniforms: Box::new([Rc::clone(&self.side) as _, Rc::clone(&self.progress) as _]),
+            textures: Box::new([]),
+        }
+    }
+}
+
+impl LineWidthCheck {
+    // Checks the width of a new spectrum line against the expected width.
+    fn check(&mut self, width: usize, expected: usize) -> LineWidth {
+        if width != expected {
+            self.dropped += 1;
+            LineWidth::Mismatch {
+                first: self.dropped == 1,
+            }
+        } else if self.dropped != 0 {
+            LineWidth::Recovered {
+                dropped: std::mem::take(&mut self.dropped),
+            }
+        } else {
+            LineWidth::Valid
+        }
+    }
+}
+
+impl LineTiming {
+    // Weight of each new measurement in the smoothed interval
+    const SMOOTHING: f32 = 0.1;
+    // Measurements are limited to this factor above or below the nominal
+    // interval, so that a pause in the arrival of lines does not disturb
+    // the smoothed interval.
+    const MAX_DEVIATION: f32 = 2.0;
+
+    // Updates the interval with the time in milliseconds elapsed between two
+    // lines.
+    fn update(&mut self, elapsed: f32, nominal: Option<f32>) {
+        if !(elapsed.is_finite() && elapsed > 0.0) {
+            return;
+        }
+        let elapsed = match nominal {
+            Some(nominal) => {
+                elapsed.clamp(nominal / Self::MAX_DEVIATION, nominal * Self::MAX_DEVIATION)
+            }
+            None => elapsed,
+        };
+        self.interval = Some(match self.interval {
+            Some(interval) => interval + Self::SMOOTHING * (elapsed - interval),
+            None => elapsed,
+        });
+    }
+
+    // Returns the number of lines between 0.0 and 1.0 that should have
+    // elapsed after the time in milliseconds since the last line arrived, or
+    // None if the interval between lines is not known.
+    fn elapsed_lines(&self, elapsed: f32, nominal: Option<f32>) -> Option<f32> {
+        let interval = self.interval.or(nominal).filter(|&x| x > 0.0)?;
+        Some((elapsed / interval).clamp(0.0, 1.0))
+    }
+}
+
+impl Comb {
+    const MAX_LINES: u32 = 1024;
+
+    fn new() -> Comb {
+        Comb {
+            origin: 0,
+            spacing: 0,
+            count: 0,
+            start: Rc::new(Uniform::new(String::from("uCombStart"), 0.0)),
+            step: Rc::new(Uniform::new(String::from("uCombStep"), 0.0)),
+            // The comb is hidden initially
+            num_indices: Rc::new(Cell::new(0)),
+        }
+    }
+
+    fn render_object(
+        &self,
+        program: &Rc<WebGlProgram>,
+        vao: &Rc<WebGlVertexArrayObject>,
+        uniforms: &Uniforms,
+    ) -> RenderObject {
+        RenderObject {
+            program: Rc::clone(program),
+            vao: Rc::clone(vao),
+            draw_mode: DrawMode::Lines,
+            draw_num_indices: Rc::clone(&self.num_indices),
+            draw_offset_elements: Rc::new(Cell::new(0)),
+            uniforms: Box::new([
+                Rc::clone(&uniforms.center_freq) as _,
+                Rc::clone(&uniforms.zoom) as _,
+                Rc::clone(&uniforms.axis_direction) as _,
+                Rc::clone(&self.start) as _,
+                Rc::clone(&self.step) as _,
+            ]),
+            textures: Box::new([]),
+        }
+    }
+}
+
+impl BinGrid {
+    fn new() -> BinGrid {
+        BinGrid {
+            enabled: false,
+            start: Rc::new(Uniform::new(String::from("uBinGridStart"), 0.0)),
+            step: Rc::new(Uniform::new(String::from("uBinGridStep"), 0.0)),
+            // The grid is hidden initially
+            num_indices: Rc::new(Cell::new(0)),
+        }
+    }
+
+    fn render_object(
+        &self,
+        program: &Rc<WebGlProgram>,
+        vao: &Rc<WebGlVertexArrayObject>,
+        uniforms: &Uniforms,
+    ) -> RenderObject {
+        RenderObject {
+            program: Rc::clone(program),
+            vao: Rc::clone(vao),
+            draw_mode: DrawMode::Lines,
+            draw_num_indices: Rc::clone(&self.num_indices),
+            draw_offset_elements: Rc::new(Cell::new(0)),
+            uniforms: Box::new([
+                Rc::clone(&uniforms.center_freq) as _,
+                Rc::clone(&uniforms.zoom) as _,
+                Rc::clone(&uniforms.axis_direction) as _,
+                Rc::clone(&self.start) as _,
+                Rc::clone(&self.step) as _,
+            ]),
+            textures: Box::new([]),
+        }
+    }
+}
+
+impl Reticle {
+    const NUM_INDICES: u32 = 4;
+
+    fn new() -> Reticle {
+        Reticle {
+            half_size: Rc::new(Uniform::new(String::from("uReticleHalfSize"), (0.0, 0.0))),
+            color: Rc::new(Uniform::new(
+                String::from("uReticleColor"),
+                (1.0, 1.0, 1.0, 1.0),
+            )),
+            // The reticle is hidden initially
+            num_indices: Rc::new(Cell::new(0)),
+        }
+    }
+
+    fn render_object(
+        &self,
+        program: &Rc<WebGlProgram>,
+        vao: &Rc<WebGlVertexArrayObject>,
+    ) -> RenderObject {
+        RenderObject {
+            program: Rc::clone(program),
+            vao: Rc::clone(vao),
+            draw_mode: DrawMode::Lines,
+            draw_num_indices: Rc::clone(&self.num_indices),
+            draw_offset_elements: Rc::new(Cell::new(0)),
+            uniforms: Box::new([Rc::clone(&self.half_size) as _, Rc::clone(&self.color) as _]),
+            textures: Box::new([]),
+        }
+    }
 }
 
 impl Textures {
@@ -752,10 +3575,26 @@ impl Uniforms {
     fn new() -> Uniforms {
         Uniforms {
             time_translation: Rc::new(Uniform::new(String::from("uTimeTranslation"), 0.0)),
+            line_height_scale: Rc::new(Uniform::new(String::from("uLineHeightScale"), 1.0)),
             center_freq: Rc::new(Uniform::new(String::from("uCenterFreq"), 0.0)),
             zoom: Rc::new(Uniform::new(String::from("uZoom"), 1.0)),
+            axis_direction: Rc::new(Uniform::new(String::from("uAxisDirection"), 1.0)),
             waterfall_scale_add: Rc::new(Uniform::new(String::from("uWaterfallScaleAdd"), 0.0)),
             waterfall_scale_mult: Rc::new(Uniform::new(String::from("uWaterfallScaleMult"), 0.0)),
+            spectral_interpolation: Rc::new(Uniform::new(
+                String::from("uSpectralInterpolation"),
+                0,
+            )),
+            linear_light_colormap: Rc::new(Uniform::new(String::from("uLinearLightColormap"), 0)),
+            over_range_color: Rc::new(Uniform::new(
+                String::from("uOverRangeColor"),
+                (0.0, 0.0, 0.0, 0.0),
+            )),
+            under_range_color: Rc::new(Uniform::new(
+                String::from("uUnderRangeColor"),
+                (0.0, 0.0, 0.0, 0.0),
+            )),
+            waterfall_gamma: Rc::new(Uniform::new(String::from("uWaterfallGamma"), 1.0)),
             freq_labels_width: Rc::new(Uniform::new(
                 String::from("uLabelWidth"),
                 Default::default(),
@@ -774,10 +3613,17 @@ impl Uniforms {
     fn waterfall_uniforms(&self) -> Box<[Rc<dyn UniformValue>]> {
         Box::new([
             Rc::clone(&self.time_translation) as _,
+            Rc::clone(&self.line_height_scale) as _,
             Rc::clone(&self.center_freq) as _,
             Rc::clone(&self.zoom) as _,
+            Rc::clone(&self.axis_direction) as _,
             Rc::clone(&self.waterfall_scale_add) as _,
             Rc::clone(&self.waterfall_scale_mult) as _,
+            Rc::clone(&self.spectral_interpolation) as _,
+            Rc::clone(&self.linear_light_colormap) as _,
+            Rc::clone(&self.over_range_color) as _,
+            Rc::clone(&self.under_range_color) as _,
+            Rc::clone(&self.waterfall_gamma) as _,
         ])
     }
 
@@ -785,6 +3631,7 @@ impl Uniforms {
         Box::new([
             Rc::clone(&self.center_freq) as _,
             Rc::clone(&self.zoom) as _,
+            Rc::clone(&self.axis_direction) as _,
             Rc::clone(&self.major_ticks_end) as _,
         ])
     }
@@ -793,6 +3640,7 @@ impl Uniforms {
         Box::new([
             Rc::clone(&self.center_freq) as _,
             Rc::clone(&self.zoom) as _,
+            Rc::clone(&self.axis_direction) as _,
             Rc::clone(&self.freq_labels_width) as _,
             Rc::clone(&self.freq_labels_height) as _,
         ])
@@ -804,3 +3652,284 @@ impl Default for Uniforms {
         Uniforms::new()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn clamp_zoom_boundaries() {
+        assert_eq!(Waterfall::clamp_zoom(Waterfall::MIN_ZOOM), 1.0);
+        assert_eq!(Waterfall::clamp_zoom(Waterfall::MAX_ZOOM), 128.0);
+        assert_eq!(Waterfall::clamp_zoom(0.5), 1.0);
+        assert_eq!(Waterfall::clamp_zoom(-3.0), 1.0);
+        assert_eq!(Waterfall::clamp_zoom(1000.0), 128.0);
+        assert_eq!(Waterfall::clamp_zoom(4.0), 4.0);
+    }
+
+    #[test]
+    fn clamp_center_frequency_boundaries() {
+        // At the minimum zoom the whole band is shown, so the view can only
+        // be centered.
+        assert_eq!(Waterfall::clamp_center_frequency(0.5, 1.0), 0.0);
+        assert_eq!(Waterfall::clamp_center_frequency(0.5, 2.0), 0.5);
+        assert_eq!(Waterfall::clamp_center_frequency(0.75, 2.0), 0.5);
+        assert_eq!(Waterfall::clamp_center_frequency(-0.75, 2.0), -0.5);
+        assert_eq!(Waterfall::clamp_center_frequency(0.25, 4.0), 0.25);
+        assert_eq!(Waterfall::clamp_center_frequency(0.25, 0.5), 0.0);
+    }
+
+    #[test]
+    fn clamp_non_finite() {
+        assert_eq!(Waterfall::clamp_zoom(f32::NAN), 1.0);
+        assert_eq!(Waterfall::clamp_zoom(f32::INFINITY), 128.0);
+        assert_eq!(Waterfall::clamp_zoom(f32::NEG_INFINITY), 1.0);
+        assert_eq!(Waterfall::clamp_center_frequency(f32::NAN, 2.0), 0.0);
+        assert_eq!(Waterfall::clamp_center_frequency(0.3, f32::NAN), 0.0);
+        assert_eq!(Waterfall::clamp_center_frequency(0.3, f32::INFINITY), 0.0);
+        assert_eq!(Waterfall::clamp_center_frequency(f32::INFINITY, 2.0), 0.5);
+        assert_eq!(
+            Waterfall::clamp_center_frequency(f32::NEG_INFINITY, 2.0),
+            -0.5
+        );
+    }
+
+    #[test]
+    fn line_width_mismatch() {
+        let mut check = LineWidthCheck::default();
+        assert_eq!(check.check(4096, 4096), LineWidth::Valid);
+        assert_eq!(check.check(2048, 4096), LineWidth::Mismatch { first: true });
+        assert_eq!(
+            check.check(2048, 4096),
+            LineWidth::Mismatch { first: false }
+        );
+        assert_eq!(check.check(0, 4096), LineWidth::Mismatch { first: false });
+        assert_eq!(check.check(4096, 4096), LineWidth::Recovered { dropped: 3 });
+        assert_eq!(check.check(4096, 4096), LineWidth::Valid);
+        assert_eq!(check.check(8192, 4096), LineWidth::Mismatch { first: true });
+        assert_eq!(check.check(4096, 4096), LineWidth::Recovered { dropped: 1 });
+    }
+
+    #[test]
+    fn hz_units_roundtrip() {
+        let samp_rate = 61.44e6;
+        let center_freq = Waterfall::actual_center_freq(2400e6, samp_rate);
+        // The DC bin is half a bin to the right of the center of the band.
+        let dc = Waterfall::hz_to_units(2400e6, center_freq, samp_rate);
+        assert!((dc - 1.0 / Waterfall::TEXTURE_WIDTH as f32).abs() < 1e-6);
+        assert_eq!(
+            Waterfall::units_to_hz(0.0, center_freq, samp_rate),
+            center_freq
+        );
+        assert_eq!(
+            Waterfall::units_to_hz(1.0, center_freq, samp_rate),
+            center_freq + 0.5 * samp_rate
+        );
+        for frequency in [2380e6, 2399.5e6, 2400e6, 2412.345e6, 2430.72e6] {
+            let units = Waterfall::hz_to_units(frequency, center_freq, samp_rate);
+            let back = Waterfall::units_to_hz(units, center_freq, samp_rate);
+            // The units are f32, so the precision is limited to a few Hz.
+            assert!((back - frequency).abs() < 10.0, "{frequency} -> {back}");
+        }
+    }
+
+    #[test]
+    fn units_to_bin() {
+        assert_eq!(Waterfall::units_to_bin(-1.0), Some(0));
+        assert_eq!(
+            Waterfall::units_to_bin(0.0),
+            Some(Waterfall::TEXTURE_WIDTH / 2)
+        );
+        assert_eq!(
+            Waterfall::units_to_bin(Waterfall::bin_to_units(1234)),
+            Some(1234)
+        );
+        assert_eq!(
+            Waterfall::units_to_bin(0.9999),
+            Some(Waterfall::TEXTURE_WIDTH - 1)
+        );
+        assert_eq!(Waterfall::units_to_bin(1.0), None);
+        assert_eq!(Waterfall::units_to_bin(-1.5), None);
+        assert_eq!(Waterfall::units_to_bin(f32::NAN), None);
+    }
+
+    #[test]
+    fn span_to_zoom() {
+        let samp_rate = 61.44e6;
+        assert_eq!(Waterfall::span_to_zoom(61.44e6, samp_rate), Some(1.0));
+        assert_eq!(Waterfall::span_to_zoom(7.68e6, samp_rate), Some(8.0));
+        // The zoom is clamped
+        assert_eq!(Waterfall::span_to_zoom(100e6, samp_rate), Some(1.0));
+        assert_eq!(Waterfall::span_to_zoom(1e3, samp_rate), Some(128.0));
+        assert_eq!(Waterfall::span_to_zoom(0.0, samp_rate), None);
+        assert_eq!(Waterfall::span_to_zoom(-1e6, samp_rate), None);
+        assert_eq!(Waterfall::span_to_zoom(f64::NAN, samp_rate), None);
+    }
+
+    #[test]
+    fn bin_grid_lines() {
+        let bin_width = 2.0 / Waterfall::TEXTURE_WIDTH as f32;
+        // The bins are too narrow at low zoom levels
+        assert_eq!(Waterfall::bin_grid_lines(0.0, 1.0, 1920), None);
+        assert_eq!(Waterfall::bin_grid_lines(0.0, 8.0, 1920), None);
+        assert_eq!(Waterfall::bin_grid_lines(0.0, 128.0, 0), None);
+        assert_eq!(Waterfall::bin_grid_lines(0.0, f32::NAN, 1920), None);
+        // At zoom 32 the view spans 128 bins, so there are 129 boundaries
+        // including both edges of the view.
+        let (start, num_lines) = Waterfall::bin_grid_lines(0.0, 32.0, 1920).unwrap();
+        assert_eq!(num_lines, 129);
+        assert!((start + 1.0 / 32.0).abs() < 1e-6);
+        // With an offset of half a bin, the edges of the view are not bin
+        // boundaries.
+        let center = 0.5 + 0.5 * bin_width;
+        let (start, num_lines) = Waterfall::bin_grid_lines(center, 32.0, 1920).unwrap();
+        assert_eq!(num_lines, 128);
+        assert!((start - (center - 1.0 / 32.0 + 0.5 * bin_width)).abs() < 1e-6);
+        // The view is at the upper band edge
+        let (start, num_lines) = Waterfall::bin_grid_lines(1.0 - 1.0 / 128.0, 128.0, 1920).unwrap();
+        assert_eq!(num_lines, 33);
+        assert!((start + 32.0 * bin_width - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn line_height_scale() {
+        // Zero selects the default height
+        assert_eq!(Waterfall::line_height_scale(0, 1024), 1.0);
+        assert_eq!(Waterfall::line_height_scale(4, 0), 1.0);
+        // With 1024 pixels, each line is 4 pixels tall by default
+        assert_eq!(Waterfall::line_height_scale(4, 1024), 1.0);
+        assert_eq!(Waterfall::line_height_scale(12, 1024), 3.0);
+        // Lines cannot be shorter than the default
+        assert_eq!(Waterfall::line_height_scale(1, 1024), 1.0);
+    }
+
+    #[test]
+    fn line_timing() {
+        let mut timing = LineTiming::default();
+        assert_eq!(timing.elapsed_lines(50.0, None), None);
+        // The nominal interval is used until lines are measured
+        assert_eq!(timing.elapsed_lines(50.0, Some(100.0)), Some(0.5));
+        assert_eq!(timing.elapsed_lines(150.0, Some(100.0)), Some(1.0));
+        // Lines arrive slower than the nominal rate
+        for _ in 0..100 {
+            timing.update(125.0, Some(100.0));
+        }
+        let elapsed = timing.elapsed_lines(62.5, Some(100.0)).unwrap();
+        assert!((elapsed - 0.5).abs() < 1e-3);
+        // A pause in the lines has a limited effect
+        timing.update(10e3, Some(100.0));
+        let interval = timing.interval.unwrap();
+        assert!(interval < 135.0);
+        timing.update(f32::NAN, Some(100.0));
+        assert_eq!(timing.interval, Some(interval));
+    }
+
+    #[test]
+    fn dc_exclusion_range() {
+        assert_eq!(Waterfall::dc_exclusion_range(4096, 0), 2048..2048);
+        assert_eq!(Waterfall::dc_exclusion_range(4096, 1), 2048..2049);
+        assert_eq!(Waterfall::dc_exclusion_range(4096, 3), 2046..2051);
+        // The range is limited to the spectrum
+        assert_eq!(Waterfall::dc_exclusion_range(10, 20), 0..10);
+        assert_eq!(Waterfall::dc_exclusion_range(0, 1), 0..0);
+    }
+
+    #[test]
+    fn passband_bins() {
+        let bin_width = 2.0 / Waterfall::TEXTURE_WIDTH as f32;
+        // The bins whose center lies in the passband are included
+        assert_eq!(
+            Waterfall::passband_bins((-0.25 * bin_width, 2.25 * bin_width)),
+            2048..2050
+        );
+        assert_eq!(
+            Waterfall::passband_bins((0.5 * bin_width, 2.5 * bin_width)),
+            2048..2051
+        );
+        // A passband between two bin centers does not contain any bins
+        assert_eq!(
+            Waterfall::passband_bins((0.6 * bin_width, 0.9 * bin_width)),
+            2049..2049
+        );
+        // The bins are limited to the band
+        assert_eq!(Waterfall::passband_bins((-2.0, -0.5)), 0..1024);
+        assert_eq!(
+            Waterfall::passband_bins((1.5, 2.0)),
+            Waterfall::TEXTURE_WIDTH..Waterfall::TEXTURE_WIDTH
+        );
+    }
+
+    #[test]
+    fn keep_visible_center() {
+        // The marker is inside the view and the margins
+        assert_eq!(Waterfall::keep_visible_center(0.0, 4.0, 0.1, 0.1), 0.0);
+        assert_eq!(Waterfall::keep_visible_center(0.0, 4.0, -0.2, 0.1), 0.0);
+        // The view is panned just enough to put the marker at the margin
+        let center = Waterfall::keep_visible_center(0.0, 4.0, 0.5, 0.1);
+        assert!((center - 0.3).abs() < 1e-6);
+        let center = Waterfall::keep_visible_center(0.0, 4.0, -0.5, 0.1);
+        assert!((center + 0.3).abs() < 1e-6);
+        // The view does not extend beyond the band edges
+        assert_eq!(Waterfall::keep_visible_center(0.0, 4.0, 0.99, 0.1), 0.75);
+        assert_eq!(Waterfall::keep_visible_center(0.0, 4.0, f32::NAN, 0.1), 0.0);
+    }
+
+    #[test]
+    fn rubber_band() {
+        assert_eq!(Waterfall::rubber_band(0.0, 0.1), 0.0);
+        // Short distances are barely compressed
+        assert!((Waterfall::rubber_band(1e-3, 0.1) - 1e-3).abs() < 1e-6);
+        assert!((Waterfall::rubber_band(-1e-3, 0.1) + 1e-3).abs() < 1e-6);
+        // Long distances do not exceed the limit
+        let long = Waterfall::rubber_band(10.0, 0.1);
+        assert!(long > 0.09 && long <= 0.1);
+        let long = Waterfall::rubber_band(-10.0, 0.1);
+        assert!(long < -0.09 && long >= -0.1);
+        // Without a limit there is no overscan
+        assert_eq!(Waterfall::rubber_band(0.5, 0.0), 0.0);
+        assert_eq!(Waterfall::rubber_band(f32::NAN, 0.1), 0.0);
+    }
+
+    #[test]
+    fn snapped_zoom() {
+        assert_eq!(Waterfall::snapped_zoom(1.0), 1.0);
+        assert_eq!(Waterfall::snapped_zoom(1.3), 1.0);
+        assert_eq!(Waterfall::snapped_zoom(1.5), 2.0);
+        assert_eq!(Waterfall::snapped_zoom(5.0), 4.0);
+        assert_eq!(Waterfall::snapped_zoom(6.0), 8.0);
+        assert_eq!(
+            Waterfall::snapped_zoom(Waterfall::MAX_ZOOM),
+            Waterfall::MAX_ZOOM
+        );
+        assert_eq!(Waterfall::snapped_zoom(f32::NAN), Waterfall::MIN_ZOOM);
+        for zoom in [Waterfall::MIN_ZOOM, Waterfall::MAX_ZOOM] {
+            assert_eq!(zoom.log2().fract(), 0.0);
+        }
+    }
+
+    #[test]
+    fn marker_flash() {
+        let base = (1.0, 0.25, 0.25, 1.0);
+        let flash = MarkerFlash {
+            color: (1.0, 1.0, 1.0, 1.0),
+            pulses: 2,
+            start: 1000.0,
+        };
+        // Each pulse starts at the flash color and fades out
+        assert_eq!(flash.color_at(base, 1000.0), Some(flash.color));
+        assert_eq!(
+            flash.color_at(base, 1000.0 + MarkerFlash::PULSE_MS),
+            Some(flash.color)
+        );
+        let (_, g, _, _) = flash
+            .color_at(base, 1000.0 + 0.5 * MarkerFlash::PULSE_MS)
+            .unwrap();
+        assert!(g > base.1 && g < flash.color.1);
+        // The flash finishes after the last pulse
+        assert_eq!(
+            flash.color_at(base, 1000.0 + 2.0 * MarkerFlash::PULSE_MS),
+            None
+        );
+        assert_eq!(flash.color_at(base, f64::NAN), None);
+    }
+}
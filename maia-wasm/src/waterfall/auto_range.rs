@@ -0,0 +1,57 @@
+//! Automatic selection of the waterfall levels.
+//!
+//! The minimum level is placed slightly below the noise floor, which is
+//! estimated as a low percentile of the power of the FFT bins (see
+//! [`noise_floor`]), and the maximum level slightly above the strongest bin.
+//! With these levels the noise is shown dark and the strongest signals are not
+//! saturated.
+
+use super::noise_floor::noise_floor;
+
+// Margin in dB between the noise floor and the minimum level.
+const MARGIN_BELOW_DB: f32 = 3.0;
+// Margin in dB between the strongest bin and the maximum level.
+const MARGIN_ABOVE_DB: f32 = 3.0;
+// Minimum difference in dB between the maximum and the minimum level.
+const MIN_SPAN_DB: f32 = 10.0;
+
+/// Computes the waterfall levels for some spectrum values.
+///
+/// The values are given in dB. Non-finite values are ignored. The minimum and
+/// maximum levels are returned in dB, or `None` if there are no finite values.
+/// The order of the values is modified.
+pub fn levels(values: &mut Vec<f32>) -> Option<(f32, f32)> {
+    values.retain(|x| x.is_finite());
+    let noise_floor = noise_floor(values)?;
+    let peak = values.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let min = noise_floor - MARGIN_BELOW_DB;
+    let max = (peak + MARGIN_ABOVE_DB).max(min + MIN_SPAN_DB);
+    Some((min, max))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn noise_and_signal() {
+        let mut values: Vec<f32> = (0..1000).map(|j| 40.0 + (j % 5) as f32).collect();
+        values[500] = 80.0;
+        values[10] = f32::NAN;
+        let (min, max) = levels(&mut values).unwrap();
+        assert_eq!(min, 38.0);
+        assert_eq!(max, 83.0);
+    }
+
+    #[test]
+    fn minimum_span() {
+        let mut values = vec![50.0; 100];
+        assert_eq!(levels(&mut values), Some((47.0, 57.0)));
+    }
+
+    #[test]
+    fn no_values() {
+        assert_eq!(levels(&mut Vec::new()), None);
+        assert_eq!(levels(&mut vec![f32::NAN, f32::INFINITY]), None);
+    }
+}
@@ -0,0 +1,85 @@
+//! Noise floor estimation.
+//!
+//! The noise floor is estimated as a low percentile of the power of the FFT
+//! bins in each spectrum line. This is robust against the presence of signals,
+//! as long as they do not occupy most of the band. The estimates obtained for
+//! each spectrum line are smoothed with a first order IIR filter.
+
+// Percentile of the FFT bins that is used as noise floor estimate.
+const PERCENTILE: f32 = 0.2;
+
+/// Estimates the noise floor of some spectrum values.
+///
+/// The estimate is the low percentile of the values used by
+/// [`NoiseFloorEstimator`], without any smoothing. The values should be
+/// finite, and their order is modified. If there are no values, `None` is
+/// returned.
+pub fn noise_floor(values: &mut [f32]) -> Option<f32> {
+    if values.is_empty() {
+        return None;
+    }
+    let n = ((values.len() - 1) as f32 * PERCENTILE).round() as usize;
+    let (_, &mut value, _) = values.select_nth_unstable_by(n, f32::total_cmp);
+    Some(value)
+}
+
+pub struct NoiseFloorEstimator {
+    scratch: Vec<f32>,
+    estimate: Option<f32>,
+    last_timestamp: Option<f32>,
+}
+
+impl NoiseFloorEstimator {
+    // Time constant of the IIR filter, in milliseconds.
+    const TIME_CONSTANT_MS: f32 = 2000.0;
+
+    pub fn new() -> NoiseFloorEstimator {
+        NoiseFloorEstimator {
+            scratch: Vec::new(),
+            estimate: None,
+            last_timestamp: None,
+        }
+    }
+
+    /// Updates the estimate with a new spectrum line.
+    ///
+    /// The FFT bins in the `excluded` range are not used. The `timestamp` is
+    /// given in milliseconds and it is used to apply the time constant of the
+    /// IIR filter.
+    pub fn update(&mut self, line: &[f32], excluded: std::ops::Range<usize>, timestamp: f32) {
+        self.scratch.clear();
+        self.scratch.extend(
+            line.iter()
+                .enumerate()
+                .filter(|(bin, x)| !excluded.contains(bin) && x.is_finite())
+                .map(|(_, &x)| x),
+        );
+        let Some(value) = noise_floor(&mut self.scratch) else {
+            return;
+        };
+        self.estimate = Some(match (self.estimate, self.last_timestamp) {
+            (Some(estimate), Some(last)) => {
+                let elapsed = (timestamp - last).max(0.0);
+                let alpha = 1.0 - (-elapsed / Self::TIME_CONSTANT_MS).exp();
+                estimate + alpha * (value - estimate)
+            }
+            _ => value,
+        });
+        self.last_timestamp = Some(timestamp);
+    }
+
+    /// Returns the current estimate.
+    ///
+    /// The estimate uses the same units as the spectrum lines given to
+    /// [`NoiseFloorEstimator::update`]. If no spectrum lines have been
+    /// processed yet, `None` is returned.
+    pub fn estimate(&self) -> Option<f32> {
+        self.estimate
+    }
+}
+
+impl Default for NoiseFloorEstimator {
+    fn default() -> NoiseFloorEstimator {
+        NoiseFloorEstimator::new()
+    }
+}
@@ -0,0 +1,167 @@
+//! Tracking of the strongest signal.
+//!
+//! The tracker follows the strongest peak in the visible part of the spectrum.
+//! To avoid jumping between peaks of similar power, the tracked peak is only
+//! abandoned when another peak is stronger by more than a hysteresis. While
+//! tracked, the peak is followed as it drifts in frequency. The tracker
+//! disengages when no peak is above the noise floor by a minimum SNR.
+
+pub struct PeakTracker {
+    // Power in dB by which another peak must be stronger than the tracked
+    // peak to move the tracking to it
+    hysteresis_db: f32,
+    // Minimum power in dB of a peak above the noise floor for the tracker to
+    // engage
+    min_snr_db: f32,
+    // FFT bin of the tracked peak
+    bin: Option<usize>,
+}
+
+impl PeakTracker {
+    // Number of FFT bins on each side of the tracked peak in which the peak is
+    // searched for in the next line, which allows following drifting signals.
+    const NEIGHBORHOOD: usize = 4;
+
+    pub fn new() -> PeakTracker {
+        PeakTracker {
+            hysteresis_db: 3.0,
+            min_snr_db: 10.0,
+            bin: None,
+        }
+    }
+
+    pub fn set_hysteresis(&mut self, hysteresis_db: f32) {
+        self.hysteresis_db = hysteresis_db;
+    }
+
+    pub fn set_min_snr(&mut self, min_snr_db: f32) {
+        self.min_snr_db = min_snr_db;
+    }
+
+    // Stops tracking the current peak.
+    pub fn reset(&mut self) {
+        self.bin = None;
+    }
+
+    // Returns the FFT bin of the tracked peak.
+    pub fn bin(&self) -> Option<usize> {
+        self.bin
+    }
+
+    /// Updates the tracker with a new spectrum line.
+    ///
+    /// The `line` contains the power of all the FFT bins in dB, and only the
+    /// bins in the `visible` range are searched. The `noise_floor` is given in
+    /// dB. Returns the FFT bin of the tracked peak, or `None` if the tracker
+    /// is disengaged.
+    pub fn update(
+        &mut self,
+        line: impl Fn(usize) -> f32,
+        visible: std::ops::RangeInclusive<usize>,
+        noise_floor: f32,
+    ) -> Option<usize> {
+        let argmax = |range: std::ops::RangeInclusive<usize>| {
+            range
+                .map(|bin| (bin, line(bin)))
+                .filter(|(_, power)| power.is_finite())
+                .max_by(|a, b| a.1.total_cmp(&b.1))
+        };
+        let Some((strongest, strongest_power)) = argmax(visible.clone()) else {
+            self.bin = None;
+            return None;
+        };
+        // The tracked peak is followed within its neighborhood, as long as it
+        // is still visible.
+        let tracked = self
+            .bin
+            .filter(|bin| visible.contains(bin))
+            .and_then(|bin| {
+                argmax(
+                    bin.saturating_sub(Self::NEIGHBORHOOD).max(*visible.start())
+                        ..=(bin + Self::NEIGHBORHOOD).min(*visible.end()),
+                )
+            });
+        let (bin, power) = match tracked {
+            Some((bin, power)) if power + self.hysteresis_db >= strongest_power => (bin, power),
+            _ => (strongest, strongest_power),
+        };
+        self.bin = Some(bin).filter(|_| power - noise_floor >= self.min_snr_db);
+        self.bin
+    }
+}
+
+impl Default for PeakTracker {
+    fn default() -> PeakTracker {
+        PeakTracker::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn tracker() -> PeakTracker {
+        let mut tracker = PeakTracker::new();
+        tracker.set_hysteresis(3.0);
+        tracker.set_min_snr(10.0);
+        tracker
+    }
+
+    fn line(peaks: &[(usize, f32)]) -> impl Fn(usize) -> f32 + '_ {
+        move |bin| {
+            peaks
+                .iter()
+                .find_map(|&(b, power)| Some(power).filter(|_| b == bin))
+                .unwrap_or(0.0)
+        }
+    }
+
+    #[test]
+    fn hysteresis() {
+        let mut tracker = tracker();
+        assert_eq!(tracker.update(line(&[(10, 30.0)]), 0..=99, 0.0), Some(10));
+        // A slightly stronger peak does not take over
+        assert_eq!(
+            tracker.update(line(&[(10, 30.0), (50, 32.0)]), 0..=99, 0.0),
+            Some(10)
+        );
+        // The tracked peak drifts
+        assert_eq!(
+            tracker.update(line(&[(12, 30.0), (50, 32.0)]), 0..=99, 0.0),
+            Some(12)
+        );
+        // A much stronger peak takes over
+        assert_eq!(
+            tracker.update(line(&[(12, 30.0), (50, 40.0)]), 0..=99, 0.0),
+            Some(50)
+        );
+        // The tracked peak leaves the visible range
+        assert_eq!(
+            tracker.update(line(&[(12, 30.0), (50, 40.0)]), 0..=20, 0.0),
+            Some(12)
+        );
+    }
+
+    #[test]
+    fn min_snr() {
+        let mut tracker = tracker();
+        assert_eq!(tracker.update(line(&[(10, 30.0)]), 0..=99, 25.0), None);
+        assert_eq!(tracker.update(line(&[(10, 30.0)]), 0..=99, 15.0), Some(10));
+        // The tracker disengages when the peak fades
+        assert_eq!(tracker.update(line(&[(10, 20.0)]), 0..=99, 15.0), None);
+        assert_eq!(tracker.bin(), None);
+        assert_eq!(tracker.update(|_| f32::NAN, 0..=99, 0.0), None);
+    }
+
+    #[test]
+    fn reset() {
+        let mut tracker = tracker();
+        tracker.update(line(&[(10, 30.0)]), 0..=99, 0.0);
+        tracker.reset();
+        assert_eq!(tracker.bin(), None);
+        assert_eq!(
+            tracker.update(line(&[(10, 30.0), (50, 32.0)]), 0..=99, 0.0),
+            Some(50)
+        );
+    }
+}
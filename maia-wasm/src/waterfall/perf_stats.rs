@@ -0,0 +1,191 @@
+//! Performance statistics.
+//!
+//! The statistics are collected from the render loop and from the spectrum
+//! lines given to the waterfall, and they are accumulated over a reporting
+//! period, which is ended by taking a report.
+
+use std::fmt;
+
+pub struct PerfStats {
+    period_start: f64,
+    frames: u32,
+    dropped_frames: u32,
+    lines: u32,
+    dropped_lines: u32,
+    latency_sum: f64,
+    latency_count: u32,
+    last_frame: Option<f64>,
+    // Shortest interval between frames seen, which estimates the display
+    // refresh interval, or the interval given by the maximum frame rate
+    frame_interval: Option<f64>,
+}
+
+/// Performance report.
+///
+/// The report summarizes the performance of the waterfall during a reporting
+/// period.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PerfReport {
+    /// Rendered frames per second.
+    pub fps: f64,
+    /// Number of frames that were expected to be rendered but were not.
+    pub dropped_frames: u32,
+    /// Spectrum lines received per second.
+    pub lines_per_second: f64,
+    /// Number of spectrum lines that were dropped because their number of FFT
+    /// bins did not match the waterfall.
+    pub dropped_lines: u32,
+    /// Average time in milliseconds between the reception of a spectrum line
+    /// and the rendering of the frame that shows it.
+    ///
+    /// This is `None` if no frames with new spectrum lines were rendered.
+    pub latency_ms: Option<f64>,
+}
+
+impl PerfStats {
+    // An interval between frames longer than this, in milliseconds, is
+    // assumed to happen because the page was hidden, rather than due to
+    // dropped frames.
+    const MAX_FRAME_GAP: f64 = 1000.0;
+    // A frame is counted as dropped once the interval since the previous frame
+    // exceeds the frame interval by this factor.
+    const DROP_THRESHOLD: f64 = 1.5;
+
+    // Creates new statistics. The timestamp is the start of the first
+    // reporting period, in milliseconds.
+    pub fn new(timestamp: f64) -> PerfStats {
+        PerfStats {
+            period_start: timestamp,
+            frames: 0,
+            dropped_frames: 0,
+            lines: 0,
+            dropped_lines: 0,
+            latency_sum: 0.0,
+            latency_count: 0,
+            last_frame: None,
+            frame_interval: None,
+        }
+    }
+
+    // Records a rendered frame. The latency is the time since the newest
+    // spectrum line shown in the frame was received, if the frame shows new
+    // lines.
+    pub fn frame(&mut self, timestamp: f64, latency: Option<f64>) {
+        self.frames += 1;
+        if let Some(latency) = latency {
+            self.latency_sum += latency.max(0.0);
+            self.latency_count += 1;
+        }
+        if let Some(last) = self.last_frame.replace(timestamp) {
+            let interval = timestamp - last;
+            if interval <= 0.0 || interval > Self::MAX_FRAME_GAP {
+                return;
+            }
+            let frame_interval = self
+                .frame_interval
+                .map_or(interval, |frame_interval| frame_interval.min(interval));
+            self.frame_interval = Some(frame_interval);
+            if interval > Self::DROP_THRESHOLD * frame_interval {
+                self.dropped_frames += (interval / frame_interval).round() as u32 - 1;
+            }
+        }
+    }
+
+    pub fn line(&mut self) {
+        self.lines += 1;
+    }
+
+    pub fn dropped_line(&mut self) {
+        self.dropped_lines += 1;
+    }
+
+    // Ends the reporting period and returns its report. A new reporting
+    // period starts at the timestamp, in milliseconds.
+    pub fn report(&mut self, timestamp: f64) -> PerfReport {
+        let elapsed_secs = (timestamp - self.period_start) * 1e-3;
+        let rate = |count: u32| {
+            if elapsed_secs > 0.0 {
+                f64::from(count) / elapsed_secs
+            } else {
+                0.0
+            }
+        };
+        let report = PerfReport {
+            fps: rate(self.frames),
+            dropped_frames: self.dropped_frames,
+            lines_per_second: rate(self.lines),
+            dropped_lines: self.dropped_lines,
+            latency_ms: Some(self.latency_sum / f64::from(self.latency_count))
+                .filter(|_| self.latency_count != 0),
+        };
+        *self = PerfStats {
+            last_frame: self.last_frame,
+            frame_interval: self.frame_interval,
+            ..PerfStats::new(timestamp)
+        };
+        report
+    }
+}
+
+impl fmt::Display for PerfReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{:.1} fps ({} dropped frames)",
+            self.fps, self.dropped_frames
+        )?;
+        writeln!(
+            f,
+            "{:.1} lines/s ({} dropped lines)",
+            self.lines_per_second, self.dropped_lines
+        )?;
+        match self.latency_ms {
+            Some(latency) => write!(f, "latency {latency:.1} ms"),
+            None => write!(f, "latency -"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rates() {
+        let mut stats = PerfStats::new(0.0);
+        for j in 0..60 {
+            stats.frame(f64::from(j) * 1e3 / 60.0, Some(5.0));
+        }
+        for _ in 0..20 {
+            stats.line();
+        }
+        stats.dropped_line();
+        let report = stats.report(2000.0);
+        assert_eq!(report.fps, 30.0);
+        assert_eq!(report.lines_per_second, 10.0);
+        assert_eq!(report.dropped_frames, 0);
+        assert_eq!(report.dropped_lines, 1);
+        assert_eq!(report.latency_ms, Some(5.0));
+        // The counts start again in the next period
+        let report = stats.report(3000.0);
+        assert_eq!(report.fps, 0.0);
+        assert_eq!(report.dropped_lines, 0);
+        assert_eq!(report.latency_ms, None);
+    }
+
+    #[test]
+    fn dropped_frames() {
+        let mut stats = PerfStats::new(0.0);
+        for timestamp in [0.0, 16.0, 32.0, 80.0, 96.0, 128.0] {
+            stats.frame(timestamp, None);
+        }
+        // 80 - 32 is 3 intervals, so 2 frames were dropped, and 128 - 96 is 2
+        // intervals, so 1 frame was dropped
+        assert_eq!(stats.report(1000.0).dropped_frames, 3);
+        // The frame interval is kept across periods, and long gaps are not
+        // counted
+        stats.frame(2000.0, None);
+        stats.frame(2032.0, None);
+        assert_eq!(stats.report(3000.0).dropped_frames, 1);
+    }
+}
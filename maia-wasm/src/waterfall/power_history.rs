@@ -0,0 +1,131 @@
+//! Power history of a frequency.
+//!
+//! The power of the FFT bin that contains a frequency is recorded in each
+//! spectrum line, so that the level of a signal can be plotted over time. Only
+//! the samples in a time window ending at the most recent sample are kept.
+
+use std::collections::VecDeque;
+
+/// Power history.
+///
+/// The history contains the power of the FFT bin of a frequency in the
+/// spectrum lines received during a time window.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PowerHistory {
+    // Frequency in Hz
+    frequency: f64,
+    // Length of the time window in milliseconds
+    window_ms: f64,
+    // Timestamps in milliseconds and power in dB. The power is NaN for the
+    // lines in which the frequency was outside of the band.
+    samples: VecDeque<(f64, f32)>,
+}
+
+impl PowerHistory {
+    /// Creates an empty history for a frequency in Hz, with a time window
+    /// given in milliseconds.
+    pub fn new(frequency: f64, window_ms: f64) -> PowerHistory {
+        PowerHistory {
+            frequency,
+            window_ms,
+            samples: VecDeque::new(),
+        }
+    }
+
+    /// Returns the frequency whose power is recorded, in Hz.
+    pub fn frequency(&self) -> f64 {
+        self.frequency
+    }
+
+    /// Returns the length of the time window, in milliseconds.
+    pub fn window(&self) -> f64 {
+        self.window_ms
+    }
+
+    /// Sets the length of the time window, in milliseconds.
+    ///
+    /// The samples that fall outside of the new window are discarded.
+    pub fn set_window(&mut self, window_ms: f64) {
+        self.window_ms = window_ms;
+        self.discard_old();
+    }
+
+    /// Adds a sample.
+    ///
+    /// Samples with a timestamp older than the most recent sample are ignored.
+    pub fn push(&mut self, timestamp: f64, power: f32) {
+        if !timestamp.is_finite() || self.last_timestamp().is_some_and(|last| timestamp < last) {
+            return;
+        }
+        self.samples.push_back((timestamp, power));
+        self.discard_old();
+    }
+
+    /// Removes all the samples.
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    /// Returns the samples, ordered from oldest to newest.
+    ///
+    /// Each sample contains the timestamp in milliseconds and the power in
+    /// dB, which is NaN if the frequency was outside of the band.
+    pub fn samples(&self) -> impl Iterator<Item = (f64, f32)> + '_ {
+        self.samples.iter().copied()
+    }
+
+    /// Returns the timestamp of the most recent sample.
+    pub fn last_timestamp(&self) -> Option<f64> {
+        self.samples.back().map(|&(timestamp, _)| timestamp)
+    }
+
+    fn discard_old(&mut self) {
+        let Some(last) = self.last_timestamp() else {
+            return;
+        };
+        while self
+            .samples
+            .front()
+            .is_some_and(|&(timestamp, _)| last - timestamp > self.window_ms)
+        {
+            self.samples.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn window() {
+        let mut history = PowerHistory::new(2.4e9, 1000.0);
+        assert_eq!(history.frequency(), 2.4e9);
+        assert_eq!(history.last_timestamp(), None);
+        for j in 0..20 {
+            history.push(100.0 * f64::from(j), j as f32);
+        }
+        // Only the samples in the last second are kept
+        let samples = history.samples().collect::<Vec<_>>();
+        assert_eq!(samples.len(), 11);
+        assert_eq!(samples[0], (900.0, 9.0));
+        assert_eq!(history.last_timestamp(), Some(1900.0));
+        history.set_window(250.0);
+        assert_eq!(history.samples().count(), 3);
+        history.clear();
+        assert_eq!(history.samples().count(), 0);
+    }
+
+    #[test]
+    fn ignores_old_timestamps() {
+        let mut history = PowerHistory::new(1e6, 1000.0);
+        history.push(500.0, 1.0);
+        history.push(400.0, 2.0);
+        history.push(f64::NAN, 3.0);
+        history.push(600.0, f32::NAN);
+        let samples = history.samples().collect::<Vec<_>>();
+        assert_eq!(samples.len(), 2);
+        assert_eq!(samples[0], (500.0, 1.0));
+        assert!(samples[1].1.is_nan());
+    }
+}
@@ -5,22 +5,57 @@
 
 use crate::pointer::{PointerGesture, PointerTracker};
 use crate::render::RenderEngine;
-use crate::ui::Ui;
-use crate::waterfall::Waterfall;
-use std::cell::RefCell;
+use crate::ui::{FrequencyUnit, NumberFormat, TimeUnit, Ui};
+use crate::waterfall::{Cursor, Waterfall};
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
-use web_sys::{HtmlCanvasElement, PointerEvent, WheelEvent};
+use web_sys::{
+    HtmlButtonElement, HtmlCanvasElement, KeyboardEvent, MessageEvent, PointerEvent, WheelEvent,
+};
+
+pub use keymap::{Action, Keymap};
+pub use view_sync::ViewState;
+use view_sync::ViewSync;
+
+mod keymap;
+mod view_sync;
 
 /// Waterfall interaction controller.
 ///
 /// This registers events that act on the waterfall to perform the following functions:
 /// * Control of zoom via on-wheel events.
+/// * Control of the RX frequency in fixed steps via on-wheel events over the
+///   RX LO marker.
 /// * Control of zoom via pinch gestures generated by a [`PointerTracker`].
+///   A quick pinch continues zooming briefly after the fingers are lifted,
+///   unless the user prefers reduced motion.
 /// * Control of center frequency via drag gestures generated by a `PointerTracker`.
+///   Dragging beyond the band edges retunes the RX frequency, and a bar on the
+///   edge of the screen shows the progress towards the retune.
+/// * Highlight of the FFT bin under the pointer when hovering over the
+///   waterfall.
 /// * Control of the cursor style according to whether the pointer is hovering or clicking
 ///   on the waterfall.
+/// * Quick navigation to DC and to the band edges via keyboard shortcuts and
+///   buttons.
+/// * Control of zoom and center frequency via buttons, which are intended for
+///   touch screens and shown in the compact layout, and via keyboard
+///   shortcuts.
+/// * Other keyboard shortcuts, such as cycling through the colormaps, pausing
+///   the waterfall or showing the shortcut help. The keys are given by the
+///   [`Keymap`].
+/// * A ruler tool, which measures the frequency and time difference between
+///   the endpoints of a drag gesture instead of panning.
+/// * Two measurement cursors, which are placed and cleared with buttons and
+///   moved by dragging them.
+/// * A power history tool, which plots the power of the frequency clicked on
+///   the waterfall over time.
+/// * Locking of the view, which prevents changes of zoom and center frequency
+///   by on-wheel events and pointer gestures.
+/// * Optional synchronization of the view (zoom and center frequency) with
+///   other browser tabs.
 #[derive(Clone)]
 pub struct WaterfallInteraction {
     render_engine: Rc<RefCell<RenderEngine>>,
@@ -28,7 +63,316 @@ pub struct WaterfallInteraction {
     pointer_tracker: Rc<RefCell<PointerTracker>>,
     waterfall: Rc<RefCell<Waterfall>>,
     ui: Ui,
-    center_freq_overflow: Rc<RefCell<f32>>,
+    drag_pan: Rc<Cell<DragPan>>,
+    view_sync: Option<Rc<ViewSync>>,
+    view_locked: Rc<Cell<bool>>,
+    ruler_tool: Rc<Cell<bool>>,
+    power_history_tool: Rc<Cell<bool>>,
+    ruler_drag: Rc<Cell<Option<RulerDrag>>>,
+    cursor_drag: Rc<Cell<Option<CursorDrag>>>,
+    passband_drag: Rc<Cell<Option<PassbandDrag>>>,
+    pinch_state: Rc<Cell<PinchState>>,
+    wheel_zoom: Rc<Cell<WheelZoom>>,
+    wheel_tune: Rc<Cell<WheelTune>>,
+    // Timeout handle to settle the zoom once the wheel stops
+    wheel_settle_timeout: Rc<Cell<Option<i32>>>,
+}
+
+// State of an ongoing ruler measurement.
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct RulerDrag {
+    pointer_id: i32,
+    // Start point in CSS pixels
+    start: (i32, i32),
+}
+
+// State of an ongoing drag of a measurement cursor.
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct CursorDrag {
+    pointer_id: i32,
+    cursor: Cursor,
+}
+
+// State of an ongoing drag of the passband.
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct PassbandDrag {
+    pointer_id: i32,
+    grab: PassbandGrab,
+}
+
+// Part of the passband grabbed by the pointer.
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum PassbandGrab {
+    // One of the edges, which resizes the passband symmetrically around its
+    // center.
+    Edge,
+    // The inside of the passband, which moves it. The offset is the distance
+    // in frequency units from the center of the passband to the pointer.
+    Body { offset: f32 },
+}
+
+// State of the pinch gestures, which is used to continue zooming with
+// momentum after a pinch.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+enum PinchState {
+    #[default]
+    Idle,
+    Pinching(PinchVelocity),
+    // The pinch has ended but one of the fingers is still down. Its drags are
+    // ignored, since they are a residue of the pinch and they would cancel the
+    // momentum.
+    Released,
+}
+
+// Estimate of the rate at which the zoom changes during a pinch.
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct PinchVelocity {
+    // Rate in natural log units of the zoom per millisecond
+    rate: f32,
+    // Center of the pinch in CSS pixels
+    center: i32,
+    // Timestamp of the last pinch event in milliseconds
+    timestamp: f64,
+}
+
+impl PinchVelocity {
+    // Time constant in milliseconds of the smoothing of the rate
+    const SMOOTHING_MS: f64 = 50.0;
+    // The pinch has no momentum if the fingers have not moved for this time
+    // in milliseconds before they are lifted.
+    const MAX_IDLE_MS: f64 = 100.0;
+    // The pinch has no momentum if the rate is below this
+    const MIN_RATE: f32 = 2e-4;
+
+    fn update(
+        previous: Option<PinchVelocity>,
+        dilation: f32,
+        center: i32,
+        timestamp: f64,
+    ) -> PinchVelocity {
+        let rate = match previous {
+            Some(previous) => {
+                let elapsed = timestamp - previous.timestamp;
+                if elapsed > 0.0 {
+                    let alpha = 1.0 - (-elapsed / Self::SMOOTHING_MS).exp();
+                    let rate = dilation.ln() / elapsed as f32;
+                    previous.rate + alpha as f32 * (rate - previous.rate)
+                } else {
+                    // Events with the same timestamp do not give a rate.
+                    return PinchVelocity { center, ..previous };
+                }
+            }
+            None => 0.0,
+        };
+        PinchVelocity {
+            rate,
+            center,
+            timestamp,
+        }
+    }
+
+    // Rate with which the zoom continues when the pinch is released at time
+    // `now`, or None if the pinch has no momentum.
+    fn release_rate(&self, now: f64) -> Option<f32> {
+        if now - self.timestamp <= Self::MAX_IDLE_MS
+            && self.rate.is_finite()
+            && self.rate.abs() >= Self::MIN_RATE
+        {
+            Some(self.rate)
+        } else {
+            None
+        }
+    }
+}
+
+// Accumulator of wheel deltas for zooming.
+//
+// Precision mice and trackpads send many small deltas, often fractions of a
+// pixel. These are accumulated and the zoom is only changed once the
+// accumulated delta is large enough, so that the net zoom after many small
+// deltas is the same as after a single large delta.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+struct WheelZoom {
+    // Accumulated delta in pixels that has not been applied yet
+    pending: f64,
+}
+
+impl WheelZoom {
+    // Zoom change in natural log units per pixel of wheel delta
+    const LOG_DILATION_PER_PX: f64 = -1e-3;
+    // Deltas are applied once they amount to this many pixels
+    const MIN_DELTA_PX: f64 = 1.0;
+    // Pixels per line and per page, for wheel events that are not given in
+    // pixels
+    const PX_PER_LINE: f64 = 40.0;
+    const PX_PER_PAGE: f64 = 800.0;
+
+    // Converts the delta of a wheel event to pixels according to its
+    // delta mode.
+    fn delta_px(delta: f64, delta_mode: u32) -> f64 {
+        match delta_mode {
+            WheelEvent::DOM_DELTA_LINE => delta * Self::PX_PER_LINE,
+            WheelEvent::DOM_DELTA_PAGE => delta * Self::PX_PER_PAGE,
+            _ => delta,
+        }
+    }
+
+    // Adds a wheel delta in pixels. Returns the dilation to apply, or None if
+    // the accumulated delta is still too small.
+    fn push(&mut self, delta_px: f64) -> Option<f32> {
+        if !delta_px.is_finite() {
+            return None;
+        }
+        self.pending += delta_px;
+        if self.pending.abs() < Self::MIN_DELTA_PX {
+            return None;
+        }
+        let dilation = (Self::LOG_DILATION_PER_PX * self.pending).exp();
+        self.pending = 0.0;
+        Some(dilation as f32)
+    }
+}
+
+// Accumulator of the wheel deltas used to tune the RX frequency with the
+// wheel over the RX marker.
+//
+// As for the zoom, the deltas are accumulated, and the frequency is moved one
+// step each time that the accumulated delta amounts to a wheel notch, so that
+// the number of steps does not depend on how many events the device sends.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+struct WheelTune {
+    // Accumulated delta in pixels that has not been applied yet
+    pending: f64,
+}
+
+impl WheelTune {
+    // Pixels of wheel delta per tuning step, which is about one wheel notch
+    const PX_PER_STEP: f64 = 100.0;
+
+    // Adds a wheel delta in pixels. Returns the number of steps to tune, which
+    // is positive to tune up (scrolling up gives negative deltas).
+    fn push(&mut self, delta_px: f64) -> i32 {
+        if !delta_px.is_finite() {
+            return 0;
+        }
+        self.pending += delta_px;
+        let steps = (self.pending / Self::PX_PER_STEP).trunc();
+        self.pending -= steps * Self::PX_PER_STEP;
+        -steps as i32
+    }
+}
+
+// State of the pan performed by a drag gesture.
+//
+// The view follows the pointer. When it is dragged beyond the band edges, the
+// distance is accumulated, and the RX frequency is retuned each time that it
+// amounts to RETUNE_SHIFT.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+struct DragPan {
+    // Distance dragged beyond the band edges that has not been used for a
+    // retune yet
+    overflow: f32,
+    // Distance beyond the band edges that the view has been dragged when the
+    // overscan is enabled, without the rubber band compression
+    overscan: f32,
+}
+
+// Change of the view produced by a drag step.
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum DragPanStep {
+    // Set the center frequency to the given value.
+    Center(f32),
+    // Drag the center frequency beyond the band edge with the overscan (see
+    // Waterfall::drag_center_frequency).
+    Overscan(f32),
+    // Retune the RX frequency by the given shift (see
+    // WaterfallInteraction::retune_frequency). If center is not None, the
+    // view was overscanned and the center frequency must be set to it.
+    Retune { shift: f32, center: Option<f32> },
+}
+
+impl DragPan {
+    // Distance beyond the band edges that produces a retune, in frequency
+    // units
+    const RETUNE_SHIFT: f32 = 0.25;
+
+    // Pans by a drag displacement of dx pixels, starting from the given
+    // center frequency.
+    fn drag(
+        &mut self,
+        center: f32,
+        zoom: f32,
+        dx: i32,
+        units_per_px: f64,
+        overscan_enabled: bool,
+    ) -> DragPanStep {
+        // The drag starts from the position that follows the pointer, which
+        // includes the distance beyond the band edges if the view is
+        // overscanned.
+        let center = Waterfall::clamp_center_frequency(center, zoom) + self.overscan;
+        let freq = (f64::from(center) - f64::from(dx) * units_per_px) as f32;
+        let clamped = Waterfall::clamp_center_frequency(freq, zoom);
+        self.overflow += freq - clamped - self.overscan;
+        if self.overflow.abs() >= Self::RETUNE_SHIFT {
+            let shift = Self::RETUNE_SHIFT.copysign(self.overflow);
+            self.overflow -= shift;
+            let overscanned = std::mem::take(&mut self.overscan) != 0.0;
+            DragPanStep::Retune {
+                shift,
+                center: overscanned.then_some(clamped),
+            }
+        } else if overscan_enabled {
+            self.overscan = freq - clamped;
+            DragPanStep::Overscan(freq)
+        } else {
+            DragPanStep::Center(clamped)
+        }
+    }
+
+    // Returns the side of the band towards which the view is being dragged
+    // beyond the band edge, in frequency units, and the progress towards the
+    // next retune, between 0 and 1.
+    fn retune_progress(&self) -> (f32, f32) {
+        (
+            self.overflow.signum(),
+            self.overflow.abs() / Self::RETUNE_SHIFT,
+        )
+    }
+}
+
+/// Navigation target.
+///
+/// This enum lists the destinations to which the waterfall view can be moved
+/// with [`WaterfallInteraction::go_to`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum NavigationTarget {
+    /// DC.
+    ///
+    /// Since the waterfall is centered at the RX LO frequency, this is also the
+    /// location of the RX LO.
+    Dc,
+    /// Edge of the band that is shown on the left of the screen.
+    ///
+    /// This is the lowest frequency edge, unless the frequency axis is
+    /// flipped.
+    LeftEdge,
+    /// Edge of the band that is shown on the right of the screen.
+    ///
+    /// This is the highest frequency edge, unless the frequency axis is
+    /// flipped.
+    RightEdge,
+}
+
+impl NavigationTarget {
+    // Center frequency that places the target in the center of the view. This
+    // is clamped afterwards, so the edges are moved to the side of the view.
+    fn center_frequency(&self, axis_direction: f32) -> f32 {
+        match self {
+            NavigationTarget::Dc => 0.0,
+            NavigationTarget::LeftEdge => -axis_direction,
+            NavigationTarget::RightEdge => axis_direction,
+        }
+    }
 }
 
 impl WaterfallInteraction {
@@ -54,14 +398,29 @@ impl WaterfallInteraction {
             pointer_tracker: Rc::new(RefCell::new(PointerTracker::new())),
             waterfall,
             ui,
-            center_freq_overflow: Rc::new(RefCell::new(0.0)),
+            drag_pan: Rc::new(Cell::new(DragPan::default())),
+            // BroadcastChannel might not be supported by the browser. In this
+            // case view synchronization is not available.
+            view_sync: ViewSync::new().ok().map(Rc::new),
+            view_locked: Rc::new(Cell::new(false)),
+            ruler_tool: Rc::new(Cell::new(false)),
+            power_history_tool: Rc::new(Cell::new(false)),
+            ruler_drag: Rc::new(Cell::new(None)),
+            cursor_drag: Rc::new(Cell::new(None)),
+            passband_drag: Rc::new(Cell::new(None)),
+            pinch_state: Rc::new(Cell::new(PinchState::Idle)),
+            wheel_zoom: Rc::new(Cell::new(WheelZoom::default())),
+            wheel_tune: Rc::new(Cell::new(WheelTune::default())),
+            wheel_settle_timeout: Rc::new(Cell::new(None)),
         }
     }
 
     /// Sets the callbacks required by the interaction controller.
     ///
     /// This registers callbacks for the on wheel and on pointer
-    /// up/down/cancel/leave/move events of the waterfall canvas.
+    /// up/down/cancel/leave/move events of the waterfall canvas, for the on key
+    /// down events of the document, for the on click events of the
+    /// navigation, zoom and pan buttons, and for the blur event of the window.
     pub fn set_callbacks(&self) {
         // We leak all the closures produced by self to prevent them from being
         // dropped immediately.
@@ -82,23 +441,689 @@ impl WaterfallInteraction {
 
         self.canvas
             .set_onpointermove(Some(self.onpointermove().into_js_value().unchecked_ref()));
+
+        if let Some(view_sync) = &self.view_sync {
+            view_sync.set_onmessage(&self.view_sync_onmessage().into_js_value());
+        }
+
+        if let Some(document) = self.canvas.owner_document() {
+            document.set_onkeydown(Some(self.onkeydown().into_js_value().unchecked_ref()));
+            if let Some(button) = Self::get_button(&document, "view_lock") {
+                button.set_onclick(Some(
+                    self.view_lock_onclick().into_js_value().unchecked_ref(),
+                ));
+            }
+            if let Some(button) = Self::get_button(&document, "ruler_tool") {
+                button.set_onclick(Some(
+                    self.ruler_tool_onclick().into_js_value().unchecked_ref(),
+                ));
+            }
+            if let Some(button) = Self::get_button(&document, "power_history_tool") {
+                button.set_onclick(Some(
+                    self.power_history_tool_onclick()
+                        .into_js_value()
+                        .unchecked_ref(),
+                ));
+            }
+            if let Some(button) = Self::get_button(&document, "cursors_place") {
+                button.set_onclick(Some(
+                    self.cursors_place_onclick().into_js_value().unchecked_ref(),
+                ));
+            }
+            if let Some(button) = Self::get_button(&document, "cursors_clear") {
+                button.set_onclick(Some(
+                    self.cursors_clear_onclick().into_js_value().unchecked_ref(),
+                ));
+            }
+            if let Some(button) = Self::get_button(&document, "passband_place") {
+                button.set_onclick(Some(
+                    self.passband_place_onclick()
+                        .into_js_value()
+                        .unchecked_ref(),
+                ));
+            }
+            if let Some(button) = Self::get_button(&document, "passband_clear") {
+                button.set_onclick(Some(
+                    self.passband_clear_onclick()
+                        .into_js_value()
+                        .unchecked_ref(),
+                ));
+            }
+            for (id, target) in [
+                ("go_to_dc", NavigationTarget::Dc),
+                ("go_to_left_edge", NavigationTarget::LeftEdge),
+                ("go_to_right_edge", NavigationTarget::RightEdge),
+            ] {
+                if let Some(button) = Self::get_button(&document, id) {
+                    button.set_onclick(Some(
+                        self.navigation_onclick(target)
+                            .into_js_value()
+                            .unchecked_ref(),
+                    ));
+                }
+            }
+            for (id, dilation) in [
+                ("zoom_in", Self::BUTTON_ZOOM_STEP),
+                ("zoom_out", Self::BUTTON_ZOOM_STEP.recip()),
+            ] {
+                if let Some(button) = Self::get_button(&document, id) {
+                    button.set_onclick(Some(
+                        self.zoom_onclick(dilation).into_js_value().unchecked_ref(),
+                    ));
+                }
+            }
+            for (id, fraction) in [
+                ("pan_left", -Self::BUTTON_PAN_STEP),
+                ("pan_right", Self::BUTTON_PAN_STEP),
+            ] {
+                if let Some(button) = Self::get_button(&document, id) {
+                    button.set_onclick(Some(
+                        self.pan_onclick(fraction).into_js_value().unchecked_ref(),
+                    ));
+                }
+            }
+        }
+
+        if let Some(window) = web_sys::window() {
+            if let Err(e) = window.add_event_listener_with_callback(
+                "blur",
+                self.onblur().into_js_value().unchecked_ref(),
+            ) {
+                web_sys::console::error_1(&e);
+            }
+        }
+    }
+
+    fn get_button(document: &web_sys::Document, id: &str) -> Option<HtmlButtonElement> {
+        document
+            .get_element_by_id(id)
+            .and_then(|b| b.dyn_into::<HtmlButtonElement>().ok())
+    }
+
+    /// Locks or unlocks the view.
+    ///
+    /// While the view is locked, on-wheel events and pointer gestures do not
+    /// change the zoom or center frequency of the waterfall. Wheel tuning over
+    /// the RX LO marker, keyboard shortcuts and navigation buttons keep working,
+    /// since they are unlikely to be used accidentally.
+    pub fn set_view_locked(&self, locked: bool) {
+        self.view_locked.set(locked);
+        if let Err(e) = self
+            .canvas
+            .class_list()
+            .toggle_with_force("view_locked", locked)
+        {
+            web_sys::console::error_1(&e);
+        }
+        if let Some(document) = self.canvas.owner_document() {
+            if let Some(button) = Self::get_button(&document, "view_lock") {
+                button.set_text_content(Some(if locked { "Unlock view" } else { "Lock view" }));
+            }
+        }
+        let message = if locked {
+            "View locked"
+        } else {
+            "View unlocked"
+        };
+        if let Err(e) = self.ui.show_toast(message) {
+            web_sys::console::error_1(&e);
+        }
+    }
+
+    /// Returns `true` if the view is locked.
+    pub fn is_view_locked(&self) -> bool {
+        self.view_locked.get()
+    }
+
+    fn view_lock_onclick(&self) -> Closure<dyn Fn()> {
+        let interaction = self.clone();
+        Closure::new(move || interaction.set_view_locked(!interaction.is_view_locked()))
+    }
+
+    /// Enables or disables the ruler tool.
+    ///
+    /// While the ruler tool is enabled, dragging on the waterfall does not pan.
+    /// Instead, a line is drawn between the start and the current point of the
+    /// drag, and the frequency difference (and the time difference, if the
+    /// waterfall update rate is known) between these points is shown. The
+    /// measurement is cleared when the pointer is released.
+    pub fn set_ruler_tool(&self, enable: bool) {
+        self.ruler_tool.set(enable);
+        if !enable {
+            self.end_ruler();
+        }
+        if let Some(document) = self.canvas.owner_document() {
+            if let Some(button) = Self::get_button(&document, "ruler_tool") {
+                button.set_text_content(Some(if enable { "Exit ruler" } else { "Ruler" }));
+            }
+        }
+        let message = if enable { "Ruler on" } else { "Ruler off" };
+        if let Err(e) = self.ui.show_toast(message) {
+            web_sys::console::error_1(&e);
+        }
+    }
+
+    /// Returns `true` if the ruler tool is enabled.
+    pub fn is_ruler_tool_enabled(&self) -> bool {
+        self.ruler_tool.get()
+    }
+
+    fn ruler_tool_onclick(&self) -> Closure<dyn Fn()> {
+        let interaction = self.clone();
+        Closure::new(move || interaction.set_ruler_tool(!interaction.is_ruler_tool_enabled()))
+    }
+
+    fn update_ruler(&self, drag: RulerDrag, end: (i32, i32)) -> Result<(), JsValue> {
+        let render_engine = self.render_engine.borrow();
+        let mut waterfall = self.waterfall.borrow_mut();
+        let (_, height) = render_engine.canvas_dims().css_pixels();
+        let height = height as f32;
+        let to_screen = |(x, y): (i32, i32)| {
+            Self::px_to_frequency(&render_engine, &waterfall, x)
+                .map(|freq| (freq, 1.0 - 2.0 * y as f32 / height))
+        };
+        let (Some(start_point), Some(end_point)) = (to_screen(drag.start), to_screen(end)) else {
+            // The canvas has no valid size yet, so nothing can be measured.
+            return Ok(());
+        };
+        waterfall.set_ruler(start_point, end_point);
+
+        let (_, samp_rate) = waterfall.get_freq_samprate();
+        let Some(hz_per_unit) = Self::hz_per_unit(samp_rate) else {
+            return Ok(());
+        };
+        let delta_freq = f64::from(end_point.0 - start_point.0) * hz_per_unit;
+        let format = self.ui.number_format();
+        let mut text = format!("Δf {}", Self::format_frequency_delta(delta_freq, &format));
+        let dy = end.1 - drag.start.1;
+        if dy != 0 {
+            if let Some(duration) = waterfall.get_screen_height_duration() {
+                // Older lines are lower in the waterfall, so dragging upwards
+                // gives a positive time difference.
+                let delta_time = -dy as f32 / height * duration;
+                text.push_str(&format!(
+                    ", Δt {}",
+                    format.time_delta(f64::from(delta_time), TimeUnit::S, 2)
+                ));
+            }
+        }
+        self.ui.show_ruler_readout(&text, end.0, end.1)
+    }
+
+    fn format_frequency_delta(hz: f64, format: &NumberFormat) -> String {
+        match FrequencyUnit::for_frequency(hz) {
+            FrequencyUnit::Hz => format.frequency_delta(hz, FrequencyUnit::Hz, 0),
+            unit => format.frequency_delta(hz, unit, 3),
+        }
+    }
+
+    fn end_ruler(&self) {
+        self.ruler_drag.set(None);
+        self.waterfall.borrow_mut().clear_ruler();
+        self.ui.hide_ruler_readout();
+    }
+
+    /// Enables or disables the power history tool.
+    ///
+    /// While the power history tool is enabled, the next click on the
+    /// waterfall selects the frequency under the pointer, whose power is then
+    /// plotted over time (see [`Ui::start_power_history`]), and the tool is
+    /// disabled.
+    pub fn set_power_history_tool(&self, enable: bool) {
+        self.power_history_tool.set(enable);
+        if let Some(document) = self.canvas.owner_document() {
+            if let Some(button) = Self::get_button(&document, "power_history_tool") {
+                button.set_text_content(Some(if enable { "Cancel" } else { "Power history" }));
+            }
+        }
+        if enable {
+            if let Err(e) = self.ui.show_toast("Click on a frequency") {
+                web_sys::console::error_1(&e);
+            }
+        }
+    }
+
+    /// Returns `true` if the power history tool is enabled.
+    pub fn is_power_history_tool_enabled(&self) -> bool {
+        self.power_history_tool.get()
+    }
+
+    fn power_history_tool_onclick(&self) -> Closure<dyn Fn()> {
+        let interaction = self.clone();
+        Closure::new(move || {
+            interaction.set_power_history_tool(!interaction.is_power_history_tool_enabled())
+        })
+    }
+
+    fn select_power_history_frequency(&self, x: i32) {
+        self.set_power_history_tool(false);
+        let Some(frequency) =
+            Self::px_to_frequency(&self.render_engine.borrow(), &self.waterfall.borrow(), x)
+        else {
+            return;
+        };
+        if let Err(e) = self.ui.start_power_history(frequency) {
+            web_sys::console::error_1(&e);
+        }
+    }
+
+    /// Places both measurement cursors in the current view.
+    ///
+    /// The cursors are placed at one quarter and three quarters of the width
+    /// of the view. Once placed, each cursor stays at its absolute frequency
+    /// until it is dragged to another frequency or the cursors are cleared.
+    pub fn place_cursors(&self) {
+        {
+            let mut waterfall = self.waterfall.borrow_mut();
+            let center = waterfall.get_center_frequency();
+            let offset = 0.5 * waterfall.get_axis_direction() / waterfall.get_zoom();
+            waterfall.set_cursor_position(Cursor::A, Some(center - offset));
+            waterfall.set_cursor_position(Cursor::B, Some(center + offset));
+        }
+        self.cursors_changed();
+    }
+
+    /// Removes both measurement cursors.
+    pub fn clear_cursors(&self) {
+        self.cursor_drag.set(None);
+        {
+            let mut waterfall = self.waterfall.borrow_mut();
+            waterfall.set_cursor_position(Cursor::A, None);
+            waterfall.set_cursor_position(Cursor::B, None);
+        }
+        self.cursors_changed();
+    }
+
+    fn cursors_changed(&self) {
+        if let Err(e) = self.ui.update_cursor_readout() {
+            web_sys::console::error_1(&e);
+        }
+    }
+
+    fn cursors_place_onclick(&self) -> Closure<dyn Fn()> {
+        let interaction = self.clone();
+        Closure::new(move || interaction.place_cursors())
+    }
+
+    fn cursors_clear_onclick(&self) -> Closure<dyn Fn()> {
+        let interaction = self.clone();
+        Closure::new(move || interaction.clear_cursors())
+    }
+
+    // Returns the measurement cursor closest to a horizontal position in CSS
+    // pixels, if there is one close enough to grab it.
+    fn cursor_at(&self, x: i32) -> Option<Cursor> {
+        // Maximum distance in CSS pixels between the pointer and the cursor.
+        const MAX_DISTANCE: f32 = 5.0;
+        let render_engine = self.render_engine.borrow();
+        let waterfall = self.waterfall.borrow();
+        [Cursor::A, Cursor::B]
+            .into_iter()
+            .filter_map(|cursor| {
+                let position = waterfall.get_cursor_position(cursor)?;
+                let cursor_x = Self::frequency_to_px(&render_engine, &waterfall, position)?;
+                Some((cursor, (x as f32 - cursor_x).abs()))
+            })
+            .filter(|&(_, distance)| distance <= MAX_DISTANCE)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(cursor, _)| cursor)
+    }
+
+    // Moves a measurement cursor to a horizontal position in CSS pixels. The
+    // cursor cannot be moved outside of the band.
+    fn move_cursor(&self, cursor: Cursor, x: i32) {
+        {
+            let mut waterfall = self.waterfall.borrow_mut();
+            let Some(position) = Self::px_to_frequency(&self.render_engine.borrow(), &waterfall, x)
+            else {
+                return;
+            };
+            waterfall.set_cursor_position(cursor, Some(position.clamp(-1.0, 1.0)));
+        }
+        self.cursors_changed();
+    }
+
+    // Bandwidths in Hz to which the passband snaps when it is resized. These
+    // are common channel and filter bandwidths.
+    const PASSBAND_SNAP_BANDWIDTHS: [f64; 24] = [
+        100.0, 200.0, 500.0, 1e3, 2e3, 2.4e3, 2.7e3, 3e3, 5e3, 6e3, 8e3, 10e3, 12.5e3, 15e3, 20e3,
+        25e3, 50e3, 100e3, 150e3, 200e3, 250e3, 500e3, 1e6, 2e6,
+    ];
+
+    /// Places the passband in the current view.
+    ///
+    /// The passband is placed at the center of the view, with a bandwidth of
+    /// about one eighth of the width of the view. Once placed, it stays at its
+    /// absolute frequency until it is dragged or cleared. Its edges can be
+    /// dragged to resize it, and its inside can be dragged to move it.
+    pub fn place_passband(&self) {
+        {
+            let mut waterfall = self.waterfall.borrow_mut();
+            let (_, samp_rate) = waterfall.get_freq_samprate();
+            let Some(hz_per_unit) = Self::hz_per_unit(samp_rate) else {
+                return;
+            };
+            // The view spans 2 / zoom frequency units.
+            let bandwidth = 0.25 * hz_per_unit / f64::from(waterfall.get_zoom());
+            let center = waterfall.get_center_frequency();
+            waterfall.set_passband(center, Self::snap_bandwidth(bandwidth));
+        }
+        self.passband_changed();
+    }
+
+    /// Removes the passband.
+    pub fn clear_passband(&self) {
+        self.passband_drag.set(None);
+        self.waterfall.borrow_mut().clear_passband();
+        self.passband_changed();
+    }
+
+    // The passband is shown in the same readout as the measurement cursors.
+    fn passband_changed(&self) {
+        self.cursors_changed();
+    }
+
+    fn passband_place_onclick(&self) -> Closure<dyn Fn()> {
+        let interaction = self.clone();
+        Closure::new(move || interaction.place_passband())
+    }
+
+    fn passband_clear_onclick(&self) -> Closure<dyn Fn()> {
+        let interaction = self.clone();
+        Closure::new(move || interaction.clear_passband())
+    }
+
+    // Returns the part of the passband under a horizontal position in CSS
+    // pixels, if the passband can be grabbed there. The edges take precedence
+    // over the inside, so that narrow passbands can still be resized.
+    fn passband_at(&self, x: i32) -> Option<PassbandGrab> {
+        // Maximum distance in CSS pixels between the pointer and an edge.
+        const MAX_DISTANCE: f32 = 5.0;
+        let render_engine = self.render_engine.borrow();
+        let waterfall = self.waterfall.borrow();
+        let (lower, upper) = waterfall.get_passband_edges()?;
+        let lower_x = Self::frequency_to_px(&render_engine, &waterfall, lower)?;
+        let upper_x = Self::frequency_to_px(&render_engine, &waterfall, upper)?;
+        let x_f32 = x as f32;
+        if (x_f32 - lower_x).abs() <= MAX_DISTANCE || (x_f32 - upper_x).abs() <= MAX_DISTANCE {
+            return Some(PassbandGrab::Edge);
+        }
+        // The pixels of the edges are swapped if the frequency axis is
+        // reversed.
+        if (lower_x.min(upper_x)..=lower_x.max(upper_x)).contains(&x_f32) {
+            let position = Self::px_to_frequency(&render_engine, &waterfall, x)?;
+            return Some(PassbandGrab::Body {
+                offset: position - 0.5 * (lower + upper),
+            });
+        }
+        None
+    }
+
+    // Moves or resizes the passband according to the part that is grabbed
+    // and a horizontal position in CSS pixels. The center of the passband
+    // cannot be moved outside of the band.
+    fn drag_passband(&self, grab: PassbandGrab, x: i32) {
+        {
+            let mut waterfall = self.waterfall.borrow_mut();
+            let Some(position) = Self::px_to_frequency(&self.render_engine.borrow(), &waterfall, x)
+            else {
+                return;
+            };
+            let (Some((lower, upper)), Some((_, bandwidth))) =
+                (waterfall.get_passband_edges(), waterfall.get_passband())
+            else {
+                return;
+            };
+            let center = 0.5 * (lower + upper);
+            match grab {
+                PassbandGrab::Edge => {
+                    let (_, samp_rate) = waterfall.get_freq_samprate();
+                    let Some(hz_per_unit) = Self::hz_per_unit(samp_rate) else {
+                        return;
+                    };
+                    let bandwidth = 2.0 * f64::from((position - center).abs()) * hz_per_unit;
+                    waterfall.set_passband_bandwidth(Self::snap_bandwidth(bandwidth));
+                }
+                PassbandGrab::Body { offset } => {
+                    waterfall.set_passband((position - offset).clamp(-1.0, 1.0), bandwidth);
+                }
+            }
+        }
+        self.passband_changed();
+    }
+
+    // Snaps a passband bandwidth in Hz to the closest common bandwidth if it
+    // is within 5% of it. Other bandwidths are rounded to 1 Hz, and the
+    // bandwidth is at least 1 Hz.
+    fn snap_bandwidth(bandwidth: f64) -> f64 {
+        const TOLERANCE: f64 = 0.05;
+        Self::PASSBAND_SNAP_BANDWIDTHS
+            .into_iter()
+            .find(|snap| (bandwidth / snap - 1.0).abs() <= TOLERANCE)
+            .unwrap_or_else(|| bandwidth.round())
+            .max(1.0)
+    }
+
+    /// Moves the waterfall view to a navigation target.
+    ///
+    /// The zoom is kept unchanged, and the center frequency is clamped so that
+    /// the view does not extend beyond the band edges. The view is animated
+    /// with the easing curve selected in the UI.
+    ///
+    /// The view is not changed while the waterfall is being annotated, since
+    /// the annotations refer to the image on the screen. This also applies to
+    /// [`zoom_by`](WaterfallInteraction::zoom_by) and
+    /// [`pan_by`](WaterfallInteraction::pan_by).
+    pub fn go_to(&self, target: NavigationTarget) {
+        if self.ui.is_annotating() {
+            return;
+        }
+        {
+            let mut waterfall = self.waterfall.borrow_mut();
+            let (zoom, _) = waterfall.get_view_target();
+            let freq = Waterfall::clamp_center_frequency(
+                target.center_frequency(waterfall.get_axis_direction()),
+                zoom,
+            );
+            waterfall.animate_view(zoom, freq);
+        }
+        self.view_changed();
+    }
+
+    // Zoom factor applied by the zoom in button.
+    const BUTTON_ZOOM_STEP: f32 = 1.5;
+    // Fraction of the view width moved by the pan buttons.
+    const BUTTON_PAN_STEP: f32 = 0.25;
+
+    /// Zooms the waterfall view by a factor.
+    ///
+    /// The zoom is applied around the center of the view. Factors larger than
+    /// one zoom in, and factors smaller than one zoom out.
+    pub fn zoom_by(&self, dilation: f32) {
+        if self.ui.is_annotating() {
+            return;
+        }
+        {
+            let mut waterfall = self.waterfall.borrow_mut();
+            // Successive calls during an animation accumulate on its target.
+            let (zoom, freq) = waterfall.get_view_target();
+            let zoom = Waterfall::clamp_zoom(dilation * zoom);
+            waterfall.animate_view(zoom, Waterfall::clamp_center_frequency(freq, zoom));
+        }
+        self.view_changed();
     }
 
-    fn clamp_zoom(zoom: f32) -> f32 {
-        let min_zoom = 1.0;
-        let max_zoom = 128.0;
-        zoom.clamp(min_zoom, max_zoom)
+    /// Pans the waterfall view by a fraction of its width.
+    ///
+    /// Positive fractions move the view to the right of the screen, which
+    /// corresponds to higher frequencies unless the frequency axis is
+    /// flipped. The center frequency is clamped so that the view does not
+    /// extend beyond the band edges. Unlike dragging, panning with this
+    /// function never changes the RX frequency.
+    pub fn pan_by(&self, fraction: f32) {
+        if self.ui.is_annotating() {
+            return;
+        }
+        {
+            let mut waterfall = self.waterfall.borrow_mut();
+            let (zoom, freq) = waterfall.get_view_target();
+            let freq = freq + waterfall.get_axis_direction() * fraction * 2.0 / zoom;
+            waterfall.animate_view(zoom, Waterfall::clamp_center_frequency(freq, zoom));
+        }
+        self.view_changed();
+    }
+
+    /// Returns the current view of the waterfall.
+    pub fn view(&self) -> ViewState {
+        // If the view is being animated, the view at the end of the animation
+        // is returned, so that other tabs do not synchronize to an
+        // intermediate view.
+        let (zoom, center_frequency) = self.waterfall.borrow().get_view_target();
+        ViewState {
+            zoom,
+            center_frequency,
+        }
+    }
+
+    /// Sets the view of the waterfall.
+    ///
+    /// The zoom and center frequency are clamped to the valid ranges, so that
+    /// the view does not extend beyond the band edges.
+    pub fn set_view(&self, view: ViewState) {
+        {
+            // The waterfall setters clamp the zoom and center frequency.
+            let mut waterfall = self.waterfall.borrow_mut();
+            waterfall.set_zoom(view.zoom);
+            waterfall.set_center_frequency(view.center_frequency);
+        }
+        self.view_changed();
+    }
+
+    // Called whenever the view is changed by the user.
+    fn view_changed(&self) {
+        if let Some(view_sync) = &self.view_sync {
+            if self.ui.view_sync_enabled() {
+                if let Err(e) = view_sync.broadcast(&self.view()) {
+                    web_sys::console::error_1(&e);
+                }
+            }
+        }
     }
 
-    fn clamp_center_frequency(frequency: f32, zoom: f32) -> f32 {
-        let max_freq = 1.0 - 1.0 / zoom;
-        frequency.clamp(-max_freq, max_freq)
+    fn view_sync_onmessage(&self) -> Closure<dyn Fn(MessageEvent)> {
+        let interaction = self.clone();
+        Closure::new(move |event: MessageEvent| {
+            if !interaction.ui.view_sync_enabled() {
+                return;
+            }
+            let view = match ViewSync::parse(&event.data()) {
+                Ok(view) => view,
+                Err(e) => {
+                    web_sys::console::error_1(&e);
+                    return;
+                }
+            };
+            if !view.zoom.is_finite() || !view.center_frequency.is_finite() {
+                return;
+            }
+            // view_sync is Some, since otherwise this closure would not have
+            // been registered.
+            interaction
+                .view_sync
+                .as_ref()
+                .unwrap()
+                .apply(|| interaction.set_view(view));
+        })
+    }
+
+    // Frequency units per CSS pixel. This is negative if the frequency axis is
+    // flipped, so that all the conversions between pixels and frequencies
+    // take the direction of the axis into account.
+    //
+    // This is computed in f64, as well as the conversions that use it. At the
+    // maximum zoom and with wide canvases, a pixel spans only a few units in
+    // the last place of an f32 frequency, so doing the arithmetic in f32
+    // makes the frequency under the pointer drift when zooming and panning.
+    //
+    // Returns None if the canvas does not have a valid size yet.
+    fn units_per_px(render_engine: &RenderEngine, waterfall: &Waterfall) -> Option<f64> {
+        Self::units_per_px_for(
+            render_engine.canvas_dims().css_pixels().0,
+            waterfall.get_zoom(),
+            waterfall.get_axis_direction(),
+        )
+    }
+
+    fn units_per_px_for(canvas_width: u32, zoom: f32, axis_direction: f32) -> Option<f64> {
+        if canvas_width == 0 {
+            return None;
+        }
+        let width_units = 2.0 / f64::from(zoom);
+        Some(f64::from(axis_direction) * width_units / f64::from(canvas_width))
+            .filter(|u| u.is_finite() && *u != 0.0)
     }
 
-    fn units_per_px(render_engine: &RenderEngine, waterfall: &Waterfall) -> f32 {
-        let canvas_width = render_engine.canvas_dims().css_pixels().0;
-        let width_units = 2.0 / waterfall.get_zoom();
-        width_units / canvas_width as f32
+    // Converts a horizontal position in CSS pixels to a frequency.
+    fn px_to_frequency(render_engine: &RenderEngine, waterfall: &Waterfall, x: i32) -> Option<f32> {
+        Some(Self::px_to_frequency_for(
+            x,
+            render_engine.canvas_dims().css_pixels().0,
+            waterfall.get_center_frequency(),
+            Self::units_per_px(render_engine, waterfall)?,
+        ))
+    }
+
+    fn px_to_frequency_for(x: i32, canvas_width: u32, center: f32, units_per_px: f64) -> f32 {
+        let offset_px = f64::from(x) - 0.5 * f64::from(canvas_width);
+        (f64::from(center) + units_per_px * offset_px) as f32
+    }
+
+    // Converts a frequency to a horizontal position in CSS pixels.
+    fn frequency_to_px(
+        render_engine: &RenderEngine,
+        waterfall: &Waterfall,
+        frequency: f32,
+    ) -> Option<f32> {
+        Some(Self::frequency_to_px_for(
+            frequency,
+            render_engine.canvas_dims().css_pixels().0,
+            waterfall.get_center_frequency(),
+            Self::units_per_px(render_engine, waterfall)?,
+        ))
+    }
+
+    fn frequency_to_px_for(
+        frequency: f32,
+        canvas_width: u32,
+        center: f32,
+        units_per_px: f64,
+    ) -> f32 {
+        let offset_units = f64::from(frequency) - f64::from(center);
+        (offset_units / units_per_px + 0.5 * f64::from(canvas_width)) as f32
+    }
+
+    // Hz per frequency unit. The frequency units span the range [-1, 1], which
+    // corresponds to the sample rate.
+    //
+    // Returns None if the sample rate is not valid (for instance, zero or NaN
+    // before the device has reported it).
+    fn hz_per_unit(samp_rate: f64) -> Option<f64> {
+        if samp_rate.is_finite() && samp_rate > 0.0 {
+            Some(0.5 * samp_rate)
+        } else {
+            None
+        }
+    }
+
+    // RX frequency after shifting the center frequency by `shift` frequency
+    // units. Returns None if the result is not a valid frequency.
+    fn retune_frequency(fc: f64, samp_rate: f64, shift: f32) -> Option<u64> {
+        let new_fc = fc + f64::from(shift) * Self::hz_per_unit(samp_rate)?;
+        if new_fc.is_finite() && new_fc > 0.0 {
+            Some(new_fc.round() as u64)
+        } else {
+            None
+        }
     }
 
     fn apply_dilation(
@@ -107,103 +1132,850 @@ impl WaterfallInteraction {
         dilation: f32,
         center: i32,
     ) {
+        if !dilation.is_finite() || dilation <= 0.0 {
+            return;
+        }
         let zoom = waterfall.get_zoom();
-        let new_zoom = Self::clamp_zoom(dilation * zoom);
+        let new_zoom = Waterfall::clamp_zoom(dilation * zoom);
         if new_zoom == zoom {
             return;
         }
-        let units_per_px = Self::units_per_px(render_engine, waterfall);
-        let freq = waterfall.get_center_frequency();
-        let center = freq + center as f32 * units_per_px - 1.0 / zoom;
-        let freq = ((dilation - 1.0) * center + freq) / dilation;
-        let freq = Self::clamp_center_frequency(freq, new_zoom);
+        let Some(center) = Self::px_to_frequency(render_engine, waterfall, center) else {
+            return;
+        };
+        let freq =
+            Self::dilated_center_frequency(waterfall.get_center_frequency(), center, dilation);
+        let freq = Waterfall::clamp_center_frequency(freq, new_zoom);
         waterfall.set_zoom(new_zoom);
         waterfall.set_center_frequency(freq);
     }
 
+    // Center frequency of the view after a dilation around the frequency
+    // `fixed`, which stays at the same position on the screen.
+    fn dilated_center_frequency(center: f32, fixed: f32, dilation: f32) -> f32 {
+        let (center, fixed, dilation) = (f64::from(center), f64::from(fixed), f64::from(dilation));
+        (((dilation - 1.0) * fixed + center) / dilation) as f32
+    }
+
     fn onwheel(&self) -> Closure<dyn Fn(WheelEvent)> {
-        let render_engine = Rc::clone(&self.render_engine);
-        let waterfall = Rc::clone(&self.waterfall);
+        let interaction = self.clone();
         Closure::new(move |event: WheelEvent| {
             event.prevent_default();
-            let dilation = (-1e-3 * event.delta_y() as f32).exp();
             let center = event.client_x();
+            if let Some(step) = interaction.ui.rx_lo_wheel_step() {
+                if interaction.is_over_rx_lo_marker(center) {
+                    let delta_px = WheelZoom::delta_px(event.delta_y(), event.delta_mode());
+                    if let Err(e) = interaction.wheel_tune(step, delta_px) {
+                        web_sys::console::error_1(&e);
+                    }
+                    return;
+                }
+            }
+            if interaction.is_view_locked() {
+                return;
+            }
+            let mut wheel_zoom = interaction.wheel_zoom.get();
+            let dilation =
+                wheel_zoom.push(WheelZoom::delta_px(event.delta_y(), event.delta_mode()));
+            interaction.wheel_zoom.set(wheel_zoom);
+            let Some(dilation) = dilation else {
+                return;
+            };
             Self::apply_dilation(
-                &render_engine.borrow(),
-                &mut waterfall.borrow_mut(),
+                &interaction.render_engine.borrow(),
+                &mut interaction.waterfall.borrow_mut(),
                 dilation,
                 center,
             );
+            interaction.view_changed();
+            interaction.schedule_wheel_settle(center);
+        })
+    }
+
+    // Settles the zoom once the wheel has been idle for a while, since wheel
+    // gestures do not have an end event. The zoom is dilated around the
+    // position `x` of the pointer, in CSS pixels.
+    fn schedule_wheel_settle(&self, x: i32) {
+        // Time in milliseconds without wheel events after which the gesture
+        // is considered to have ended.
+        const IDLE_MS: i32 = 250;
+        let Some(window) = web_sys::window() else {
+            return;
+        };
+        if let Some(handle) = self.wheel_settle_timeout.take() {
+            window.clear_timeout_with_handle(handle);
+        }
+        let interaction = self.clone();
+        let settle = Closure::once_into_js(move || {
+            interaction.wheel_settle_timeout.set(None);
+            interaction.settle_zoom(x);
+        });
+        match window
+            .set_timeout_with_callback_and_timeout_and_arguments_0(settle.unchecked_ref(), IDLE_MS)
+        {
+            Ok(handle) => self.wheel_settle_timeout.set(Some(handle)),
+            Err(e) => web_sys::console::error_1(&e),
+        }
+    }
+
+    // Settles the zoom at the end of a zoom gesture, keeping the frequency at
+    // the position `x`, in CSS pixels, at the same place on the screen.
+    fn settle_zoom(&self, x: i32) {
+        if self.is_view_locked() {
+            return;
+        }
+        let changed = {
+            let mut waterfall = self.waterfall.borrow_mut();
+            Self::px_to_frequency(&self.render_engine.borrow(), &waterfall, x)
+                .map_or(false, |fixed| waterfall.settle_zoom(fixed))
+        };
+        if changed {
+            self.view_changed();
+        }
+    }
+
+    fn is_over_rx_lo_marker(&self, x: i32) -> bool {
+        // Maximum distance in CSS pixels between the pointer and the marker.
+        const MAX_DISTANCE: f32 = 3.0;
+        let waterfall = self.waterfall.borrow();
+        Self::frequency_to_px(
+            &self.render_engine.borrow(),
+            &waterfall,
+            waterfall.get_rx_lo_marker_position(),
+        )
+        .map_or(false, |marker_x| {
+            (x as f32 - marker_x).abs() <= MAX_DISTANCE
         })
     }
 
+    fn wheel_tune(&self, step: u32, delta_px: f64) -> Result<(), JsValue> {
+        let mut wheel_tune = self.wheel_tune.get();
+        let steps = wheel_tune.push(delta_px);
+        self.wheel_tune.set(wheel_tune);
+        if steps == 0 {
+            return Ok(());
+        }
+        // The steps start from the frequency in the UI rather than from the
+        // frequency of the waterfall, which is only updated once the device
+        // has been retuned, so that successive notches are not lost.
+        let Some(mut new_fc) = self.ui.rx_lo_frequency().map(|f| f as f64) else {
+            return Ok(());
+        };
+        for _ in 0..steps.unsigned_abs() {
+            new_fc = Self::wheel_tuning_frequency(new_fc, f64::from(step), steps > 0);
+        }
+        if new_fc.is_finite() && new_fc > 0.0 {
+            self.ui.tune_rx_lo_frequency(new_fc.round() as u64)?;
+        }
+        Ok(())
+    }
+
+    // Moves the frequency one step up or down, snapping to a multiple of the
+    // step.
+    pub(crate) fn wheel_tuning_frequency(freq: f64, step: f64, up: bool) -> f64 {
+        // Rounding to 1 Hz avoids snapping to the wrong step due to rounding
+        // errors in freq.
+        let k = freq.round() / step;
+        if up {
+            (k.floor() + 1.0) * step
+        } else {
+            (k.ceil() - 1.0) * step
+        }
+    }
+
     fn onpointerdown(&self) -> Closure<dyn Fn(PointerEvent)> {
-        let canvas = Rc::clone(&self.canvas);
-        let pointer_tracker = Rc::clone(&self.pointer_tracker);
+        let interaction = self.clone();
         Closure::new(move |event: PointerEvent| {
-            canvas.style().set_property("cursor", "col-resize").unwrap();
-            pointer_tracker.borrow_mut().on_pointer_down(event);
+            if interaction.is_power_history_tool_enabled() {
+                if event.is_primary() {
+                    interaction.select_power_history_frequency(event.client_x());
+                }
+                return;
+            }
+            if interaction.is_ruler_tool_enabled() {
+                if interaction.ruler_drag.get().is_none() && event.is_primary() {
+                    let drag = RulerDrag {
+                        pointer_id: event.pointer_id(),
+                        start: (event.client_x(), event.client_y()),
+                    };
+                    interaction.ruler_drag.set(Some(drag));
+                    if let Err(e) = interaction.update_ruler(drag, drag.start) {
+                        web_sys::console::error_1(&e);
+                    }
+                }
+                return;
+            }
+            if interaction.cursor_drag.get().is_none() && event.is_primary() {
+                if let Some(cursor) = interaction.cursor_at(event.client_x()) {
+                    interaction.cursor_drag.set(Some(CursorDrag {
+                        pointer_id: event.pointer_id(),
+                        cursor,
+                    }));
+                    return;
+                }
+                if let Some(grab) = interaction.passband_at(event.client_x()) {
+                    interaction.passband_drag.set(Some(PassbandDrag {
+                        pointer_id: event.pointer_id(),
+                        grab,
+                    }));
+                    return;
+                }
+            }
+            // A new gesture stops the momentum of the previous one.
+            interaction.waterfall.borrow_mut().stop_zoom_momentum();
+            if !interaction.is_view_locked() {
+                interaction
+                    .canvas
+                    .style()
+                    .set_property("cursor", "col-resize")
+                    .unwrap();
+            }
+            interaction
+                .pointer_tracker
+                .borrow_mut()
+                .on_pointer_down(event);
         })
     }
 
     fn onpointerup(&self) -> Closure<dyn Fn(PointerEvent)> {
         let interaction = self.clone();
         Closure::new(move |event: PointerEvent| {
+            if let Some(drag) = interaction.ruler_drag.get() {
+                if drag.pointer_id == event.pointer_id() {
+                    interaction.end_ruler();
+                }
+            }
+            if let Some(drag) = interaction.cursor_drag.get() {
+                if drag.pointer_id == event.pointer_id() {
+                    interaction.cursor_drag.set(None);
+                }
+            }
+            if let Some(drag) = interaction.passband_drag.get() {
+                if drag.pointer_id == event.pointer_id() {
+                    interaction.passband_drag.set(None);
+                }
+            }
+            if event.type_() != "pointerup" {
+                // The pointer has left the waterfall
+                interaction.waterfall.borrow_mut().set_bin_highlight(None);
+            }
+            let timestamp = event.time_stamp();
             let mut pointer_tracker = interaction.pointer_tracker.borrow_mut();
             pointer_tracker.on_pointer_up(event);
+            interaction.end_pinch(timestamp, pointer_tracker.has_active_pointers());
             if !pointer_tracker.has_active_pointers() {
-                interaction
-                    .canvas
-                    .style()
-                    .set_property("cursor", "crosshair")
-                    .unwrap();
-                // Reset frequency overflow when we release.
-                *interaction.center_freq_overflow.borrow_mut() = 0.0;
+                interaction.pointers_released();
+            }
+        })
+    }
+
+    // The pointer up events are not delivered while the window does not have
+    // focus, so all the pointers are released when it loses focus.
+    fn onblur(&self) -> Closure<dyn Fn()> {
+        let interaction = self.clone();
+        Closure::new(move || {
+            if interaction.ruler_drag.get().is_some() {
+                interaction.end_ruler();
             }
+            interaction.cursor_drag.set(None);
+            interaction.passband_drag.set(None);
+            interaction.pointer_tracker.borrow_mut().clear();
+            // The pinch is ended without momentum, since the pointers were
+            // not released by the user.
+            interaction.pinch_state.set(PinchState::Idle);
+            interaction.pointers_released();
         })
     }
 
+    // Restores the state after all the pointers have been released.
+    fn pointers_released(&self) {
+        self.canvas
+            .style()
+            .set_property("cursor", "crosshair")
+            .unwrap();
+        // Reset frequency overflow when we release.
+        self.drag_pan.set(DragPan::default());
+        let mut waterfall = self.waterfall.borrow_mut();
+        waterfall.set_retune_progress(0.0, 0.0);
+        waterfall.release_overscan(!Self::prefers_reduced_motion());
+    }
+
     fn onpointermove(&self) -> Closure<dyn Fn(PointerEvent)> {
         let interaction = self.clone();
         Closure::new(move |event: PointerEvent| {
-            if let Some(gesture) = interaction
-                .pointer_tracker
-                .borrow_mut()
-                .on_pointer_move(event)
+            if let Some(drag) = interaction.ruler_drag.get() {
+                if drag.pointer_id == event.pointer_id() {
+                    let end = (event.client_x(), event.client_y());
+                    if let Err(e) = interaction.update_ruler(drag, end) {
+                        web_sys::console::error_1(&e);
+                    }
+                }
+                return;
+            }
+            if let Some(drag) = interaction.cursor_drag.get() {
+                if drag.pointer_id == event.pointer_id() {
+                    interaction.move_cursor(drag.cursor, event.client_x());
+                }
+                return;
+            }
+            if let Some(drag) = interaction.passband_drag.get() {
+                if drag.pointer_id == event.pointer_id() {
+                    interaction.drag_passband(drag.grab, event.client_x());
+                }
+                return;
+            }
+            interaction.update_bin_highlight(&event);
+            interaction.update_hover_cursor_style(&event);
+            // The pointer tracker is updated even if the view is locked, so
+            // that its state is consistent if the view is unlocked during a
+            // gesture.
+            for event in Self::coalesced_events(event) {
+                let timestamp = event.time_stamp();
+                let gesture = interaction
+                    .pointer_tracker
+                    .borrow_mut()
+                    .on_pointer_move(event);
+                if let Some(gesture) = gesture {
+                    if !interaction.is_view_locked() {
+                        interaction.process_gesture(gesture, timestamp).unwrap();
+                    }
+                }
+            }
+        })
+    }
+
+    // Returns the events that the browser has coalesced into a pointer move
+    // event, in the order in which they happened. High report rate pointers
+    // produce many more moves than pointermove events are dispatched, so
+    // processing each of them gives smoother drags. Since the pointer tracker
+    // computes the displacement with respect to the last event it recorded,
+    // the total displacement is the same as if only the dispatched event was
+    // processed. If the browser does not support coalesced events, only the
+    // dispatched event is returned.
+    fn coalesced_events(event: PointerEvent) -> Vec<PointerEvent> {
+        let supported =
+            js_sys::Reflect::has(&event, &JsValue::from_str("getCoalescedEvents")).unwrap_or(false);
+        if !supported {
+            return vec![event];
+        }
+        let events: Vec<PointerEvent> = event
+            .get_coalesced_events()
+            .iter()
+            .filter_map(|e| e.dyn_into::<PointerEvent>().ok())
+            .collect();
+        if events.is_empty() {
+            vec![event]
+        } else {
+            events
+        }
+    }
+
+    // Highlights the FFT bin under the pointer while hovering. The highlight
+    // is hidden during gestures and for touch pointers, which do not hover.
+    fn update_bin_highlight(&self, event: &PointerEvent) {
+        let hovering =
+            event.pointer_type() != "touch" && !self.pointer_tracker.borrow().has_active_pointers();
+        let frequency = if hovering {
+            Self::px_to_frequency(
+                &self.render_engine.borrow(),
+                &self.waterfall.borrow(),
+                event.client_x(),
+            )
+        } else {
+            None
+        };
+        self.waterfall.borrow_mut().set_bin_highlight(frequency);
+    }
+
+    // Shows a resize cursor style while hovering over a measurement cursor or
+    // a passband edge, and a move cursor style while hovering over the inside
+    // of the passband, to indicate that they can be dragged.
+    fn update_hover_cursor_style(&self, event: &PointerEvent) {
+        if self.pointer_tracker.borrow().has_active_pointers() {
+            return;
+        }
+        let x = event.client_x();
+        let style = if self.cursor_at(x).is_some() {
+            "ew-resize"
+        } else {
+            match self.passband_at(x) {
+                Some(PassbandGrab::Edge) => "ew-resize",
+                Some(PassbandGrab::Body { .. }) => "move",
+                None => "crosshair",
+            }
+        };
+        self.canvas.style().set_property("cursor", style).unwrap();
+    }
+
+    fn navigation_onclick(&self, target: NavigationTarget) -> Closure<dyn Fn()> {
+        let interaction = self.clone();
+        Closure::new(move || interaction.go_to(target))
+    }
+
+    fn zoom_onclick(&self, dilation: f32) -> Closure<dyn Fn()> {
+        let interaction = self.clone();
+        Closure::new(move || interaction.zoom_by(dilation))
+    }
+
+    fn pan_onclick(&self, fraction: f32) -> Closure<dyn Fn()> {
+        let interaction = self.clone();
+        Closure::new(move || interaction.pan_by(fraction))
+    }
+
+    fn onkeydown(&self) -> Closure<dyn Fn(KeyboardEvent)> {
+        let interaction = self.clone();
+        Closure::new(move |event: KeyboardEvent| {
+            if event.ctrl_key()
+                || event.alt_key()
+                || event.meta_key()
+                || Self::is_form_element_event(&event)
             {
-                interaction.process_gesture(gesture).unwrap();
+                return;
             }
+            let Some(action) = interaction.ui.keymap_action(&event.key()) else {
+                return;
+            };
+            match action {
+                Action::Dc => interaction.go_to(NavigationTarget::Dc),
+                Action::LeftEdge => interaction.go_to(NavigationTarget::LeftEdge),
+                Action::RightEdge => interaction.go_to(NavigationTarget::RightEdge),
+                Action::CycleColormap => {
+                    if let Err(e) = interaction.ui.cycle_colormap() {
+                        web_sys::console::error_1(&e);
+                    }
+                }
+                Action::AutoRange => {
+                    if let Err(e) = interaction.ui.auto_range() {
+                        web_sys::console::error_1(&e);
+                    }
+                }
+                Action::ViewLock => interaction.set_view_locked(!interaction.is_view_locked()),
+                Action::Ruler => interaction.set_ruler_tool(!interaction.is_ruler_tool_enabled()),
+                Action::PerfOverlay => {
+                    if let Err(e) = interaction.ui.toggle_perf_overlay() {
+                        web_sys::console::error_1(&e);
+                    }
+                }
+                Action::ZoomIn => interaction.zoom_by(Self::BUTTON_ZOOM_STEP),
+                Action::ZoomOut => interaction.zoom_by(Self::BUTTON_ZOOM_STEP.recip()),
+                Action::PanLeft => interaction.pan_by(-Self::BUTTON_PAN_STEP),
+                Action::PanRight => interaction.pan_by(Self::BUTTON_PAN_STEP),
+                Action::Pause => {
+                    if let Err(e) = interaction.ui.toggle_paused() {
+                        web_sys::console::error_1(&e);
+                    }
+                }
+                Action::Help => {
+                    if let Err(e) = interaction.ui.toggle_shortcut_help() {
+                        web_sys::console::error_1(&e);
+                    }
+                }
+            }
+            event.prevent_default();
         })
     }
 
-    fn process_gesture(&self, gesture: PointerGesture) -> Result<(), JsValue> {
+    // Returns true if the event is targeted to a form element, such as a text
+    // input. Keyboard shortcuts are ignored in this case.
+    fn is_form_element_event(event: &KeyboardEvent) -> bool {
+        event
+            .target()
+            .and_then(|target| target.dyn_into::<web_sys::Element>().ok())
+            .map_or(false, |element| {
+                matches!(element.tag_name().as_str(), "INPUT" | "SELECT" | "TEXTAREA")
+            })
+    }
+
+    // Called when a pointer is released. If a pinch was in progress, the zoom
+    // continues with momentum.
+    fn end_pinch(&self, timestamp: f64, has_active_pointers: bool) {
+        let state = self.pinch_state.get();
+        self.pinch_state.set(match state {
+            PinchState::Pinching(_) | PinchState::Released if has_active_pointers => {
+                PinchState::Released
+            }
+            _ => PinchState::Idle,
+        });
+        let PinchState::Pinching(velocity) = state else {
+            return;
+        };
+        let rate = velocity
+            .release_rate(timestamp)
+            .filter(|_| !self.is_view_locked() && !Self::prefers_reduced_motion());
+        let Some(rate) = rate else {
+            // Without momentum, the gesture ends here. Otherwise, the zoom is
+            // settled by the waterfall when the momentum stops.
+            self.settle_zoom(velocity.center);
+            return;
+        };
+        let mut waterfall = self.waterfall.borrow_mut();
+        if let Some(center) =
+            Self::px_to_frequency(&self.render_engine.borrow(), &waterfall, velocity.center)
+        {
+            waterfall.start_zoom_momentum(rate, center);
+        }
+    }
+
+    // Returns true if the user has requested the browser to minimize the
+    // amount of animation.
+    fn prefers_reduced_motion() -> bool {
+        web_sys::window()
+            .and_then(|window| {
+                window
+                    .match_media("(prefers-reduced-motion: reduce)")
+                    .ok()
+                    .flatten()
+            })
+            .map_or(false, |query| query.matches())
+    }
+
+    fn process_gesture(&self, gesture: PointerGesture, timestamp: f64) -> Result<(), JsValue> {
         match gesture {
             PointerGesture::Drag { dx, .. } => {
+                if self.pinch_state.get() == PinchState::Released {
+                    return Ok(());
+                }
                 let mut waterfall = self.waterfall.borrow_mut();
-                let units_per_px = Self::units_per_px(&self.render_engine.borrow(), &waterfall);
-                let freq = waterfall.get_center_frequency() - (dx as f32 * units_per_px);
-                let clamped = Self::clamp_center_frequency(freq, waterfall.get_zoom());
-                let mut overflow = self.center_freq_overflow.borrow_mut();
-                *overflow += freq - clamped;
-                let shift_threshold = 0.25;
-                if overflow.abs() >= shift_threshold {
-                    // Change receive frequency
-                    let shift = shift_threshold.copysign(*overflow);
-                    *overflow -= shift;
-                    let (fc, fs) = waterfall.get_freq_samprate();
-                    let new_fc = fc + 0.5 * f64::from(shift) * fs;
-                    self.ui.set_rx_lo_frequency(new_fc as u64)?;
-                } else {
-                    waterfall.set_center_frequency(clamped);
+                let Some(units_per_px) =
+                    Self::units_per_px(&self.render_engine.borrow(), &waterfall)
+                else {
+                    return Ok(());
+                };
+                let mut drag_pan = self.drag_pan.get();
+                let step = drag_pan.drag(
+                    waterfall.get_center_frequency(),
+                    waterfall.get_zoom(),
+                    dx,
+                    units_per_px,
+                    waterfall.is_overscan_enabled(),
+                );
+                self.drag_pan.set(drag_pan);
+                match step {
+                    DragPanStep::Center(freq) => waterfall.set_center_frequency(freq),
+                    DragPanStep::Overscan(freq) => waterfall.drag_center_frequency(freq),
+                    DragPanStep::Retune { shift, center } => {
+                        // Change receive frequency
+                        if let Some(center) = center {
+                            waterfall.set_center_frequency(center);
+                        }
+                        let (fc, fs) = waterfall.get_freq_samprate();
+                        if let Some(new_fc) = Self::retune_frequency(fc, fs, shift) {
+                            // If the step was limited, jump_rx_lo_frequency
+                            // has already shown a toast with the new
+                            // frequency.
+                            if self.ui.jump_rx_lo_frequency(new_fc)? == new_fc {
+                                self.ui.show_toast(&format!(
+                                    "RX freq {}",
+                                    self.ui.number_format().frequency(
+                                        new_fc as f64,
+                                        FrequencyUnit::MHz,
+                                        3
+                                    )
+                                ))?;
+                            }
+                        }
+                    }
                 }
+                // The progress bar is shown on the screen edge towards which
+                // the waterfall is being dragged beyond the band edge.
+                let (side, progress) = drag_pan.retune_progress();
+                waterfall.set_retune_progress(side * waterfall.get_axis_direction(), progress);
+            }
+            PointerGesture::Pinch { center, dilation } => {
+                let previous = match self.pinch_state.get() {
+                    PinchState::Pinching(velocity) => Some(velocity),
+                    _ => None,
+                };
+                self.pinch_state
+                    .set(PinchState::Pinching(PinchVelocity::update(
+                        previous, dilation.0, center.0, timestamp,
+                    )));
+                Self::apply_dilation(
+                    &self.render_engine.borrow(),
+                    &mut self.waterfall.borrow_mut(),
+                    dilation.0,
+                    center.0,
+                );
             }
-            PointerGesture::Pinch { center, dilation } => Self::apply_dilation(
-                &self.render_engine.borrow(),
-                &mut self.waterfall.borrow_mut(),
-                dilation.0,
-                center.0,
-            ),
         }
+        self.view_changed();
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::pointer::Pointer;
+
+    #[test]
+    fn hz_per_unit_invalid_samp_rate() {
+        for samp_rate in [0.0, -1e6, f64::NAN, f64::INFINITY] {
+            assert_eq!(WaterfallInteraction::hz_per_unit(samp_rate), None);
+        }
+        assert_eq!(WaterfallInteraction::hz_per_unit(61.44e6), Some(30.72e6));
+    }
+
+    #[test]
+    fn retune_frequency_invalid_samp_rate() {
+        for samp_rate in [0.0, f64::NAN, f64::NEG_INFINITY] {
+            assert_eq!(
+                WaterfallInteraction::retune_frequency(2.4e9, samp_rate, 0.25),
+                None
+            );
+        }
+        assert_eq!(
+            WaterfallInteraction::retune_frequency(2.4e9, 40e6, 0.25),
+            Some(2_405_000_000)
+        );
+        // Retuning below 0 Hz is rejected
+        assert_eq!(
+            WaterfallInteraction::retune_frequency(1e6, 40e6, -0.25),
+            None
+        );
+    }
+
+    #[test]
+    fn units_per_px_invalid() {
+        assert_eq!(WaterfallInteraction::units_per_px_for(0, 1.0, 1.0), None);
+        assert_eq!(
+            WaterfallInteraction::units_per_px_for(1000, f32::NAN, 1.0),
+            None
+        );
+        assert_eq!(WaterfallInteraction::units_per_px_for(1000, 0.0, 1.0), None);
+        assert_eq!(
+            WaterfallInteraction::units_per_px_for(1000, 2.0, -1.0),
+            Some(-1e-3)
+        );
+    }
+
+    #[test]
+    fn px_frequency_roundtrip_max_zoom() {
+        let zoom = Waterfall::MAX_ZOOM;
+        for canvas_width in [320, 1920, 7680] {
+            for axis_direction in [1.0, -1.0] {
+                let units_per_px =
+                    WaterfallInteraction::units_per_px_for(canvas_width, zoom, axis_direction)
+                        .unwrap();
+                for center in [-1.0 + 1.0 / zoom, -0.3, 0.0, 0.9, 1.0 - 1.0 / zoom] {
+                    for x in [0, 1, canvas_width as i32 / 2 + 1, canvas_width as i32 - 1] {
+                        let freq = WaterfallInteraction::px_to_frequency_for(
+                            x,
+                            canvas_width,
+                            center,
+                            units_per_px,
+                        );
+                        let px = WaterfallInteraction::frequency_to_px_for(
+                            freq,
+                            canvas_width,
+                            center,
+                            units_per_px,
+                        );
+                        assert!((px - x as f32).abs() < 0.05, "x = {x}, px = {px}");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn dilation_keeps_frequency_under_pointer() {
+        // The frequency under the pointer must not move by more than a small
+        // fraction of a pixel when zooming in up to the maximum zoom, even near
+        // the band edges and with wide canvases and high sample rates.
+        let canvas_width = 3840;
+        for samp_rate in [61.44e6, 2.5e6] {
+            let hz_per_unit = WaterfallInteraction::hz_per_unit(samp_rate).unwrap();
+            for (start_center, x) in [(0.9, 3000), (-0.95, 100), (0.3, 1921)] {
+                let mut zoom = 1.0;
+                let mut center = Waterfall::clamp_center_frequency(start_center, zoom);
+                let dilation = 1.1;
+                while zoom < Waterfall::MAX_ZOOM {
+                    let new_zoom = Waterfall::clamp_zoom(dilation * zoom);
+                    let dilation = new_zoom / zoom;
+                    let units_per_px =
+                        WaterfallInteraction::units_per_px_for(canvas_width, zoom, 1.0).unwrap();
+                    let fixed = WaterfallInteraction::px_to_frequency_for(
+                        x,
+                        canvas_width,
+                        center,
+                        units_per_px,
+                    );
+                    let new_center =
+                        WaterfallInteraction::dilated_center_frequency(center, fixed, dilation);
+                    let new_units_per_px =
+                        WaterfallInteraction::units_per_px_for(canvas_width, new_zoom, 1.0)
+                            .unwrap();
+                    // Only check the invariant if the view did not hit the
+                    // band edges, where the center frequency gets clamped.
+                    if Waterfall::clamp_center_frequency(new_center, new_zoom) == new_center {
+                        let after = WaterfallInteraction::px_to_frequency_for(
+                            x,
+                            canvas_width,
+                            new_center,
+                            new_units_per_px,
+                        );
+                        let error_hz = f64::from(after - fixed).abs() * hz_per_unit;
+                        let hz_per_px = new_units_per_px * hz_per_unit;
+                        assert!(
+                            error_hz < 0.05 * hz_per_px,
+                            "zoom = {new_zoom}, error = {error_hz} Hz, pixel = {hz_per_px} Hz"
+                        );
+                    }
+                    zoom = new_zoom;
+                    center = Waterfall::clamp_center_frequency(new_center, new_zoom);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn snap_bandwidth() {
+        // Bandwidths close to a common bandwidth snap to it
+        assert_eq!(WaterfallInteraction::snap_bandwidth(12.1e3), 12.5e3);
+        assert_eq!(WaterfallInteraction::snap_bandwidth(2.65e3), 2.7e3);
+        assert_eq!(WaterfallInteraction::snap_bandwidth(1.04e6), 1e6);
+        // Other bandwidths are rounded to 1 Hz
+        assert_eq!(WaterfallInteraction::snap_bandwidth(4000.4), 4000.0);
+        assert_eq!(WaterfallInteraction::snap_bandwidth(7.5e6), 7.5e6);
+        assert_eq!(WaterfallInteraction::snap_bandwidth(0.0), 1.0);
+    }
+
+    #[test]
+    fn pinch_velocity() {
+        let mut velocity = PinchVelocity::update(None, 1.1, 100, 0.0);
+        assert_eq!(velocity.rate, 0.0);
+        // A steady pinch that doubles the zoom every 100 ms
+        let dilation = 2.0f32.powf(0.1);
+        for j in 1..=50 {
+            velocity = PinchVelocity::update(Some(velocity), dilation, 100, 10.0 * j as f64);
+        }
+        let expected = 2.0f32.ln() / 100.0;
+        assert!((velocity.rate - expected).abs() < 1e-2 * expected);
+        // Events with the same timestamp are ignored
+        let same = PinchVelocity::update(Some(velocity), 1.5, 120, 500.0);
+        assert_eq!(same.rate, velocity.rate);
+        assert_eq!(same.center, 120);
+        assert_eq!(velocity.release_rate(550.0), Some(velocity.rate));
+        assert_eq!(velocity.release_rate(700.0), None);
+        // A pinch that has stopped has no momentum
+        for j in 51..=70 {
+            velocity = PinchVelocity::update(Some(velocity), 1.0, 100, 10.0 * j as f64);
+        }
+        assert_eq!(velocity.release_rate(700.0), None);
+    }
+
+    #[test]
+    fn wheel_zoom_small_deltas() {
+        let expected = WheelZoom::default().push(50.0).unwrap();
+        // Many small deltas that add up to the same total
+        let mut wheel_zoom = WheelZoom::default();
+        let mut dilation = 1.0f32;
+        for _ in 0..200 {
+            if let Some(d) = wheel_zoom.push(0.25) {
+                dilation *= d;
+            }
+        }
+        assert_eq!(wheel_zoom.pending, 0.0);
+        assert!((dilation - expected).abs() < 1e-5);
+        // Deltas in opposite directions cancel out
+        let mut wheel_zoom = WheelZoom::default();
+        assert_eq!(wheel_zoom.push(0.5), None);
+        assert_eq!(wheel_zoom.push(-0.5), None);
+        assert_eq!(wheel_zoom.push(f64::NAN), None);
+        assert_eq!(wheel_zoom.pending, 0.0);
+    }
+
+    #[test]
+    fn wheel_tune_steps() {
+        // A mouse wheel notch tunes one step, both in pixel and in line mode
+        let mut wheel_tune = WheelTune::default();
+        assert_eq!(wheel_tune.push(-100.0), 1);
+        assert_eq!(
+            wheel_tune.push(WheelZoom::delta_px(3.0, WheelEvent::DOM_DELTA_LINE)),
+            -1
+        );
+        // Many small trackpad deltas only tune once they add up to a notch
+        let mut wheel_tune = WheelTune::default();
+        let steps: i32 = (0..100).map(|_| wheel_tune.push(-2.5)).sum();
+        assert_eq!(steps, 2);
+        assert!((wheel_tune.pending + 50.0).abs() < 1e-9);
+        // Deltas in opposite directions cancel out
+        assert_eq!(wheel_tune.push(60.0), 0);
+        assert_eq!(wheel_tune.push(f64::NAN), 0);
+        assert!((wheel_tune.pending - 10.0).abs() < 1e-9);
+    }
+
+    // Feeds the coalesced moves of a drag gesture through a pointer tracker
+    // and pans as process_gesture does, starting from center. Returns the
+    // final center frequency and the retune shifts.
+    fn coalesced_drag(
+        events: &[&[i32]],
+        center: f32,
+        zoom: f32,
+        units_per_px: f64,
+    ) -> (f32, Vec<f32>) {
+        let mut tracker = PointerTracker::new();
+        tracker.pointer_down(Pointer::new(1, events[0][0], 50, 0.0));
+        let mut drag_pan = DragPan::default();
+        let mut center = center;
+        let mut shifts = Vec::new();
+        let mut timestamp = 0.0;
+        for &x in events.iter().copied().flatten().skip(1) {
+            timestamp += 1.0;
+            let Some(PointerGesture::Drag { dx, .. }) =
+                tracker.pointer_move(Pointer::new(1, x, 50, timestamp))
+            else {
+                panic!("coalesced move at x = {x} is not a drag");
+            };
+            match drag_pan.drag(center, zoom, dx, units_per_px, false) {
+                DragPanStep::Center(freq) => center = freq,
+                DragPanStep::Overscan(_) => panic!("overscan is disabled"),
+                DragPanStep::Retune { shift, .. } => shifts.push(shift),
+            }
+        }
+        (center, shifts)
+    }
+
+    #[test]
+    fn coalesced_drag_total_displacement() {
+        // A fast drag with a high report rate mouse, where the browser
+        // coalesces several moves into each pointermove event. Processing each
+        // coalesced move must pan the waterfall by the same amount as the
+        // pointer displacement.
+        let zoom = 16.0;
+        let units_per_px = WaterfallInteraction::units_per_px_for(1920, zoom, 1.0).unwrap();
+        let events: [&[i32]; 5] = [
+            &[100],
+            &[103, 109, 118],
+            &[130, 145, 163, 184],
+            &[208],
+            &[235, 240, 238],
+        ];
+        let (center, shifts) = coalesced_drag(&events, 0.25, zoom, units_per_px);
+        assert!(shifts.is_empty());
+        let expected = (0.25 - 138.0 * units_per_px) as f32;
+        assert!((center - expected).abs() < 1e-6, "center = {center}");
+
+        // Dragging beyond the band edge retunes once for each RETUNE_SHIFT of
+        // the distance beyond the edge, and the view stays at the edge.
+        let max_center = 1.0 - 1.0 / zoom;
+        let edge_px = (f64::from(max_center - 0.25) / units_per_px).round() as i32;
+        let retune_px = (f64::from(DragPan::RETUNE_SHIFT) / units_per_px).ceil() as i32;
+        let end = 1000 - edge_px - 2 * retune_px - 10;
+        let moves: Vec<i32> = (0..=40).map(|j| 1000 + (end - 1000) * j / 40).collect();
+        let (center, shifts) = coalesced_drag(&[&moves], 0.25, zoom, units_per_px);
+        assert_eq!(center, max_center);
+        assert_eq!(shifts, vec![DragPan::RETUNE_SHIFT; 2]);
+    }
+
+    #[test]
+    fn wheel_zoom_delta_mode() {
+        assert_eq!(WheelZoom::delta_px(3.0, WheelEvent::DOM_DELTA_PIXEL), 3.0);
+        assert_eq!(WheelZoom::delta_px(3.0, WheelEvent::DOM_DELTA_LINE), 120.0);
+        assert_eq!(
+            WheelZoom::delta_px(-1.0, WheelEvent::DOM_DELTA_PAGE),
+            -800.0
+        );
+    }
+}
@@ -0,0 +1,322 @@
+use std::fmt;
+
+/// Action that can be performed with a keyboard shortcut.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Action {
+    /// Go to DC.
+    Dc,
+    /// Go to the band edge shown on the left of the screen.
+    LeftEdge,
+    /// Go to the band edge shown on the right of the screen.
+    RightEdge,
+    /// Select the next colormap.
+    CycleColormap,
+    /// Fit the waterfall levels to the visible spectrum.
+    AutoRange,
+    /// Toggle the view lock.
+    ViewLock,
+    /// Toggle the ruler tool.
+    Ruler,
+    /// Toggle the performance overlay.
+    PerfOverlay,
+    /// Zoom in.
+    ZoomIn,
+    /// Zoom out.
+    ZoomOut,
+    /// Pan towards the left of the screen.
+    PanLeft,
+    /// Pan towards the right of the screen.
+    PanRight,
+    /// Pause or resume the waterfall.
+    Pause,
+    /// Show or hide the keyboard shortcut help.
+    Help,
+}
+
+impl Action {
+    /// List of all the actions.
+    pub const ALL: [Action; 14] = [
+        Action::Dc,
+        Action::LeftEdge,
+        Action::RightEdge,
+        Action::CycleColormap,
+        Action::AutoRange,
+        Action::ViewLock,
+        Action::Ruler,
+        Action::PerfOverlay,
+        Action::ZoomIn,
+        Action::ZoomOut,
+        Action::PanLeft,
+        Action::PanRight,
+        Action::Pause,
+        Action::Help,
+    ];
+
+    /// Returns the name of the action used in the keymap text.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::Dc => "dc",
+            Action::LeftEdge => "left_edge",
+            Action::RightEdge => "right_edge",
+            Action::CycleColormap => "colormap",
+            Action::AutoRange => "auto_range",
+            Action::ViewLock => "view_lock",
+            Action::Ruler => "ruler",
+            Action::PerfOverlay => "perf_overlay",
+            Action::ZoomIn => "zoom_in",
+            Action::ZoomOut => "zoom_out",
+            Action::PanLeft => "pan_left",
+            Action::PanRight => "pan_right",
+            Action::Pause => "pause",
+            Action::Help => "help",
+        }
+    }
+
+    /// Returns a description of the action, which is suitable for the title of
+    /// the corresponding UI element.
+    pub fn description(&self) -> &'static str {
+        match self {
+            Action::Dc => "Go to DC",
+            Action::LeftEdge => "Go to left edge",
+            Action::RightEdge => "Go to right edge",
+            Action::CycleColormap => "Colormap",
+            Action::AutoRange => "Fit the levels to the visible spectrum",
+            Action::ViewLock => "Lock zoom and pan",
+            Action::Ruler => "Measure by dragging on the waterfall",
+            Action::PerfOverlay => "Show frame rate, ingest rate and latency",
+            Action::ZoomIn => "Zoom in",
+            Action::ZoomOut => "Zoom out",
+            Action::PanLeft => "Pan left",
+            Action::PanRight => "Pan right",
+            Action::Pause => "Pause the waterfall",
+            Action::Help => "Show the keyboard shortcuts",
+        }
+    }
+
+    /// Returns the ID of the HTML element that performs the same action.
+    ///
+    /// `None` is returned if the action can only be performed with the
+    /// keyboard.
+    pub fn element_id(&self) -> Option<&'static str> {
+        Some(match self {
+            Action::Dc => "go_to_dc",
+            Action::LeftEdge => "go_to_left_edge",
+            Action::RightEdge => "go_to_right_edge",
+            Action::CycleColormap => "colormap_select",
+            Action::AutoRange => "auto_range_button",
+            Action::ViewLock => "view_lock",
+            Action::Ruler => "ruler_tool",
+            Action::PerfOverlay => "perf_overlay",
+            Action::ZoomIn => "zoom_in",
+            Action::ZoomOut => "zoom_out",
+            Action::PanLeft => "pan_left",
+            Action::PanRight => "pan_right",
+            Action::Pause | Action::Help => return None,
+        })
+    }
+
+    fn default_key(&self) -> &'static str {
+        match self {
+            Action::Dc => "d",
+            Action::LeftEdge => "Home",
+            Action::RightEdge => "End",
+            Action::CycleColormap => "c",
+            Action::AutoRange => "a",
+            Action::ViewLock => "l",
+            Action::Ruler => "r",
+            Action::PerfOverlay => "p",
+            Action::ZoomIn => "+",
+            Action::ZoomOut => "-",
+            Action::PanLeft => "ArrowLeft",
+            Action::PanRight => "ArrowRight",
+            Action::Pause => " ",
+            Action::Help => "?",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Action> {
+        Action::ALL.into_iter().find(|action| action.name() == name)
+    }
+}
+
+/// Keymap.
+///
+/// The keymap maps keys, given as the value of the `key` property of keyboard
+/// events, to actions. It is described by a text with comma separated
+/// `action=key` bindings, such as `dc=x, ruler=m`. Whitespace around the
+/// bindings is ignored, so the space bar and the comma key are given by the
+/// names `Space` and `Comma`, as in `view_lock=Space`. Actions that are not
+/// listed keep their default key, and actions bound to an empty key are
+/// disabled.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Keymap {
+    // Keys in the same order as Action::ALL
+    keys: Vec<String>,
+}
+
+impl Keymap {
+    // Names of the keys that cannot be written literally in a keymap text.
+    const SPACE: &'static str = "Space";
+    const COMMA: &'static str = "Comma";
+
+    /// Parses a keymap text.
+    ///
+    /// An error is returned if the text contains an unknown action or a
+    /// malformed binding.
+    pub fn parse(text: &str) -> Result<Keymap, String> {
+        let mut keymap = Keymap::default();
+        for binding in text.split(',').map(str::trim) {
+            if binding.is_empty() {
+                continue;
+            }
+            let (name, key) = binding
+                .split_once('=')
+                .ok_or_else(|| format!("binding '{binding}' is not of the form action=key"))?;
+            let name = name.trim();
+            let action =
+                Action::from_name(name).ok_or_else(|| format!("unknown action '{name}'"))?;
+            let key = match key.trim() {
+                Self::SPACE => " ",
+                Self::COMMA => ",",
+                key if key.contains(char::is_whitespace) => {
+                    return Err(format!("invalid key '{key}' in binding '{binding}'"))
+                }
+                key => key,
+            };
+            keymap.keys[Self::index(action)] = key.to_string();
+        }
+        Ok(keymap)
+    }
+
+    /// Returns the key bound to an action.
+    ///
+    /// If the action is disabled, `None` is returned.
+    pub fn key(&self, action: Action) -> Option<&str> {
+        Some(self.keys[Self::index(action)].as_str()).filter(|key| !key.is_empty())
+    }
+
+    /// Returns the action bound to a key.
+    ///
+    /// If several actions are bound to the same key, the first one in
+    /// [`Action::ALL`] is returned.
+    pub fn action(&self, key: &str) -> Option<Action> {
+        Action::ALL
+            .into_iter()
+            .find(|&action| self.key(action) == Some(key))
+    }
+
+    /// Returns the keys that are bound to more than one action.
+    ///
+    /// Each key is returned together with the actions bound to it.
+    pub fn conflicts(&self) -> Vec<(&str, Vec<Action>)> {
+        let mut conflicts: Vec<(&str, Vec<Action>)> = Vec::new();
+        for action in Action::ALL {
+            let Some(key) = self.key(action) else {
+                continue;
+            };
+            if let Some((_, actions)) = conflicts.iter_mut().find(|(k, _)| *k == key) {
+                actions.push(action);
+            } else {
+                conflicts.push((key, vec![action]));
+            }
+        }
+        conflicts.retain(|(_, actions)| actions.len() > 1);
+        conflicts
+    }
+
+    /// Returns the name of a key as it is written in a keymap text.
+    ///
+    /// This is the `key` itself, except for the space bar and the comma key,
+    /// which are named `Space` and `Comma`.
+    pub fn key_name(key: &str) -> &str {
+        match key {
+            " " => Self::SPACE,
+            "," => Self::COMMA,
+            key => key,
+        }
+    }
+
+    fn index(action: Action) -> usize {
+        Action::ALL.iter().position(|&a| a == action).unwrap()
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Keymap {
+        Keymap {
+            keys: Action::ALL
+                .iter()
+                .map(|action| action.default_key().to_string())
+                .collect(),
+        }
+    }
+}
+
+impl fmt::Display for Keymap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (j, (action, key)) in Action::ALL.iter().zip(&self.keys).enumerate() {
+            if j != 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}={}", action.name(), Self::key_name(key))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn default_keymap() {
+        let keymap = Keymap::parse("").unwrap();
+        assert_eq!(keymap, Keymap::default());
+        assert_eq!(keymap.action("d"), Some(Action::Dc));
+        assert_eq!(keymap.action("Home"), Some(Action::LeftEdge));
+        assert_eq!(keymap.action(" "), Some(Action::Pause));
+        assert_eq!(keymap.action("x"), None);
+        assert!(keymap.conflicts().is_empty());
+        assert_eq!(Keymap::parse(&keymap.to_string()).unwrap(), keymap);
+    }
+
+    #[test]
+    fn rebind() {
+        let keymap = Keymap::parse("dc=x,  ruler=m ,view_lock=").unwrap();
+        assert_eq!(keymap.action("x"), Some(Action::Dc));
+        assert_eq!(keymap.action("d"), None);
+        assert_eq!(keymap.key(Action::Ruler), Some("m"));
+        assert_eq!(keymap.key(Action::ViewLock), None);
+        assert_eq!(keymap.action(""), None);
+        assert_eq!(keymap.key(Action::AutoRange), Some("a"));
+    }
+
+    #[test]
+    fn named_keys() {
+        let keymap = Keymap::parse("pause=, view_lock=Space, ruler=Comma, dc==").unwrap();
+        assert_eq!(keymap.action(" "), Some(Action::ViewLock));
+        assert_eq!(keymap.action(","), Some(Action::Ruler));
+        assert_eq!(keymap.action("="), Some(Action::Dc));
+        assert_eq!(Keymap::key_name(" "), "Space");
+        assert_eq!(Keymap::parse(&keymap.to_string()).unwrap(), keymap);
+    }
+
+    #[test]
+    fn conflicts() {
+        let keymap = Keymap::parse("ruler=d, auto_range=d, colormap=").unwrap();
+        assert_eq!(
+            keymap.conflicts(),
+            vec![("d", vec![Action::Dc, Action::AutoRange, Action::Ruler])]
+        );
+        // The first action wins
+        assert_eq!(keymap.action("d"), Some(Action::Dc));
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(Keymap::parse("zoom=z").is_err());
+        assert!(Keymap::parse("dc").is_err());
+        // Bindings are separated by commas, not by whitespace
+        assert!(Keymap::parse("dc=x ruler=m").is_err());
+    }
+}
@@ -0,0 +1,66 @@
+//! Synchronization of the waterfall view across browser tabs.
+//!
+//! The view (zoom and center frequency) is shared between tabs using a
+//! `BroadcastChannel`.
+
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::BroadcastChannel;
+
+const CHANNEL_NAME: &str = "maia-wasm-view";
+
+pub struct ViewSync {
+    channel: BroadcastChannel,
+    // This is true while a view received from another tab is being applied. It
+    // prevents the view from being broadcast back, which would cause a
+    // feedback loop between tabs.
+    applying: Cell<bool>,
+}
+
+/// Waterfall view state.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+pub struct ViewState {
+    /// Waterfall zoom.
+    pub zoom: f32,
+    /// Waterfall center frequency, in the units used by
+    /// [`Waterfall::set_center_frequency`](crate::waterfall::Waterfall::set_center_frequency).
+    pub center_frequency: f32,
+}
+
+impl ViewSync {
+    pub fn new() -> Result<ViewSync, JsValue> {
+        Ok(ViewSync {
+            channel: BroadcastChannel::new(CHANNEL_NAME)?,
+            applying: Cell::new(false),
+        })
+    }
+
+    pub fn set_onmessage(&self, onmessage: &JsValue) {
+        self.channel.set_onmessage(Some(onmessage.unchecked_ref()));
+    }
+
+    pub fn broadcast(&self, view: &ViewState) -> Result<(), JsValue> {
+        if self.applying.get() {
+            return Ok(());
+        }
+        let json = serde_json::to_string(view).map_err(|_| "unable to format view JSON")?;
+        self.channel.post_message(&json.into())
+    }
+
+    pub fn parse(data: &JsValue) -> Result<ViewState, JsValue> {
+        let data = data
+            .as_string()
+            .ok_or("view sync message is not a string")?;
+        let view = serde_json::from_str(&data).map_err(|_| "unable to parse view JSON")?;
+        Ok(view)
+    }
+
+    // Runs f with the feedback loop guard enabled.
+    pub fn apply<F: FnOnce()>(&self, f: F) {
+        self.applying.set(true);
+        f();
+        self.applying.set(false);
+    }
+}